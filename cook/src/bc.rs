@@ -0,0 +1,154 @@
+use engine_core::asset::TextureFormat;
+
+use crate::texture::Image;
+
+type Block = [[u8; 4]; 16];
+
+/// Compresses one mip level to BC1 or BC3 with a bounding box fit: fast and good enough for
+/// most colour textures, but noticeably worse than an exhaustive encoder on gradients.
+pub fn compress(image: &Image, format: TextureFormat) -> Vec<u8> {
+    let mut out = Vec::with_capacity(format.level_size(image.width, image.height));
+    for block_y in 0..image.height.div_ceil(4) {
+        for block_x in 0..image.width.div_ceil(4) {
+            let block = read_block(image, block_x * 4, block_y * 4);
+            match format {
+                TextureFormat::Bc1 => encode_color(&block, &mut out),
+                TextureFormat::Bc3 => {
+                    encode_alpha(&block, &mut out);
+                    encode_color(&block, &mut out);
+                }
+                TextureFormat::Rgba8 => unreachable!("rgba8 is not block compressed"),
+            }
+        }
+    }
+    out
+}
+
+/// Edge blocks repeat the last row and column.
+fn read_block(image: &Image, x: u32, y: u32) -> Block {
+    let mut block = [[0; 4]; 16];
+    for (i, texel) in block.iter_mut().enumerate() {
+        let px = (x + i as u32 % 4).min(image.width - 1);
+        let py = (y + i as u32 / 4).min(image.height - 1);
+        *texel = image.pixel(px, py);
+    }
+    block
+}
+
+fn encode_color(block: &Block, out: &mut Vec<u8>) {
+    let (mut min, mut max) = ([255u8; 3], [0u8; 3]);
+    for texel in block.iter() {
+        for channel in 0..3 {
+            min[channel] = min[channel].min(texel[channel]);
+            max[channel] = max[channel].max(texel[channel]);
+        }
+    }
+    flip_anticorrelated(block, &mut min, &mut max);
+    // Pulling the ends in by 1/16 of the range lowers the average error.
+    for channel in 0..3 {
+        let inset = (max[channel] as i32 - min[channel] as i32) / 16;
+        min[channel] = (min[channel] as i32 + inset).clamp(0, 255) as u8;
+        max[channel] = (max[channel] as i32 - inset).clamp(0, 255) as u8;
+    }
+
+    let (mut c0, mut c1) = (to_565(max), to_565(min));
+    // c0 > c1 selects the four colour mode.
+    if c0 < c1 {
+        std::mem::swap(&mut c0, &mut c1);
+    }
+    let mut indices = 0u32;
+    if c0 != c1 {
+        let (p0, p1) = (from_565(c0), from_565(c1));
+        let palette = [
+            p0,
+            p1,
+            mix(p0, p1, 2, 1),
+            mix(p0, p1, 1, 2),
+        ];
+        for (i, texel) in block.iter().enumerate() {
+            let index = nearest(&palette, |color| distance(color, texel));
+            indices |= (index as u32) << (i * 2);
+        }
+    }
+    out.extend_from_slice(&c0.to_le_bytes());
+    out.extend_from_slice(&c1.to_le_bytes());
+    out.extend_from_slice(&indices.to_le_bytes());
+}
+
+/// The bounding box diagonal runs from min to max in every channel; when a channel falls
+/// while the widest one rises, swap its ends so the diagonal follows the colours.
+fn flip_anticorrelated(block: &Block, min: &mut [u8; 3], max: &mut [u8; 3]) {
+    let widest = (0..3).max_by_key(|&channel| max[channel] - min[channel]).unwrap();
+    let mean = |channel: usize| block.iter().map(|texel| texel[channel] as i32).sum::<i32>() / 16;
+    let widest_mean = mean(widest);
+    for channel in (0..3).filter(|&channel| channel != widest) {
+        let channel_mean = mean(channel);
+        let covariance: i32 = block
+            .iter()
+            .map(|texel| (texel[widest] as i32 - widest_mean) * (texel[channel] as i32 - channel_mean))
+            .sum();
+        if covariance < 0 {
+            std::mem::swap(&mut min[channel], &mut max[channel]);
+        }
+    }
+}
+
+fn encode_alpha(block: &Block, out: &mut Vec<u8>) {
+    let a0 = block.iter().map(|texel| texel[3]).max().unwrap();
+    let a1 = block.iter().map(|texel| texel[3]).min().unwrap();
+    let mut indices = 0u64;
+    if a0 != a1 {
+        // a0 > a1 selects six interpolated values between the ends.
+        let mut palette = [a0 as u32, a1 as u32, 0, 0, 0, 0, 0, 0];
+        for (i, value) in palette.iter_mut().enumerate().skip(2) {
+            let weight = i as u32 - 1;
+            *value = ((7 - weight) * a0 as u32 + weight * a1 as u32) / 7;
+        }
+        for (i, texel) in block.iter().enumerate() {
+            let index = nearest(&palette, |value| (*value as i32 - texel[3] as i32).unsigned_abs());
+            indices |= (index as u64) << (i * 3);
+        }
+    }
+    out.push(a0);
+    out.push(a1);
+    out.extend_from_slice(&indices.to_le_bytes()[..6]);
+}
+
+fn nearest<T>(palette: &[T], error: impl Fn(&T) -> u32) -> usize {
+    (0..palette.len()).min_by_key(|&index| error(&palette[index])).unwrap()
+}
+
+fn distance(color: &[u8; 3], texel: &[u8; 4]) -> u32 {
+    (0..3)
+        .map(|channel| {
+            let diff = color[channel] as i32 - texel[channel] as i32;
+            (diff * diff) as u32
+        })
+        .sum()
+}
+
+fn mix(a: [u8; 3], b: [u8; 3], weight_a: u32, weight_b: u32) -> [u8; 3] {
+    let mut color = [0; 3];
+    for channel in 0..3 {
+        color[channel] = ((a[channel] as u32 * weight_a + b[channel] as u32 * weight_b) / 3) as u8;
+    }
+    color
+}
+
+fn to_565(color: [u8; 3]) -> u16 {
+    let r = (color[0] as u16 * 31 + 127) / 255;
+    let g = (color[1] as u16 * 63 + 127) / 255;
+    let b = (color[2] as u16 * 31 + 127) / 255;
+    (r << 11) | (g << 5) | b
+}
+
+fn from_565(color: u16) -> [u8; 3] {
+    let r = (color >> 11) & 31;
+    let g = (color >> 5) & 63;
+    let b = color & 31;
+    [
+        ((r << 3) | (r >> 2)) as u8,
+        ((g << 2) | (g >> 4)) as u8,
+        ((b << 3) | (b >> 2)) as u8,
+    ]
+}
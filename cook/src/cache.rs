@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use engine_core::asset::COOKED_FORMAT_VERSION;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::CookError;
+use crate::meta::ImportSettings;
+
+/// Kept in the output directory, next to the cooked files it describes.
+pub const CACHE_FILE: &str = ".cook-cache.json";
+
+/// Part of every key, so a new tool release cooks everything again.
+const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CacheEntry {
+    pub key: String,
+    /// Written files, relative to the output directory.
+    pub outputs: Vec<PathBuf>,
+}
+
+/// What was cooked from which source, keyed by the source path relative to the source directory.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CookCache {
+    entries: BTreeMap<PathBuf, CacheEntry>,
+}
+
+impl CookCache {
+    /// A missing or unreadable cache just means everything is cooked again.
+    pub fn load(out_dir: &Path) -> Self {
+        let path = out_dir.join(CACHE_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => return CookCache::default(),
+        };
+        serde_json::from_str(&text).unwrap_or_else(|err| {
+            log::warn!("Ignoring unreadable cook cache {}: {}", path.display(), err);
+            CookCache::default()
+        })
+    }
+
+    pub fn save(&self, out_dir: &Path) -> Result<(), CookError> {
+        let json = serde_json::to_string_pretty(self).map_err(|err| CookError::Io(err.to_string()))?;
+        write_file(&out_dir.join(CACHE_FILE), json.as_bytes())
+    }
+
+    /// True when `source` was last cooked with the same key and its outputs are still there.
+    pub fn is_fresh(&self, source: &Path, key: &str, out_dir: &Path) -> bool {
+        self.entries
+            .get(source)
+            .is_some_and(|entry| entry.key == key && entry.outputs.iter().all(|output| out_dir.join(output).is_file()))
+    }
+
+    pub fn get(&self, source: &Path) -> Option<&CacheEntry> {
+        self.entries.get(source)
+    }
+
+    pub fn insert(&mut self, source: PathBuf, entry: CacheEntry) {
+        self.entries.insert(source, entry);
+    }
+
+    pub fn remove(&mut self, source: &Path) -> Option<CacheEntry> {
+        self.entries.remove(source)
+    }
+
+    pub fn sources(&self) -> Vec<PathBuf> {
+        self.entries.keys().cloned().collect()
+    }
+}
+
+/// Hash of everything a cooked result depends on: tool and format version, import settings,
+/// the source bytes and the bytes of every file the source references.
+pub struct ContentKey(blake3::Hasher);
+
+impl ContentKey {
+    pub fn new(settings: &ImportSettings) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(TOOL_VERSION.as_bytes());
+        hasher.update(&COOKED_FORMAT_VERSION.to_le_bytes());
+        // Field order is fixed by the struct definitions, so this is stable across runs.
+        let settings = serde_json::to_vec(settings).expect("import settings always serialize");
+        let mut key = ContentKey(hasher);
+        key.add("settings", &settings);
+        key
+    }
+
+    pub fn add(&mut self, name: &str, bytes: &[u8]) {
+        // Length prefixes keep ("ab", "c") and ("a", "bc") apart.
+        for part in [name.as_bytes(), bytes] {
+            self.0.update(&(part.len() as u64).to_le_bytes());
+            self.0.update(part);
+        }
+    }
+
+    pub fn finish(&self) -> String {
+        self.0.finalize().to_hex().to_string()
+    }
+}
+
+/// Writes through a temporary file so an interrupted cook never leaves a truncated output.
+pub fn write_file(path: &Path, bytes: &[u8]) -> Result<(), CookError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| CookError::io(parent, err))?;
+    }
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    fs::write(&temp, bytes).map_err(|err| CookError::io(path, err))?;
+    fs::rename(&temp, path).map_err(|err| CookError::io(path, err))
+}
@@ -0,0 +1,221 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use engine_core::material::SHADER_TEMPLATE_EXTENSION;
+
+use crate::cache::{write_file, CacheEntry, ContentKey, CookCache};
+use crate::error::CookError;
+use crate::meta::{self, ImportSettings, META_EXTENSION};
+use crate::mesh::{cook_mesh, dependencies};
+use crate::shader::compile_shader;
+use crate::template::{cook_template, template_dependencies};
+use crate::texture::cook_texture;
+
+pub struct CookOptions {
+    pub source: PathBuf,
+    pub out: PathBuf,
+    /// Cook every source even when the cache says it is up to date.
+    pub force: bool,
+    /// Save default `.meta` files for sources that have none.
+    pub write_meta: bool,
+    /// Worker threads, 0 uses every core.
+    pub jobs: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct CookReport {
+    pub cooked: usize,
+    pub skipped: usize,
+    /// Outputs of sources that no longer exist.
+    pub removed: usize,
+    pub failed: Vec<(PathBuf, CookError)>,
+}
+
+enum Outcome {
+    Skipped,
+    Cooked(CacheEntry),
+    Failed(CookError),
+}
+
+/// Cooks every file below `options.source` into `options.out`, skipping sources whose content
+/// key matches the last cook. A failing source is reported and the rest still cook.
+pub fn cook(options: &CookOptions) -> Result<CookReport, CookError> {
+    if !options.source.is_dir() {
+        return Err(CookError::Io(format!("{} is not a directory", options.source.display())));
+    }
+    fs::create_dir_all(&options.out).map_err(|err| CookError::io(&options.out, err))?;
+    let out_dir = options.out.canonicalize().map_err(|err| CookError::io(&options.out, err))?;
+    let mut sources = Vec::new();
+    collect_sources(&options.source, Path::new(""), &out_dir, &mut sources)?;
+
+    let mut cache = CookCache::load(&options.out);
+    let outcomes = cook_all(options, &cache, &sources);
+
+    let mut report = CookReport::default();
+    for (source, outcome) in sources.iter().zip(outcomes) {
+        match outcome {
+            Outcome::Skipped => report.skipped += 1,
+            Outcome::Cooked(entry) => {
+                // Outputs the new cook no longer writes, e.g. after renaming a shader stage.
+                if let Some(old) = cache.get(source) {
+                    for stale in old.outputs.iter().filter(|output| !entry.outputs.contains(output)) {
+                        remove_output(&options.out, stale);
+                    }
+                }
+                cache.insert(source.clone(), entry);
+                report.cooked += 1;
+            }
+            Outcome::Failed(err) => {
+                // Forget the old key so the source is tried again next time.
+                cache.remove(source);
+                report.failed.push((source.clone(), err));
+            }
+        }
+    }
+    for source in cache.sources() {
+        if !sources.contains(&source) {
+            for output in cache.remove(&source).unwrap().outputs {
+                remove_output(&options.out, &output);
+                report.removed += 1;
+            }
+        }
+    }
+    cache.save(&options.out)?;
+    Ok(report)
+}
+
+fn cook_all(options: &CookOptions, cache: &CookCache, sources: &[PathBuf]) -> Vec<Outcome> {
+    let jobs = match options.jobs {
+        0 => thread::available_parallelism().map_or(1, |count| count.get()),
+        jobs => jobs,
+    };
+    let next = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<Option<Outcome>>> = Mutex::new(sources.iter().map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(sources.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let source = match sources.get(index) {
+                    Some(source) => source,
+                    None => break,
+                };
+                let outcome = cook_source(options, cache, source);
+                outcomes.lock().unwrap()[index] = Some(outcome);
+            });
+        }
+    });
+    outcomes.into_inner().unwrap().into_iter().map(Option::unwrap).collect()
+}
+
+fn cook_source(options: &CookOptions, cache: &CookCache, source: &Path) -> Outcome {
+    let path = options.source.join(source);
+    let result = meta::load_settings(&path, options.write_meta).and_then(|settings| {
+        let bytes = fs::read(&path).map_err(|err| CookError::io(&path, err))?;
+        let key = content_key(&path, &settings, &bytes)?;
+        if !options.force && cache.is_fresh(source, &key, &options.out) {
+            return Ok(None);
+        }
+        let outputs = cook_bytes(source, &path, &settings, &bytes)?;
+        for (output, data) in outputs.iter() {
+            write_file(&options.out.join(output), data)?;
+        }
+        log::info!("Cooked {}", source.display());
+        Ok(Some(CacheEntry {
+            key,
+            outputs: outputs.into_iter().map(|(output, _)| output).collect(),
+        }))
+    });
+    match result {
+        Ok(Some(entry)) => Outcome::Cooked(entry),
+        Ok(None) => Outcome::Skipped,
+        Err(err) => {
+            log::error!("Failed to cook {}: {}", source.display(), err);
+            Outcome::Failed(err)
+        }
+    }
+}
+
+fn content_key(path: &Path, settings: &ImportSettings, bytes: &[u8]) -> Result<String, CookError> {
+    let mut key = ContentKey::new(settings);
+    key.add("source", bytes);
+    let dependencies = match settings {
+        ImportSettings::Mesh(_) => dependencies(bytes)?,
+        ImportSettings::ShaderTemplate(_) => template_dependencies(bytes)?,
+        _ => Vec::new(),
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    for dependency in dependencies {
+        let dependency_path = dir.join(&dependency);
+        let dependency_bytes = fs::read(&dependency_path).map_err(|err| CookError::io(&dependency_path, err))?;
+        key.add(&dependency.to_string_lossy(), &dependency_bytes);
+    }
+    Ok(key.finish())
+}
+
+/// The files to write for one source, relative to the output directory.
+fn cook_bytes(
+    source: &Path,
+    path: &Path,
+    settings: &ImportSettings,
+    bytes: &[u8],
+) -> Result<Vec<(PathBuf, Vec<u8>)>, CookError> {
+    Ok(match settings {
+        ImportSettings::Texture(settings) => {
+            vec![(source.with_extension("dtex"), cook_texture(bytes, settings)?.to_bytes())]
+        }
+        ImportSettings::Mesh(settings) => {
+            vec![(source.with_extension("dmesh"), cook_mesh(path, bytes, settings)?.to_bytes())]
+        }
+        ImportSettings::Shader(settings) => {
+            let text = std::str::from_utf8(bytes).map_err(|err| CookError::Shader(err.to_string()))?;
+            let words = compile_shader(path, text, settings)?;
+            let mut output = source.as_os_str().to_owned();
+            // Keep the stage in the name: lit.vert and lit.frag cook to different files.
+            output.push(".spv");
+            vec![(PathBuf::from(output), words.iter().flat_map(|word| word.to_le_bytes()).collect())]
+        }
+        ImportSettings::ShaderTemplate(settings) => {
+            let template = cook_template(path, bytes, settings)?;
+            let bytes = template.to_bytes().map_err(|err| CookError::Shader(err.to_string()))?;
+            vec![(source.with_extension(SHADER_TEMPLATE_EXTENSION), bytes)]
+        }
+        ImportSettings::Copy => vec![(source.to_path_buf(), bytes.to_vec())],
+    })
+}
+
+/// Source files below `dir`, relative to the source root, in a stable order. Hidden files,
+/// `.meta` files and the output directory are left out.
+fn collect_sources(dir: &Path, relative: &Path, out_dir: &Path, sources: &mut Vec<PathBuf>) -> Result<(), CookError> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<Result<_, _>>())
+        .map_err(|err| CookError::io(dir, err))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        let path = entry.path();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            if path.canonicalize().is_ok_and(|path| path == out_dir) {
+                continue;
+            }
+            collect_sources(&path, &relative.join(&name), out_dir, sources)?;
+        } else if path.extension().is_none_or(|extension| extension != META_EXTENSION) {
+            sources.push(relative.join(&name));
+        }
+    }
+    Ok(())
+}
+
+fn remove_output(out_dir: &Path, output: &Path) {
+    let path = out_dir.join(output);
+    match fs::remove_file(&path) {
+        Ok(()) => log::info!("Removed stale {}", output.display()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => log::warn!("Cannot remove stale {}: {}", path.display(), err),
+    }
+}
@@ -0,0 +1,32 @@
+use std::fmt::Display;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum CookError {
+    Io(String),
+    /// A `.meta` file that does not parse or does not fit the asset.
+    Meta(String),
+    Texture(String),
+    Mesh(String),
+    Shader(String),
+}
+
+impl CookError {
+    pub fn io(path: &Path, err: std::io::Error) -> Self {
+        CookError::Io(format!("{}: {}", path.display(), err))
+    }
+}
+
+impl Display for CookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CookError::Io(err) => write!(f, "{}", err),
+            CookError::Meta(err) => write!(f, "invalid import settings: {}", err),
+            CookError::Texture(err) => write!(f, "cannot cook texture: {}", err),
+            CookError::Mesh(err) => write!(f, "cannot cook mesh: {}", err),
+            CookError::Shader(err) => write!(f, "cannot compile shader: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CookError {}
@@ -0,0 +1,101 @@
+mod bc;
+mod cache;
+mod cook;
+mod error;
+mod mesh;
+mod meta;
+mod pack;
+mod reflect;
+mod shader;
+mod template;
+mod texture;
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use engine_core::logging;
+
+use crate::cook::{cook, CookOptions};
+use crate::pack::write_pack;
+
+#[derive(Parser, Debug)]
+#[clap(
+    name = "daybreak-cook",
+    version,
+    about = "Converts source assets into the formats the engine loads at runtime"
+)]
+struct Cli {
+    /// Source asset directory
+    #[clap(default_value = "assets")]
+    source: PathBuf,
+
+    /// Output directory for cooked assets
+    #[clap(long, short, default_value = "cooked", value_name = "DIR")]
+    out: PathBuf,
+
+    /// Cook every asset, even those the cache says are up to date
+    #[clap(long)]
+    force: bool,
+
+    /// Do not save default .meta files next to sources without one
+    #[clap(long)]
+    no_write_meta: bool,
+
+    /// Worker threads, 0 uses every core
+    #[clap(long, short, default_value = "0", value_name = "N")]
+    jobs: usize,
+
+    /// Also pack the cooked output into a read-only archive, e.g. `project/assets.dpak`
+    #[clap(long, value_name = "FILE")]
+    pack: Option<PathBuf>,
+
+    /// Directory inside the archive the cooked files are stored below
+    #[clap(long, default_value = "assets", value_name = "DIR")]
+    pack_prefix: String,
+
+    /// Store files in the archive uncompressed
+    #[clap(long)]
+    no_compress: bool,
+}
+
+fn main() {
+    logging::init(log::LevelFilter::Info).unwrap();
+    let cli = Cli::parse();
+
+    let options = CookOptions {
+        source: cli.source,
+        out: cli.out.clone(),
+        force: cli.force,
+        write_meta: !cli.no_write_meta,
+        jobs: cli.jobs,
+    };
+    let report = match cook(&options) {
+        Ok(report) => report,
+        Err(err) => {
+            log::error!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    log::info!(
+        "Cooked {}, up to date {}, removed {}, failed {}",
+        report.cooked,
+        report.skipped,
+        report.removed,
+        report.failed.len()
+    );
+    if !report.failed.is_empty() {
+        for (source, err) in report.failed.iter() {
+            log::error!("{}: {}", source.display(), err);
+        }
+        std::process::exit(1);
+    }
+    if let Some(pack) = &cli.pack {
+        match write_pack(&cli.out, pack, &cli.pack_prefix, !cli.no_compress) {
+            Ok(count) => log::info!("Packed {} files into {}", count, pack.display()),
+            Err(err) => {
+                log::error!("Cannot write {}: {}", pack.display(), err);
+                std::process::exit(2);
+            }
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::path::{Path, PathBuf};
+
+use engine_core::asset::{CookedMesh, Submesh, Vertex};
+use engine_core::glam::{Mat4, Vec2, Vec3};
+use gltf::buffer::Source;
+use gltf::mesh::Mode;
+use gltf::{Gltf, Node};
+
+use crate::error::CookError;
+use crate::meta::MeshSettings;
+
+/// External buffer files a `.gltf` reads, relative to its directory. Data URIs and `.glb`
+/// blobs are part of the source itself.
+pub fn dependencies(bytes: &[u8]) -> Result<Vec<PathBuf>, CookError> {
+    let gltf = Gltf::from_slice(bytes).map_err(|err| CookError::Mesh(err.to_string()))?;
+    Ok(gltf
+        .buffers()
+        .filter_map(|buffer| match buffer.source() {
+            Source::Uri(uri) if !uri.starts_with("data:") => Some(PathBuf::from(uri)),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Flattens every mesh the default scene places into one vertex and index buffer, one submesh
+/// per primitive, with the node transforms baked in.
+pub fn cook_mesh(path: &Path, bytes: &[u8], settings: &MeshSettings) -> Result<CookedMesh, CookError> {
+    let Gltf { document, blob } = Gltf::from_slice(bytes).map_err(|err| CookError::Mesh(err.to_string()))?;
+    let buffers = gltf::import_buffers(&document, path.parent(), blob)
+        .map_err(|err| CookError::Mesh(format!("cannot read buffers: {}", err)))?;
+
+    let mut mesh = CookedMesh {
+        vertices: Vec::new(),
+        indices: Vec::new(),
+        submeshes: Vec::new(),
+        bounds_min: [0.0; 3],
+        bounds_max: [0.0; 3],
+    };
+    let root = Mat4::from_scale(Vec3::splat(settings.scale));
+    let mut context = Context {
+        buffers: &buffers,
+        settings,
+        mesh: &mut mesh,
+    };
+    match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(scene) => {
+            for node in scene.nodes() {
+                context.add_node(&node, root)?;
+            }
+        }
+        // A file without scenes still has meshes worth cooking.
+        None => {
+            for source in document.meshes() {
+                context.add_mesh(&source, root)?;
+            }
+        }
+    }
+    if mesh.indices.is_empty() {
+        return Err(CookError::Mesh("no triangles in the default scene".to_string()));
+    }
+
+    let mut min = Vec3::splat(f32::MAX);
+    let mut max = Vec3::splat(f32::MIN);
+    for vertex in mesh.vertices.iter() {
+        min = min.min(Vec3::from(vertex.position));
+        max = max.max(Vec3::from(vertex.position));
+    }
+    mesh.bounds_min = min.into();
+    mesh.bounds_max = max.into();
+    Ok(mesh)
+}
+
+struct Context<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    settings: &'a MeshSettings,
+    mesh: &'a mut CookedMesh,
+}
+
+impl Context<'_> {
+    fn add_node(&mut self, node: &Node, parent: Mat4) -> Result<(), CookError> {
+        let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+        if let Some(source) = node.mesh() {
+            self.add_mesh(&source, transform)?;
+        }
+        for child in node.children() {
+            self.add_node(&child, transform)?;
+        }
+        Ok(())
+    }
+
+    fn add_mesh(&mut self, source: &gltf::Mesh, transform: Mat4) -> Result<(), CookError> {
+        let normal_matrix = transform.inverse().transpose();
+        // Mirroring transforms turn triangles inside out.
+        let mirrored = transform.determinant() < 0.0;
+
+        for primitive in source.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                log::warn!(
+                    "Skipping {:?} primitive of mesh '{}', only triangles are cooked",
+                    primitive.mode(),
+                    source.name().unwrap_or("")
+                );
+                continue;
+            }
+            let reader = primitive.reader(|buffer| self.buffers.get(buffer.index()).map(|data| &data.0[..]));
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .ok_or_else(|| CookError::Mesh("primitive without positions".to_string()))?
+                .collect();
+            let mut vertices: Vec<Vertex> = positions
+                .iter()
+                .map(|position| Vertex {
+                    position: *position,
+                    tangent: [0.0, 0.0, 0.0, 1.0],
+                    ..Vertex::default()
+                })
+                .collect();
+            if let Some(uvs) = reader.read_tex_coords(0) {
+                for (vertex, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+                    vertex.uv = if self.settings.flip_uvs { [uv[0], 1.0 - uv[1]] } else { uv };
+                }
+            }
+            let mut indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..vertices.len() as u32).collect(),
+            };
+            if let Some(index) = indices.iter().find(|&&index| index as usize >= vertices.len()) {
+                return Err(CookError::Mesh(format!("index {} out of {} vertices", index, vertices.len())));
+            }
+            match reader.read_normals() {
+                Some(normals) => vertices.iter_mut().zip(normals).for_each(|(vertex, normal)| vertex.normal = normal),
+                None => generate_normals(&mut vertices, &indices),
+            }
+            match reader.read_tangents() {
+                Some(tangents) => vertices
+                    .iter_mut()
+                    .zip(tangents)
+                    .for_each(|(vertex, tangent)| vertex.tangent = tangent),
+                None => generate_tangents(&mut vertices, &indices),
+            }
+
+            for vertex in vertices.iter_mut() {
+                vertex.position = transform.transform_point3(vertex.position.into()).into();
+                vertex.normal = normal_matrix.transform_vector3(vertex.normal.into()).normalize_or_zero().into();
+                let tangent = transform
+                    .transform_vector3(Vec3::new(vertex.tangent[0], vertex.tangent[1], vertex.tangent[2]))
+                    .normalize_or_zero();
+                let handedness = if mirrored { -vertex.tangent[3] } else { vertex.tangent[3] };
+                vertex.tangent = [tangent.x, tangent.y, tangent.z, handedness];
+            }
+            if mirrored {
+                indices.chunks_exact_mut(3).for_each(|triangle| triangle.swap(1, 2));
+            }
+
+            let base = self.mesh.vertices.len() as u32;
+            self.mesh.submeshes.push(Submesh {
+                first_index: self.mesh.indices.len() as u32,
+                index_count: indices.len() as u32,
+                material: primitive.material().index().map(|index| index as u32),
+            });
+            self.mesh.indices.extend(indices.iter().map(|index| base + index));
+            self.mesh.vertices.extend(vertices);
+        }
+        Ok(())
+    }
+}
+
+/// Smooth normals from the area weighted face normals.
+fn generate_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut normals = vec![Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] =
+            [triangle[0], triangle[1], triangle[2]].map(|index| Vec3::from(vertices[index as usize].position));
+        let face = (b - a).cross(c - a);
+        triangle.iter().for_each(|&index| normals[index as usize] += face);
+    }
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        vertex.normal = normal.normalize_or_zero().into();
+    }
+}
+
+/// Per-vertex tangents from the UV gradients of the adjacent triangles.
+fn generate_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut tangents = vec![Vec3::ZERO; vertices.len()];
+    let mut bitangents = vec![Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| vertices[index as usize]);
+        let edge1 = Vec3::from(b.position) - Vec3::from(a.position);
+        let edge2 = Vec3::from(c.position) - Vec3::from(a.position);
+        let duv1 = Vec2::from(b.uv) - Vec2::from(a.uv);
+        let duv2 = Vec2::from(c.uv) - Vec2::from(a.uv);
+        let det = duv1.x * duv2.y - duv2.x * duv1.y;
+        if det.abs() < f32::EPSILON {
+            continue;
+        }
+        let tangent = (edge1 * duv2.y - edge2 * duv1.y) / det;
+        let bitangent = (edge2 * duv1.x - edge1 * duv2.x) / det;
+        for &index in triangle {
+            tangents[index as usize] += tangent;
+            bitangents[index as usize] += bitangent;
+        }
+    }
+    for (i, vertex) in vertices.iter_mut().enumerate() {
+        let normal = Vec3::from(vertex.normal);
+        // Gram-Schmidt against the normal, falling back to any perpendicular axis.
+        let mut tangent = (tangents[i] - normal * normal.dot(tangents[i])).normalize_or_zero();
+        if tangent == Vec3::ZERO {
+            tangent = normal.any_orthonormal_vector();
+        }
+        let handedness = if normal.cross(tangent).dot(bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+        vertex.tangent = [tangent.x, tangent.y, tangent.z, handedness];
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::CookError;
+
+pub const META_EXTENSION: &str = "meta";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    Texture,
+    Mesh,
+    Shader,
+    /// A `.shader` program: stages plus the keywords to cook variants for.
+    ShaderTemplate,
+    /// Anything else is copied to the output unchanged.
+    Copy,
+}
+
+impl AssetKind {
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+        match extension.as_str() {
+            "png" => AssetKind::Texture,
+            "gltf" | "glb" => AssetKind::Mesh,
+            "vert" | "frag" | "comp" | "wgsl" => AssetKind::Shader,
+            "shader" => AssetKind::ShaderTemplate,
+            _ => AssetKind::Copy,
+        }
+    }
+}
+
+/// Contents of the `.meta` file next to a source asset, e.g. `wood.png.meta`:
+///
+/// ```yaml
+/// texture:
+///   format: bc1
+///   srgb: true
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportSettings {
+    Texture(TextureSettings),
+    Mesh(MeshSettings),
+    Shader(ShaderSettings),
+    /// Defines shared by every variant of a `.shader` template.
+    ShaderTemplate(ShaderSettings),
+    Copy,
+}
+
+impl ImportSettings {
+    pub fn default_for(kind: AssetKind) -> Self {
+        match kind {
+            AssetKind::Texture => ImportSettings::Texture(TextureSettings::default()),
+            AssetKind::Mesh => ImportSettings::Mesh(MeshSettings::default()),
+            AssetKind::Shader => ImportSettings::Shader(ShaderSettings::default()),
+            AssetKind::ShaderTemplate => ImportSettings::ShaderTemplate(ShaderSettings::default()),
+            AssetKind::Copy => ImportSettings::Copy,
+        }
+    }
+
+    fn kind(&self) -> AssetKind {
+        match self {
+            ImportSettings::Texture(_) => AssetKind::Texture,
+            ImportSettings::Mesh(_) => AssetKind::Mesh,
+            ImportSettings::Shader(_) => AssetKind::Shader,
+            ImportSettings::ShaderTemplate(_) => AssetKind::ShaderTemplate,
+            ImportSettings::Copy => AssetKind::Copy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureCompression {
+    /// BC3 when any pixel is translucent, BC1 otherwise.
+    Auto,
+    Rgba8,
+    Bc1,
+    Bc3,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TextureSettings {
+    pub format: TextureCompression,
+    /// Colour data rather than normals, masks or other linear values.
+    pub srgb: bool,
+    pub mipmaps: bool,
+}
+
+impl Default for TextureSettings {
+    fn default() -> Self {
+        TextureSettings {
+            format: TextureCompression::Auto,
+            srgb: true,
+            mipmaps: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MeshSettings {
+    /// Uniform scale applied on top of the node transforms, e.g. 0.01 for centimetre sources.
+    pub scale: f32,
+    pub flip_uvs: bool,
+}
+
+impl Default for MeshSettings {
+    fn default() -> Self {
+        MeshSettings {
+            scale: 1.0,
+            flip_uvs: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ShaderSettings {
+    /// Preprocessor defines for GLSL sources.
+    pub defines: BTreeMap<String, String>,
+}
+
+pub fn meta_path(source: &Path) -> PathBuf {
+    let mut path = source.as_os_str().to_owned();
+    path.push(".");
+    path.push(META_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Reads the import settings of `source`. Without a `.meta` file the defaults are used and,
+/// with `write_missing`, saved next to the source so they can be edited and committed.
+pub fn load_settings(source: &Path, write_missing: bool) -> Result<ImportSettings, CookError> {
+    let kind = AssetKind::from_path(source);
+    let path = meta_path(source);
+    if !path.is_file() {
+        let settings = ImportSettings::default_for(kind);
+        if write_missing && kind != AssetKind::Copy {
+            let yaml = serde_yaml::to_string(&settings).map_err(|err| CookError::Meta(err.to_string()))?;
+            fs::write(&path, yaml).map_err(|err| CookError::io(&path, err))?;
+            log::info!("Wrote default import settings to {}", path.display());
+        }
+        return Ok(settings);
+    }
+
+    let text = fs::read_to_string(&path).map_err(|err| CookError::io(&path, err))?;
+    let settings: ImportSettings =
+        serde_yaml::from_str(&text).map_err(|err| CookError::Meta(format!("{}: {}", path.display(), err)))?;
+    if settings.kind() != kind {
+        return Err(CookError::Meta(format!(
+            "{} holds {:?} settings but the source is a {:?} asset",
+            path.display(),
+            settings.kind(),
+            kind
+        )));
+    }
+    Ok(settings)
+}
@@ -0,0 +1,35 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use engine_core::vfs::{PackCompression, PackWriter};
+
+use crate::cache::CookCache;
+use crate::error::CookError;
+
+/// Packs every output the cook cache of `out_dir` records into the archive `pack`, each
+/// stored below `prefix`, so mounting the archive at the project root shadows the directory
+/// the engine would otherwise load them from. Returns the number of files packed.
+pub fn write_pack(out_dir: &Path, pack: &Path, prefix: &str, compress: bool) -> Result<usize, CookError> {
+    let cache = CookCache::load(out_dir);
+    let outputs: BTreeSet<PathBuf> = cache
+        .sources()
+        .iter()
+        .filter_map(|source| cache.get(source))
+        .flat_map(|entry| entry.outputs.iter().cloned())
+        .collect();
+    let compression = if compress { PackCompression::Deflate } else { PackCompression::None };
+
+    let mut writer = PackWriter::create(pack).map_err(|err| CookError::Io(err.to_string()))?;
+    for output in outputs.iter() {
+        let path = out_dir.join(output);
+        let bytes = fs::read(&path).map_err(|err| CookError::io(&path, err))?;
+        let name = Path::new(prefix).join(output);
+        let name: Vec<_> = name.iter().map(|part| part.to_string_lossy()).collect();
+        writer
+            .add(&name.join("/"), &bytes, compression)
+            .map_err(|err| CookError::Io(err.to_string()))?;
+    }
+    writer.finish().map_err(|err| CookError::Io(err.to_string()))?;
+    Ok(outputs.len())
+}
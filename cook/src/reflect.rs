@@ -0,0 +1,102 @@
+use engine_core::material::{BindingKind, BlockLayout, BlockMember, ParamType, ShaderBinding, ShaderLayout, ShaderStage};
+use naga::valid::ModuleInfo;
+use naga::{ImageClass, Module, ScalarKind, StorageClass, Type, TypeInner, VectorSize};
+
+use crate::error::CookError;
+
+/// The descriptor bindings and push constants the entry point of `module` actually uses.
+/// Declared but unused resources are left out, so variants that compile a feature away do
+/// not ask for its textures.
+pub fn reflect(module: &Module, info: &ModuleInfo, stage: ShaderStage) -> Result<ShaderLayout, CookError> {
+    let (index, _) = module
+        .entry_points
+        .iter()
+        .enumerate()
+        .find(|(_, entry_point)| convert_stage(entry_point.stage) == stage)
+        .ok_or_else(|| CookError::Shader(format!("no {:?} entry point", stage)))?;
+    let uses = info.get_entry_point(index);
+
+    let mut layout = ShaderLayout::default();
+    for (handle, global) in module.global_variables.iter() {
+        if uses[handle].is_empty() {
+            continue;
+        }
+        let ty = &module.types[global.ty];
+        if global.class == StorageClass::PushConstant {
+            layout.push_constants = Some(block_layout(module, ty));
+            layout.push_constant_stages.push(stage);
+            continue;
+        }
+        let binding = match &global.binding {
+            Some(binding) => binding,
+            None => continue,
+        };
+        let kind = match (global.class, &ty.inner) {
+            (StorageClass::Uniform, _) => BindingKind::UniformBuffer(block_layout(module, ty)),
+            (StorageClass::Storage { .. }, _) => BindingKind::StorageBuffer,
+            (StorageClass::Handle, TypeInner::Sampler { .. }) => BindingKind::Sampler,
+            (StorageClass::Handle, TypeInner::Image { class: ImageClass::Storage { .. }, .. }) => {
+                BindingKind::StorageTexture
+            }
+            (StorageClass::Handle, TypeInner::Image { .. }) => BindingKind::Texture,
+            _ => continue,
+        };
+        let name = global.name.clone().or_else(|| ty.name.clone()).unwrap_or_default();
+        layout.bindings.push(ShaderBinding {
+            set: binding.group,
+            binding: binding.binding,
+            name,
+            kind,
+            stages: vec![stage],
+        });
+    }
+    layout.bindings.sort_by_key(|binding| (binding.set, binding.binding));
+    Ok(layout)
+}
+
+pub fn convert_stage(stage: naga::ShaderStage) -> ShaderStage {
+    match stage {
+        naga::ShaderStage::Vertex => ShaderStage::Vertex,
+        naga::ShaderStage::Fragment => ShaderStage::Fragment,
+        naga::ShaderStage::Compute => ShaderStage::Compute,
+    }
+}
+
+fn block_layout(module: &Module, ty: &Type) -> BlockLayout {
+    match &ty.inner {
+        TypeInner::Struct { members, span } => BlockLayout {
+            size: *span,
+            members: members
+                .iter()
+                .map(|member| BlockMember {
+                    name: member.name.clone().unwrap_or_default(),
+                    offset: member.offset,
+                    ty: param_type(module, &module.types[member.ty].inner),
+                })
+                .collect(),
+        },
+        // A block of one bare value, e.g. a push constant `uint`.
+        inner => BlockLayout {
+            size: inner.span(&module.constants),
+            members: Vec::new(),
+        },
+    }
+}
+
+fn param_type(module: &Module, inner: &TypeInner) -> ParamType {
+    match inner {
+        TypeInner::Scalar { kind: ScalarKind::Float, width: 4 } => ParamType::Float,
+        TypeInner::Scalar { kind: ScalarKind::Sint, width: 4 } => ParamType::Int,
+        TypeInner::Scalar { kind: ScalarKind::Uint, width: 4 } => ParamType::UInt,
+        TypeInner::Scalar { kind: ScalarKind::Bool, .. } => ParamType::Bool,
+        TypeInner::Vector { size, kind: ScalarKind::Float, width: 4 } => match size {
+            VectorSize::Bi => ParamType::Vec2,
+            VectorSize::Tri => ParamType::Vec3,
+            VectorSize::Quad => ParamType::Vec4,
+        },
+        TypeInner::Matrix { columns: VectorSize::Quad, rows: VectorSize::Quad, width: 4 } => ParamType::Mat4,
+        other => ParamType::Other {
+            size: other.span(&module.constants),
+        },
+    }
+}
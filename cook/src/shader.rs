@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use naga::back::spv;
+use naga::front::{glsl, wgsl};
+use naga::valid::{Capabilities, ModuleInfo, ValidationFlags, Validator};
+use naga::{Module, ShaderStage};
+
+use crate::error::CookError;
+use crate::meta::ShaderSettings;
+
+/// Compiles a GLSL stage (`.vert`, `.frag`, `.comp`) or a WGSL module to SPIR-V for Vulkan.
+pub fn compile_shader(path: &Path, source: &str, settings: &ShaderSettings) -> Result<Vec<u32>, CookError> {
+    let (module, info) = parse_shader(path, source, &settings.defines)?;
+    write_spirv(&module, &info)
+}
+
+/// Parses and validates a stage with `defines` set, for callers that also reflect on it.
+pub fn parse_shader(
+    path: &Path,
+    source: &str,
+    defines: &BTreeMap<String, String>,
+) -> Result<(Module, ModuleInfo), CookError> {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+    let module = match extension {
+        "wgsl" => wgsl::parse_str(source).map_err(|err| CookError::Shader(err.emit_to_string(source)))?,
+        _ => parse_glsl(extension, source, defines)?,
+    };
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|err| CookError::Shader(format!("validation failed: {}", err)))?;
+    Ok((module, info))
+}
+
+pub fn write_spirv(module: &Module, info: &ModuleInfo) -> Result<Vec<u32>, CookError> {
+    // The sources already use Vulkan's clip space, so no Y flip.
+    let options = spv::Options {
+        flags: spv::WriterFlags::LABEL_VARYINGS,
+        ..spv::Options::default()
+    };
+    spv::write_vec(module, info, &options, None).map_err(|err| CookError::Shader(err.to_string()))
+}
+
+fn parse_glsl(extension: &str, source: &str, defines: &BTreeMap<String, String>) -> Result<Module, CookError> {
+    let stage = match extension {
+        "vert" => ShaderStage::Vertex,
+        "frag" => ShaderStage::Fragment,
+        "comp" => ShaderStage::Compute,
+        _ => return Err(CookError::Shader(format!("unknown shader stage '.{}'", extension))),
+    };
+    let mut options = glsl::Options::from(stage);
+    options.defines.extend(defines.clone());
+    glsl::Parser::default().parse(&options, source).map_err(|errors| {
+        let messages: Vec<String> = errors
+            .iter()
+            .map(|err| match err.meta.to_range() {
+                Some(range) => format!("line {}: {}", line_of(source, range.start), err.kind),
+                None => err.kind.to_string(),
+            })
+            .collect();
+        CookError::Shader(messages.join("; "))
+    })
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
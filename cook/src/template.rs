@@ -0,0 +1,147 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use engine_core::material::{
+    ShaderLayout, ShaderStage, ShaderStageCode, ShaderTemplate, ShaderVariant, MAX_SHADER_KEYWORDS,
+};
+use serde_derive::Deserialize;
+
+use crate::error::CookError;
+use crate::meta::ShaderSettings;
+use crate::reflect::{convert_stage, reflect};
+use crate::shader::{parse_shader, write_spirv};
+
+/// A `.shader` file, e.g. `shaders/lit.shader`:
+///
+/// ```yaml
+/// vertex: lit.vert
+/// fragment: lit.frag
+/// keywords: [NORMAL_MAP, ALPHA_TEST]
+/// ```
+///
+/// Stage paths are relative to the `.shader` file. Every combination of keywords is compiled,
+/// with the enabled ones defined as `1`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateSource {
+    vertex: Option<PathBuf>,
+    fragment: Option<PathBuf>,
+    compute: Option<PathBuf>,
+    #[serde(default)]
+    keywords: Vec<String>,
+}
+
+impl TemplateSource {
+    fn parse(bytes: &[u8]) -> Result<Self, CookError> {
+        let source: TemplateSource = serde_yaml::from_slice(bytes)
+            .map_err(|err| CookError::Shader(format!("invalid shader template: {}", err)))?;
+        if source.stages().is_empty() {
+            return Err(CookError::Shader("shader template names no stages".to_string()));
+        }
+        if source.compute.is_some() && (source.vertex.is_some() || source.fragment.is_some()) {
+            return Err(CookError::Shader("a compute template cannot also have graphics stages".to_string()));
+        }
+        if source.keywords.len() > MAX_SHADER_KEYWORDS {
+            return Err(CookError::Shader(format!(
+                "{} keywords would cook {} variants, at most {} keywords are allowed",
+                source.keywords.len(),
+                1u64 << source.keywords.len(),
+                MAX_SHADER_KEYWORDS
+            )));
+        }
+        for (index, keyword) in source.keywords.iter().enumerate() {
+            let valid = keyword.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && keyword.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(CookError::Shader(format!("keyword '{}' is not a valid preprocessor name", keyword)));
+            }
+            if source.keywords[..index].contains(keyword) {
+                return Err(CookError::Shader(format!("keyword '{}' is listed twice", keyword)));
+            }
+        }
+        Ok(source)
+    }
+
+    fn stages(&self) -> Vec<(ShaderStage, &Path)> {
+        [
+            (ShaderStage::Vertex, &self.vertex),
+            (ShaderStage::Fragment, &self.fragment),
+            (ShaderStage::Compute, &self.compute),
+        ]
+        .into_iter()
+        .filter_map(|(stage, path)| path.as_deref().map(|path| (stage, path)))
+        .collect()
+    }
+}
+
+/// Stage files a `.shader` compiles, relative to its directory.
+pub fn template_dependencies(bytes: &[u8]) -> Result<Vec<PathBuf>, CookError> {
+    let source = TemplateSource::parse(bytes)?;
+    Ok(source.stages().into_iter().map(|(_, path)| path.to_path_buf()).collect())
+}
+
+/// Compiles and reflects every keyword variant of the template at `path`.
+pub fn cook_template(path: &Path, bytes: &[u8], settings: &ShaderSettings) -> Result<ShaderTemplate, CookError> {
+    let source = TemplateSource::parse(bytes)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut stages = Vec::new();
+    for (stage, stage_path) in source.stages() {
+        let full = dir.join(stage_path);
+        let text = fs::read_to_string(&full).map_err(|err| CookError::io(&full, err))?;
+        stages.push((stage, full, text));
+    }
+
+    let mut keywords = source.keywords.clone();
+    keywords.sort();
+    let mut variants = Vec::new();
+    for mask in 0..1u32 << keywords.len() {
+        let enabled: Vec<String> = keywords
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| mask & (1 << index) != 0)
+            .map(|(_, keyword)| keyword.clone())
+            .collect();
+        let mut defines = settings.defines.clone();
+        defines.extend(enabled.iter().map(|keyword| (keyword.clone(), "1".to_string())));
+        let in_variant = |err: CookError| match err {
+            CookError::Shader(err) => CookError::Shader(format!("[{}] {}", enabled.join(", "), err)),
+            other => other,
+        };
+
+        let mut layout = ShaderLayout::default();
+        let mut codes = Vec::new();
+        for (stage, stage_path, text) in stages.iter() {
+            let name = stage_path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+            let (module, info) = parse_shader(stage_path, text, &defines)
+                .map_err(|err| in_variant(prefix(&name, err)))?;
+            let stage_layout = reflect(&module, &info, *stage).map_err(|err| in_variant(prefix(&name, err)))?;
+            layout
+                .merge(&stage_layout)
+                .map_err(|err| in_variant(CookError::Shader(err.to_string())))?;
+            let entry_point = module
+                .entry_points
+                .iter()
+                .find(|entry_point| convert_stage(entry_point.stage) == *stage)
+                .map(|entry_point| entry_point.name.clone())
+                .unwrap_or_default();
+            codes.push(ShaderStageCode {
+                stage: *stage,
+                entry_point,
+                spirv: write_spirv(&module, &info).map_err(in_variant)?,
+            });
+        }
+        variants.push(ShaderVariant {
+            keywords: enabled,
+            stages: codes,
+            layout,
+        });
+    }
+    Ok(ShaderTemplate { keywords, variants })
+}
+
+fn prefix(name: &str, err: CookError) -> CookError {
+    match err {
+        CookError::Shader(err) => CookError::Shader(format!("{}: {}", name, err)),
+        other => other,
+    }
+}
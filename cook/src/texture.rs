@@ -0,0 +1,128 @@
+use engine_core::asset::{CookedTexture, TextureFormat};
+use png::{BitDepth, ColorType, Transformations};
+
+use crate::bc;
+use crate::error::CookError;
+use crate::meta::{TextureCompression, TextureSettings};
+
+/// 8-bit RGBA pixels, row by row.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let offset = (y as usize * self.width as usize + x as usize) * 4;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.pixels[offset..offset + 4]);
+        pixel
+    }
+
+    fn has_alpha(&self) -> bool {
+        self.pixels.chunks_exact(4).any(|pixel| pixel[3] != 255)
+    }
+
+    /// Half the size, averaging 2x2 texels. Colour is averaged in linear space when `srgb` so
+    /// mips do not darken.
+    fn downsample(&self, srgb: bool) -> Image {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0f32; 4];
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let texel = self.pixel((x * 2 + dx).min(self.width - 1), (y * 2 + dy).min(self.height - 1));
+                    for channel in 0..4 {
+                        let value = texel[channel] as f32 / 255.0;
+                        sum[channel] += if srgb && channel < 3 { srgb_to_linear(value) } else { value };
+                    }
+                }
+                for (channel, value) in sum.iter().enumerate() {
+                    let value = value / 4.0;
+                    let value = if srgb && channel < 3 { linear_to_srgb(value) } else { value };
+                    pixels.push((value * 255.0).round().clamp(0.0, 255.0) as u8);
+                }
+            }
+        }
+        Image { width, height, pixels }
+    }
+}
+
+pub fn decode_png(bytes: &[u8]) -> Result<Image, CookError> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| CookError::Texture(err.to_string()))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|err| CookError::Texture(err.to_string()))?;
+    if info.bit_depth != BitDepth::Eight {
+        return Err(CookError::Texture(format!("unsupported bit depth {:?}", info.bit_depth)));
+    }
+    let buffer = &buffer[..info.buffer_size()];
+    let pixels = match info.color_type {
+        ColorType::Rgba => buffer.to_vec(),
+        ColorType::Rgb => buffer.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        ColorType::GrayscaleAlpha => buffer.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        ColorType::Grayscale => buffer.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        ColorType::Indexed => return Err(CookError::Texture("palette was not expanded".to_string())),
+    };
+    Ok(Image {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+pub fn cook_texture(bytes: &[u8], settings: &TextureSettings) -> Result<CookedTexture, CookError> {
+    let image = decode_png(bytes)?;
+    let format = match settings.format {
+        TextureCompression::Auto if image.has_alpha() => TextureFormat::Bc3,
+        TextureCompression::Auto | TextureCompression::Bc1 => TextureFormat::Bc1,
+        TextureCompression::Bc3 => TextureFormat::Bc3,
+        TextureCompression::Rgba8 => TextureFormat::Rgba8,
+    };
+    if format == TextureFormat::Bc1 && image.has_alpha() {
+        log::warn!("BC1 drops the alpha channel of this texture; use bc3 to keep it");
+    }
+
+    let (width, height) = (image.width, image.height);
+    let mut levels = vec![image];
+    if settings.mipmaps {
+        while let Some(last) = levels.last().filter(|last| last.width > 1 || last.height > 1) {
+            let next = last.downsample(settings.srgb);
+            levels.push(next);
+        }
+    }
+    let mips = levels
+        .into_iter()
+        .map(|level| match format {
+            TextureFormat::Rgba8 => level.pixels,
+            _ => bc::compress(&level, format),
+        })
+        .collect();
+    Ok(CookedTexture {
+        format,
+        srgb: settings.srgb,
+        width,
+        height,
+        mips,
+    })
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
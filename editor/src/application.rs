@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use engine_core::app::Application;
+use engine_core::Engine;
+
+/// The editor as a client of the engine loop; panels and tools hook in through these callbacks.
+pub struct EditorApplication {
+    project: Option<PathBuf>,
+}
+
+impl EditorApplication {
+    pub fn new(project: Option<PathBuf>) -> Self {
+        EditorApplication { project }
+    }
+}
+
+impl Application for EditorApplication {
+    fn init(&mut self, engine: &mut Engine) {
+        match &self.project {
+            Some(project) => log::info!("Opened project {:?}", project),
+            None => log::info!("No project open"),
+        }
+        engine.info();
+    }
+
+    fn shutdown(&mut self, _engine: &mut Engine) {
+        log::info!("Closing editor");
+    }
+}
@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand};
+use engine_core::conf::DEFAULT_CONFIG_FILE;
+
+/// Repo-relative location of the engine defaults, used when `--config` is not given.
+const ENGINE_CONFIG_DIR: &str = "engine-core/config";
+
+#[derive(Parser, Debug)]
+#[clap(name = "daybreak-editor", version, about = "Daybreak engine editor")]
+pub struct Cli {
+    /// Directory holding the engine's default.yaml
+    #[clap(long, global = true, env = "DAYBREAK_CONFIG_DIR", value_name = "DIR")]
+    pub config: Option<PathBuf>,
+
+    /// Project directory, layers its daybreak.yaml over the defaults
+    #[clap(long, global = true, value_name = "PATH")]
+    pub project: Option<PathBuf>,
+
+    /// Overrides logging.level
+    #[clap(
+        long,
+        global = true,
+        value_name = "LEVEL",
+        possible_values = ["off", "error", "warn", "info", "debug", "trace"]
+    )]
+    pub log_level: Option<String>,
+
+    /// Prefer the GPU whose name contains NAME
+    #[clap(long, global = true, value_name = "NAME")]
+    pub gpu: Option<String>,
+
+    /// Load and validate everything without opening a window
+    #[clap(long, global = true)]
+    pub headless: bool,
+
+    /// Overrides a config value, e.g. --set window.width=1920
+    #[clap(long = "set", global = true, value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+
+    /// Print every effective config value with the layer it came from, then exit
+    #[clap(long, global = true)]
+    pub print_config: bool,
+
+    /// Rewrite outdated config files on disk after migrating them
+    #[clap(long, global = true)]
+    pub upgrade_config: bool,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Create a new project directory
+    New {
+        path: PathBuf,
+        /// Project name, defaults to the directory name
+        #[clap(long)]
+        name: Option<String>,
+    },
+    /// Open a project in the editor (the default with --project)
+    Open { path: PathBuf },
+    /// Validate a project and write its resolved config to the output directory
+    Build {
+        /// Project directory, defaults to --project or the current directory
+        path: Option<PathBuf>,
+        /// Output directory, relative to the project
+        #[clap(long, default_value = "build")]
+        out: PathBuf,
+    },
+    /// List the GPUs Vulkan can see and whether they suit the config
+    Info {
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+/// Finds the engine defaults: `--config`/`DAYBREAK_CONFIG_DIR`, then the repo layout relative to
+/// the working directory, then relative to this crate's source checkout.
+pub fn resolve_config_dir(explicit: Option<&Path>) -> Result<PathBuf, String> {
+    if let Some(dir) = explicit {
+        return if dir.join(DEFAULT_CONFIG_FILE).is_file() {
+            Ok(dir.to_path_buf())
+        } else {
+            Err(format!(
+                "{:?} does not contain {}; point --config at the engine's config directory",
+                dir, DEFAULT_CONFIG_FILE
+            ))
+        };
+    }
+
+    let candidates = [
+        PathBuf::from(ENGINE_CONFIG_DIR),
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(ENGINE_CONFIG_DIR),
+    ];
+    candidates
+        .iter()
+        .find(|dir| dir.join(DEFAULT_CONFIG_FILE).is_file())
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "could not find the engine defaults ({}) in {:?}; run from the repository root, \
+                 pass --config <dir> or set DAYBREAK_CONFIG_DIR",
+                DEFAULT_CONFIG_FILE, candidates
+            )
+        })
+}
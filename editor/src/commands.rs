@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+
+use engine_core::conf::{
+    save_config, validate, ConfigLoader, EngineConfig, Migrator, PROJECT_CONFIG_FILE,
+};
+use engine_core::vulkan::DeviceReport;
+use engine_core::{logging, Engine};
+
+use crate::application::EditorApplication;
+use crate::cli::{resolve_config_dir, Cli};
+
+/// Directories every new project starts with.
+const PROJECT_DIRS: [&str; 3] = ["assets", "scenes", "shaders"];
+
+pub type CommandResult = Result<(), String>;
+
+/// Builds the config stack for the editor. `personal` adds the user and environment layers, which
+/// build output must not pick up.
+pub fn config_loader(cli: &Cli, project: Option<&Path>, personal: bool) -> Result<ConfigLoader, String> {
+    let config_dir = resolve_config_dir(cli.config.as_deref())?;
+    let mut loader = ConfigLoader::new(&config_dir);
+    if let Some(project) = project {
+        loader = loader.with_project(project);
+    }
+    if personal {
+        loader = loader.with_user("engine").with_env();
+    }
+    if let Some(level) = &cli.log_level {
+        loader = loader.with_value("logging.level", level.as_str(), "--log-level");
+    }
+    if let Some(gpu) = &cli.gpu {
+        loader = loader.with_value("vulkan.physical_device.preferred_device", gpu.as_str(), "--gpu");
+    }
+    loader
+        .with_migrator(Migrator::engine())
+        .with_write_back(cli.upgrade_config)
+        .with_overrides(&cli.overrides)
+        .map_err(|err| err.to_string())
+}
+
+fn check_project(path: &Path) -> CommandResult {
+    if path.join(PROJECT_CONFIG_FILE).is_file() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{:?} is not a Daybreak project (no {}); create one with `new {}`",
+            path,
+            PROJECT_CONFIG_FILE,
+            path.display()
+        ))
+    }
+}
+
+fn load_config(loader: &ConfigLoader) -> Result<EngineConfig, String> {
+    let config = loader.extract::<EngineConfig>().map_err(|err| err.to_string())?;
+    validate(&config).map_err(|err| err.to_string())?;
+    Ok(config)
+}
+
+pub fn new_project(path: &Path, name: Option<&str>) -> CommandResult {
+    if path.join(PROJECT_CONFIG_FILE).exists() {
+        return Err(format!("{:?} already contains a project", path));
+    }
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| format!("cannot derive a project name from {:?}, pass --name", path))?,
+    };
+
+    for dir in PROJECT_DIRS.iter() {
+        std::fs::create_dir_all(path.join(dir)).map_err(|err| format!("{:?}: {}", path.join(dir), err))?;
+    }
+    let project = serde_json::json!({
+        "window": { "title": name },
+        "vulkan": { "instance": { "app_name": name } },
+    });
+    save_config(&project, &path.join(PROJECT_CONFIG_FILE)).map_err(|err| err.to_string())?;
+    log::info!("Created project '{}' in {:?}", name, path);
+    Ok(())
+}
+
+pub fn open(cli: &Cli, project: Option<&Path>) -> CommandResult {
+    if let Some(project) = project {
+        check_project(project)?;
+    }
+    let loader = config_loader(cli, project, true)?;
+    if cli.print_config {
+        print!("{}", loader.describe().map_err(|err| err.to_string())?);
+        return Ok(());
+    }
+    let mut engine = Engine::try_from_loader(&loader).map_err(|err| err.to_string())?;
+    logging::configure(&engine.config.logging, project);
+    if cli.headless {
+        log::info!("Headless: config loaded and validated, not opening a window");
+        return Ok(());
+    }
+
+    if let Err(err) = engine.watch_config() {
+        log::warn!("Config hot reload disabled: {}", err);
+    }
+    engine.run(EditorApplication::new(project.map(Path::to_path_buf)));
+    Ok(())
+}
+
+pub fn build(cli: &Cli, project: &Path, out: &Path) -> CommandResult {
+    check_project(project)?;
+    let loader = config_loader(cli, Some(project), false)?;
+    let config = load_config(&loader)?;
+
+    let out = project.join(out);
+    let target = out.join(PROJECT_CONFIG_FILE);
+    save_config(&config, &target).map_err(|err| err.to_string())?;
+    log::info!("Wrote resolved config to {:?}", target);
+    Ok(())
+}
+
+pub fn info(cli: &Cli, project: Option<&Path>, json: bool) -> CommandResult {
+    let loader = config_loader(cli, project, true)?;
+    let config = load_config(&loader)?;
+    let reports = DeviceReport::collect_all(&config);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports).map_err(|err| err.to_string())?);
+    } else {
+        for report in reports.iter() {
+            println!("{}", report);
+        }
+    }
+    Ok(())
+}
+
+pub fn current_dir() -> Result<PathBuf, String> {
+    std::env::current_dir().map_err(|err| format!("cannot read the working directory: {}", err))
+}
@@ -1,7 +1,7 @@
 use engine_core::conf::{VulkanConfig, WindowConfig};
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct EditorConfig {
   pub version: i32,
   pub window: WindowConfig,
@@ -1,14 +1,16 @@
 mod gui;
 mod conf;
+mod cli;
+mod commands;
+mod application;
 
-use std::path::Path;
-
+use clap::Parser;
+use cli::{Cli, Command};
 use conf::EditorConfig;
-use figment::{Figment, providers::{Yaml, Format}};
-use engine_core::vulkan::VulkanApp;
+use engine_core::conf::{ConfigLoader, Migrator};
+use engine_core::logging;
 use gui::EditorUI;
 use iced::{Settings, Application};
-use simple_logger::SimpleLogger;
 
 
 struct Editor {
@@ -17,14 +19,11 @@ struct Editor {
 }
 
 impl Editor {
-    pub fn new(config_dir: String) -> Self {
-        let config_path = Path::new(config_dir.as_str()).join("default.yaml");
-        let config = Figment::new()
-            .merge(Yaml::file(config_path))
-            .extract::<EditorConfig>();
+    pub fn new(loader: &ConfigLoader) -> Self {
+        let config = loader.clone().with_migrator(Migrator::editor()).extract::<EditorConfig>();
 
         if config.is_err() {
-            log::error!("Failed to read config directory '{:?}': {:?}", config_dir, config);
+            log::error!("Failed to read config: {}", config.as_ref().err().unwrap());
             panic!("{:?}", config);
         }
         Self {
@@ -43,9 +42,24 @@ impl Editor {
 }
 
 fn main() {
-    SimpleLogger::new().init().unwrap();
-    engine_core::test();
-    // let editor: Editor = Editor::new("editor/config/".to_string());
+    logging::init(log::LevelFilter::Info).unwrap();
+    let cli = Cli::parse();
+
+    let result = match &cli.command {
+        Some(Command::New { path, name }) => commands::new_project(path, name.as_deref()),
+        Some(Command::Open { path }) => commands::open(&cli, Some(path)),
+        Some(Command::Build { path, out }) => match path.clone().or_else(|| cli.project.clone()) {
+            Some(project) => commands::build(&cli, &project, out),
+            None => commands::current_dir().and_then(|project| commands::build(&cli, &project, out)),
+        },
+        Some(Command::Info { json }) => commands::info(&cli, cli.project.as_deref(), *json),
+        None => commands::open(&cli, cli.project.as_deref()),
+    };
+    if let Err(err) = result {
+        log::error!("{}", err);
+        std::process::exit(2);
+    }
+    // let editor: Editor = Editor::new(&ConfigLoader::new("editor/config/").with_user("editor").with_env());
     // editor.info();
     // editor.run()
 }
@@ -0,0 +1,189 @@
+#version 450
+// Metallic-roughness shading as in glTF 2.0: Lambert diffuse plus a GGX specular lobe with a
+// height-correlated Smith visibility term. Direct light comes from the lights assigned to the
+// fragment's cluster, ambient light from the prefiltered environment.
+
+layout(set = 0, binding = 0) uniform Frame {
+    mat4 view;
+    mat4 projection;
+    mat4 inverse_projection;
+    vec4 camera_position;
+    vec2 screen_size;
+    float near_plane;
+    float far_plane;
+    uvec4 cluster_grid;
+    float exposure;
+    float environment_intensity;
+    float prefiltered_mips;
+    uint max_lights_per_cluster;
+} frame;
+
+struct Light {
+    vec4 position_range;
+    vec4 color_type;
+    vec4 direction;
+    vec4 spot;
+};
+
+layout(set = 0, binding = 1, std430) readonly buffer Lights {
+    Light lights[];
+};
+
+layout(set = 0, binding = 2, std430) readonly buffer LightGrid {
+    uint light_counts[];
+};
+
+layout(set = 0, binding = 3, std430) readonly buffer LightIndices {
+    uint light_indices[];
+};
+
+layout(set = 0, binding = 4) uniform textureCube irradiance_map;
+layout(set = 0, binding = 5) uniform textureCube prefiltered_map;
+layout(set = 0, binding = 6) uniform texture2D brdf_lut;
+layout(set = 0, binding = 7) uniform sampler environment_sampler;
+
+layout(set = 1, binding = 0) uniform Material {
+    vec4 base_color_factor;
+    vec3 emissive_factor;
+    float metallic_factor;
+    float roughness_factor;
+    float occlusion_strength;
+    float normal_scale;
+    float alpha_cutoff;
+} material;
+
+layout(set = 1, binding = 1) uniform texture2D base_color_texture;
+layout(set = 1, binding = 2) uniform texture2D metallic_roughness_texture;
+layout(set = 1, binding = 3) uniform texture2D occlusion_texture;
+layout(set = 1, binding = 4) uniform texture2D emissive_texture;
+#ifdef NORMAL_MAP
+layout(set = 1, binding = 5) uniform texture2D normal_texture;
+#endif
+layout(set = 1, binding = 6) uniform sampler material_sampler;
+
+layout(location = 0) in vec3 v_world_position;
+layout(location = 1) in float v_view_depth;
+layout(location = 2) in vec3 v_normal;
+layout(location = 3) in vec2 v_uv;
+layout(location = 4) in vec4 v_tangent;
+
+layout(location = 0) out vec4 out_color;
+
+const float PI = 3.14159265359;
+const float MIN_ROUGHNESS = 0.045;
+
+uint cluster_index() {
+    vec3 grid = vec3(frame.cluster_grid.xyz);
+    vec2 tile = clamp(floor(gl_FragCoord.xy / frame.screen_size * grid.xy), vec2(0.0), grid.xy - 1.0);
+    float slice = log(v_view_depth / frame.near_plane) / log(frame.far_plane / frame.near_plane) * grid.z;
+    slice = clamp(floor(slice), 0.0, grid.z - 1.0);
+    return uint(tile.x) + uint(tile.y) * frame.cluster_grid.x
+        + uint(slice) * frame.cluster_grid.x * frame.cluster_grid.y;
+}
+
+float distribution_ggx(float n_dot_h, float alpha) {
+    float alpha2 = alpha * alpha;
+    float denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    return alpha2 / (PI * denominator * denominator);
+}
+
+float visibility_smith_ggx(float n_dot_v, float n_dot_l, float alpha) {
+    float alpha2 = alpha * alpha;
+    float ggx_v = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - alpha2) + alpha2);
+    float ggx_l = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - alpha2) + alpha2);
+    return 0.5 / max(ggx_v + ggx_l, 0.0001);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+// Inverse square falloff windowed to reach zero at the light's range.
+float distance_attenuation(float distance_sq, float range) {
+    float ratio = distance_sq / (range * range);
+    float window = clamp(1.0 - ratio * ratio, 0.0, 1.0);
+    return window * window / max(distance_sq, 0.0001);
+}
+
+vec3 surface_normal() {
+    vec3 normal = normalize(v_normal);
+#ifdef NORMAL_MAP
+    vec3 tangent = normalize(v_tangent.xyz - normal * dot(normal, v_tangent.xyz));
+    vec3 bitangent = cross(normal, tangent) * v_tangent.w;
+    vec3 sampled = texture(sampler2D(normal_texture, material_sampler), v_uv).xyz * 2.0 - 1.0;
+    sampled.xy *= material.normal_scale;
+    normal = normalize(mat3(tangent, bitangent, normal) * sampled);
+#endif
+    if (!gl_FrontFacing) {
+        normal = -normal;
+    }
+    return normal;
+}
+
+void main() {
+    vec4 base_color = material.base_color_factor * texture(sampler2D(base_color_texture, material_sampler), v_uv);
+    // glTF keeps roughness in the green channel and metalness in blue.
+    vec4 metallic_roughness = texture(sampler2D(metallic_roughness_texture, material_sampler), v_uv);
+    float metallic = clamp(material.metallic_factor * metallic_roughness.b, 0.0, 1.0);
+    float roughness = clamp(material.roughness_factor * metallic_roughness.g, MIN_ROUGHNESS, 1.0);
+    float alpha = roughness * roughness;
+    float occlusion = 1.0 + material.occlusion_strength
+        * (texture(sampler2D(occlusion_texture, material_sampler), v_uv).r - 1.0);
+    vec3 emissive = material.emissive_factor * texture(sampler2D(emissive_texture, material_sampler), v_uv).rgb;
+
+    vec3 normal = surface_normal();
+    // Only after every implicit-derivative sample, which must stay in uniform control flow.
+#ifdef ALPHA_TEST
+    if (base_color.a < material.alpha_cutoff) {
+        discard;
+    }
+#endif
+    vec3 view = normalize(frame.camera_position.xyz - v_world_position);
+    float n_dot_v = max(dot(normal, view), 0.0001);
+    vec3 diffuse_color = base_color.rgb * (1.0 - metallic);
+    vec3 f0 = mix(vec3(0.04), base_color.rgb, metallic);
+
+    vec3 direct = vec3(0.0);
+    uint cluster = cluster_index();
+    uint first = cluster * frame.max_lights_per_cluster;
+    uint count = light_counts[cluster];
+    for (uint offset = 0u; offset < count; offset++) {
+        uint index = light_indices[first + offset];
+        vec3 to_light = lights[index].position_range.xyz - v_world_position;
+        float distance_sq = dot(to_light, to_light);
+        vec3 light_direction = to_light * inversesqrt(max(distance_sq, 0.0001));
+        float attenuation = distance_attenuation(distance_sq, lights[index].position_range.w);
+        if (lights[index].color_type.w > 0.5) {
+            float cos_angle = dot(-light_direction, normalize(lights[index].direction.xyz));
+            float spot = clamp(cos_angle * lights[index].spot.x + lights[index].spot.y, 0.0, 1.0);
+            attenuation *= spot * spot;
+        }
+        float n_dot_l = dot(normal, light_direction);
+        if (n_dot_l <= 0.0 || attenuation <= 0.0) {
+            continue;
+        }
+        vec3 half_vector = normalize(view + light_direction);
+        float n_dot_h = max(dot(normal, half_vector), 0.0);
+        float l_dot_h = max(dot(light_direction, half_vector), 0.0);
+        vec3 fresnel = fresnel_schlick(l_dot_h, f0);
+        vec3 specular = fresnel * distribution_ggx(n_dot_h, alpha) * visibility_smith_ggx(n_dot_v, n_dot_l, alpha);
+        vec3 diffuse = (1.0 - fresnel) * diffuse_color / PI;
+        direct += (diffuse + specular) * lights[index].color_type.rgb * attenuation * n_dot_l;
+    }
+
+    vec3 ambient_fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+    vec3 irradiance = textureLod(samplerCube(irradiance_map, environment_sampler), normal, 0.0).rgb;
+    vec3 reflected = reflect(-view, normal);
+    float lod = roughness * (frame.prefiltered_mips - 1.0);
+    vec3 prefiltered = textureLod(samplerCube(prefiltered_map, environment_sampler), reflected, lod).rgb;
+    vec2 environment_brdf = textureLod(sampler2D(brdf_lut, environment_sampler), vec2(n_dot_v, roughness), 0.0).rg;
+    vec3 ambient_diffuse = (1.0 - ambient_fresnel) * diffuse_color * irradiance;
+    vec3 ambient_specular = prefiltered * (f0 * environment_brdf.x + environment_brdf.y);
+    vec3 ambient = (ambient_diffuse + ambient_specular) * occlusion * frame.environment_intensity;
+
+    out_color = vec4((direct + ambient + emissive) * frame.exposure, base_color.a);
+}
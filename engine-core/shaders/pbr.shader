@@ -0,0 +1,5 @@
+# The engine shaders are cooked into the engine data directory, where the renderer reads them:
+#   daybreak-cook engine-core/shaders --out engine-core/config/shaders --no-write-meta
+vertex: pbr.vert
+fragment: pbr.frag
+keywords: [NORMAL_MAP, ALPHA_TEST]
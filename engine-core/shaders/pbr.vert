@@ -0,0 +1,44 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform Frame {
+    mat4 view;
+    mat4 projection;
+    mat4 inverse_projection;
+    vec4 camera_position;
+    vec2 screen_size;
+    float near_plane;
+    float far_plane;
+    uvec4 cluster_grid;
+    float exposure;
+    float environment_intensity;
+    float prefiltered_mips;
+    uint max_lights_per_cluster;
+} frame;
+
+layout(push_constant) uniform Object {
+    mat4 model;
+    // Inverse transpose of the model matrix, so non-uniform scale keeps normals perpendicular.
+    mat4 normal_matrix;
+} object;
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec2 uv;
+layout(location = 3) in vec4 tangent;
+
+layout(location = 0) out vec3 v_world_position;
+layout(location = 1) out float v_view_depth;
+layout(location = 2) out vec3 v_normal;
+layout(location = 3) out vec2 v_uv;
+layout(location = 4) out vec4 v_tangent;
+
+void main() {
+    vec4 world_position = object.model * vec4(position, 1.0);
+    vec4 view_position = frame.view * world_position;
+    v_world_position = world_position.xyz;
+    v_view_depth = -view_position.z;
+    v_normal = (object.normal_matrix * vec4(normal, 0.0)).xyz;
+    v_uv = uv;
+    v_tangent = vec4((object.model * vec4(tangent.xyz, 0.0)).xyz, tangent.w);
+    gl_Position = frame.projection * view_position;
+}
@@ -0,0 +1,37 @@
+use winit::event::WindowEvent;
+
+use crate::Engine;
+
+/// Where a system added with `Engine::add_system` runs: just before the matching `Application`
+/// callback, with the frame's `Time` available as a resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    FixedUpdate,
+    Update,
+}
+
+/// A game or tool driven by `Engine::run`. Every callback receives the engine so it can reach the
+/// config, the Vulkan device and window, or request shutdown with `Engine::exit`.
+pub trait Application {
+    /// Called once after the window and device exist, before the first frame.
+    fn init(&mut self, _engine: &mut Engine) {}
+
+    /// Called at `time.fixed_hz`, zero or more times per frame; step by `Time::fixed_delta`.
+    fn fixed_update(&mut self, _engine: &mut Engine) {}
+
+    /// Called once per frame before `render`.
+    fn update(&mut self, _engine: &mut Engine) {}
+
+    /// Called once per frame after `update`; blend simulation state by `Time::alpha`. Submit to
+    /// the graphics queue; the frame counts as finished once that work completes.
+    fn render(&mut self, _engine: &mut Engine) {}
+
+    /// Sees window events before the engine does. Returning `true` marks the event as handled:
+    /// `Input` never sees it and the close button no longer exits.
+    fn on_event(&mut self, _engine: &mut Engine, _event: &WindowEvent) -> bool {
+        false
+    }
+
+    /// Called once when the loop exits, while the device is still alive.
+    fn shutdown(&mut self, _engine: &mut Engine) {}
+}
@@ -0,0 +1,6 @@
+mod application;
+mod run;
+mod time;
+
+pub use application::*;
+pub use time::*;
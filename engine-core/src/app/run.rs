@@ -0,0 +1,134 @@
+use std::time::Instant;
+
+use winit::event::{Event, StartCause, WindowEvent};
+use winit::event_loop::ControlFlow;
+
+use super::{Application, Stage};
+use crate::asset::AssetEvents;
+use crate::profile::Profiler;
+use crate::profile_scope;
+use crate::vulkan::{FrameFences, VulkanApp};
+use crate::Engine;
+
+impl Engine {
+    /// Creates the window and device if needed and drives `application` until exit. Never returns;
+    /// the engine and application are dropped once the event loop is destroyed.
+    pub fn run<A: Application + 'static>(mut self, application: A) {
+        if self.vulkan.is_none() {
+            self.vulkan = Some(VulkanApp::new(&self.config));
+        }
+        let event_loop = self.vulkan.as_mut().unwrap().take_event_loop().unwrap();
+        let logical_device = self.vulkan().get_logical_device();
+        self.frame_fences = Some(FrameFences::new(
+            logical_device.get_device(),
+            logical_device.get_deletion_queue().clone(),
+        ));
+
+        log::info!("Beginning game loop");
+        let mut state = Some((self, application));
+        event_loop.run(move |event, _, control_flow| {
+            profile_scope!("event", "event");
+            let (engine, application) = match state.as_mut() {
+                Some(state) => state,
+                None => return,
+            };
+
+            match event {
+                Event::NewEvents(StartCause::Init) => {
+                    profile_scope!("Application::init");
+                    application.init(engine);
+                    engine.time.reset();
+                }
+                Event::WindowEvent { event, .. } if !application.on_event(engine, &event) => {
+                    engine.input.handle_window_event(&event);
+                    if let WindowEvent::CloseRequested = event {
+                        engine.exit();
+                    }
+                }
+                Event::DeviceEvent { event, .. } => engine.input.handle_device_event(&event),
+                Event::MainEventsCleared => {
+                    let now = Instant::now();
+                    // With a frame rate cap, sleep until the next frame is due instead of spinning.
+                    if let Some(deadline) = engine.time.next_frame_deadline() {
+                        if now < deadline {
+                            *control_flow = ControlFlow::WaitUntil(deadline);
+                            return;
+                        }
+                    }
+                    engine.poll_config();
+                    let asset_events = engine.assets.update();
+                    engine.world.insert_resource(AssetEvents(asset_events));
+                    engine.input.begin_frame();
+                    engine.handle_default_actions();
+                    let steps = engine.time.begin_frame(now);
+                    {
+                        profile_scope!("fixed_update");
+                        for _ in 0..steps {
+                            engine.run_stage(Stage::FixedUpdate);
+                            application.fixed_update(engine);
+                            engine.time.end_fixed_step();
+                        }
+                    }
+                    {
+                        profile_scope!("update");
+                        engine.run_stage(Stage::Update);
+                        application.update(engine);
+                    }
+                    {
+                        profile_scope!("transform_propagation");
+                        engine.transform_propagator.run(&mut engine.world);
+                    }
+                    let window = engine.vulkan.as_ref().unwrap().get_window();
+                    engine.input.end_frame(window);
+                    window.request_redraw();
+                    *control_flow = match engine.time.next_frame_deadline() {
+                        Some(deadline) => ControlFlow::WaitUntil(deadline),
+                        None => ControlFlow::Poll,
+                    };
+                }
+                Event::RedrawRequested(_) => {
+                    profile_scope!("render");
+                    // Waiting for the frame slot also destroys resources released two frames ago.
+                    engine.frame_fences.as_mut().unwrap().begin_frame();
+                    application.render(engine);
+                    let queue = *engine.vulkan().get_logical_device().get_queue();
+                    engine.frame_fences.as_mut().unwrap().end_frame(queue);
+                }
+                Event::LoopDestroyed => {
+                    application.shutdown(engine);
+                    engine.vulkan().get_logical_device().wait_idle();
+                    state.take();
+                    return;
+                }
+                _ => {}
+            }
+
+            if engine.exit_requested {
+                *control_flow = ControlFlow::Exit;
+            }
+        })
+    }
+
+    fn run_stage(&mut self, stage: Stage) {
+        let schedule = match stage {
+            Stage::FixedUpdate => &mut self.fixed_schedule,
+            Stage::Update => &mut self.update_schedule,
+        };
+        if schedule.is_empty() {
+            return;
+        }
+        self.world.insert_resource(self.time.clone());
+        schedule.run(&mut self.world);
+    }
+
+    fn handle_default_actions(&mut self) {
+        if self.input.action_pressed("quit") {
+            self.exit();
+        }
+        if self.input.action_pressed("profiler_trace") {
+            if let Err(err) = Profiler::write_chrome_trace(&self.config.profiler.trace_path) {
+                log::error!("Failed to write profiler trace: {:?}", err);
+            }
+        }
+    }
+}
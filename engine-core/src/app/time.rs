@@ -0,0 +1,159 @@
+use std::time::{Duration, Instant};
+
+use crate::conf::TimeConfig;
+
+/// Frame and simulation clock owned by the engine loop. Scaled values follow `time_scale`, so a
+/// scale of 0 pauses `fixed_update` and scaled deltas while rendering keeps going.
+#[derive(Clone)]
+pub struct Time {
+    startup: Instant,
+    last_frame: Instant,
+    raw_delta: Duration,
+    delta: Duration,
+    raw_elapsed: Duration,
+    elapsed: Duration,
+    frame_count: u64,
+    time_scale: f64,
+    fixed_delta: Duration,
+    fixed_elapsed: Duration,
+    accumulator: Duration,
+    max_fixed_steps: u32,
+    alpha: f64,
+    frame_budget: Option<Duration>,
+}
+
+impl Time {
+    pub fn new(config: &TimeConfig) -> Self {
+        let now = Instant::now();
+        let mut time = Time {
+            startup: now,
+            last_frame: now,
+            raw_delta: Duration::ZERO,
+            delta: Duration::ZERO,
+            raw_elapsed: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            frame_count: 0,
+            time_scale: 1.0,
+            fixed_delta: Duration::ZERO,
+            fixed_elapsed: Duration::ZERO,
+            accumulator: Duration::ZERO,
+            max_fixed_steps: 1,
+            alpha: 0.0,
+            frame_budget: None,
+        };
+        time.configure(config);
+        time
+    }
+
+    pub fn configure(&mut self, config: &TimeConfig) {
+        self.fixed_delta = Duration::from_secs_f64(1.0 / config.fixed_hz);
+        self.max_fixed_steps = config.max_fixed_steps.max(1);
+        self.frame_budget = match config.max_fps {
+            0 => None,
+            fps => Some(Duration::from_secs_f64(1.0 / fps as f64)),
+        };
+    }
+
+    /// Restarts the frame clock, so time spent before the loop does not count as the first delta.
+    pub(crate) fn reset(&mut self) {
+        self.last_frame = Instant::now();
+    }
+
+    /// Earliest instant the next frame may start when a frame rate cap is set.
+    pub(crate) fn next_frame_deadline(&self) -> Option<Instant> {
+        self.frame_budget.map(|budget| self.last_frame + budget)
+    }
+
+    /// Advances the clock to `now` and returns how many fixed steps the frame should run.
+    pub(crate) fn begin_frame(&mut self, now: Instant) -> u32 {
+        self.raw_delta = now - self.last_frame;
+        self.last_frame = now;
+        self.delta = self.raw_delta.mul_f64(self.time_scale);
+        self.raw_elapsed = now - self.startup;
+        self.elapsed += self.delta;
+        self.frame_count += 1;
+
+        self.accumulator += self.delta;
+        let mut steps = 0;
+        while self.accumulator >= self.fixed_delta && steps < self.max_fixed_steps {
+            self.accumulator -= self.fixed_delta;
+            steps += 1;
+        }
+        if self.accumulator >= self.fixed_delta {
+            log::debug!(
+                "Simulation fell behind by {:?}, dropping the backlog",
+                self.accumulator
+            );
+            self.accumulator = Duration::from_nanos(
+                (self.accumulator.as_nanos() % self.fixed_delta.as_nanos()) as u64,
+            );
+        }
+        self.alpha = self.accumulator.as_secs_f64() / self.fixed_delta.as_secs_f64();
+        steps
+    }
+
+    pub(crate) fn end_fixed_step(&mut self) {
+        self.fixed_elapsed += self.fixed_delta;
+    }
+
+    /// Scaled time since the previous frame.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Wall-clock time since the previous frame, ignoring `time_scale`.
+    pub fn raw_delta(&self) -> Duration {
+        self.raw_delta
+    }
+
+    /// Scaled time accumulated since startup.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Wall-clock time since startup.
+    pub fn raw_elapsed(&self) -> Duration {
+        self.raw_elapsed
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Length of one `fixed_update` step; use this rather than `delta` inside `fixed_update`.
+    pub fn fixed_delta(&self) -> Duration {
+        self.fixed_delta
+    }
+
+    pub fn fixed_delta_seconds(&self) -> f32 {
+        self.fixed_delta.as_secs_f32()
+    }
+
+    /// Simulation time covered by the fixed steps run so far.
+    pub fn fixed_elapsed(&self) -> Duration {
+        self.fixed_elapsed
+    }
+
+    /// How far rendering is between the last fixed step and the next, in `[0, 1)`. Blend the
+    /// previous and current simulation state by this to render smoothly at any frame rate.
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// 1.0 is real time, 0.5 slow motion and 0.0 pauses the simulation.
+    pub fn set_time_scale(&mut self, scale: f64) {
+        self.time_scale = scale.max(0.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.time_scale == 0.0
+    }
+}
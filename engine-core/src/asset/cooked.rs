@@ -0,0 +1,338 @@
+use super::{AssetError, AssetLoader, LoadContext};
+
+/// Files written by `daybreak-cook` start with a four byte magic and this version, followed by
+/// little-endian values. Bumped whenever a layout below changes, which also invalidates the
+/// cook cache.
+pub const COOKED_FORMAT_VERSION: u32 = 1;
+
+const TEXTURE_MAGIC: &[u8; 4] = b"DTEX";
+const MESH_MAGIC: &[u8; 4] = b"DMSH";
+const SRGB_FLAG: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    Rgba8,
+    /// Opaque colour, 8 bytes per 4x4 block.
+    Bc1,
+    /// Colour with alpha, 16 bytes per 4x4 block.
+    Bc3,
+}
+
+impl TextureFormat {
+    fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(TextureFormat::Rgba8),
+            1 => Some(TextureFormat::Bc1),
+            2 => Some(TextureFormat::Bc3),
+            _ => None,
+        }
+    }
+
+    fn to_u32(self) -> u32 {
+        match self {
+            TextureFormat::Rgba8 => 0,
+            TextureFormat::Bc1 => 1,
+            TextureFormat::Bc3 => 2,
+        }
+    }
+
+    pub fn is_compressed(self) -> bool {
+        self != TextureFormat::Rgba8
+    }
+
+    /// Size in bytes of one mip level.
+    pub fn level_size(self, width: u32, height: u32) -> usize {
+        let blocks = |size: u32| size.div_ceil(4) as usize;
+        match self {
+            TextureFormat::Rgba8 => width as usize * height as usize * 4,
+            TextureFormat::Bc1 => blocks(width) * blocks(height) * 8,
+            TextureFormat::Bc3 => blocks(width) * blocks(height) * 16,
+        }
+    }
+}
+
+/// `.dtex`: header of format, flags, width, height and mip count, then every mip level from
+/// the largest down, each prefixed with its byte length.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CookedTexture {
+    pub format: TextureFormat,
+    pub srgb: bool,
+    pub width: u32,
+    pub height: u32,
+    pub mips: Vec<Vec<u8>>,
+}
+
+impl CookedTexture {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = ByteWriter::new(TEXTURE_MAGIC);
+        writer.u32(self.format.to_u32());
+        writer.u32(if self.srgb { SRGB_FLAG } else { 0 });
+        writer.u32(self.width);
+        writer.u32(self.height);
+        writer.u32(self.mips.len() as u32);
+        for mip in self.mips.iter() {
+            writer.u32(mip.len() as u32);
+            writer.bytes.extend_from_slice(mip);
+        }
+        writer.bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AssetError> {
+        let mut reader = ByteReader::new(bytes, TEXTURE_MAGIC)?;
+        let format = reader.u32()?;
+        let format = TextureFormat::from_u32(format)
+            .ok_or_else(|| AssetError::Load(format!("unknown texture format {}", format)))?;
+        let srgb = reader.u32()? & SRGB_FLAG != 0;
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+        let mip_count = reader.u32()?;
+        let max_mips = 32 - width.max(height).leading_zeros();
+        if mip_count > max_mips {
+            return Err(AssetError::Load(format!(
+                "{} mips for a {}x{} texture, at most {} fit",
+                mip_count, width, height, max_mips
+            )));
+        }
+        let mut mips = Vec::new();
+        for level in 0..mip_count {
+            let len = reader.u32()? as usize;
+            let expected = format.level_size((width >> level).max(1), (height >> level).max(1));
+            if len != expected {
+                return Err(AssetError::Load(format!(
+                    "mip {} holds {} bytes, expected {}",
+                    level, len, expected
+                )));
+            }
+            mips.push(reader.take(len)?.to_vec());
+        }
+        Ok(CookedTexture {
+            format,
+            srgb,
+            width,
+            height,
+            mips,
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    /// Handedness in `w`.
+    pub tangent: [f32; 4],
+}
+
+/// A range of the index buffer drawn with one material.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Submesh {
+    pub first_index: u32,
+    pub index_count: u32,
+    /// Index into the source file's materials.
+    pub material: Option<u32>,
+}
+
+/// `.dmesh`: vertex, index and submesh counts, the bounding box, then the vertex buffer exactly
+/// as `Vertex` lays it out, `u32` indices and the submeshes. Node transforms of the source are
+/// already applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CookedMesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub submeshes: Vec<Submesh>,
+    pub bounds_min: [f32; 3],
+    pub bounds_max: [f32; 3],
+}
+
+impl CookedMesh {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = ByteWriter::new(MESH_MAGIC);
+        writer.u32(self.vertices.len() as u32);
+        writer.u32(self.indices.len() as u32);
+        writer.u32(self.submeshes.len() as u32);
+        self.bounds_min.iter().chain(self.bounds_max.iter()).for_each(|value| writer.f32(*value));
+        for vertex in self.vertices.iter() {
+            let Vertex {
+                position,
+                normal,
+                uv,
+                tangent,
+            } = vertex;
+            position.iter().chain(normal).chain(uv).chain(tangent).for_each(|value| writer.f32(*value));
+        }
+        self.indices.iter().for_each(|index| writer.u32(*index));
+        for submesh in self.submeshes.iter() {
+            writer.u32(submesh.first_index);
+            writer.u32(submesh.index_count);
+            writer.u32(submesh.material.unwrap_or(u32::MAX));
+        }
+        writer.bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AssetError> {
+        let mut reader = ByteReader::new(bytes, MESH_MAGIC)?;
+        let vertex_count = reader.u32()? as usize;
+        let index_count = reader.u32()? as usize;
+        let submesh_count = reader.u32()? as usize;
+        let mut bounds = [0.0; 6];
+        for value in bounds.iter_mut() {
+            *value = reader.f32()?;
+        }
+
+        let mut vertices = Vec::with_capacity(vertex_count.min(bytes.len() / 48));
+        for _ in 0..vertex_count {
+            let mut values = [0.0; 12];
+            for value in values.iter_mut() {
+                *value = reader.f32()?;
+            }
+            vertices.push(Vertex {
+                position: [values[0], values[1], values[2]],
+                normal: [values[3], values[4], values[5]],
+                uv: [values[6], values[7]],
+                tangent: [values[8], values[9], values[10], values[11]],
+            });
+        }
+        let mut indices = Vec::with_capacity(index_count.min(bytes.len() / 4));
+        for _ in 0..index_count {
+            let index = reader.u32()?;
+            if index as usize >= vertex_count {
+                return Err(AssetError::Load(format!("index {} out of {} vertices", index, vertex_count)));
+            }
+            indices.push(index);
+        }
+        let mut submeshes = Vec::new();
+        for _ in 0..submesh_count {
+            let first_index = reader.u32()?;
+            let count = reader.u32()?;
+            let material = reader.u32()?;
+            if first_index as usize + count as usize > index_count {
+                return Err(AssetError::Load("submesh past the end of the index buffer".to_string()));
+            }
+            submeshes.push(Submesh {
+                first_index,
+                index_count: count,
+                material: (material != u32::MAX).then_some(material),
+            });
+        }
+        Ok(CookedMesh {
+            vertices,
+            indices,
+            submeshes,
+            bounds_min: [bounds[0], bounds[1], bounds[2]],
+            bounds_max: [bounds[3], bounds[4], bounds[5]],
+        })
+    }
+}
+
+/// A compiled SPIR-V module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spirv(pub Vec<u32>);
+
+pub struct CookedTextureLoader;
+
+impl AssetLoader for CookedTextureLoader {
+    type Asset = CookedTexture;
+
+    fn extensions(&self) -> &[&str] {
+        &["dtex"]
+    }
+
+    fn load(&self, bytes: &[u8], _context: &mut LoadContext) -> Result<CookedTexture, AssetError> {
+        CookedTexture::from_bytes(bytes)
+    }
+}
+
+pub struct CookedMeshLoader;
+
+impl AssetLoader for CookedMeshLoader {
+    type Asset = CookedMesh;
+
+    fn extensions(&self) -> &[&str] {
+        &["dmesh"]
+    }
+
+    fn load(&self, bytes: &[u8], _context: &mut LoadContext) -> Result<CookedMesh, AssetError> {
+        CookedMesh::from_bytes(bytes)
+    }
+}
+
+pub struct SpirvLoader;
+
+impl AssetLoader for SpirvLoader {
+    type Asset = Spirv;
+
+    fn extensions(&self) -> &[&str] {
+        &["spv"]
+    }
+
+    fn load(&self, bytes: &[u8], _context: &mut LoadContext) -> Result<Spirv, AssetError> {
+        ash::util::read_spv(&mut std::io::Cursor::new(bytes))
+            .map(Spirv)
+            .map_err(|err| AssetError::Load(format!("invalid SPIR-V: {}", err)))
+    }
+}
+
+struct ByteWriter {
+    bytes: Vec<u8>,
+}
+
+impl ByteWriter {
+    fn new(magic: &[u8; 4]) -> Self {
+        let mut writer = ByteWriter { bytes: magic.to_vec() };
+        writer.u32(COOKED_FORMAT_VERSION);
+        writer
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8], magic: &[u8; 4]) -> Result<Self, AssetError> {
+        let mut reader = ByteReader { bytes };
+        if reader.take(4)? != magic {
+            return Err(AssetError::Load(format!(
+                "not a {} file",
+                String::from_utf8_lossy(magic)
+            )));
+        }
+        let version = reader.u32()?;
+        if version != COOKED_FORMAT_VERSION {
+            return Err(AssetError::Load(format!(
+                "cooked with format version {}, this build reads {}; cook the assets again",
+                version, COOKED_FORMAT_VERSION
+            )));
+        }
+        Ok(reader)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], AssetError> {
+        if self.bytes.len() < len {
+            return Err(AssetError::Load("unexpected end of file".to_string()));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, AssetError> {
+        let mut word = [0; 4];
+        word.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(word))
+    }
+
+    fn f32(&mut self) -> Result<f32, AssetError> {
+        self.u32().map(f32::from_bits)
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt::Display;
+
+/// Why an asset failed to load. Kept as strings so `LoadState` can be cloned out of the server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetError {
+    Io(String),
+    NoLoader(String),
+    WrongType { path: String, expected: &'static str, loaded: &'static str },
+    Load(String),
+    Watch(String),
+}
+
+impl Display for AssetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetError::Io(err) => write!(f, "failed to read asset: {}", err),
+            AssetError::NoLoader(path) => write!(f, "no loader registered for the extension of '{}'", path),
+            AssetError::WrongType { path, expected, loaded } => write!(
+                f,
+                "'{}' loads as {}, not the requested {}",
+                path, loaded, expected
+            ),
+            AssetError::Load(err) => write!(f, "{}", err),
+            AssetError::Watch(err) => write!(f, "failed to watch assets: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+impl From<std::io::Error> for AssetError {
+    fn from(err: std::io::Error) -> Self {
+        AssetError::Io(err.to_string())
+    }
+}
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use super::{AssetError, AssetId, Handle};
+
+/// Emitted by `AssetServer::update`. Code that keeps derived data, such as GPU copies of a
+/// texture or pipelines built from a shader, should rebuild it on `Modified`: the handle stays
+/// the same but `AssetServer::get` now returns the new asset.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetEvent {
+    Loaded { id: AssetId, path: Option<PathBuf> },
+    Modified { id: AssetId, path: Option<PathBuf> },
+    /// Loading, or reloading, failed. After a failed reload the previous asset stays in use.
+    Failed { id: AssetId, path: Option<PathBuf>, error: AssetError },
+    Unloaded { id: AssetId, path: Option<PathBuf> },
+}
+
+impl AssetEvent {
+    pub fn id(&self) -> AssetId {
+        match self {
+            AssetEvent::Loaded { id, .. }
+            | AssetEvent::Modified { id, .. }
+            | AssetEvent::Failed { id, .. }
+            | AssetEvent::Unloaded { id, .. } => *id,
+        }
+    }
+
+    pub fn is_for<T>(&self, handle: &Handle<T>) -> bool {
+        self.id() == handle.id()
+    }
+}
+
+/// ECS resource holding the events of the current frame.
+#[derive(Debug, Clone, Default)]
+pub struct AssetEvents(pub Vec<AssetEvent>);
+
+impl AssetEvents {
+    pub fn iter(&self) -> impl Iterator<Item = &AssetEvent> {
+        self.0.iter()
+    }
+
+    /// Handles whose asset was reloaded this frame.
+    pub fn modified<T>(&self, handle: &Handle<T>) -> bool {
+        self.0
+            .iter()
+            .any(|event| matches!(event, AssetEvent::Modified { .. }) && event.is_for(handle))
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetId(pub(crate) u64);
+
+impl Display for AssetId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Shared by every clone of a handle; dropping the last one tells the server the asset can be
+/// unloaded on its next `update`.
+#[derive(Debug)]
+pub(crate) struct HandleInner {
+    pub(crate) id: AssetId,
+    pub(crate) path: Option<PathBuf>,
+    pub(crate) drops: Sender<AssetId>,
+}
+
+impl Drop for HandleInner {
+    fn drop(&mut self) {
+        // The server may already be gone, in which case there is nothing left to unload.
+        let _ = self.drops.send(self.id);
+    }
+}
+
+/// Ref-counted reference to an asset of type `T`, valid while the asset is still loading.
+/// Cloning is cheap; the asset stays loaded while any clone is alive.
+pub struct Handle<T> {
+    pub(crate) inner: Arc<HandleInner>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub(crate) fn new(inner: Arc<HandleInner>) -> Self {
+        Handle {
+            inner,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> AssetId {
+        self.inner.id
+    }
+
+    /// Path relative to the asset root; `None` for assets added from memory.
+    pub fn path(&self) -> Option<&Path> {
+        self.inner.path.as_deref()
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle::new(self.inner.clone())
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id().hash(state)
+    }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.path() {
+            Some(path) => write!(f, "Handle<{}>({}, {})", std::any::type_name::<T>(), self.id(), path.display()),
+            None => write!(f, "Handle<{}>({})", std::any::type_name::<T>(), self.id()),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use super::{AssetError, AssetLoader, LoadContext};
+
+/// A high dynamic range image in linear RGB, rows from the top. Environment maps are
+/// equirectangular: `x` wraps around the horizon and `y` runs from straight up to straight down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[f32; 3]>,
+}
+
+impl HdrImage {
+    pub fn pixel(&self, x: u32, y: u32) -> [f32; 3] {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Bilinear lookup at `u`, `v` in 0..1, wrapping horizontally and clamping vertically.
+    pub fn sample(&self, u: f32, v: f32) -> [f32; 3] {
+        let x = u * self.width as f32 - 0.5;
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let wrap = |x: f32| (x as i64).rem_euclid(self.width as i64) as u32;
+        let (x0, x1) = (wrap(x0), wrap(x0 + 1.0));
+        let (y0, y1) = (y0 as u32, (y0 as u32 + 1).min(self.height - 1));
+        let mut color = [0.0; 3];
+        for (channel, value) in color.iter_mut().enumerate() {
+            let top = self.pixel(x0, y0)[channel] * (1.0 - fx) + self.pixel(x1, y0)[channel] * fx;
+            let bottom = self.pixel(x0, y1)[channel] * (1.0 - fx) + self.pixel(x1, y1)[channel] * fx;
+            *value = top * (1.0 - fy) + bottom * fy;
+        }
+        color
+    }
+
+    /// Parses a Radiance `.hdr` file in the usual `-Y height +X width` orientation, with
+    /// run-length encoded or flat scanlines.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AssetError> {
+        let mut reader = HdrReader { bytes };
+        let magic = reader.line()?;
+        if magic != "#?RADIANCE" && magic != "#?RGBE" {
+            return Err(AssetError::Load("not a Radiance HDR file".to_string()));
+        }
+        loop {
+            let line = reader.line()?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(AssetError::Load(format!("unsupported HDR pixel format '{}'", format)));
+                }
+            }
+        }
+        let resolution = reader.line()?;
+        let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["-Y", height, "+X", width] => (height.parse::<u32>().ok(), width.parse::<u32>().ok()),
+            _ => (None, None),
+        };
+        let (width, height) = match (width, height) {
+            (Some(width), Some(height)) if width > 0 && height > 0 => (width, height),
+            _ => {
+                return Err(AssetError::Load(format!(
+                    "unsupported HDR resolution line '{}', only '-Y height +X width' is read",
+                    resolution
+                )))
+            }
+        };
+
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        let mut scanline = vec![[0u8; 4]; width as usize];
+        for _ in 0..height {
+            reader.scanline(&mut scanline)?;
+            pixels.extend(scanline.iter().map(|rgbe| rgbe_to_rgb(*rgbe)));
+        }
+        Ok(HdrImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+fn rgbe_to_rgb([r, g, b, e]: [u8; 4]) -> [f32; 3] {
+    if e == 0 {
+        return [0.0; 3];
+    }
+    let scale = 2f32.powi(e as i32 - (128 + 8));
+    [
+        (r as f32 + 0.5) * scale,
+        (g as f32 + 0.5) * scale,
+        (b as f32 + 0.5) * scale,
+    ]
+}
+
+struct HdrReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> HdrReader<'a> {
+    fn byte(&mut self) -> Result<u8, AssetError> {
+        let (first, rest) = self
+            .bytes
+            .split_first()
+            .ok_or_else(|| AssetError::Load("unexpected end of HDR file".to_string()))?;
+        self.bytes = rest;
+        Ok(*first)
+    }
+
+    fn line(&mut self) -> Result<String, AssetError> {
+        let end = self
+            .bytes
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .ok_or_else(|| AssetError::Load("unexpected end of HDR header".to_string()))?;
+        let line = String::from_utf8_lossy(&self.bytes[..end]).trim_end().to_string();
+        self.bytes = &self.bytes[end + 1..];
+        Ok(line)
+    }
+
+    fn scanline(&mut self, scanline: &mut [[u8; 4]]) -> Result<(), AssetError> {
+        let width = scanline.len();
+        let is_rle = (8..0x8000).contains(&width) && self.bytes.len() >= 4 && self.bytes[0] == 2 && self.bytes[1] == 2;
+        if !is_rle || self.bytes[2] & 0x80 != 0 {
+            return self.flat_scanline(scanline);
+        }
+        let encoded_width = ((self.bytes[2] as usize) << 8) | self.bytes[3] as usize;
+        if encoded_width != width {
+            return Err(AssetError::Load(format!(
+                "HDR scanline is {} pixels wide, expected {}",
+                encoded_width, width
+            )));
+        }
+        self.bytes = &self.bytes[4..];
+        // Each channel is stored separately as runs and literal spans.
+        for channel in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = self.byte()? as usize;
+                let (count, run) = if count > 128 { (count - 128, true) } else { (count, false) };
+                if count == 0 || x + count > width {
+                    return Err(AssetError::Load("corrupt run-length encoded HDR scanline".to_string()));
+                }
+                if run {
+                    let value = self.byte()?;
+                    scanline[x..x + count].iter_mut().for_each(|pixel| pixel[channel] = value);
+                } else {
+                    for pixel in scanline[x..x + count].iter_mut() {
+                        pixel[channel] = self.byte()?;
+                    }
+                }
+                x += count;
+            }
+        }
+        Ok(())
+    }
+
+    /// Plain RGBE pixels, where a `1 1 1 n` pixel repeats the previous one.
+    fn flat_scanline(&mut self, scanline: &mut [[u8; 4]]) -> Result<(), AssetError> {
+        let mut x = 0;
+        let mut shift = 0;
+        while x < scanline.len() {
+            let pixel = [self.byte()?, self.byte()?, self.byte()?, self.byte()?];
+            if pixel[..3] == [1, 1, 1] && x > 0 {
+                let repeat = (pixel[3] as usize) << shift;
+                if x + repeat > scanline.len() {
+                    return Err(AssetError::Load("corrupt run-length encoded HDR scanline".to_string()));
+                }
+                let previous = scanline[x - 1];
+                scanline[x..x + repeat].fill(previous);
+                x += repeat;
+                shift += 8;
+            } else {
+                scanline[x] = pixel;
+                x += 1;
+                shift = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Radiance `.hdr` images, e.g. environment maps for image based lighting.
+pub struct HdrImageLoader;
+
+impl AssetLoader for HdrImageLoader {
+    type Asset = HdrImage;
+
+    fn extensions(&self) -> &[&str] {
+        &["hdr"]
+    }
+
+    fn load(&self, bytes: &[u8], _context: &mut LoadContext) -> Result<HdrImage, AssetError> {
+        HdrImage::from_bytes(bytes)
+    }
+}
@@ -0,0 +1,78 @@
+use std::any::{Any, TypeId};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::{AssetError, AssetServer, Handle};
+
+/// Turns file bytes into an asset. Register one per file type with
+/// `AssetServer::register_loader`; loaders run on the server's worker threads.
+pub trait AssetLoader: Send + Sync + 'static {
+    type Asset: Send + Sync + 'static;
+
+    /// Lowercase extensions without the dot, e.g. `["png", "jpg"]`.
+    fn extensions(&self) -> &[&str];
+
+    fn load(&self, bytes: &[u8], context: &mut LoadContext) -> Result<Self::Asset, AssetError>;
+}
+
+/// What a loader can reach besides the asset's bytes.
+pub struct LoadContext<'a> {
+    path: &'a Path,
+    server: &'a AssetServer,
+    dependencies: Vec<PathBuf>,
+}
+
+impl<'a> LoadContext<'a> {
+    pub(crate) fn new(path: &'a Path, server: &'a AssetServer) -> Self {
+        LoadContext {
+            path,
+            server,
+            dependencies: Vec::new(),
+        }
+    }
+
+    /// Path of the asset being loaded, relative to the asset root.
+    pub fn path(&self) -> &Path {
+        self.path
+    }
+
+    /// Starts loading another asset this one refers to, e.g. a material's textures. Keep the
+    /// handle in the loaded asset to keep the dependency alive.
+    pub fn load<T: Send + Sync + 'static, P: AsRef<Path>>(&mut self, path: P) -> Handle<T> {
+        self.dependencies.push(path.as_ref().to_path_buf());
+        self.server.load(path)
+    }
+
+    /// Reads another file, e.g. a sidecar, as part of this asset.
+    pub fn read<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<u8>, AssetError> {
+        self.dependencies.push(path.as_ref().to_path_buf());
+        self.server.read(path.as_ref())
+    }
+
+    pub(crate) fn into_dependencies(self) -> Vec<PathBuf> {
+        self.dependencies
+    }
+}
+
+pub(crate) type ErasedAsset = Arc<dyn Any + Send + Sync>;
+
+/// Object-safe form of `AssetLoader` so loaders of different asset types share one registry.
+pub(crate) trait ErasedLoader: Send + Sync {
+    fn asset_type(&self) -> (TypeId, &'static str);
+    fn extensions(&self) -> Vec<String>;
+    fn load(&self, bytes: &[u8], context: &mut LoadContext) -> Result<ErasedAsset, AssetError>;
+}
+
+impl<L: AssetLoader> ErasedLoader for L {
+    fn asset_type(&self) -> (TypeId, &'static str) {
+        (TypeId::of::<L::Asset>(), std::any::type_name::<L::Asset>())
+    }
+
+    fn extensions(&self) -> Vec<String> {
+        AssetLoader::extensions(self).iter().map(|extension| extension.to_lowercase()).collect()
+    }
+
+    fn load(&self, bytes: &[u8], context: &mut LoadContext) -> Result<ErasedAsset, AssetError> {
+        AssetLoader::load(self, bytes, context).map(|asset| Arc::new(asset) as ErasedAsset)
+    }
+}
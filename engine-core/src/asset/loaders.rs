@@ -0,0 +1,48 @@
+use super::{AssetError, AssetLoader, LoadContext};
+use crate::scene::{Scene, SceneFormat};
+
+/// UTF-8 text files.
+pub struct TextLoader;
+
+impl AssetLoader for TextLoader {
+    type Asset = String;
+
+    fn extensions(&self) -> &[&str] {
+        &["txt", "md", "json"]
+    }
+
+    fn load(&self, bytes: &[u8], _context: &mut LoadContext) -> Result<String, AssetError> {
+        String::from_utf8(bytes.to_vec()).map_err(|err| AssetError::Load(err.to_string()))
+    }
+}
+
+/// Raw bytes, for formats the engine does not parse itself.
+pub struct BytesLoader;
+
+impl AssetLoader for BytesLoader {
+    type Asset = Vec<u8>;
+
+    fn extensions(&self) -> &[&str] {
+        &["bin"]
+    }
+
+    fn load(&self, bytes: &[u8], _context: &mut LoadContext) -> Result<Vec<u8>, AssetError> {
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Scene and prefab files, parsed but not spawned.
+pub struct SceneLoader;
+
+impl AssetLoader for SceneLoader {
+    type Asset = Scene;
+
+    fn extensions(&self) -> &[&str] {
+        &["scene", "sceneb", "prefab"]
+    }
+
+    fn load(&self, bytes: &[u8], context: &mut LoadContext) -> Result<Scene, AssetError> {
+        let format = SceneFormat::from_path(context.path()).unwrap_or(SceneFormat::Yaml);
+        Scene::from_bytes(bytes, format).map_err(|err| AssetError::Load(err.to_string()))
+    }
+}
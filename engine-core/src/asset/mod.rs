@@ -0,0 +1,17 @@
+mod cooked;
+mod error;
+mod event;
+mod handle;
+mod hdr;
+mod loader;
+mod loaders;
+mod server;
+
+pub use cooked::*;
+pub use error::*;
+pub use event::*;
+pub use handle::*;
+pub use hdr::*;
+pub use loader::*;
+pub use loaders::*;
+pub use server::*;
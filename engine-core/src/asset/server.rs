@@ -0,0 +1,507 @@
+use std::any::TypeId;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock, Weak};
+use std::thread;
+use std::time::Duration;
+
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use super::{
+    AssetError, AssetEvent, AssetId, AssetLoader, BytesLoader, CookedMeshLoader, CookedTextureLoader, ErasedAsset,
+    ErasedLoader, Handle, HandleInner, HdrImageLoader, LoadContext, SceneLoader, SpirvLoader, TextLoader,
+};
+use crate::material::{MaterialLoader, ShaderTemplateLoader};
+use crate::vfs::{Vfs, VfsPath};
+
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    Failed(AssetError),
+}
+
+struct Entry {
+    path: Option<PathBuf>,
+    type_id: TypeId,
+    type_name: &'static str,
+    state: LoadState,
+    asset: Option<ErasedAsset>,
+    handle: Weak<HandleInner>,
+    dependencies: Vec<PathBuf>,
+    reloading: bool,
+}
+
+#[derive(Default)]
+struct Assets {
+    entries: HashMap<AssetId, Entry>,
+    by_path: HashMap<(PathBuf, TypeId), AssetId>,
+    next_id: u64,
+    /// Finished reloads waiting for `update` to swap them in.
+    reloaded: Vec<(AssetId, Result<ErasedAsset, AssetError>, Vec<PathBuf>)>,
+    /// Events of first loads, published by the next `update`.
+    events: Vec<AssetEvent>,
+}
+
+struct AssetWatcher {
+    root: PathBuf,
+    events: Receiver<DebouncedEvent>,
+    _watcher: RecommendedWatcher,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct Shared {
+    vfs: Vfs,
+    root: VfsPath,
+    assets: Mutex<Assets>,
+    state_changed: Condvar,
+    loaders: RwLock<HashMap<String, Arc<dyn ErasedLoader>>>,
+    jobs: Sender<Job>,
+    drops: Sender<AssetId>,
+    dropped: Mutex<Receiver<AssetId>>,
+    watcher: Mutex<Option<AssetWatcher>>,
+    subscribers: Mutex<Vec<Sender<AssetEvent>>>,
+}
+
+/// Loads assets from files below `root`, a directory in the virtual filesystem, on a pool of
+/// worker threads. `load` returns a handle
+/// at once; the asset becomes available through `get` when its `LoadState` is `Loaded`.
+/// Requests for the same path and type share one asset, which is unloaded by the `update`
+/// after its last handle drops. Clones share the same server.
+#[derive(Clone)]
+pub struct AssetServer {
+    shared: Arc<Shared>,
+}
+
+impl AssetServer {
+    /// `threads` of 0 uses one less than the number of cores, but at least one.
+    pub fn new(vfs: Vfs, root: VfsPath, threads: usize) -> Self {
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, |count| count.get().saturating_sub(1).max(1)),
+            threads => threads,
+        };
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..threads {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("asset-loader-{}", index))
+                .spawn(move || loop {
+                    // Exits once every server clone, and with it the job sender, is gone.
+                    let job = match receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    job();
+                })
+                .expect("failed to spawn asset loader thread");
+        }
+
+        let (drops, dropped) = mpsc::channel();
+        let server = AssetServer {
+            shared: Arc::new(Shared {
+                vfs,
+                root,
+                assets: Mutex::new(Assets::default()),
+                state_changed: Condvar::new(),
+                loaders: RwLock::new(HashMap::new()),
+                jobs,
+                drops,
+                dropped: Mutex::new(dropped),
+                watcher: Mutex::new(None),
+                subscribers: Mutex::new(Vec::new()),
+            }),
+        };
+        server.register_loader(TextLoader);
+        server.register_loader(BytesLoader);
+        server.register_loader(SceneLoader);
+        server.register_loader(CookedTextureLoader);
+        server.register_loader(CookedMeshLoader);
+        server.register_loader(HdrImageLoader);
+        server.register_loader(SpirvLoader);
+        server.register_loader(ShaderTemplateLoader);
+        server.register_loader(MaterialLoader);
+        server
+    }
+
+    pub fn root(&self) -> &VfsPath {
+        &self.shared.root
+    }
+
+    /// Makes `loader` handle its extensions, replacing earlier loaders for them.
+    pub fn register_loader<L: AssetLoader>(&self, loader: L) {
+        let loader: Arc<dyn ErasedLoader> = Arc::new(loader);
+        let mut loaders = self.shared.loaders.write().unwrap();
+        for extension in loader.extensions() {
+            if loaders.insert(extension.clone(), loader.clone()).is_some() {
+                log::debug!("Replacing the asset loader for .{}", extension);
+            }
+        }
+    }
+
+    /// Returns a handle to the asset at `path`, relative to the asset root, and starts loading
+    /// it unless it is already loaded or loading.
+    pub fn load<T: Send + Sync + 'static, P: AsRef<Path>>(&self, path: P) -> Handle<T> {
+        let path = normalize(path.as_ref());
+        let key = (path.clone(), TypeId::of::<T>());
+        let mut assets = self.lock();
+        if let Some(id) = assets.by_path.get(&key).copied() {
+            let entry = assets.entries.get_mut(&id).unwrap();
+            if let Some(inner) = entry.handle.upgrade() {
+                return Handle::new(inner);
+            }
+            // Every handle was dropped but `update` has not unloaded it yet; revive it.
+            if entry.state != LoadState::Loading {
+                let inner = self.new_handle(id, Some(path));
+                entry.handle = Arc::downgrade(&inner);
+                return Handle::new(inner);
+            }
+        }
+
+        let id = next_id(&mut assets);
+        let inner = self.new_handle(id, Some(path.clone()));
+        assets.entries.insert(
+            id,
+            Entry {
+                path: Some(path.clone()),
+                type_id: TypeId::of::<T>(),
+                type_name: std::any::type_name::<T>(),
+                state: LoadState::Loading,
+                asset: None,
+                handle: Arc::downgrade(&inner),
+                dependencies: Vec::new(),
+                reloading: false,
+            },
+        );
+        assets.by_path.insert(key, id);
+        drop(assets);
+
+        self.queue_load(id, path, (TypeId::of::<T>(), std::any::type_name::<T>()), false);
+        Handle::new(inner)
+    }
+
+    /// Adds an asset built in memory, e.g. a generated mesh.
+    pub fn add<T: Send + Sync + 'static>(&self, asset: T) -> Handle<T> {
+        let mut assets = self.lock();
+        let id = next_id(&mut assets);
+        let inner = self.new_handle(id, None);
+        assets.entries.insert(
+            id,
+            Entry {
+                path: None,
+                type_id: TypeId::of::<T>(),
+                type_name: std::any::type_name::<T>(),
+                state: LoadState::Loaded,
+                asset: Some(Arc::new(asset)),
+                handle: Arc::downgrade(&inner),
+                dependencies: Vec::new(),
+                reloading: false,
+            },
+        );
+        Handle::new(inner)
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self, handle: &Handle<T>) -> Option<Arc<T>> {
+        let asset = self.lock().entries.get(&handle.id())?.asset.clone()?;
+        asset.downcast::<T>().ok()
+    }
+
+    pub fn load_state<T>(&self, handle: &Handle<T>) -> LoadState {
+        self.lock()
+            .entries
+            .get(&handle.id())
+            .map_or(LoadState::Loading, |entry| entry.state.clone())
+    }
+
+    pub fn is_loaded<T>(&self, handle: &Handle<T>) -> bool {
+        self.load_state(handle) == LoadState::Loaded
+    }
+
+    /// Blocks until the asset has finished loading, for tools and loading screens.
+    pub fn wait<T>(&self, handle: &Handle<T>) -> LoadState {
+        let mut assets = self.lock();
+        loop {
+            match assets.entries.get(&handle.id()).map(|entry| &entry.state) {
+                Some(LoadState::Loading) => assets = self.shared.state_changed.wait(assets).unwrap(),
+                Some(state) => return state.clone(),
+                None => return LoadState::Loading,
+            }
+        }
+    }
+
+    /// Files the asset read through its `LoadContext` while loading.
+    pub fn dependencies<T>(&self, handle: &Handle<T>) -> Vec<PathBuf> {
+        self.lock()
+            .entries
+            .get(&handle.id())
+            .map_or_else(Vec::new, |entry| entry.dependencies.clone())
+    }
+
+    /// Number of assets currently held, loaded or not.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Receives every event `update` returns.
+    pub fn subscribe(&self) -> Receiver<AssetEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.shared.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Watches the asset root and reloads assets, and assets depending on them, when their
+    /// files change. Only the highest priority directory mounted under the root is watched;
+    /// archives never change while mounted.
+    pub fn watch_for_changes(&self) -> Result<(), AssetError> {
+        let root = self
+            .shared
+            .vfs
+            .native_path(&self.shared.root)
+            .ok_or_else(|| AssetError::Watch(format!("{} is not backed by a directory", self.shared.root)))?;
+        let root = root
+            .canonicalize()
+            .map_err(|err| AssetError::Watch(format!("{}: {}", root.display(), err)))?;
+        let (sender, events) = mpsc::channel();
+        let mut fs_watcher = watcher(sender, DEBOUNCE).map_err(|err| AssetError::Watch(err.to_string()))?;
+        fs_watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|err| AssetError::Watch(err.to_string()))?;
+        log::info!("Watching asset directory {:?}", root);
+        *self.shared.watcher.lock().unwrap() = Some(AssetWatcher {
+            root,
+            events,
+            _watcher: fs_watcher,
+        });
+        Ok(())
+    }
+
+    pub fn stop_watching(&self) {
+        self.shared.watcher.lock().unwrap().take();
+    }
+
+    pub fn is_watching(&self) -> bool {
+        self.shared.watcher.lock().unwrap().is_some()
+    }
+
+    /// Reloads every loaded asset read from `path` or depending on it, and returns how many.
+    /// The new versions are swapped in by a later `update`; until then `get` returns the old.
+    pub fn reload<P: AsRef<Path>>(&self, path: P) -> usize {
+        let path = normalize(path.as_ref());
+        let mut assets = self.lock();
+        let mut queued = Vec::new();
+        for (id, entry) in assets.entries.iter_mut() {
+            let affected = entry.path.as_ref() == Some(&path)
+                || entry.dependencies.iter().any(|dependency| normalize(dependency) == path);
+            if !affected || entry.reloading || entry.state == LoadState::Loading {
+                continue;
+            }
+            entry.reloading = true;
+            queued.push((*id, entry.path.clone().unwrap(), (entry.type_id, entry.type_name)));
+        }
+        drop(assets);
+        for (id, path, asset_type) in queued.iter() {
+            log::info!("Reloading asset '{}'", path.display());
+            self.queue_load(*id, path.clone(), *asset_type, true);
+        }
+        queued.len()
+    }
+
+    /// Runs once per frame: starts reloads for changed files, swaps in finished reloads and
+    /// unloads assets whose last handle has dropped. Returns what happened since the last call.
+    pub fn update(&self) -> Vec<AssetEvent> {
+        for path in self.changed_files() {
+            self.reload(path);
+        }
+
+        let mut events = Vec::new();
+        let mut released = Vec::new();
+        let mut assets = self.lock();
+        events.append(&mut assets.events);
+        let mut state_changed = false;
+        for (id, result, dependencies) in std::mem::take(&mut assets.reloaded) {
+            let entry = match assets.entries.get_mut(&id) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            entry.reloading = false;
+            let path = entry.path.clone();
+            match result {
+                Ok(asset) => {
+                    released.extend(entry.asset.replace(asset));
+                    entry.dependencies = dependencies;
+                    // A reload may fix an asset whose first load failed.
+                    state_changed |= entry.state != LoadState::Loaded;
+                    entry.state = LoadState::Loaded;
+                    events.push(AssetEvent::Modified { id, path });
+                }
+                Err(error) => events.push(AssetEvent::Failed { id, path, error }),
+            }
+        }
+
+        let dropped: Vec<AssetId> = self.shared.dropped.lock().unwrap().try_iter().collect();
+        for id in dropped {
+            match assets.entries.get(&id) {
+                Some(entry) if entry.handle.strong_count() == 0 => {}
+                _ => continue,
+            }
+            let entry = assets.entries.remove(&id).unwrap();
+            if let Some(path) = entry.path.clone() {
+                let key = (path, entry.type_id);
+                if assets.by_path.get(&key) == Some(&id) {
+                    assets.by_path.remove(&key);
+                }
+            }
+            log::debug!("Unloaded asset {}", entry.path.as_deref().unwrap_or_else(|| Path::new("<memory>")).display());
+            events.push(AssetEvent::Unloaded { id, path: entry.path });
+            released.extend(entry.asset);
+        }
+        if state_changed {
+            self.shared.state_changed.notify_all();
+        }
+        // Assets may hold handles to other assets, so they are dropped after the lock is released;
+        // those handles are unloaded by a later `update`.
+        drop(assets);
+        drop(released);
+
+        if !events.is_empty() {
+            self.shared
+                .subscribers
+                .lock()
+                .unwrap()
+                .retain(|subscriber| events.iter().all(|event| subscriber.send(event.clone()).is_ok()));
+        }
+        events
+    }
+
+    pub(crate) fn read(&self, path: &Path) -> Result<Vec<u8>, AssetError> {
+        let full = self
+            .shared
+            .root
+            .join(path)
+            .map_err(|err| AssetError::Io(err.to_string()))?;
+        self.shared.vfs.read(&full).map_err(|err| AssetError::Io(err.to_string()))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Assets> {
+        self.shared.assets.lock().unwrap()
+    }
+
+    fn new_handle(&self, id: AssetId, path: Option<PathBuf>) -> Arc<HandleInner> {
+        Arc::new(HandleInner {
+            id,
+            path,
+            drops: self.shared.drops.clone(),
+        })
+    }
+
+    /// Changed files below the root since the last call, relative to the root.
+    fn changed_files(&self) -> BTreeSet<PathBuf> {
+        let watcher = self.shared.watcher.lock().unwrap();
+        let watcher = match watcher.as_ref() {
+            Some(watcher) => watcher,
+            None => return BTreeSet::new(),
+        };
+        let mut changed = BTreeSet::new();
+        while let Ok(event) = watcher.events.try_recv() {
+            match event {
+                // Editors often save by writing a new file and renaming it over the old one.
+                DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Rename(_, path) => {
+                    if let Ok(relative) = path.strip_prefix(&watcher.root) {
+                        changed.insert(relative.to_path_buf());
+                    }
+                }
+                DebouncedEvent::Error(err, path) => log::warn!("Asset watcher error on {:?}: {:?}", path, err),
+                _ => {}
+            }
+        }
+        changed
+    }
+
+    fn queue_load(&self, id: AssetId, path: PathBuf, asset_type: (TypeId, &'static str), reload: bool) {
+        let server = self.clone();
+        let job: Job = Box::new(move || {
+            let mut context = LoadContext::new(&path, &server);
+            let result = server.run_loader(&path, asset_type, &mut context);
+            let dependencies = context.into_dependencies();
+            if let Err(err) = &result {
+                log::error!("Failed to load asset '{}': {}", path.display(), err);
+            }
+            server.finish(id, result, dependencies, reload);
+        });
+        if self.shared.jobs.send(job).is_err() {
+            log::error!("Asset loader threads are gone, cannot load asset {}", id);
+        }
+    }
+
+    fn run_loader(
+        &self,
+        path: &Path,
+        (expected, expected_name): (TypeId, &'static str),
+        context: &mut LoadContext,
+    ) -> Result<ErasedAsset, AssetError> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+        let loader = self.shared.loaders.read().unwrap().get(&extension).cloned();
+        let loader = loader.ok_or_else(|| AssetError::NoLoader(path.display().to_string()))?;
+        let (type_id, type_name) = loader.asset_type();
+        if type_id != expected {
+            return Err(AssetError::WrongType {
+                path: path.display().to_string(),
+                expected: expected_name,
+                loaded: type_name,
+            });
+        }
+        let bytes = self.read(path)?;
+        loader.load(&bytes, context)
+    }
+
+    fn finish(&self, id: AssetId, result: Result<ErasedAsset, AssetError>, dependencies: Vec<PathBuf>, reload: bool) {
+        let mut assets = self.lock();
+        if reload {
+            assets.reloaded.push((id, result, dependencies));
+            return;
+        }
+        // The asset may have been unloaded while it was loading.
+        let entry = match assets.entries.get_mut(&id) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let path = entry.path.clone();
+        let event = match result {
+            Ok(asset) => {
+                entry.asset = Some(asset);
+                entry.state = LoadState::Loaded;
+                AssetEvent::Loaded { id, path }
+            }
+            Err(error) => {
+                entry.state = LoadState::Failed(error.clone());
+                AssetEvent::Failed { id, path, error }
+            }
+        };
+        entry.dependencies = dependencies;
+        assets.events.push(event);
+        drop(assets);
+        self.shared.state_changed.notify_all();
+    }
+}
+
+fn next_id(assets: &mut Assets) -> AssetId {
+    assets.next_id += 1;
+    AssetId(assets.next_id)
+}
+
+/// Drops `.` components so `./a.txt` and `a.txt` share one asset.
+fn normalize(path: &Path) -> PathBuf {
+    path.components().filter(|component| *component != Component::CurDir).collect()
+}
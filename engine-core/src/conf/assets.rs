@@ -0,0 +1,11 @@
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AssetsConfig {
+  /// Asset directory, relative to the project directory when there is one.
+  pub root: String,
+  /// Background loading threads, 0 picks one less than the number of cores.
+  pub threads: u32,
+  /// Reload assets when their files change, switchable while running.
+  pub hot_reload: bool
+}
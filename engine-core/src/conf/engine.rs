@@ -1,10 +1,15 @@
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
-use super::{VulkanConfig, WindowConfig};
+use super::{AssetsConfig, LoggingConfig, ProfilerConfig, RenderConfig, TimeConfig, VulkanConfig, WindowConfig};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct EngineConfig {
   pub version: i32,
   pub window: WindowConfig,
-  pub vulkan: VulkanConfig
-}
\ No newline at end of file
+  pub vulkan: VulkanConfig,
+  pub profiler: ProfilerConfig,
+  pub logging: LoggingConfig,
+  pub time: TimeConfig,
+  pub assets: AssetsConfig,
+  pub render: RenderConfig
+}
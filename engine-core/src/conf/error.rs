@@ -0,0 +1,61 @@
+use std::fmt::Display;
+
+use super::ConfigIssue;
+
+#[derive(Debug)]
+pub enum ConfigError {
+  Figment(Box<figment::Error>),
+  InvalidOverride(String),
+  MissingVersion,
+  UnsupportedVersion { found: i32, supported: i32 },
+  MissingMigration { from: i32, to: i32 },
+  Read(String),
+  Write(String),
+  Invalid(Vec<ConfigIssue>),
+  Watch(String),
+  InvalidInputMap(String),
+}
+
+impl Display for ConfigError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ConfigError::Figment(err) => write!(f, "{}", err),
+      ConfigError::InvalidOverride(arg) => write!(
+        f,
+        "invalid override '{}', expected the form 'key.path=value' (e.g. window.width=1920)",
+        arg
+      ),
+      ConfigError::MissingVersion => write!(f, "config has no integer 'version' key"),
+      ConfigError::UnsupportedVersion { found, supported } => write!(
+        f,
+        "config version {} is newer than the newest supported version {}; upgrade the engine or \
+         lower 'version' after checking the file against the current schema",
+        found, supported
+      ),
+      ConfigError::MissingMigration { from, to } => write!(
+        f,
+        "no migration registered to upgrade config from version {} towards {}",
+        from, to
+      ),
+      ConfigError::Read(err) => write!(f, "failed to read config: {}", err),
+      ConfigError::Write(err) => write!(f, "failed to write config: {}", err),
+      ConfigError::Watch(err) => write!(f, "failed to watch config files: {}", err),
+      ConfigError::InvalidInputMap(err) => write!(f, "invalid input map {}", err),
+      ConfigError::Invalid(issues) => {
+        write!(f, "config has {} problem(s):", issues.len())?;
+        for issue in issues.iter() {
+          write!(f, "\n  - {}", issue)?;
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<figment::Error> for ConfigError {
+  fn from(err: figment::Error) -> Self {
+    ConfigError::Figment(Box::new(err))
+  }
+}
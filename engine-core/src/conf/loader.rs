@@ -0,0 +1,410 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use figment::{
+  providers::{Env, Format, Json, Serialized, Toml, Yaml},
+  value::{Dict, Map, Value},
+  Figment, Metadata, Profile, Provider,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{save_config, ConfigError, ConfigFormat, Migrator};
+use crate::vfs::{Vfs, VfsError, VfsPath, ENGINE_SCHEME, PROJECT_SCHEME, USER_SCHEME};
+
+pub const DEFAULT_CONFIG_FILE: &str = "default.yaml";
+pub const PROJECT_CONFIG_FILE: &str = "daybreak.yaml";
+pub const ENV_PREFIX: &str = "DAYBREAK_";
+
+/// Wraps a provider so `describe` can report which layer a value came from.
+struct Layer<P> {
+  name: &'static str,
+  detail: Option<String>,
+  provider: P,
+}
+
+impl<P: Provider> Provider for Layer<P> {
+  fn metadata(&self) -> Metadata {
+    let mut metadata = self.provider.metadata();
+    match &self.detail {
+      Some(detail) => {
+        metadata.name = format!("{} ({})", self.name, detail).into();
+        metadata.source = None;
+      }
+      None => metadata.name = format!("{} ({})", self.name, metadata.name).into(),
+    }
+    metadata
+  }
+
+  fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+    self.provider.data()
+  }
+}
+
+#[derive(Debug, Clone)]
+enum LayerSpec {
+  File(&'static str, VfsPath),
+  Env,
+  Override(String),
+  Value(String, Value, String),
+}
+
+impl LayerSpec {
+  fn merge_into(&self, figment: Figment, vfs: &Vfs) -> Result<Figment, ConfigError> {
+    Ok(match self {
+      LayerSpec::File(name, path) => merge_config_file(figment, name, path, vfs)?,
+      LayerSpec::Env => figment.merge(Layer {
+        name: "environment",
+        detail: None,
+        // `DAYBREAK_CONFIG_DIR` locates the defaults for the editor CLI and is not a config key.
+        provider: Env::prefixed(ENV_PREFIX).ignore(&["CONFIG_DIR"]).split("__"),
+      }),
+      LayerSpec::Override(arg) => {
+        let (key, value) = parse_override(arg)?;
+        figment.merge(Layer {
+          name: "command line",
+          detail: Some(format!("--set {}", arg)),
+          provider: Serialized::default(key, value),
+        })
+      }
+      LayerSpec::Value(key, value, origin) => figment.merge(Layer {
+        name: "command line",
+        detail: Some(origin.to_string()),
+        provider: Serialized::default(key, value.clone()),
+      }),
+    })
+  }
+}
+
+/// Files on disk go through figment's file providers so `describe` reports the real path;
+/// files inside a pack archive are read through the VFS. Missing files are empty layers.
+fn merge_config_file(figment: Figment, name: &'static str, path: &VfsPath, vfs: &Vfs) -> Result<Figment, ConfigError> {
+  let format = ConfigFormat::from_path(path.path());
+  if let Some(native) = vfs.native_path(path) {
+    return Ok(match format {
+      ConfigFormat::Yaml => figment.merge(Layer {
+        name,
+        detail: None,
+        provider: Yaml::file(native),
+      }),
+      ConfigFormat::Toml => figment.merge(Layer {
+        name,
+        detail: None,
+        provider: Toml::file(native),
+      }),
+      ConfigFormat::Json => figment.merge(Layer {
+        name,
+        detail: None,
+        provider: Json::file(native),
+      }),
+    });
+  }
+  let contents = match vfs.read_to_string(path) {
+    Ok(contents) => contents,
+    Err(VfsError::NotFound(_)) | Err(VfsError::NotMounted(_)) => return Ok(figment),
+    Err(err) => return Err(ConfigError::Read(err.to_string())),
+  };
+  let detail = Some(path.to_string());
+  Ok(match format {
+    ConfigFormat::Yaml => figment.merge(Layer {
+      name,
+      detail,
+      provider: Yaml::string(&contents),
+    }),
+    ConfigFormat::Toml => figment.merge(Layer {
+      name,
+      detail,
+      provider: Toml::string(&contents),
+    }),
+    ConfigFormat::Json => figment.merge(Layer {
+      name,
+      detail,
+      provider: Json::string(&contents),
+    }),
+  })
+}
+
+/// Merges configuration layers in increasing priority: built-in defaults, project config,
+/// per-user config, `DAYBREAK_` environment variables and `--set key=value` overrides.
+/// Config files are read through the loader's virtual filesystem, so they may also come from
+/// pack archives mounted at `engine://`, `project://` or `user://`.
+#[derive(Clone)]
+pub struct ConfigLoader {
+  figment: Figment,
+  layers: Vec<LayerSpec>,
+  migrator: Option<Migrator>,
+  write_back: bool,
+  vfs: Vfs,
+}
+
+impl ConfigLoader {
+  /// Mounts `defaults_dir` at `engine://` and layers its `default.yaml`.
+  pub fn new<P: AsRef<Path>>(defaults_dir: P) -> Self {
+    let vfs = Vfs::new();
+    if let Err(err) = vfs.mount_dir(ENGINE_SCHEME, defaults_dir.as_ref(), 0) {
+      log::error!("Failed to mount {:?} at {}://: {}", defaults_dir.as_ref(), ENGINE_SCHEME, err);
+    }
+    ConfigLoader::from_vfs(&vfs)
+  }
+
+  /// Layers `engine://default.yaml`, then `project://daybreak.yaml` when a project is mounted,
+  /// from a filesystem the caller has already set up.
+  pub fn from_vfs(vfs: &Vfs) -> Self {
+    let loader = ConfigLoader {
+      figment: Figment::new(),
+      layers: Vec::new(),
+      migrator: None,
+      write_back: false,
+      vfs: vfs.clone(),
+    }
+    .merge_file("defaults", ENGINE_SCHEME, DEFAULT_CONFIG_FILE);
+    if vfs.is_mounted(PROJECT_SCHEME) {
+      loader.merge_file("project", PROJECT_SCHEME, PROJECT_CONFIG_FILE)
+    } else {
+      loader
+    }
+  }
+
+  /// Mounts `project_dir` at `project://` and layers its `daybreak.yaml` over the defaults, if
+  /// present.
+  pub fn with_project<P: AsRef<Path>>(self, project_dir: P) -> Self {
+    if let Err(err) = self.vfs.mount_dir(PROJECT_SCHEME, project_dir.as_ref(), 0) {
+      log::error!("Failed to mount {:?} at {}://: {}", project_dir.as_ref(), PROJECT_SCHEME, err);
+    }
+    self.merge_file("project", PROJECT_SCHEME, PROJECT_CONFIG_FILE)
+  }
+
+  /// Layers `user://<app_name>.yaml`, by default `~/.config/daybreak/<app_name>.yaml`.
+  pub fn with_user(self, app_name: &str) -> Self {
+    self.vfs.mount_user_dir();
+    if !self.vfs.is_mounted(USER_SCHEME) {
+      log::warn!("Could not determine the user config directory, skipping user config");
+      return self;
+    }
+    self.merge_file("user", USER_SCHEME, &format!("{}.yaml", app_name))
+  }
+
+  /// Layers environment variables, `DAYBREAK_WINDOW__WIDTH=1920` sets `window.width`.
+  pub fn with_env(self) -> Self {
+    self.merge(LayerSpec::Env).unwrap()
+  }
+
+  /// Layers command line overrides of the form `window.width=1920`.
+  pub fn with_overrides<S: AsRef<str>>(mut self, overrides: &[S]) -> Result<Self, ConfigError> {
+    for arg in overrides.iter() {
+      self = self.merge(LayerSpec::Override(arg.as_ref().to_string()))?;
+    }
+    Ok(self)
+  }
+
+  /// Layers a single typed value at a dotted key, e.g. from a dedicated command line flag.
+  /// `origin` is what `describe` reports as its source.
+  pub fn with_value<V: Into<Value>>(self, key: &str, value: V, origin: &str) -> Self {
+    self
+      .merge(LayerSpec::Value(key.to_string(), value.into(), origin.to_string()))
+      .unwrap()
+  }
+
+  /// The full stack used by the binaries: defaults, optional project, user, environment, overrides.
+  pub fn standard<S: AsRef<str>>(
+    defaults_dir: &Path,
+    project_dir: Option<&Path>,
+    app_name: &str,
+    overrides: &[S],
+  ) -> Result<Self, ConfigError> {
+    let mut loader = ConfigLoader::new(defaults_dir);
+    if let Some(project_dir) = project_dir {
+      loader = loader.with_project(project_dir);
+    }
+    loader.with_user(app_name).with_env().with_overrides(overrides)
+  }
+
+  /// Runs `migrator` over the merged config before extraction.
+  pub fn with_migrator(mut self, migrator: Migrator) -> Self {
+    self.migrator = Some(migrator);
+    self
+  }
+
+  /// When a migration runs, also rewrite the outdated config files on disk.
+  pub fn with_write_back(mut self, write_back: bool) -> Self {
+    self.write_back = write_back;
+    self
+  }
+
+  /// Re-reads every layer; figment snapshots provider data when it is merged.
+  pub fn reload(&self) -> Result<ConfigLoader, ConfigError> {
+    let mut figment = Figment::new();
+    for layer in self.layers.iter() {
+      figment = layer.merge_into(figment, &self.vfs)?;
+    }
+    Ok(ConfigLoader {
+      figment,
+      ..self.clone()
+    })
+  }
+
+  /// Files on disk backing the file layers, in priority order, whether or not they exist.
+  /// Layers read from pack archives have no file and are left out.
+  pub fn files(&self) -> Vec<PathBuf> {
+    self.file_layers().filter_map(|(_, path)| self.vfs.native_path(path)).collect()
+  }
+
+  /// Saves `config` into the highest-priority file layer, normally the per-user config, and
+  /// returns the path written. Unknown keys already in that file are preserved.
+  pub fn save<T: Serialize>(&self, config: &T) -> Result<PathBuf, ConfigError> {
+    let layer = match self.file_layers().last() {
+      Some((_, layer)) => layer,
+      None => return Err(ConfigError::Write("loader has no config file to save to".to_string())),
+    };
+    let path = match self.vfs.native_path(layer) {
+      Some(path) => path,
+      None => return Err(ConfigError::Write(format!("{} is inside a read-only archive", layer))),
+    };
+    save_config(config, &path)?;
+    Ok(path)
+  }
+
+  /// Directory of the built-in defaults, where sibling files such as the input map live.
+  /// `None` when the defaults come from an archive.
+  pub fn defaults_dir(&self) -> Option<PathBuf> {
+    self.layer_dir("defaults")
+  }
+
+  /// The directory given to `with_project`, if any.
+  pub fn project_dir(&self) -> Option<PathBuf> {
+    self.layer_dir("project")
+  }
+
+  /// The filesystem config files are read from; the engine loads input maps and assets
+  /// through the same mounts.
+  pub fn vfs(&self) -> &Vfs {
+    &self.vfs
+  }
+
+  fn file_layers(&self) -> impl Iterator<Item = (&'static str, &VfsPath)> {
+    self.layers.iter().filter_map(|layer| match layer {
+      LayerSpec::File(name, path) => Some((*name, path)),
+      _ => None,
+    })
+  }
+
+  fn layer_dir(&self, name: &str) -> Option<PathBuf> {
+    let (_, path) = self.file_layers().find(|(layer, _)| *layer == name)?;
+    self.vfs.native_path(path)?.parent().map(Path::to_path_buf)
+  }
+
+  fn merge(mut self, layer: LayerSpec) -> Result<Self, ConfigError> {
+    self.figment = layer.merge_into(self.figment, &self.vfs)?;
+    self.layers.push(layer);
+    Ok(self)
+  }
+
+  /// A file that cannot be read, e.g. a damaged archive entry, is logged and left out of the
+  /// merge but stays a layer, so `reload` reports it.
+  fn merge_file(mut self, name: &'static str, scheme: &str, file: &str) -> Self {
+    let path = VfsPath::new(scheme, file).expect("config file names are valid paths");
+    match merge_config_file(self.figment.clone(), name, &path, &self.vfs) {
+      Ok(figment) => self.figment = figment,
+      Err(err) => log::error!("Skipping {} config {}: {}", name, path, err),
+    }
+    self.layers.push(LayerSpec::File(name, path));
+    self
+  }
+
+  pub fn figment(&self) -> &Figment {
+    &self.figment
+  }
+
+  pub fn extract<T: DeserializeOwned>(&self) -> Result<T, ConfigError> {
+    let migrator = match &self.migrator {
+      Some(migrator) => migrator,
+      None => return Ok(self.figment.extract::<T>()?),
+    };
+
+    let mut dict: Dict = self.figment.extract()?;
+    if migrator.migrate(&mut dict)?.is_empty() {
+      return Ok(self.figment.extract::<T>()?);
+    }
+    if self.write_back {
+      for file in self.files() {
+        migrator.migrate_file(&file)?;
+      }
+    } else {
+      log::warn!("Config is outdated, run with config write-back enabled to upgrade the files on disk");
+    }
+    Ok(Figment::from(Serialized::defaults(dict)).extract::<T>()?)
+  }
+
+  /// Every effective leaf value keyed by its dotted path, rendered as text.
+  pub fn flatten(&self) -> Result<Vec<(String, String)>, ConfigError> {
+    let value: Value = self.figment.extract()?;
+    let mut leaves = Vec::new();
+    flatten("", &value, &mut leaves);
+    Ok(leaves)
+  }
+
+  /// Lists every effective value alongside the layer and source that provided it.
+  pub fn describe(&self) -> Result<String, ConfigError> {
+    let leaves = self.flatten()?;
+
+    let width = leaves.iter().map(|(key, value)| key.len() + value.len() + 3).max().unwrap_or(0);
+    let mut out = String::new();
+    for (key, value) in leaves.iter() {
+      let origin = match self.figment.find_metadata(key) {
+        Some(metadata) => match &metadata.source {
+          Some(source) => format!("{} - {}", metadata.name, source),
+          None => metadata.name.to_string(),
+        },
+        None => "unknown".to_string(),
+      };
+      let assignment = format!("{} = {}", key, value);
+      writeln!(out, "{:width$}  # {}", assignment, origin, width = width).unwrap();
+    }
+    Ok(out)
+  }
+}
+
+fn parse_override(arg: &str) -> Result<(&str, Value), ConfigError> {
+  match arg.split_once('=') {
+    Some((key, value)) if !key.trim().is_empty() => {
+      let value: Value = value.trim().parse().expect("infallible");
+      Ok((key.trim(), value))
+    }
+    _ => Err(ConfigError::InvalidOverride(arg.to_string())),
+  }
+}
+
+fn flatten(prefix: &str, value: &Value, leaves: &mut Vec<(String, String)>) {
+  match value {
+    Value::Dict(_, dict) if !dict.is_empty() => {
+      for (key, child) in dict.iter() {
+        let path = if prefix.is_empty() {
+          key.to_string()
+        } else {
+          format!("{}.{}", prefix, key)
+        };
+        flatten(&path, child, leaves);
+      }
+    }
+    _ => leaves.push((prefix.to_string(), display_value(value))),
+  }
+}
+
+fn display_value(value: &Value) -> String {
+  match value {
+    Value::String(_, string) => format!("{:?}", string),
+    Value::Char(_, char) => format!("{:?}", char),
+    Value::Bool(_, bool) => bool.to_string(),
+    Value::Num(_, num) => match (num.to_u128(), num.to_i128(), num.to_f64()) {
+      (Some(unsigned), _, _) => unsigned.to_string(),
+      (_, Some(signed), _) => signed.to_string(),
+      (_, _, Some(float)) => float.to_string(),
+      _ => String::new(),
+    },
+    Value::Empty(_, _) => "~".to_string(),
+    Value::Array(_, array) => format!(
+      "[{}]",
+      array.iter().map(display_value).collect::<Vec<_>>().join(", ")
+    ),
+    Value::Dict(_, _) => "{}".to_string(),
+  }
+}
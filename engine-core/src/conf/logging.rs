@@ -0,0 +1,21 @@
+use std::collections::BTreeMap;
+
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LoggingConfig {
+  pub level: String,
+  pub modules: BTreeMap<String, String>,
+  pub color: bool,
+  pub file: LogFileConfig
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LogFileConfig {
+  pub enabled: bool,
+  pub directory: String,
+  pub name: String,
+  pub max_size_kb: u64,
+  pub max_files: u32,
+  pub json: bool
+}
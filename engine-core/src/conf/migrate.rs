@@ -0,0 +1,262 @@
+use std::path::Path;
+
+use figment::{
+  providers::{Format, Yaml},
+  value::{Dict, Tag, Value},
+  Figment,
+};
+
+use super::ConfigError;
+
+pub const ENGINE_CONFIG_VERSION: i32 = 107;
+pub const EDITOR_CONFIG_VERSION: i32 = 100;
+
+pub type MigrationFn = fn(&mut Dict);
+
+#[derive(Clone)]
+pub struct Migration {
+  pub from: i32,
+  pub to: i32,
+  pub description: &'static str,
+  pub apply: MigrationFn,
+}
+
+/// Chain of schema upgrades applied to a raw config tree before it is deserialized.
+#[derive(Clone)]
+pub struct Migrator {
+  current: i32,
+  migrations: Vec<Migration>,
+}
+
+impl Migrator {
+  pub fn new(current: i32) -> Self {
+    Migrator {
+      current,
+      migrations: Vec::new(),
+    }
+  }
+
+  pub fn engine() -> Self {
+    Migrator::new(ENGINE_CONFIG_VERSION).register(
+      100,
+      101,
+      "add profiler section",
+      |dict| {
+        insert_default(dict, "profiler.enabled", Value::from(false));
+        insert_default(dict, "profiler.trace_path", Value::from("profile.json"));
+      },
+    )
+    .register(
+      101,
+      102,
+      "add live-reloadable window.vsync, logging and vulkan.validation settings",
+      |dict| {
+        insert_default(dict, "window.vsync", Value::from(true));
+        insert_default(dict, "logging.level", Value::from("debug"));
+        insert_default(dict, "vulkan.validation.min_severity", Value::from("verbose"));
+        insert_default(dict, "vulkan.validation.ignored_messages", Value::from(Vec::<String>::new()));
+      },
+    )
+    .register(
+      102,
+      103,
+      "add per-module log levels, console colour and rotating log files",
+      |dict| {
+        insert_default(dict, "logging.modules", Value::from(Dict::new()));
+        insert_default(dict, "logging.color", Value::from(true));
+        insert_default(dict, "logging.file.enabled", Value::from(false));
+        insert_default(dict, "logging.file.directory", Value::from("logs"));
+        insert_default(dict, "logging.file.name", Value::from("daybreak"));
+        insert_default(dict, "logging.file.max_size_kb", Value::from(1024));
+        insert_default(dict, "logging.file.max_files", Value::from(5));
+        insert_default(dict, "logging.file.json", Value::from(false));
+      },
+    )
+    .register(
+      103,
+      104,
+      "add fixed timestep and frame rate cap",
+      |dict| {
+        insert_default(dict, "time.fixed_hz", Value::from(60.0));
+        insert_default(dict, "time.max_fixed_steps", Value::from(5));
+        insert_default(dict, "time.max_fps", Value::from(0));
+      },
+    )
+    .register(
+      104,
+      105,
+      "add asset server",
+      |dict| {
+        insert_default(dict, "assets.root", Value::from("assets"));
+        insert_default(dict, "assets.threads", Value::from(0));
+      },
+    )
+    .register(
+      105,
+      106,
+      "add asset hot reload",
+      |dict| {
+        insert_default(dict, "assets.hot_reload", Value::from(true));
+      },
+    )
+    .register(
+      106,
+      107,
+      "add clustered forward renderer",
+      |dict| {
+        insert_default(dict, "render.clusters", Value::from(vec![16, 9, 24]));
+        insert_default(dict, "render.max_lights", Value::from(1024));
+        insert_default(dict, "render.max_lights_per_cluster", Value::from(128));
+        insert_default(dict, "render.environment_size", Value::from(256));
+      },
+    )
+  }
+
+  /// Upgrades for `EditorConfig`; none yet, but it still rejects configs newer than the editor.
+  pub fn editor() -> Self {
+    Migrator::new(EDITOR_CONFIG_VERSION)
+  }
+
+  pub fn register(mut self, from: i32, to: i32, description: &'static str, apply: MigrationFn) -> Self {
+    self.migrations.push(Migration {
+      from,
+      to,
+      description,
+      apply,
+    });
+    self
+  }
+
+  pub fn current_version(&self) -> i32 {
+    self.current
+  }
+
+  /// Upgrades `dict` in place to the current version, returning the steps that ran.
+  pub fn migrate(&self, dict: &mut Dict) -> Result<Vec<&Migration>, ConfigError> {
+    let mut version = read_version(dict)?;
+    if version > self.current {
+      return Err(ConfigError::UnsupportedVersion {
+        found: version,
+        supported: self.current,
+      });
+    }
+
+    let mut applied = Vec::new();
+    while version < self.current {
+      let step = self.migrations.iter().find(|migration| migration.from == version);
+      let step = match step {
+        Some(step) => step,
+        None => {
+          return Err(ConfigError::MissingMigration {
+            from: version,
+            to: self.current,
+          })
+        }
+      };
+      (step.apply)(dict);
+      dict.insert("version".to_string(), Value::from(step.to));
+      log::warn!(
+        "Migrated config from version {} to {}: {}",
+        step.from,
+        step.to,
+        step.description
+      );
+      version = step.to;
+      applied.push(step);
+    }
+    Ok(applied)
+  }
+
+  /// Upgrades a single config file on disk. Files without a `version` key are left alone since
+  /// they are partial overrides of a versioned base. Comments are not preserved.
+  pub fn migrate_file(&self, path: &Path) -> Result<bool, ConfigError> {
+    if !path.exists() {
+      return Ok(false);
+    }
+    let mut dict: Dict = Figment::from(Yaml::file(path)).extract()?;
+    if !dict.contains_key("version") {
+      return Ok(false);
+    }
+    if self.migrate(&mut dict)?.is_empty() {
+      return Ok(false);
+    }
+    let contents = serde_yaml::to_string(&dict).map_err(|err| ConfigError::Write(err.to_string()))?;
+    std::fs::write(path, contents).map_err(|err| ConfigError::Write(err.to_string()))?;
+    log::warn!("Wrote upgraded config back to {:?}", path);
+    Ok(true)
+  }
+}
+
+fn read_version(dict: &Dict) -> Result<i32, ConfigError> {
+  match dict.get("version") {
+    Some(Value::Num(_, num)) => num
+      .to_i128()
+      .or_else(|| num.to_u128().map(|version| version as i128))
+      .map(|version| version as i32)
+      .ok_or(ConfigError::MissingVersion),
+    _ => Err(ConfigError::MissingVersion),
+  }
+}
+
+/// Looks up a dotted key such as `window.title`.
+pub fn get_key<'a>(dict: &'a Dict, key: &str) -> Option<&'a Value> {
+  let mut parts = key.split('.');
+  let mut value = dict.get(parts.next()?)?;
+  for part in parts {
+    value = value.as_dict()?.get(part)?;
+  }
+  Some(value)
+}
+
+/// Inserts `value` at a dotted key, creating intermediate tables, unless a value is already set.
+pub fn insert_default(dict: &mut Dict, key: &str, value: Value) {
+  if get_key(dict, key).is_none() {
+    set_key(dict, key, value);
+  }
+}
+
+pub fn set_key(dict: &mut Dict, key: &str, value: Value) {
+  let (parent, leaf) = match key.rsplit_once('.') {
+    Some((parent, leaf)) => (Some(parent), leaf),
+    None => (None, key),
+  };
+  let mut current = dict;
+  if let Some(parent) = parent {
+    for part in parent.split('.') {
+      let entry = current
+        .entry(part.to_string())
+        .or_insert_with(|| Value::Dict(Tag::Default, Dict::new()));
+      if entry.as_dict().is_none() {
+        *entry = Value::Dict(Tag::Default, Dict::new());
+      }
+      current = match entry {
+        Value::Dict(_, dict) => dict,
+        _ => unreachable!(),
+      };
+    }
+  }
+  current.insert(leaf.to_string(), value);
+}
+
+pub fn remove_key(dict: &mut Dict, key: &str) -> Option<Value> {
+  match key.rsplit_once('.') {
+    Some((parent, leaf)) => {
+      let mut current = dict;
+      for part in parent.split('.') {
+        current = match current.get_mut(part)? {
+          Value::Dict(_, dict) => dict,
+          _ => return None,
+        };
+      }
+      current.remove(leaf)
+    }
+    None => dict.remove(key),
+  }
+}
+
+/// Moves the value at `from` to `to`, for renamed keys.
+pub fn rename_key(dict: &mut Dict, from: &str, to: &str) {
+  if let Some(value) = remove_key(dict, from) {
+    set_key(dict, to, value);
+  }
+}
@@ -1,7 +1,29 @@
 mod window;
 mod vulkan;
 mod engine;
+mod profiler;
+mod logging;
+mod time;
+mod assets;
+mod render;
+mod error;
+mod loader;
+mod migrate;
+mod validate;
+mod watch;
+mod save;
 
 pub use window::*;
 pub use vulkan::*;
-pub use engine::*;
\ No newline at end of file
+pub use engine::*;
+pub use profiler::*;
+pub use logging::*;
+pub use time::*;
+pub use assets::*;
+pub use render::*;
+pub use error::*;
+pub use loader::*;
+pub use migrate::*;
+pub use validate::*;
+pub use watch::*;
+pub use save::*;
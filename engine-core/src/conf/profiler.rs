@@ -0,0 +1,7 @@
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ProfilerConfig {
+  pub enabled: bool,
+  pub trace_path: String
+}
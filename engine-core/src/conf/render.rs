@@ -0,0 +1,13 @@
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RenderConfig {
+  /// Light culling clusters along screen x, screen y and view depth.
+  pub clusters: (u32, u32, u32),
+  /// Lights uploaded per frame; any beyond are dropped.
+  pub max_lights: u32,
+  /// Lights one cluster can list; more reaching the same cluster are ignored there.
+  pub max_lights_per_cluster: u32,
+  /// Resolution of the environment cube map the specular reflections are prefiltered from.
+  pub environment_size: u32
+}
@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+
+use figment::{
+  providers::{Format, Json, Toml, Yaml},
+  value::{Dict, Value},
+  Figment,
+};
+use serde::Serialize;
+
+use super::ConfigError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+  Yaml,
+  Toml,
+  Json,
+}
+
+impl ConfigFormat {
+  /// Picks the format from the file extension, YAML for anything unrecognised.
+  pub fn from_path(path: &Path) -> Self {
+    match path.extension().and_then(|ext| ext.to_str()) {
+      Some("toml") => ConfigFormat::Toml,
+      Some("json") => ConfigFormat::Json,
+      _ => ConfigFormat::Yaml,
+    }
+  }
+
+  pub fn read(&self, path: &Path) -> Result<Dict, ConfigError> {
+    let figment = match self {
+      ConfigFormat::Yaml => Figment::from(Yaml::file(path)),
+      ConfigFormat::Toml => Figment::from(Toml::file(path)),
+      ConfigFormat::Json => Figment::from(Json::file(path)),
+    };
+    Ok(figment.extract()?)
+  }
+
+  pub fn to_string<T: Serialize>(&self, value: &T) -> Result<String, ConfigError> {
+    let write_err = |err: &dyn std::fmt::Display| ConfigError::Write(err.to_string());
+    match self {
+      ConfigFormat::Yaml => serde_yaml::to_string(value).map_err(|err| write_err(&err)),
+      ConfigFormat::Json => serde_json::to_string_pretty(value).map_err(|err| write_err(&err)),
+      // Going through `toml::Value` first orders plain values ahead of tables.
+      ConfigFormat::Toml => toml::Value::try_from(value)
+        .and_then(|value| toml::to_string_pretty(&value))
+        .map_err(|err| write_err(&err)),
+    }
+  }
+}
+
+/// Writes `config` to `path` in the format given by its extension. Keys already in the file that
+/// `config` does not know about are kept, so newer or tool-specific settings survive a save.
+pub fn save_config<T: Serialize>(config: &T, path: &Path) -> Result<(), ConfigError> {
+  let format = ConfigFormat::from_path(path);
+  let mut dict = if path.exists() {
+    format.read(path)?
+  } else {
+    Dict::new()
+  };
+  match Value::serialize(config)? {
+    Value::Dict(_, values) => merge_dict(&mut dict, values),
+    _ => return Err(ConfigError::Write("config must serialize to a table".to_string())),
+  }
+
+  let contents = format.to_string(&dict)?;
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir).map_err(|err| ConfigError::Write(err.to_string()))?;
+  }
+  // Write next to the target and rename so a crash never leaves a truncated config behind.
+  let temp = temp_path(path);
+  std::fs::write(&temp, contents).map_err(|err| ConfigError::Write(err.to_string()))?;
+  std::fs::rename(&temp, path).map_err(|err| ConfigError::Write(err.to_string()))?;
+  log::info!("Saved config to {:?}", path);
+  Ok(())
+}
+
+/// Recursively overlays `values` onto `dict`; tables merge, everything else is replaced.
+pub fn merge_dict(dict: &mut Dict, values: Dict) {
+  for (key, value) in values.into_iter() {
+    match (dict.get_mut(&key), value) {
+      (Some(Value::Dict(_, existing)), Value::Dict(_, overlay)) => merge_dict(existing, overlay),
+      (_, value) => {
+        dict.insert(key, value);
+      }
+    }
+  }
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+  let mut name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+  name.push(".tmp");
+  path.with_file_name(name)
+}
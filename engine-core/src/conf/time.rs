@@ -0,0 +1,11 @@
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TimeConfig {
+  /// Simulation rate for `Application::fixed_update`.
+  pub fixed_hz: f64,
+  /// Fixed steps run at most per frame; after a long stall the remaining backlog is dropped.
+  pub max_fixed_steps: u32,
+  /// Frame rate cap, 0 renders as fast as possible.
+  pub max_fps: u32
+}
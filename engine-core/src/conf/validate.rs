@@ -0,0 +1,319 @@
+use std::fmt::Display;
+
+use super::{
+  AssetsConfig, ConfigError, EngineConfig, LogFileConfig, LoggingConfig, ProfilerConfig, RenderConfig, TimeConfig,
+  VulkanConfig, VulkanInstanceConfig, VulkanPhysicalDeviceConfig, VulkanValidationConfig, WindowConfig,
+  ENGINE_CONFIG_VERSION,
+};
+use crate::vfs::{VfsPath, PROJECT_SCHEME};
+use crate::vulkan::severity_from_name;
+
+const MAX_WINDOW_DIMENSION: i32 = 16384;
+const MAX_VULKAN_MINOR_VERSION: u32 = 3;
+const MAX_FIXED_HZ: f64 = 1000.0;
+const MAX_ASSET_THREADS: u32 = 64;
+const MAX_CLUSTERS_PER_AXIS: u32 = 128;
+const MAX_LIGHTS: u32 = 65536;
+const MAX_LIGHTS_PER_CLUSTER: u32 = 1024;
+const ENVIRONMENT_SIZES: (u32, u32) = (16, 2048);
+
+#[derive(Debug, Clone)]
+pub struct ConfigIssue {
+  pub path: String,
+  pub message: String,
+  pub suggestion: String,
+}
+
+impl Display for ConfigIssue {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}: {} (fix: {})", self.path, self.message, self.suggestion)
+  }
+}
+
+/// Semantic checks that deserialization alone cannot express. Implementations push every
+/// problem they find rather than stopping at the first.
+pub trait Validate {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>);
+}
+
+pub fn validate<T: Validate>(config: &T) -> Result<(), ConfigError> {
+  let mut issues = Vec::new();
+  config.validate("", &mut issues);
+  if issues.is_empty() {
+    Ok(())
+  } else {
+    Err(ConfigError::Invalid(issues))
+  }
+}
+
+fn join(path: &str, key: &str) -> String {
+  if path.is_empty() {
+    key.to_string()
+  } else {
+    format!("{}.{}", path, key)
+  }
+}
+
+fn issue(issues: &mut Vec<ConfigIssue>, path: String, message: String, suggestion: &str) {
+  issues.push(ConfigIssue {
+    path,
+    message,
+    suggestion: suggestion.to_string(),
+  });
+}
+
+fn check_name(issues: &mut Vec<ConfigIssue>, path: String, value: &str, suggestion: &str) {
+  if value.trim().is_empty() {
+    issue(issues, path, "must not be empty".to_string(), suggestion);
+  } else if value.contains('\0') {
+    issue(issues, path, "must not contain NUL characters".to_string(), "remove the \\0 character");
+  }
+}
+
+impl Validate for EngineConfig {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>) {
+    if self.version < 1 || self.version > ENGINE_CONFIG_VERSION {
+      issue(
+        issues,
+        join(path, "version"),
+        format!("{} is not a known config version", self.version),
+        &format!("set version to {}", ENGINE_CONFIG_VERSION),
+      );
+    }
+    self.window.validate(&join(path, "window"), issues);
+    self.vulkan.validate(&join(path, "vulkan"), issues);
+    self.profiler.validate(&join(path, "profiler"), issues);
+    self.logging.validate(&join(path, "logging"), issues);
+    self.time.validate(&join(path, "time"), issues);
+    self.assets.validate(&join(path, "assets"), issues);
+    self.render.validate(&join(path, "render"), issues);
+  }
+}
+
+impl Validate for WindowConfig {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>) {
+    check_name(issues, join(path, "title"), &self.title, "set a title such as \"Daybreak\"");
+    for (key, value, example) in [("width", self.width, 1280), ("height", self.height, 720)] {
+      if value <= 0 {
+        issue(
+          issues,
+          join(path, key),
+          format!("must be positive, got {}", value),
+          &format!("set {} to a pixel size such as {}", key, example),
+        );
+      } else if value > MAX_WINDOW_DIMENSION {
+        issue(
+          issues,
+          join(path, key),
+          format!("{} exceeds the maximum of {}", value, MAX_WINDOW_DIMENSION),
+          &format!("set {} to at most {}", key, MAX_WINDOW_DIMENSION),
+        );
+      }
+    }
+  }
+}
+
+impl Validate for VulkanConfig {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>) {
+    self.instance.validate(&join(path, "instance"), issues);
+    self.physical_device.validate(&join(path, "physical_device"), issues);
+    self.validation.validate(&join(path, "validation"), issues);
+  }
+}
+
+impl Validate for VulkanInstanceConfig {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>) {
+    check_name(issues, join(path, "app_name"), &self.app_name, "set app_name to your game's name");
+    check_name(issues, join(path, "engine_name"), &self.engine_name, "set engine_name to \"Daybreak Engine\"");
+
+    let (major, minor, patch) = self.api_version;
+    if major != 1 || minor > MAX_VULKAN_MINOR_VERSION {
+      issue(
+        issues,
+        join(path, "api_version"),
+        format!(
+          "[{}, {}, {}] is not a Vulkan version, expected [major, minor, patch] with major 1 and minor 0-{}",
+          major, minor, patch, MAX_VULKAN_MINOR_VERSION
+        ),
+        "set api_version to [1, 2, 0]",
+      );
+    }
+  }
+}
+
+impl Validate for VulkanPhysicalDeviceConfig {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>) {
+    if !self.desired_queue_flags.graphics {
+      issue(
+        issues,
+        join(path, "desired_queue_flags.graphics"),
+        "the renderer always needs a graphics queue".to_string(),
+        "set graphics to true",
+      );
+    }
+    if let Some(preferred) = &self.preferred_device {
+      if preferred.trim().is_empty() {
+        issue(
+          issues,
+          join(path, "preferred_device"),
+          "is empty, which would match any GPU".to_string(),
+          "remove preferred_device or set it to part of a device name",
+        );
+      }
+    }
+  }
+}
+
+impl Validate for ProfilerConfig {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>) {
+    if self.enabled && self.trace_path.trim().is_empty() {
+      issue(
+        issues,
+        join(path, "trace_path"),
+        "must name a file when the profiler is enabled".to_string(),
+        "set trace_path to \"profile.json\"",
+      );
+    }
+  }
+}
+
+impl Validate for VulkanValidationConfig {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>) {
+    if severity_from_name(&self.min_severity).is_none() {
+      issue(
+        issues,
+        join(path, "min_severity"),
+        format!("unknown severity \"{}\"", self.min_severity),
+        "use one of verbose, info, warning or error",
+      );
+    }
+  }
+}
+
+impl Validate for LoggingConfig {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>) {
+    if self.level.parse::<log::LevelFilter>().is_err() {
+      issue(
+        issues,
+        join(path, "level"),
+        format!("unknown log level \"{}\"", self.level),
+        "use one of off, error, warn, info, debug or trace",
+      );
+    }
+    for (module, level) in self.modules.iter() {
+      if level.parse::<log::LevelFilter>().is_err() {
+        issue(
+          issues,
+          join(&join(path, "modules"), module),
+          format!("unknown log level \"{}\"", level),
+          "use one of off, error, warn, info, debug or trace",
+        );
+      }
+    }
+    self.file.validate(&join(path, "file"), issues);
+  }
+}
+
+impl Validate for LogFileConfig {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>) {
+    if !self.enabled {
+      return;
+    }
+    if self.name.trim().is_empty() || self.name.contains(['/', '\\']) {
+      issue(
+        issues,
+        join(path, "name"),
+        format!("\"{}\" is not a valid log file name", self.name),
+        "set name to a plain file stem such as \"daybreak\"",
+      );
+    }
+    if self.max_files == 0 {
+      issue(
+        issues,
+        join(path, "max_files"),
+        "must keep at least one rotated file".to_string(),
+        "set max_files to 5",
+      );
+    }
+  }
+}
+
+impl Validate for TimeConfig {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>) {
+    if !(self.fixed_hz > 0.0 && self.fixed_hz <= MAX_FIXED_HZ) {
+      issue(
+        issues,
+        join(path, "fixed_hz"),
+        format!("must be between 0 and {}, got {}", MAX_FIXED_HZ, self.fixed_hz),
+        "set fixed_hz to 60.0",
+      );
+    }
+    if self.max_fixed_steps == 0 {
+      issue(
+        issues,
+        join(path, "max_fixed_steps"),
+        "must allow at least one step per frame".to_string(),
+        "set max_fixed_steps to 5",
+      );
+    }
+  }
+}
+
+impl Validate for AssetsConfig {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>) {
+    check_name(issues, join(path, "root"), &self.root, "set root to \"assets\"");
+    if VfsPath::new(PROJECT_SCHEME, &self.root).is_err() {
+      issue(
+        issues,
+        join(path, "root"),
+        format!("'{}' must be a relative path inside the project", self.root),
+        "set root to \"assets\"",
+      );
+    }
+    if self.threads > MAX_ASSET_THREADS {
+      issue(
+        issues,
+        join(path, "threads"),
+        format!("must be at most {}, got {}", MAX_ASSET_THREADS, self.threads),
+        "set threads to 0 to size the pool from the core count",
+      );
+    }
+  }
+}
+
+impl Validate for RenderConfig {
+  fn validate(&self, path: &str, issues: &mut Vec<ConfigIssue>) {
+    let (x, y, z) = self.clusters;
+    if [x, y, z].iter().any(|count| *count == 0 || *count > MAX_CLUSTERS_PER_AXIS) {
+      issue(
+        issues,
+        join(path, "clusters"),
+        format!("each count must be between 1 and {}, got [{}, {}, {}]", MAX_CLUSTERS_PER_AXIS, x, y, z),
+        "set clusters to [16, 9, 24]",
+      );
+    }
+    for (key, value, max, default) in [
+      ("max_lights", self.max_lights, MAX_LIGHTS, 1024),
+      ("max_lights_per_cluster", self.max_lights_per_cluster, MAX_LIGHTS_PER_CLUSTER, 128),
+    ] {
+      if value == 0 || value > max {
+        issue(
+          issues,
+          join(path, key),
+          format!("must be between 1 and {}, got {}", max, value),
+          &format!("set {} to {}", key, default),
+        );
+      }
+    }
+    let (min_size, max_size) = ENVIRONMENT_SIZES;
+    let size = self.environment_size;
+    if !size.is_power_of_two() || size < min_size || size > max_size {
+      issue(
+        issues,
+        join(path, "environment_size"),
+        format!("must be a power of two between {} and {}, got {}", min_size, max_size, size),
+        "set environment_size to 256",
+      );
+    }
+  }
+}
@@ -1,14 +1,15 @@
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::vulkan::{QueueFlagSupportMatrix, DeviceFeatureSupportMatrix};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct VulkanConfig {
   pub instance: VulkanInstanceConfig,
-  pub physical_device: VulkanPhysicalDeviceConfig
+  pub physical_device: VulkanPhysicalDeviceConfig,
+  pub validation: VulkanValidationConfig
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct VulkanInstanceConfig {
   pub app_name: String,
   pub app_version: u32,
@@ -17,8 +18,16 @@ pub struct VulkanInstanceConfig {
   pub api_version: (u32, u32, u32)
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct VulkanPhysicalDeviceConfig {
   pub desired_queue_flags: QueueFlagSupportMatrix,
-  pub desired_device_features: DeviceFeatureSupportMatrix
-}
\ No newline at end of file
+  pub desired_device_features: DeviceFeatureSupportMatrix,
+  /// Case-insensitive substring of the device name to prefer over the first suitable GPU.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub preferred_device: Option<String>
+}
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct VulkanValidationConfig {
+  pub min_severity: String,
+  pub ignored_messages: Vec<String>
+}
@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use super::{validate, ConfigError, ConfigLoader, EngineConfig};
+
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Keys (or key prefixes ending in `.`) that the running engine can apply without a restart.
+pub const LIVE_CONFIG_KEYS: [&str; 10] = [
+  "window.title",
+  "window.width",
+  "window.height",
+  "window.fullscreen",
+  "window.vsync",
+  "logging.",
+  "vulkan.validation.",
+  "profiler.",
+  "time.",
+  "assets.hot_reload",
+];
+
+pub fn is_live_key(key: &str) -> bool {
+  LIVE_CONFIG_KEYS.iter().any(|live| {
+    if live.ends_with('.') {
+      key.starts_with(live)
+    } else {
+      key == *live
+    }
+  })
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigChange {
+  pub key: String,
+  pub old: Option<String>,
+  pub new: Option<String>,
+  pub live: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigReload {
+  pub config: EngineConfig,
+  pub changes: Vec<ConfigChange>,
+}
+
+impl ConfigReload {
+  pub fn restart_required(&self) -> bool {
+    self.changes.iter().any(|change| !change.live)
+  }
+
+  pub fn restart_required_keys(&self) -> Vec<&str> {
+    self
+      .changes
+      .iter()
+      .filter(|change| !change.live)
+      .map(|change| change.key.as_str())
+      .collect()
+  }
+}
+
+/// Watches the directories of every config file layer and re-extracts the config on change.
+pub struct ConfigWatcher {
+  loader: ConfigLoader,
+  files: BTreeSet<PathBuf>,
+  values: BTreeMap<String, String>,
+  events: Receiver<DebouncedEvent>,
+  subscribers: Vec<Sender<ConfigReload>>,
+  _watcher: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+  pub fn new(loader: &ConfigLoader) -> Result<Self, ConfigError> {
+    let (sender, events) = channel();
+    let mut fs_watcher = watcher(sender, DEBOUNCE).map_err(|err| ConfigError::Watch(err.to_string()))?;
+
+    let files: BTreeSet<PathBuf> = loader.files().into_iter().collect();
+    let directories: BTreeSet<PathBuf> = files
+      .iter()
+      .filter_map(|file| file.parent())
+      .filter(|dir| dir.is_dir())
+      .map(|dir| dir.to_path_buf())
+      .collect();
+    for dir in directories.iter() {
+      fs_watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .map_err(|err| ConfigError::Watch(err.to_string()))?;
+      log::info!("Watching config directory {:?}", dir);
+    }
+
+    Ok(ConfigWatcher {
+      loader: loader.clone(),
+      files,
+      values: loader.flatten()?.into_iter().collect(),
+      events,
+      subscribers: Vec::new(),
+      _watcher: fs_watcher,
+    })
+  }
+
+  /// Receives every successful reload in addition to the value returned by `poll`.
+  pub fn subscribe(&mut self) -> Receiver<ConfigReload> {
+    let (sender, receiver) = channel();
+    self.subscribers.push(sender);
+    receiver
+  }
+
+  /// Non-blocking; reloads once if any watched config file changed since the last call.
+  /// Invalid edits are logged and ignored so the running config stays in effect.
+  pub fn poll(&mut self) -> Option<ConfigReload> {
+    let mut touched = false;
+    while let Ok(event) = self.events.try_recv() {
+      touched |= match event {
+        DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Remove(path) => {
+          self.is_config_file(&path)
+        }
+        DebouncedEvent::Rename(from, to) => self.is_config_file(&from) || self.is_config_file(&to),
+        DebouncedEvent::Rescan => true,
+        DebouncedEvent::Error(err, path) => {
+          log::warn!("Config watcher error on {:?}: {:?}", path, err);
+          false
+        }
+        _ => false,
+      };
+    }
+    if !touched {
+      return None;
+    }
+
+    match self.reload() {
+      Ok(reload) => {
+        if reload.changes.is_empty() {
+          return None;
+        }
+        for change in reload.changes.iter() {
+          log::info!(
+            "Config '{}' changed from {} to {}{}",
+            change.key,
+            change.old.as_deref().unwrap_or("<unset>"),
+            change.new.as_deref().unwrap_or("<unset>"),
+            if change.live { "" } else { " (restart required)" }
+          );
+        }
+        self.subscribers.retain(|subscriber| subscriber.send(reload.clone()).is_ok());
+        Some(reload)
+      }
+      Err(err) => {
+        log::error!("Ignoring config reload: {}", err);
+        None
+      }
+    }
+  }
+
+  fn reload(&mut self) -> Result<ConfigReload, ConfigError> {
+    let loader = self.loader.reload()?;
+    let config = loader.extract::<EngineConfig>()?;
+    validate(&config)?;
+
+    let values: BTreeMap<String, String> = loader.flatten()?.into_iter().collect();
+    let keys: BTreeSet<&String> = self.values.keys().chain(values.keys()).collect();
+    let changes = keys
+      .into_iter()
+      .filter(|key| self.values.get(*key) != values.get(*key))
+      .map(|key| ConfigChange {
+        key: key.to_string(),
+        old: self.values.get(key).cloned(),
+        new: values.get(key).cloned(),
+        live: is_live_key(key),
+      })
+      .collect();
+
+    self.values = values;
+    Ok(ConfigReload { config, changes })
+  }
+
+  fn is_config_file(&self, path: &std::path::Path) -> bool {
+    self.files.iter().any(|file| {
+      file == path
+        || match (file.file_name(), path.file_name()) {
+          (Some(a), Some(b)) => a == b && file.parent().map(|dir| path.starts_with(dir)).unwrap_or(false),
+          _ => false,
+        }
+    })
+  }
+}
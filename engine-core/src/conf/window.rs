@@ -1,9 +1,10 @@
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct WindowConfig {
   pub title: String,
   pub width: i32,
   pub height: i32,
-  pub fullscreen: bool
-}
\ No newline at end of file
+  pub fullscreen: bool,
+  pub vsync: bool
+}
@@ -0,0 +1,31 @@
+use super::{Component, Entity, World};
+
+/// A set of components inserted together, written as a tuple: `world.spawn((Name("a"), Health(3)))`.
+pub trait Bundle: Send + Sync + 'static {
+    fn insert_into(self, world: &mut World, entity: Entity);
+}
+
+impl Bundle for () {
+    fn insert_into(self, _world: &mut World, _entity: Entity) {}
+}
+
+macro_rules! impl_bundle {
+    ($($name:ident),+) => {
+        #[allow(non_snake_case)]
+        impl<$($name: Component),+> Bundle for ($($name,)+) {
+            fn insert_into(self, world: &mut World, entity: Entity) {
+                let ($($name,)+) = self;
+                $(world.insert(entity, $name);)+
+            }
+        }
+    };
+}
+
+impl_bundle!(A);
+impl_bundle!(A, B);
+impl_bundle!(A, B, C);
+impl_bundle!(A, B, C, D);
+impl_bundle!(A, B, C, D, E);
+impl_bundle!(A, B, C, D, E, F);
+impl_bundle!(A, B, C, D, E, F, G);
+impl_bundle!(A, B, C, D, E, F, G, H);
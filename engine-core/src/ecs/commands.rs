@@ -0,0 +1,65 @@
+use std::sync::Mutex;
+
+use super::{Bundle, Component, Entity, World};
+
+type Command = Box<dyn FnOnce(&mut World) + Send>;
+
+/// Structural changes queued by a system; `Schedule` applies them in order after its last batch.
+#[derive(Default)]
+pub struct Commands {
+    queue: Mutex<Vec<Command>>,
+}
+
+impl Commands {
+    pub fn add<F: FnOnce(&mut World) + Send + 'static>(&self, command: F) {
+        self.queue.lock().unwrap().push(Box::new(command));
+    }
+
+    pub fn spawn<B: Bundle>(&self, bundle: B) {
+        self.add(move |world| {
+            world.spawn(bundle);
+        });
+    }
+
+    pub fn despawn(&self, entity: Entity) {
+        self.add(move |world| {
+            world.despawn(entity);
+        });
+    }
+
+    /// Skipped if the entity is despawned before the command is applied.
+    pub fn insert<T: Component>(&self, entity: Entity, component: T) {
+        self.add(move |world| {
+            if world.is_alive(entity) {
+                world.insert(entity, component);
+            }
+        });
+    }
+
+    pub fn remove<T: Component>(&self, entity: Entity) {
+        self.add(move |world| {
+            world.remove::<T>(entity);
+        });
+    }
+
+    pub fn insert_resource<R: Component>(&self, resource: R) {
+        self.add(move |world| world.insert_resource(resource));
+    }
+
+    pub fn remove_resource<R: Component>(&self) {
+        self.add(|world| {
+            world.remove_resource::<R>();
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.lock().unwrap().is_empty()
+    }
+
+    pub fn apply(&mut self, world: &mut World) {
+        let queue = std::mem::take(self.queue.get_mut().unwrap());
+        for command in queue {
+            command(world);
+        }
+    }
+}
@@ -0,0 +1,18 @@
+mod entity;
+mod storage;
+mod world;
+mod bundle;
+mod query;
+mod system;
+mod commands;
+mod schedule;
+mod pool;
+
+pub use entity::*;
+pub use storage::*;
+pub use world::*;
+pub use bundle::*;
+pub use query::*;
+pub use system::*;
+pub use commands::*;
+pub use schedule::*;
@@ -0,0 +1,100 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+static POOL: OnceLock<WorkerPool> = OnceLock::new();
+
+/// Threads that run the parallel batches of every `Schedule`, started on first use and kept
+/// for the rest of the process so a frame never pays for spawning threads.
+pub(crate) struct WorkerPool {
+    jobs: Mutex<Sender<Job>>,
+}
+
+#[derive(Default)]
+struct Pending {
+    remaining: usize,
+    panicked: bool,
+}
+
+impl WorkerPool {
+    /// The shared pool, with one thread less than the number of cores since the calling thread
+    /// works too.
+    pub(crate) fn get() -> &'static WorkerPool {
+        POOL.get_or_init(|| {
+            let threads = thread::available_parallelism().map_or(1, |count| count.get().saturating_sub(1).max(1));
+            WorkerPool::new(threads)
+        })
+    }
+
+    fn new(threads: usize) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..threads {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("ecs-worker-{}", index))
+                .spawn(move || loop {
+                    let job = match receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    job();
+                })
+                .expect("failed to spawn ecs worker thread");
+        }
+        WorkerPool { jobs: Mutex::new(jobs) }
+    }
+
+    /// Runs every job, the first on the calling thread, and returns once all of them have
+    /// finished. A panic in any job is raised again here after the others are done.
+    pub(crate) fn run_all<'a>(&self, mut jobs: Vec<Box<dyn FnOnce() + Send + 'a>>) {
+        if jobs.is_empty() {
+            return;
+        }
+        let local = jobs.remove(0);
+        let pending = Arc::new((
+            Mutex::new(Pending {
+                remaining: jobs.len(),
+                panicked: false,
+            }),
+            Condvar::new(),
+        ));
+        {
+            let sender = self.jobs.lock().unwrap();
+            for job in jobs {
+                // SAFETY: the job only outlives 'a on paper; this function does not return, even
+                // when a job panics, until every job has run and signalled `pending`.
+                let job: Job = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'a>, Job>(job) };
+                let pending = pending.clone();
+                sender
+                    .send(Box::new(move || {
+                        let panicked = panic::catch_unwind(AssertUnwindSafe(job)).is_err();
+                        let (state, done) = &*pending;
+                        let mut state = state.lock().unwrap();
+                        state.panicked |= panicked;
+                        state.remaining -= 1;
+                        if state.remaining == 0 {
+                            done.notify_all();
+                        }
+                    }))
+                    .expect("ecs worker threads exited");
+            }
+        }
+        let local_result = panic::catch_unwind(AssertUnwindSafe(local));
+
+        let (state, done) = &*pending;
+        let mut state = state.lock().unwrap();
+        while state.remaining > 0 {
+            state = done.wait(state).unwrap();
+        }
+        if let Err(payload) = local_result {
+            panic::resume_unwind(payload);
+        }
+        if state.panicked {
+            panic!("a system panicked on an ecs worker thread");
+        }
+    }
+}
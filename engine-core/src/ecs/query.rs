@@ -0,0 +1,415 @@
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+use super::{AccessKey, AnyStorage, Component, ComponentTicks, Entity, SparseSet, SystemAccess, World};
+
+type ReadLock<'w> = Option<RwLockReadGuard<'w, Box<dyn AnyStorage>>>;
+type WriteLock<'w> = Option<RwLockWriteGuard<'w, Box<dyn AnyStorage>>>;
+
+/// The change window of the running system: components touched after `last_run` count as
+/// added or changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ticks {
+    pub last_run: u32,
+    pub this_run: u32,
+}
+
+impl Ticks {
+    pub fn is_newer(&self, tick: u32) -> bool {
+        tick > self.last_run
+    }
+}
+
+/// What a query fetches per entity: `&T`, `&mut T`, `Option<&T>`, `Entity` or a tuple of these.
+pub trait QueryParam {
+    type Lock<'w>;
+    type Item<'a>;
+
+    fn access(access: &mut SystemAccess);
+    fn lock(world: &World) -> Self::Lock<'_>;
+    /// Entities that may match, if this parameter restricts them; the query walks the smallest set.
+    fn candidates<'l>(lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]>;
+    fn fetch<'a>(lock: &'a mut Self::Lock<'_>, entity: Entity, ticks: Ticks) -> Option<Self::Item<'a>>;
+}
+
+/// Narrows a query without fetching anything: `With<T>`, `Without<T>`, `Added<T>`, `Changed<T>`
+/// or a tuple of these.
+pub trait QueryFilter {
+    type Lock<'w>;
+
+    fn access(access: &mut SystemAccess);
+    fn lock(world: &World) -> Self::Lock<'_>;
+    fn candidates<'l>(lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]>;
+    fn matches(lock: &Self::Lock<'_>, entity: Entity, ticks: Ticks) -> bool;
+}
+
+fn sparse_set<'l, T: Component>(lock: &'l ReadLock<'_>) -> Option<&'l SparseSet<T>> {
+    lock.as_ref()
+        .map(|storage| storage.as_any().downcast_ref::<SparseSet<T>>().unwrap())
+}
+
+fn entities_of<'l>(lock: &'l ReadLock<'_>) -> Option<&'l [Entity]> {
+    Some(lock.as_ref().map_or(&[][..], |storage| storage.entities()))
+}
+
+impl<T: Component> QueryParam for &T {
+    type Lock<'w> = ReadLock<'w>;
+    type Item<'a> = &'a T;
+
+    fn access(access: &mut SystemAccess) {
+        access.add_read(AccessKey::component::<T>());
+    }
+
+    fn lock(world: &World) -> Self::Lock<'_> {
+        world.read_storage::<T>()
+    }
+
+    fn candidates<'l>(lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]> {
+        entities_of(lock)
+    }
+
+    fn fetch<'a>(lock: &'a mut Self::Lock<'_>, entity: Entity, _ticks: Ticks) -> Option<Self::Item<'a>> {
+        sparse_set::<T>(lock)?.get(entity)
+    }
+}
+
+impl<T: Component> QueryParam for &mut T {
+    type Lock<'w> = WriteLock<'w>;
+    type Item<'a> = Mut<'a, T>;
+
+    fn access(access: &mut SystemAccess) {
+        access.add_write(AccessKey::component::<T>());
+    }
+
+    fn lock(world: &World) -> Self::Lock<'_> {
+        world.write_storage::<T>()
+    }
+
+    fn candidates<'l>(lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]> {
+        Some(lock.as_ref().map_or(&[][..], |storage| storage.entities()))
+    }
+
+    fn fetch<'a>(lock: &'a mut Self::Lock<'_>, entity: Entity, ticks: Ticks) -> Option<Self::Item<'a>> {
+        let storage = lock.as_mut()?.as_any_mut().downcast_mut::<SparseSet<T>>().unwrap();
+        let (value, component_ticks) = storage.get_mut(entity)?;
+        Some(Mut {
+            value,
+            ticks: component_ticks,
+            this_run: ticks.this_run,
+            last_run: ticks.last_run,
+        })
+    }
+}
+
+impl<T: Component> QueryParam for Option<&T> {
+    type Lock<'w> = ReadLock<'w>;
+    type Item<'a> = Option<&'a T>;
+
+    fn access(access: &mut SystemAccess) {
+        access.add_read(AccessKey::component::<T>());
+    }
+
+    fn lock(world: &World) -> Self::Lock<'_> {
+        world.read_storage::<T>()
+    }
+
+    fn candidates<'l>(_lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]> {
+        None
+    }
+
+    fn fetch<'a>(lock: &'a mut Self::Lock<'_>, entity: Entity, _ticks: Ticks) -> Option<Self::Item<'a>> {
+        Some(sparse_set::<T>(lock).and_then(|storage| storage.get(entity)))
+    }
+}
+
+impl QueryParam for Entity {
+    type Lock<'w> = ();
+    type Item<'a> = Entity;
+
+    fn access(_access: &mut SystemAccess) {}
+
+    fn lock(_world: &World) -> Self::Lock<'_> {}
+
+    fn candidates<'l>(_lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]> {
+        None
+    }
+
+    fn fetch<'a>(_lock: &'a mut Self::Lock<'_>, entity: Entity, _ticks: Ticks) -> Option<Self::Item<'a>> {
+        Some(entity)
+    }
+}
+
+/// Mutable component access that marks the component changed on first write.
+pub struct Mut<'a, T> {
+    value: &'a mut T,
+    ticks: &'a mut ComponentTicks,
+    this_run: u32,
+    last_run: u32,
+}
+
+impl<T> Mut<'_, T> {
+    pub fn is_added(&self) -> bool {
+        self.ticks.added > self.last_run
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.ticks.changed > self.last_run
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.ticks.changed = self.this_run;
+        self.value
+    }
+}
+
+/// Entities that have a `T`.
+pub struct With<T>(PhantomData<T>);
+
+/// Entities without a `T`.
+pub struct Without<T>(PhantomData<T>);
+
+/// Entities whose `T` was inserted since the system last ran.
+pub struct Added<T>(PhantomData<T>);
+
+/// Entities whose `T` was inserted or mutably accessed since the system last ran.
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for With<T> {
+    type Lock<'w> = ReadLock<'w>;
+
+    fn access(access: &mut SystemAccess) {
+        access.add_read(AccessKey::component::<T>());
+    }
+
+    fn lock(world: &World) -> Self::Lock<'_> {
+        world.read_storage::<T>()
+    }
+
+    fn candidates<'l>(lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]> {
+        entities_of(lock)
+    }
+
+    fn matches(lock: &Self::Lock<'_>, entity: Entity, _ticks: Ticks) -> bool {
+        lock.as_ref().is_some_and(|storage| storage.contains(entity))
+    }
+}
+
+impl<T: Component> QueryFilter for Without<T> {
+    type Lock<'w> = ReadLock<'w>;
+
+    fn access(access: &mut SystemAccess) {
+        access.add_read(AccessKey::component::<T>());
+    }
+
+    fn lock(world: &World) -> Self::Lock<'_> {
+        world.read_storage::<T>()
+    }
+
+    fn candidates<'l>(_lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]> {
+        None
+    }
+
+    fn matches(lock: &Self::Lock<'_>, entity: Entity, _ticks: Ticks) -> bool {
+        lock.as_ref().is_none_or(|storage| !storage.contains(entity))
+    }
+}
+
+impl<T: Component> QueryFilter for Added<T> {
+    type Lock<'w> = ReadLock<'w>;
+
+    fn access(access: &mut SystemAccess) {
+        access.add_read(AccessKey::component::<T>());
+    }
+
+    fn lock(world: &World) -> Self::Lock<'_> {
+        world.read_storage::<T>()
+    }
+
+    fn candidates<'l>(lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]> {
+        entities_of(lock)
+    }
+
+    fn matches(lock: &Self::Lock<'_>, entity: Entity, ticks: Ticks) -> bool {
+        sparse_set::<T>(lock)
+            .and_then(|storage| storage.ticks(entity))
+            .is_some_and(|component| ticks.is_newer(component.added))
+    }
+}
+
+impl<T: Component> QueryFilter for Changed<T> {
+    type Lock<'w> = ReadLock<'w>;
+
+    fn access(access: &mut SystemAccess) {
+        access.add_read(AccessKey::component::<T>());
+    }
+
+    fn lock(world: &World) -> Self::Lock<'_> {
+        world.read_storage::<T>()
+    }
+
+    fn candidates<'l>(lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]> {
+        entities_of(lock)
+    }
+
+    fn matches(lock: &Self::Lock<'_>, entity: Entity, ticks: Ticks) -> bool {
+        sparse_set::<T>(lock)
+            .and_then(|storage| storage.ticks(entity))
+            .is_some_and(|component| ticks.is_newer(component.changed))
+    }
+}
+
+impl QueryFilter for () {
+    type Lock<'w> = ();
+
+    fn access(_access: &mut SystemAccess) {}
+
+    fn lock(_world: &World) -> Self::Lock<'_> {}
+
+    fn candidates<'l>(_lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]> {
+        None
+    }
+
+    fn matches(_lock: &Self::Lock<'_>, _entity: Entity, _ticks: Ticks) -> bool {
+        true
+    }
+}
+
+fn smallest<'l>(sets: impl IntoIterator<Item = Option<&'l [Entity]>>) -> Option<&'l [Entity]> {
+    sets.into_iter().flatten().min_by_key(|set| set.len())
+}
+
+macro_rules! impl_query_tuple {
+    ($(($name:ident, $index:tt)),+) => {
+        impl<$($name: QueryParam),+> QueryParam for ($($name,)+) {
+            type Lock<'w> = ($($name::Lock<'w>,)+);
+            type Item<'a> = ($($name::Item<'a>,)+);
+
+            fn access(access: &mut SystemAccess) {
+                $($name::access(access);)+
+            }
+
+            fn lock(world: &World) -> Self::Lock<'_> {
+                ($($name::lock(world),)+)
+            }
+
+            fn candidates<'l>(lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]> {
+                smallest([$($name::candidates(&lock.$index)),+])
+            }
+
+            fn fetch<'a>(lock: &'a mut Self::Lock<'_>, entity: Entity, ticks: Ticks) -> Option<Self::Item<'a>> {
+                Some(($($name::fetch(&mut lock.$index, entity, ticks)?,)+))
+            }
+        }
+
+        impl<$($name: QueryFilter),+> QueryFilter for ($($name,)+) {
+            type Lock<'w> = ($($name::Lock<'w>,)+);
+
+            fn access(access: &mut SystemAccess) {
+                $($name::access(access);)+
+            }
+
+            fn lock(world: &World) -> Self::Lock<'_> {
+                ($($name::lock(world),)+)
+            }
+
+            fn candidates<'l>(lock: &'l Self::Lock<'_>) -> Option<&'l [Entity]> {
+                smallest([$($name::candidates(&lock.$index)),+])
+            }
+
+            fn matches(lock: &Self::Lock<'_>, entity: Entity, ticks: Ticks) -> bool {
+                $($name::matches(&lock.$index, entity, ticks))&&+
+            }
+        }
+    };
+}
+
+impl_query_tuple!((A, 0));
+impl_query_tuple!((A, 0), (B, 1));
+impl_query_tuple!((A, 0), (B, 1), (C, 2));
+impl_query_tuple!((A, 0), (B, 1), (C, 2), (D, 3));
+impl_query_tuple!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4));
+impl_query_tuple!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5));
+
+/// Locks the storages `Q` and `F` touch for as long as it lives. Borrowing the same component
+/// mutably twice, in one query or across live queries, panics.
+pub struct Query<'w, Q: QueryParam, F: QueryFilter = ()> {
+    world: &'w World,
+    lock: Q::Lock<'w>,
+    filter: F::Lock<'w>,
+    ticks: Ticks,
+}
+
+impl<'w, Q: QueryParam, F: QueryFilter> Query<'w, Q, F> {
+    pub(crate) fn new(world: &'w World, ticks: Ticks) -> Self {
+        Query {
+            world,
+            lock: Q::lock(world),
+            filter: F::lock(world),
+            ticks,
+        }
+    }
+
+    fn candidates(&self) -> Vec<Entity> {
+        match smallest([Q::candidates(&self.lock), F::candidates(&self.filter)]) {
+            Some(set) => set.to_vec(),
+            None => self.world.entities().iter().collect(),
+        }
+    }
+
+    pub fn for_each(&mut self, mut f: impl FnMut(Entity, Q::Item<'_>)) {
+        for entity in self.candidates() {
+            if !F::matches(&self.filter, entity, self.ticks) {
+                continue;
+            }
+            if let Some(item) = Q::fetch(&mut self.lock, entity, self.ticks) {
+                f(entity, item);
+            }
+        }
+    }
+
+    pub fn get(&mut self, entity: Entity) -> Option<Q::Item<'_>> {
+        if !self.world.is_alive(entity) || !F::matches(&self.filter, entity, self.ticks) {
+            return None;
+        }
+        Q::fetch(&mut self.lock, entity, self.ticks)
+    }
+
+    pub fn contains(&mut self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+
+    /// Matching entities, in storage order.
+    pub fn entities(&mut self) -> Vec<Entity> {
+        let mut matching = Vec::new();
+        self.for_each(|entity, _| matching.push(entity));
+        matching
+    }
+
+    pub fn count(&mut self) -> usize {
+        let mut count = 0;
+        self.for_each(|_, _| count += 1);
+        count
+    }
+
+    /// The only match, or `None` if there are zero or several.
+    pub fn single(&mut self) -> Option<Q::Item<'_>> {
+        match self.entities().as_slice() {
+            [entity] => {
+                let entity = *entity;
+                Q::fetch(&mut self.lock, entity, self.ticks)
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use super::pool::WorkerPool;
+use super::{Commands, System, SystemAccess, SystemContext, Ticks, World};
+use crate::profile::ProfileScope;
+
+struct ScheduledSystem {
+    system: Box<dyn System>,
+    name: &'static str,
+    access: SystemAccess,
+    batch: usize,
+    last_run: u32,
+    commands: Commands,
+}
+
+impl ScheduledSystem {
+    fn run(&mut self, world: &World, this_run: u32) {
+        let _scope = ProfileScope::new(self.name, "system");
+        let ticks = Ticks {
+            last_run: self.last_run,
+            this_run,
+        };
+        let context = SystemContext::new(world, self.name, &self.access, ticks, &self.commands);
+        self.system.run(&context);
+        self.last_run = this_run;
+    }
+}
+
+/// Runs systems in batches: a system goes into the batch after the last earlier system it
+/// conflicts with, so conflicting systems keep the order they were added in while the rest of a
+/// batch runs in parallel on a shared pool of worker threads.
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<ScheduledSystem>,
+    batches: usize,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_system<S: System + 'static>(&mut self, system: S) -> &mut Self {
+        let access = system.access().clone();
+        let batch = self
+            .systems
+            .iter()
+            .filter(|other| other.access.conflicts_with(&access))
+            .map(|other| other.batch + 1)
+            .max()
+            .unwrap_or(0);
+        self.batches = self.batches.max(batch + 1);
+        self.systems.push(ScheduledSystem {
+            name: system.name(),
+            system: Box::new(system),
+            access,
+            batch,
+            last_run: 0,
+            commands: Commands::default(),
+        });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    /// System names grouped by the batch they run in.
+    pub fn batches(&self) -> Vec<Vec<&'static str>> {
+        let mut batches = vec![Vec::new(); self.batches];
+        for system in &self.systems {
+            batches[system.batch].push(system.name);
+        }
+        batches
+    }
+
+    pub fn run(&mut self, world: &mut World) {
+        for batch in 0..self.batches {
+            let this_run = world.increment_change_tick();
+            let world: &World = world;
+            let mut systems: Vec<&mut ScheduledSystem> =
+                self.systems.iter_mut().filter(|system| system.batch == batch).collect();
+            if let [system] = systems.as_mut_slice() {
+                system.run(world, this_run);
+                continue;
+            }
+            let jobs: Vec<Box<dyn FnOnce() + Send + '_>> = systems
+                .into_iter()
+                .map(|system| Box::new(move || system.run(world, this_run)) as Box<dyn FnOnce() + Send + '_>)
+                .collect();
+            WorkerPool::get().run_all(jobs);
+        }
+        // Commands land on a fresh tick so every system sees them as added next run.
+        world.increment_change_tick();
+        for system in &mut self.systems {
+            system.commands.apply(world);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::any::Any;
+
+use super::Entity;
+
+const EMPTY: u32 = u32::MAX;
+
+/// Anything `Send + Sync + 'static` can be attached to an entity.
+pub trait Component: Send + Sync + 'static {}
+
+impl<T: Send + Sync + 'static> Component for T {}
+
+/// World ticks at which a component was inserted and last mutably accessed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentTicks {
+    pub added: u32,
+    pub changed: u32,
+}
+
+/// Packed storage for one component type: `sparse` maps entity indices into the dense arrays,
+/// which stay contiguous for fast iteration.
+pub struct SparseSet<T> {
+    sparse: Vec<u32>,
+    entities: Vec<Entity>,
+    data: Vec<T>,
+    ticks: Vec<ComponentTicks>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        SparseSet {
+            sparse: Vec::new(),
+            entities: Vec::new(),
+            data: Vec::new(),
+            ticks: Vec::new(),
+        }
+    }
+}
+
+impl<T> SparseSet<T> {
+    fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let dense = *self.sparse.get(entity.index() as usize)?;
+        if dense == EMPTY || self.entities[dense as usize] != entity {
+            return None;
+        }
+        Some(dense as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.dense_index(entity).is_some()
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Inserts or replaces the component, returning the previous value.
+    pub fn insert(&mut self, entity: Entity, value: T, tick: u32) -> Option<T> {
+        if let Some(dense) = self.dense_index(entity) {
+            self.ticks[dense].changed = tick;
+            return Some(std::mem::replace(&mut self.data[dense], value));
+        }
+        let index = entity.index() as usize;
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, EMPTY);
+        }
+        self.sparse[index] = self.data.len() as u32;
+        self.entities.push(entity);
+        self.data.push(value);
+        self.ticks.push(ComponentTicks {
+            added: tick,
+            changed: tick,
+        });
+        None
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let dense = self.dense_index(entity)?;
+        self.sparse[entity.index() as usize] = EMPTY;
+        self.entities.swap_remove(dense);
+        self.ticks.swap_remove(dense);
+        let value = self.data.swap_remove(dense);
+        if let Some(moved) = self.entities.get(dense) {
+            self.sparse[moved.index() as usize] = dense as u32;
+        }
+        Some(value)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.dense_index(entity).map(|dense| &self.data[dense])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<(&mut T, &mut ComponentTicks)> {
+        let dense = self.dense_index(entity)?;
+        Some((&mut self.data[dense], &mut self.ticks[dense]))
+    }
+
+    pub fn ticks(&self, entity: Entity) -> Option<ComponentTicks> {
+        self.dense_index(entity).map(|dense| self.ticks[dense])
+    }
+}
+
+/// Type-erased view of a `SparseSet` so the world can hold every component type in one map.
+pub trait AnyStorage: Send + Sync {
+    fn type_name(&self) -> &'static str;
+    fn contains(&self, entity: Entity) -> bool;
+    fn entities(&self) -> &[Entity];
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Component> AnyStorage for SparseSet<T> {
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn contains(&self, entity: Entity) -> bool {
+        SparseSet::contains(self, entity)
+    }
+
+    fn entities(&self) -> &[Entity] {
+        SparseSet::entities(self)
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,232 @@
+use std::any::TypeId;
+use std::fmt::Display;
+
+use super::{Commands, Component, Entities, Entity, Query, QueryFilter, QueryParam, Res, ResMut, Ticks, World};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccessKey {
+    Component(TypeId, &'static str),
+    Resource(TypeId, &'static str),
+}
+
+impl AccessKey {
+    pub fn component<T: Component>() -> Self {
+        AccessKey::Component(TypeId::of::<T>(), std::any::type_name::<T>())
+    }
+
+    pub fn resource<R: Component>() -> Self {
+        AccessKey::Resource(TypeId::of::<R>(), std::any::type_name::<R>())
+    }
+}
+
+impl Display for AccessKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessKey::Component(_, name) => write!(f, "component {}", name),
+            AccessKey::Resource(_, name) => write!(f, "resource {}", name),
+        }
+    }
+}
+
+/// The components and resources a system reads and writes. Systems whose access does not
+/// conflict may run at the same time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SystemAccess {
+    reads: Vec<AccessKey>,
+    writes: Vec<AccessKey>,
+}
+
+impl SystemAccess {
+    pub fn add_read(&mut self, key: AccessKey) {
+        if !self.reads.contains(&key) {
+            self.reads.push(key);
+        }
+    }
+
+    pub fn add_write(&mut self, key: AccessKey) {
+        if !self.writes.contains(&key) {
+            self.writes.push(key);
+        }
+    }
+
+    pub fn reads(&self) -> &[AccessKey] {
+        &self.reads
+    }
+
+    pub fn writes(&self) -> &[AccessKey] {
+        &self.writes
+    }
+
+    pub fn can_read(&self, key: &AccessKey) -> bool {
+        self.reads.contains(key) || self.writes.contains(key)
+    }
+
+    pub fn can_write(&self, key: &AccessKey) -> bool {
+        self.writes.contains(key)
+    }
+
+    pub fn conflicts_with(&self, other: &SystemAccess) -> bool {
+        self.writes.iter().any(|key| other.can_read(key)) || other.writes.iter().any(|key| self.can_read(key))
+    }
+
+    /// The first key in `requested` this access does not allow.
+    pub fn undeclared(&self, requested: &SystemAccess) -> Option<AccessKey> {
+        let reads = requested.reads.iter().find(|key| !self.can_read(key));
+        let writes = requested.writes.iter().find(|key| !self.can_write(key));
+        reads.or(writes).copied()
+    }
+}
+
+pub trait System: Send {
+    fn name(&self) -> &'static str;
+    fn access(&self) -> &SystemAccess;
+    fn run(&mut self, context: &SystemContext);
+}
+
+/// A system backed by a closure; build it with `system` and declare its access with the
+/// `reads`/`writes` methods.
+pub struct FnSystem<F> {
+    name: &'static str,
+    access: SystemAccess,
+    func: F,
+}
+
+pub fn system<F>(name: &'static str, func: F) -> FnSystem<F>
+where
+    F: FnMut(&SystemContext) + Send + 'static,
+{
+    FnSystem {
+        name,
+        access: SystemAccess::default(),
+        func,
+    }
+}
+
+impl<F> FnSystem<F> {
+    pub fn reads<T: Component>(mut self) -> Self {
+        self.access.add_read(AccessKey::component::<T>());
+        self
+    }
+
+    pub fn writes<T: Component>(mut self) -> Self {
+        self.access.add_write(AccessKey::component::<T>());
+        self
+    }
+
+    pub fn reads_resource<R: Component>(mut self) -> Self {
+        self.access.add_read(AccessKey::resource::<R>());
+        self
+    }
+
+    pub fn writes_resource<R: Component>(mut self) -> Self {
+        self.access.add_write(AccessKey::resource::<R>());
+        self
+    }
+}
+
+impl<F> System for FnSystem<F>
+where
+    F: FnMut(&SystemContext) + Send + 'static,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn access(&self) -> &SystemAccess {
+        &self.access
+    }
+
+    fn run(&mut self, context: &SystemContext) {
+        (self.func)(context)
+    }
+}
+
+/// A system's view of the world, limited to the access it declared. Structural changes go
+/// through `commands` and are applied once the schedule finishes.
+pub struct SystemContext<'w> {
+    world: &'w World,
+    name: &'static str,
+    access: &'w SystemAccess,
+    ticks: Ticks,
+    commands: &'w Commands,
+}
+
+impl<'w> SystemContext<'w> {
+    pub(crate) fn new(
+        world: &'w World,
+        name: &'static str,
+        access: &'w SystemAccess,
+        ticks: Ticks,
+        commands: &'w Commands,
+    ) -> Self {
+        SystemContext {
+            world,
+            name,
+            access,
+            ticks,
+            commands,
+        }
+    }
+
+    fn check(&self, requested: &SystemAccess) {
+        if let Some(key) = self.access.undeclared(requested) {
+            panic!("system '{}' accessed {} without declaring it", self.name, key);
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn ticks(&self) -> Ticks {
+        self.ticks
+    }
+
+    pub fn query<Q: QueryParam>(&self) -> Query<'w, Q> {
+        self.query_filtered::<Q, ()>()
+    }
+
+    pub fn query_filtered<Q: QueryParam, F: QueryFilter>(&self) -> Query<'w, Q, F> {
+        let mut requested = SystemAccess::default();
+        Q::access(&mut requested);
+        F::access(&mut requested);
+        self.check(&requested);
+        Query::new(self.world, self.ticks)
+    }
+
+    pub fn get_resource<R: Component>(&self) -> Option<Res<'w, R>> {
+        let mut requested = SystemAccess::default();
+        requested.add_read(AccessKey::resource::<R>());
+        self.check(&requested);
+        self.world.get_resource::<R>()
+    }
+
+    pub fn get_resource_mut<R: Component>(&self) -> Option<ResMut<'w, R>> {
+        let mut requested = SystemAccess::default();
+        requested.add_write(AccessKey::resource::<R>());
+        self.check(&requested);
+        self.world.get_resource_mut::<R>()
+    }
+
+    pub fn resource<R: Component>(&self) -> Res<'w, R> {
+        self.get_resource::<R>()
+            .unwrap_or_else(|| panic!("resource {} does not exist", std::any::type_name::<R>()))
+    }
+
+    pub fn resource_mut<R: Component>(&self) -> ResMut<'w, R> {
+        self.get_resource_mut::<R>()
+            .unwrap_or_else(|| panic!("resource {} does not exist", std::any::type_name::<R>()))
+    }
+
+    pub fn commands(&self) -> &Commands {
+        self.commands
+    }
+
+    pub fn entities(&self) -> &Entities {
+        self.world.entities()
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.world.is_alive(entity)
+    }
+}
@@ -0,0 +1,299 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+
+use super::{AnyStorage, Bundle, Component, Entities, Entity, Query, QueryFilter, QueryParam, SparseSet, Ticks};
+
+type Storage = RwLock<Box<dyn AnyStorage>>;
+type Resource = RwLock<Box<dyn Any + Send + Sync>>;
+
+/// Entities, their components and global resources. Structural changes (spawning, inserting,
+/// despawning) need `&mut World`; queries and resources only need `&World` and lock per type,
+/// which is what lets the schedule run non-conflicting systems in parallel.
+pub struct World {
+    entities: Entities,
+    storages: HashMap<TypeId, Storage>,
+    resources: HashMap<TypeId, Resource>,
+    change_tick: AtomicU32,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        World {
+            entities: Entities::default(),
+            storages: HashMap::new(),
+            resources: HashMap::new(),
+            change_tick: AtomicU32::new(1),
+        }
+    }
+
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let entity = self.entities.alloc();
+        bundle.insert_into(self, entity);
+        entity
+    }
+
+    pub fn spawn_empty(&mut self) -> Entity {
+        self.entities.alloc()
+    }
+
+    /// Removes the entity and all its components; false if it was already gone.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.free(entity) {
+            return false;
+        }
+        for storage in self.storages.values_mut() {
+            storage.get_mut().unwrap().remove_entity(entity);
+        }
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.is_alive(entity)
+    }
+
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+
+    /// Attaches or replaces a component, returning the previous value.
+    ///
+    /// Panics if `entity` has been despawned.
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) -> Option<T> {
+        assert!(self.is_alive(entity), "entity {} does not exist", entity);
+        let tick = self.change_tick();
+        self.storage_mut::<T>().insert(entity, component, tick)
+    }
+
+    pub fn insert_bundle<B: Bundle>(&mut self, entity: Entity, bundle: B) {
+        assert!(self.is_alive(entity), "entity {} does not exist", entity);
+        bundle.insert_into(self, entity);
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        let storage = self.storages.get_mut(&TypeId::of::<T>())?.get_mut().unwrap();
+        storage.as_any_mut().downcast_mut::<SparseSet<T>>().unwrap().remove(entity)
+    }
+
+    pub fn has<T: Component>(&self, entity: Entity) -> bool {
+        match self.storages.get(&TypeId::of::<T>()) {
+            Some(storage) => read_lock(storage, std::any::type_name::<T>()).contains(entity),
+            None => false,
+        }
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<ComponentRef<'_, T>> {
+        let guard = read_lock(self.storages.get(&TypeId::of::<T>())?, std::any::type_name::<T>());
+        if !guard.contains(entity) {
+            return None;
+        }
+        Some(ComponentRef {
+            guard,
+            entity,
+            marker: PhantomData,
+        })
+    }
+
+    /// Mutable access outside of systems; marks the component changed.
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
+        let tick = self.change_tick();
+        let storage = self.storages.get_mut(&TypeId::of::<T>())?.get_mut().unwrap();
+        let (value, ticks) = storage
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .unwrap()
+            .get_mut(entity)?;
+        ticks.changed = tick;
+        Some(value)
+    }
+
+    /// Names of the component types attached to `entity`, for debugging and editor panels.
+    pub fn component_names(&self, entity: Entity) -> Vec<&'static str> {
+        let mut names: Vec<&'static str> = self
+            .storages
+            .values()
+            .map(|storage| read_lock(storage, "component"))
+            .filter(|storage| storage.contains(entity))
+            .map(|storage| storage.type_name())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Runs a query outside of any system. Every component counts as changed.
+    pub fn query<Q: QueryParam>(&self) -> Query<'_, Q> {
+        self.query_filtered::<Q, ()>()
+    }
+
+    pub fn query_filtered<Q: QueryParam, F: QueryFilter>(&self) -> Query<'_, Q, F> {
+        self.query_since(0)
+    }
+
+    /// Like `query_filtered`, but `Added`/`Changed` only match changes made after `last_run`.
+    pub fn query_since<Q: QueryParam, F: QueryFilter>(&self, last_run: u32) -> Query<'_, Q, F> {
+        Query::new(
+            self,
+            Ticks {
+                last_run,
+                this_run: self.change_tick(),
+            },
+        )
+    }
+
+    pub fn insert_resource<R: Component>(&mut self, resource: R) {
+        self.resources
+            .insert(TypeId::of::<R>(), RwLock::new(Box::new(resource)));
+    }
+
+    pub fn remove_resource<R: Component>(&mut self) -> Option<R> {
+        let resource = self.resources.remove(&TypeId::of::<R>())?;
+        resource.into_inner().unwrap().downcast::<R>().ok().map(|resource| *resource)
+    }
+
+    pub fn contains_resource<R: Component>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    pub fn get_resource<R: Component>(&self) -> Option<Res<'_, R>> {
+        let resource = self.resources.get(&TypeId::of::<R>())?;
+        Some(Res {
+            guard: read_lock(resource, std::any::type_name::<R>()),
+            marker: PhantomData,
+        })
+    }
+
+    pub fn get_resource_mut<R: Component>(&self) -> Option<ResMut<'_, R>> {
+        let resource = self.resources.get(&TypeId::of::<R>())?;
+        Some(ResMut {
+            guard: write_lock(resource, std::any::type_name::<R>()),
+            marker: PhantomData,
+        })
+    }
+
+    /// Panics if the resource was never inserted.
+    pub fn resource<R: Component>(&self) -> Res<'_, R> {
+        self.get_resource::<R>()
+            .unwrap_or_else(|| panic!("resource {} does not exist", std::any::type_name::<R>()))
+    }
+
+    /// Panics if the resource was never inserted.
+    pub fn resource_mut<R: Component>(&self) -> ResMut<'_, R> {
+        self.get_resource_mut::<R>()
+            .unwrap_or_else(|| panic!("resource {} does not exist", std::any::type_name::<R>()))
+    }
+
+    pub fn change_tick(&self) -> u32 {
+        self.change_tick.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn increment_change_tick(&self) -> u32 {
+        self.change_tick.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    fn storage_mut<T: Component>(&mut self) -> &mut SparseSet<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| RwLock::new(Box::new(SparseSet::<T>::default())))
+            .get_mut()
+            .unwrap()
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .unwrap()
+    }
+
+    pub(crate) fn read_storage<T: Component>(&self) -> Option<RwLockReadGuard<'_, Box<dyn AnyStorage>>> {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .map(|storage| read_lock(storage, std::any::type_name::<T>()))
+    }
+
+    pub(crate) fn write_storage<T: Component>(&self) -> Option<RwLockWriteGuard<'_, Box<dyn AnyStorage>>> {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .map(|storage| write_lock(storage, std::any::type_name::<T>()))
+    }
+}
+
+// The schedule never runs conflicting systems together, so a lock that is already taken means the
+// same system holds two overlapping borrows. Failing loudly beats deadlocking.
+fn read_lock<'a, T: ?Sized>(lock: &'a RwLock<Box<T>>, name: &str) -> RwLockReadGuard<'a, Box<T>> {
+    match lock.try_read() {
+        Ok(guard) => guard,
+        Err(TryLockError::WouldBlock) => panic!("{} is already borrowed mutably", name),
+        Err(TryLockError::Poisoned(err)) => err.into_inner(),
+    }
+}
+
+fn write_lock<'a, T: ?Sized>(lock: &'a RwLock<Box<T>>, name: &str) -> RwLockWriteGuard<'a, Box<T>> {
+    match lock.try_write() {
+        Ok(guard) => guard,
+        Err(TryLockError::WouldBlock) => panic!("{} is already borrowed", name),
+        Err(TryLockError::Poisoned(err)) => err.into_inner(),
+    }
+}
+
+pub struct ComponentRef<'w, T: Component> {
+    guard: RwLockReadGuard<'w, Box<dyn AnyStorage>>,
+    entity: Entity,
+    marker: PhantomData<&'w T>,
+}
+
+impl<T: Component> Deref for ComponentRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.guard
+            .as_any()
+            .downcast_ref::<SparseSet<T>>()
+            .unwrap()
+            .get(self.entity)
+            .unwrap()
+    }
+}
+
+impl<T: Component + std::fmt::Debug> std::fmt::Debug for ComponentRef<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+pub struct Res<'w, R: Component> {
+    guard: RwLockReadGuard<'w, Box<dyn Any + Send + Sync>>,
+    marker: PhantomData<&'w R>,
+}
+
+impl<R: Component> Deref for Res<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        self.guard.downcast_ref::<R>().unwrap()
+    }
+}
+
+pub struct ResMut<'w, R: Component> {
+    guard: RwLockWriteGuard<'w, Box<dyn Any + Send + Sync>>,
+    marker: PhantomData<&'w mut R>,
+}
+
+impl<R: Component> Deref for ResMut<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        self.guard.downcast_ref::<R>().unwrap()
+    }
+}
+
+impl<R: Component> DerefMut for ResMut<'_, R> {
+    fn deref_mut(&mut self) -> &mut R {
+        self.guard.downcast_mut::<R>().unwrap()
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use super::ResourceHandle;
+
+#[derive(Debug, Clone, Copy)]
+pub struct AliasRequest {
+    pub resource: ResourceHandle,
+    pub lifetime: (usize, usize),
+    pub size: u64,
+    pub alignment: u64,
+    pub memory_type_bits: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemoryBlock {
+    pub size: u64,
+    pub alignment: u64,
+    pub memory_type_bits: u32,
+    pub users: Vec<(ResourceHandle, (usize, usize))>,
+}
+
+impl MemoryBlock {
+    fn is_free_during(&self, lifetime: (usize, usize)) -> bool {
+        self.users
+            .iter()
+            .all(|(_, used)| used.1 < lifetime.0 || lifetime.1 < used.0)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AliasPlan {
+    pub blocks: Vec<MemoryBlock>,
+    pub assignments: HashMap<ResourceHandle, usize>,
+}
+
+impl AliasPlan {
+    pub fn total_size(&self) -> u64 {
+        self.blocks.iter().map(|block| block.size).sum()
+    }
+}
+
+/// Packs transient resources into shared memory blocks whenever their lifetimes do not overlap.
+pub fn plan_aliasing(requests: &[AliasRequest]) -> AliasPlan {
+    let mut sorted: Vec<&AliasRequest> = requests.iter().collect();
+    sorted.sort_by(|a, b| b.size.cmp(&a.size).then(a.resource.cmp(&b.resource)));
+
+    let mut plan = AliasPlan::default();
+    for request in sorted {
+        let existing = plan.blocks.iter().position(|block| {
+            block.memory_type_bits & request.memory_type_bits != 0
+                && block.size >= request.size
+                && block.is_free_during(request.lifetime)
+        });
+        let index = match existing {
+            Some(index) => {
+                let block = &mut plan.blocks[index];
+                block.memory_type_bits &= request.memory_type_bits;
+                block.alignment = block.alignment.max(request.alignment);
+                block.users.push((request.resource, request.lifetime));
+                index
+            }
+            None => {
+                plan.blocks.push(MemoryBlock {
+                    size: request.size,
+                    alignment: request.alignment,
+                    memory_type_bits: request.memory_type_bits,
+                    users: vec![(request.resource, request.lifetime)],
+                });
+                plan.blocks.len() - 1
+            }
+        };
+        plan.assignments.insert(request.resource, index);
+    }
+    plan
+}
@@ -0,0 +1,79 @@
+use std::fmt::Write;
+
+use super::{CompiledGraph, ResourceDesc, ResourceLifetime};
+
+impl CompiledGraph {
+    /// Renders passes, resources and their dependencies as a Graphviz DOT document.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph render_graph {{").unwrap();
+        writeln!(dot, "  rankdir=LR;").unwrap();
+        writeln!(dot, "  node [fontname=\"Helvetica\"];").unwrap();
+
+        for (order, step) in self.steps.iter().enumerate() {
+            let pass = &self.passes[step.pass.0];
+            writeln!(
+                dot,
+                "  p{} [shape=box, style=filled, fillcolor=\"#9ecae1\", label=\"{}. {}\\n{} barriers\"];",
+                step.pass.0,
+                order,
+                escape(&pass.name),
+                step.barriers.len()
+            )
+            .unwrap();
+        }
+        for handle in self.culled.iter() {
+            writeln!(
+                dot,
+                "  p{} [shape=box, style=dashed, color=gray, fontcolor=gray, label=\"{} (culled)\"];",
+                handle.0,
+                escape(&self.passes[handle.0].name)
+            )
+            .unwrap();
+        }
+
+        for (index, resource) in self.resources.iter().enumerate() {
+            let kind = match resource.desc {
+                ResourceDesc::Image(desc) => format!(
+                    "{}x{} {:?}",
+                    desc.extent.width, desc.extent.height, desc.format
+                ),
+                ResourceDesc::Buffer(desc) => format!("{} bytes", desc.size),
+            };
+            let (color, lifetime) = match resource.lifetime {
+                ResourceLifetime::Imported(_) => ("#fdd0a2", "imported".to_string()),
+                ResourceLifetime::Transient => match self.lifetimes[index] {
+                    Some((first, last)) => ("#c7e9c0", format!("transient [{}..{}]", first, last)),
+                    None => ("#eeeeee", "unused".to_string()),
+                },
+            };
+            let peripheries = if self.outputs.iter().any(|output| output.0 == index) { 2 } else { 1 };
+            writeln!(
+                dot,
+                "  r{} [shape=ellipse, style=filled, fillcolor=\"{}\", peripheries={}, label=\"{}\\n{}\\n{}\"];",
+                index,
+                color,
+                peripheries,
+                escape(&resource.name),
+                escape(&kind),
+                lifetime
+            )
+            .unwrap();
+        }
+
+        for (index, pass) in self.passes.iter().enumerate() {
+            for access in pass.reads.iter() {
+                writeln!(dot, "  r{} -> p{};", access.resource.0, index).unwrap();
+            }
+            for access in pass.writes.iter() {
+                writeln!(dot, "  p{} -> r{} [color=red];", index, access.resource.0).unwrap();
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,329 @@
+use ash::vk;
+
+use crate::profile_scope;
+use crate::vulkan::{allocate_memory, DeferredResource, DeletionQueue, VulkanInstance, VulkanPhysicalDevice};
+
+use super::{
+    plan_aliasing, AliasPlan, AliasRequest, Barrier, CompiledGraph, PassContext, ResourceDesc, ResourceHandle,
+    ResourceUsage,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum PhysicalResource {
+    Image {
+        image: vk::Image,
+        view: vk::ImageView,
+    },
+    Buffer(vk::Buffer),
+}
+
+/// Physical Vulkan objects backing a compiled graph. Transient resources are owned and
+/// share memory according to an `AliasPlan`; imported ones are bound by the caller.
+pub struct GraphResources {
+    physical: Vec<Option<PhysicalResource>>,
+    owned: Vec<ResourceHandle>,
+    memory: Vec<vk::DeviceMemory>,
+    plan: AliasPlan,
+}
+
+impl GraphResources {
+    pub fn realize(
+        device: &ash::Device,
+        instance: &VulkanInstance,
+        physical_device: &VulkanPhysicalDevice,
+        graph: &CompiledGraph,
+    ) -> Self {
+        let mut physical = vec![None; graph.resources.len()];
+        let mut owned = Vec::new();
+        let mut requests = Vec::new();
+
+        for (index, resource) in graph.resources.iter().enumerate() {
+            let handle = ResourceHandle(index);
+            let lifetime = match (resource.is_transient(), graph.lifetime(handle)) {
+                (true, Some(lifetime)) => lifetime,
+                _ => continue,
+            };
+            let (created, requirements) = unsafe {
+                match resource.desc {
+                    ResourceDesc::Image(desc) => {
+                        let create_info = vk::ImageCreateInfo::builder()
+                            .image_type(vk::ImageType::TYPE_2D)
+                            .format(desc.format)
+                            .extent(desc.extent)
+                            .mip_levels(desc.mip_levels)
+                            .array_layers(desc.array_layers)
+                            .samples(vk::SampleCountFlags::TYPE_1)
+                            .tiling(vk::ImageTiling::OPTIMAL)
+                            .usage(desc.usage)
+                            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                            .initial_layout(vk::ImageLayout::UNDEFINED);
+                        let image = device
+                            .create_image(&create_info, None)
+                            .expect("Failed to create transient image");
+                        (
+                            PhysicalResource::Image {
+                                image,
+                                view: vk::ImageView::null(),
+                            },
+                            device.get_image_memory_requirements(image),
+                        )
+                    }
+                    ResourceDesc::Buffer(desc) => {
+                        let create_info = vk::BufferCreateInfo::builder()
+                            .size(desc.size)
+                            .usage(desc.usage)
+                            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+                        let buffer = device
+                            .create_buffer(&create_info, None)
+                            .expect("Failed to create transient buffer");
+                        (
+                            PhysicalResource::Buffer(buffer),
+                            device.get_buffer_memory_requirements(buffer),
+                        )
+                    }
+                }
+            };
+            physical[index] = Some(created);
+            owned.push(handle);
+            requests.push(AliasRequest {
+                resource: handle,
+                lifetime,
+                size: requirements.size,
+                alignment: requirements.alignment,
+                memory_type_bits: requirements.memory_type_bits,
+            });
+        }
+
+        let plan = plan_aliasing(&requests);
+        let memory: Vec<vk::DeviceMemory> = plan
+            .blocks
+            .iter()
+            .map(|block| {
+                let requirements = vk::MemoryRequirements {
+                    size: block.size,
+                    alignment: block.alignment,
+                    memory_type_bits: block.memory_type_bits,
+                };
+                allocate_memory(
+                    device,
+                    instance,
+                    physical_device,
+                    requirements,
+                    vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                )
+            })
+            .collect();
+
+        for handle in owned.iter() {
+            let block = memory[plan.assignments[handle]];
+            let slot = physical[handle.0].as_mut().unwrap();
+            unsafe {
+                match slot {
+                    PhysicalResource::Image { image, view } => {
+                        device
+                            .bind_image_memory(*image, block, 0)
+                            .expect("Failed to bind transient image memory");
+                        if let ResourceDesc::Image(desc) = graph.resources[handle.0].desc {
+                            let view_info = vk::ImageViewCreateInfo::builder()
+                                .image(*image)
+                                .view_type(vk::ImageViewType::TYPE_2D)
+                                .format(desc.format)
+                                .subresource_range(desc.subresource_range());
+                            *view = device
+                                .create_image_view(&view_info, None)
+                                .expect("Failed to create transient image view");
+                        }
+                    }
+                    PhysicalResource::Buffer(buffer) => {
+                        device
+                            .bind_buffer_memory(*buffer, block, 0)
+                            .expect("Failed to bind transient buffer memory");
+                    }
+                }
+            }
+        }
+
+        log::debug!(
+            "Realized {} transient graph resources in {} memory blocks ({} bytes)",
+            owned.len(),
+            plan.blocks.len(),
+            plan.total_size()
+        );
+        GraphResources {
+            physical,
+            owned,
+            memory,
+            plan,
+        }
+    }
+
+    pub fn bind_imported(&mut self, handle: ResourceHandle, resource: PhysicalResource) {
+        self.physical[handle.0] = Some(resource);
+    }
+
+    pub fn get(&self, handle: ResourceHandle) -> Option<PhysicalResource> {
+        self.physical[handle.0]
+    }
+
+    pub fn image(&self, handle: ResourceHandle) -> Option<(vk::Image, vk::ImageView)> {
+        match self.physical[handle.0] {
+            Some(PhysicalResource::Image { image, view }) => Some((image, view)),
+            _ => None,
+        }
+    }
+
+    pub fn buffer(&self, handle: ResourceHandle) -> Option<vk::Buffer> {
+        match self.physical[handle.0] {
+            Some(PhysicalResource::Buffer(buffer)) => Some(buffer),
+            _ => None,
+        }
+    }
+
+    pub fn alias_plan(&self) -> &AliasPlan {
+        &self.plan
+    }
+
+    /// Hands the transient objects to the deletion queue so they outlive in-flight frames.
+    pub fn release(&mut self, deletion_queue: &mut DeletionQueue) {
+        for handle in self.owned.drain(..) {
+            match self.physical[handle.0].take() {
+                Some(PhysicalResource::Image { image, view }) => {
+                    deletion_queue.push(DeferredResource::ImageView(view));
+                    deletion_queue.push(DeferredResource::Image(image));
+                }
+                Some(PhysicalResource::Buffer(buffer)) => {
+                    deletion_queue.push(DeferredResource::Buffer(buffer))
+                }
+                None => {}
+            }
+        }
+        for memory in self.memory.drain(..) {
+            deletion_queue.push(DeferredResource::Memory(memory));
+        }
+    }
+}
+
+impl CompiledGraph {
+    pub fn execute(&self, device: &ash::Device, command_buffer: vk::CommandBuffer, resources: &GraphResources) {
+        profile_scope!("RenderGraph::execute", "render");
+        let context = PassContext {
+            device,
+            command_buffer,
+            resources,
+        };
+        let aliasing = self.aliasing_barriers(resources.alias_plan());
+        for (index, step) in self.steps.iter().enumerate() {
+            let mut barriers = step.barriers.clone();
+            for (_, alias) in aliasing.iter().filter(|(step_index, _)| *step_index == index) {
+                match barriers.iter_mut().find(|barrier| barrier.resource == alias.resource) {
+                    // The first barrier of a transient already starts from an undefined layout.
+                    Some(barrier) => {
+                        barrier.src.stage |= alias.src.stage;
+                        barrier.src.access |= alias.src.access;
+                    }
+                    None => barriers.push(*alias),
+                }
+            }
+            self.record_barriers(device, command_buffer, resources, &barriers);
+            (self.passes[step.pass.0].executor)(&context);
+        }
+        self.record_barriers(device, command_buffer, resources, &self.final_barriers);
+    }
+
+    /// Barriers ordering the first use of each transient after everything the previous occupant
+    /// of its memory block did, keyed by the step they go before. Contents do not survive a
+    /// change of occupant, so images start from an undefined layout.
+    fn aliasing_barriers(&self, plan: &AliasPlan) -> Vec<(usize, Barrier)> {
+        let mut barriers = Vec::new();
+        for block in plan.blocks.iter() {
+            let mut users = block.users.clone();
+            users.sort_by_key(|(_, lifetime)| lifetime.0);
+            for pair in users.windows(2) {
+                let (previous, (next, (first_step, _))) = (pair[0].0, pair[1]);
+                let (used, first) = match (self.usages[previous.0], self.usages[next.0]) {
+                    (Some((_, used)), Some((first, _))) => (used, first),
+                    _ => continue,
+                };
+                barriers.push((
+                    first_step,
+                    Barrier {
+                        resource: next,
+                        src: ResourceUsage {
+                            layout: vk::ImageLayout::UNDEFINED,
+                            ..used
+                        },
+                        dst: first,
+                    },
+                ));
+            }
+        }
+        barriers
+    }
+
+    fn record_barriers(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        resources: &GraphResources,
+        barriers: &[Barrier],
+    ) {
+        if barriers.is_empty() {
+            return;
+        }
+        let mut src_stage = vk::PipelineStageFlags::empty();
+        let mut dst_stage = vk::PipelineStageFlags::empty();
+        let mut image_barriers = Vec::new();
+        let mut buffer_barriers = Vec::new();
+
+        for barrier in barriers.iter() {
+            src_stage |= barrier.src.stage;
+            dst_stage |= barrier.dst.stage;
+            let resource = &self.resources[barrier.resource.0];
+            match (resource.desc, resources.get(barrier.resource)) {
+                (ResourceDesc::Image(desc), Some(PhysicalResource::Image { image, .. })) => {
+                    image_barriers.push(
+                        vk::ImageMemoryBarrier::builder()
+                            .src_access_mask(barrier.src.access)
+                            .dst_access_mask(barrier.dst.access)
+                            .old_layout(barrier.src.layout)
+                            .new_layout(barrier.dst.layout)
+                            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                            .image(image)
+                            .subresource_range(desc.subresource_range())
+                            .build(),
+                    );
+                }
+                (ResourceDesc::Buffer(_), Some(PhysicalResource::Buffer(buffer))) => {
+                    buffer_barriers.push(
+                        vk::BufferMemoryBarrier::builder()
+                            .src_access_mask(barrier.src.access)
+                            .dst_access_mask(barrier.dst.access)
+                            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                            .buffer(buffer)
+                            .offset(0)
+                            .size(vk::WHOLE_SIZE)
+                            .build(),
+                    );
+                }
+                _ => {
+                    log::error!("Render graph resource '{}' has no physical resource bound.", resource.name);
+                    panic!("Unbound render graph resource '{}'", resource.name);
+                }
+            }
+        }
+
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                src_stage,
+                dst_stage,
+                vk::DependencyFlags::empty(),
+                &[],
+                &buffer_barriers,
+                &image_barriers,
+            );
+        }
+    }
+}
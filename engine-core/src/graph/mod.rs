@@ -0,0 +1,12 @@
+mod resource;
+mod pass;
+mod render_graph;
+mod alias;
+mod execute;
+mod dot;
+
+pub use resource::*;
+pub use pass::*;
+pub use render_graph::*;
+pub use alias::*;
+pub use execute::*;
@@ -0,0 +1,63 @@
+use ash::vk;
+
+use super::{GraphResources, ResourceHandle, ResourceUsage};
+
+pub struct PassContext<'a> {
+    pub device: &'a ash::Device,
+    pub command_buffer: vk::CommandBuffer,
+    pub resources: &'a GraphResources,
+}
+
+pub type PassExecutor = Box<dyn Fn(&PassContext)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PassHandle(pub(crate) usize);
+
+#[derive(Debug, Clone, Copy)]
+pub struct PassAccess {
+    pub resource: ResourceHandle,
+    pub usage: ResourceUsage,
+}
+
+pub struct RenderPass {
+    pub name: String,
+    pub reads: Vec<PassAccess>,
+    pub writes: Vec<PassAccess>,
+    pub side_effects: bool,
+    pub(crate) executor: PassExecutor,
+}
+
+impl RenderPass {
+    pub fn accesses(&self) -> impl Iterator<Item = &PassAccess> {
+        self.reads.iter().chain(self.writes.iter())
+    }
+}
+
+/// Handed to the setup closure of `RenderGraph::add_pass` to declare resource accesses.
+pub struct PassBuilder<'a> {
+    pub(crate) pass: &'a mut RenderPass,
+}
+
+impl<'a> PassBuilder<'a> {
+    pub fn read(&mut self, resource: ResourceHandle, usage: ResourceUsage) -> &mut Self {
+        self.pass.reads.push(PassAccess { resource, usage });
+        self
+    }
+
+    pub fn write(&mut self, resource: ResourceHandle, usage: ResourceUsage) -> &mut Self {
+        self.pass.writes.push(PassAccess {
+            resource,
+            usage: ResourceUsage {
+                write: true,
+                ..usage
+            },
+        });
+        self
+    }
+
+    /// Keeps the pass alive during culling even if none of its outputs are consumed.
+    pub fn side_effects(&mut self) -> &mut Self {
+        self.pass.side_effects = true;
+        self
+    }
+}
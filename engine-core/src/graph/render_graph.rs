@@ -0,0 +1,348 @@
+use std::collections::{BTreeMap, HashMap};
+
+use ash::vk;
+
+use super::{
+    BufferDesc, GraphResource, ImageDesc, ImportedState, PassBuilder, PassContext, PassHandle,
+    RenderPass, ResourceDesc, ResourceHandle, ResourceLifetime, ResourceUsage,
+};
+
+/// A pipeline barrier the compiled graph records before a pass (or after the last one).
+#[derive(Debug, Clone, Copy)]
+pub struct Barrier {
+    pub resource: ResourceHandle,
+    pub src: ResourceUsage,
+    pub dst: ResourceUsage,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompiledStep {
+    pub pass: PassHandle,
+    pub barriers: Vec<Barrier>,
+}
+
+pub struct RenderGraph {
+    resources: Vec<GraphResource>,
+    names: HashMap<String, ResourceHandle>,
+    passes: Vec<RenderPass>,
+    outputs: Vec<ResourceHandle>,
+}
+
+impl Default for RenderGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        RenderGraph {
+            resources: Vec::new(),
+            names: HashMap::new(),
+            passes: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    pub fn create_image(&mut self, name: &str, desc: ImageDesc) -> ResourceHandle {
+        self.add_resource(name, ResourceDesc::Image(desc), ResourceLifetime::Transient)
+    }
+
+    pub fn create_buffer(&mut self, name: &str, desc: BufferDesc) -> ResourceHandle {
+        self.add_resource(name, ResourceDesc::Buffer(desc), ResourceLifetime::Transient)
+    }
+
+    pub fn import_image(&mut self, name: &str, desc: ImageDesc, state: ImportedState) -> ResourceHandle {
+        self.add_resource(name, ResourceDesc::Image(desc), ResourceLifetime::Imported(state))
+    }
+
+    pub fn import_buffer(&mut self, name: &str, desc: BufferDesc, state: ImportedState) -> ResourceHandle {
+        self.add_resource(name, ResourceDesc::Buffer(desc), ResourceLifetime::Imported(state))
+    }
+
+    pub fn resource(&self, name: &str) -> Option<ResourceHandle> {
+        self.names.get(name).copied()
+    }
+
+    pub fn add_pass<S, E>(&mut self, name: &str, setup: S, executor: E) -> PassHandle
+    where
+        S: FnOnce(&mut PassBuilder),
+        E: Fn(&PassContext) + 'static,
+    {
+        let mut pass = RenderPass {
+            name: name.to_string(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+            side_effects: false,
+            executor: Box::new(executor),
+        };
+        setup(&mut PassBuilder { pass: &mut pass });
+        self.passes.push(pass);
+        PassHandle(self.passes.len() - 1)
+    }
+
+    /// Marks a resource as consumed outside the graph so the passes producing it survive culling.
+    pub fn mark_output(&mut self, resource: ResourceHandle) {
+        if !self.outputs.contains(&resource) {
+            self.outputs.push(resource);
+        }
+    }
+
+    fn add_resource(&mut self, name: &str, desc: ResourceDesc, lifetime: ResourceLifetime) -> ResourceHandle {
+        if self.names.contains_key(name) {
+            log::error!("Render graph resource '{}' declared twice.", name);
+            panic!("Duplicate render graph resource '{}'", name);
+        }
+        let handle = ResourceHandle(self.resources.len());
+        self.resources.push(GraphResource {
+            name: name.to_string(),
+            desc,
+            lifetime,
+        });
+        self.names.insert(name.to_string(), handle);
+        handle
+    }
+
+    pub fn compile(self) -> CompiledGraph {
+        let alive = self.cull();
+        let order: Vec<PassHandle> = (0..self.passes.len())
+            .filter(|index| alive[*index])
+            .map(PassHandle)
+            .collect();
+        let culled = (0..self.passes.len())
+            .filter(|index| !alive[*index])
+            .map(PassHandle)
+            .collect::<Vec<_>>();
+        for pass in culled.iter() {
+            log::debug!("Culled render pass '{}'", self.passes[pass.0].name);
+        }
+
+        let mut states: Vec<ResourceState> = self
+            .resources
+            .iter()
+            .map(|resource| match resource.lifetime {
+                ResourceLifetime::Imported(state) => ResourceState::new(state.initial, true),
+                ResourceLifetime::Transient => ResourceState::new(ResourceUsage::undefined(), false),
+            })
+            .collect();
+        let mut lifetimes: Vec<Option<(usize, usize)>> = vec![None; self.resources.len()];
+        let mut usages: Vec<Option<(ResourceUsage, ResourceUsage)>> = vec![None; self.resources.len()];
+
+        let mut steps = Vec::with_capacity(order.len());
+        for (step_index, pass_handle) in order.iter().enumerate() {
+            let pass = &self.passes[pass_handle.0];
+            let mut barriers = Vec::new();
+            for (resource, usage) in merged_accesses(pass) {
+                let desc = &self.resources[resource.0];
+                if desc.is_transient() && !usage.write && !states[resource.0].written {
+                    log::warn!(
+                        "Render pass '{}' reads '{}' before any pass writes it",
+                        pass.name,
+                        desc.name
+                    );
+                }
+                if let Some(barrier) = states[resource.0].transition(resource, usage, desc.desc.is_image()) {
+                    barriers.push(barrier);
+                }
+                lifetimes[resource.0] = Some(match lifetimes[resource.0] {
+                    Some((first, _)) => (first, step_index),
+                    None => (step_index, step_index),
+                });
+                usages[resource.0] = Some(match usages[resource.0] {
+                    Some((first, used)) => (first, accumulate(used, usage)),
+                    None => (usage, accumulate(ResourceUsage::undefined(), usage)),
+                });
+            }
+            steps.push(CompiledStep {
+                pass: *pass_handle,
+                barriers,
+            });
+        }
+
+        let mut final_barriers = Vec::new();
+        for (index, resource) in self.resources.iter().enumerate() {
+            if let ResourceLifetime::Imported(ImportedState {
+                final_usage: Some(final_usage),
+                ..
+            }) = resource.lifetime
+            {
+                let handle = ResourceHandle(index);
+                if let Some(barrier) = states[index].transition(handle, final_usage, resource.desc.is_image()) {
+                    final_barriers.push(barrier);
+                }
+            }
+        }
+
+        CompiledGraph {
+            resources: self.resources,
+            passes: self.passes,
+            outputs: self.outputs,
+            steps,
+            final_barriers,
+            culled,
+            lifetimes,
+            usages,
+        }
+    }
+
+    fn cull(&self) -> Vec<bool> {
+        let mut needed = vec![false; self.resources.len()];
+        for output in self.outputs.iter() {
+            needed[output.0] = true;
+        }
+        let mut alive = vec![false; self.passes.len()];
+        for (index, pass) in self.passes.iter().enumerate().rev() {
+            let produces_needed = pass.writes.iter().any(|access| needed[access.resource.0]);
+            if pass.side_effects || produces_needed {
+                alive[index] = true;
+                for access in pass.reads.iter() {
+                    needed[access.resource.0] = true;
+                }
+            }
+        }
+        alive
+    }
+}
+
+/// Folds all accesses a pass makes to the same resource into a single usage.
+fn merged_accesses(pass: &RenderPass) -> Vec<(ResourceHandle, ResourceUsage)> {
+    let mut merged: BTreeMap<ResourceHandle, ResourceUsage> = BTreeMap::new();
+    for access in pass.accesses() {
+        merged
+            .entry(access.resource)
+            .and_modify(|usage| {
+                usage.stage |= access.usage.stage;
+                usage.access |= access.usage.access;
+                if access.usage.write {
+                    usage.write = true;
+                    usage.layout = access.usage.layout;
+                }
+            })
+            .or_insert(access.usage);
+    }
+    merged.into_iter().collect()
+}
+
+/// Folds `usage` into everything a resource was used for so far. Only writes keep their access,
+/// since reads need no availability operation before the memory is reused.
+fn accumulate(used: ResourceUsage, usage: ResourceUsage) -> ResourceUsage {
+    ResourceUsage {
+        stage: used.stage | usage.stage,
+        access: if usage.write { used.access | usage.access } else { used.access },
+        layout: usage.layout,
+        write: used.write || usage.write,
+    }
+}
+
+struct ResourceState {
+    current: ResourceUsage,
+    last_write: Option<ResourceUsage>,
+    synced_read_stages: vk::PipelineStageFlags,
+    written: bool,
+}
+
+impl ResourceState {
+    fn new(initial: ResourceUsage, imported: bool) -> Self {
+        ResourceState {
+            current: initial,
+            last_write: if imported && initial.write { Some(initial) } else { None },
+            synced_read_stages: vk::PipelineStageFlags::empty(),
+            written: imported,
+        }
+    }
+
+    fn transition(&mut self, resource: ResourceHandle, usage: ResourceUsage, is_image: bool) -> Option<Barrier> {
+        let layout_change = is_image && self.current.layout != usage.layout;
+        let barrier = if usage.write || layout_change {
+            // Write-after-read only needs an execution dependency on the readers.
+            let src = if !self.synced_read_stages.is_empty() {
+                ResourceUsage {
+                    stage: self.synced_read_stages,
+                    access: vk::AccessFlags::empty(),
+                    ..self.current
+                }
+            } else if let Some(write) = self.last_write {
+                ResourceUsage {
+                    layout: self.current.layout,
+                    ..write
+                }
+            } else {
+                ResourceUsage {
+                    stage: vk::PipelineStageFlags::TOP_OF_PIPE,
+                    access: vk::AccessFlags::empty(),
+                    ..self.current
+                }
+            };
+            self.synced_read_stages = if usage.write {
+                vk::PipelineStageFlags::empty()
+            } else {
+                usage.stage
+            };
+            Some(Barrier {
+                resource,
+                src,
+                dst: usage,
+            })
+        } else if self.last_write.is_some() && !self.synced_read_stages.contains(usage.stage) {
+            self.synced_read_stages |= usage.stage;
+            Some(Barrier {
+                resource,
+                src: self.last_write.unwrap(),
+                dst: usage,
+            })
+        } else {
+            self.synced_read_stages |= usage.stage;
+            None
+        };
+
+        if usage.write {
+            self.last_write = Some(usage);
+            self.written = true;
+        }
+        self.current = usage;
+        barrier
+    }
+}
+
+pub struct CompiledGraph {
+    pub(crate) resources: Vec<GraphResource>,
+    pub(crate) passes: Vec<RenderPass>,
+    pub(crate) outputs: Vec<ResourceHandle>,
+    pub(crate) steps: Vec<CompiledStep>,
+    pub(crate) final_barriers: Vec<Barrier>,
+    pub(crate) culled: Vec<PassHandle>,
+    pub(crate) lifetimes: Vec<Option<(usize, usize)>>,
+    /// First usage of each resource and every stage it is used in, with the accesses that write.
+    pub(crate) usages: Vec<Option<(ResourceUsage, ResourceUsage)>>,
+}
+
+impl CompiledGraph {
+    pub fn resources(&self) -> &[GraphResource] {
+        &self.resources
+    }
+
+    pub fn resource(&self, handle: ResourceHandle) -> &GraphResource {
+        &self.resources[handle.0]
+    }
+
+    pub fn pass(&self, handle: PassHandle) -> &RenderPass {
+        &self.passes[handle.0]
+    }
+
+    pub fn steps(&self) -> &[CompiledStep] {
+        &self.steps
+    }
+
+    pub fn final_barriers(&self) -> &[Barrier] {
+        &self.final_barriers
+    }
+
+    pub fn culled_passes(&self) -> &[PassHandle] {
+        &self.culled
+    }
+
+    /// First and last step index that touches the resource, `None` if it is never used.
+    pub fn lifetime(&self, handle: ResourceHandle) -> Option<(usize, usize)> {
+        self.lifetimes[handle.0]
+    }
+}
@@ -0,0 +1,242 @@
+use ash::vk;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResourceHandle(pub(crate) usize);
+
+impl ResourceHandle {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImageDesc {
+    pub format: vk::Format,
+    pub extent: vk::Extent3D,
+    pub usage: vk::ImageUsageFlags,
+    pub aspect: vk::ImageAspectFlags,
+    pub mip_levels: u32,
+    pub array_layers: u32,
+}
+
+impl ImageDesc {
+    pub fn new_2d(format: vk::Format, width: u32, height: u32, usage: vk::ImageUsageFlags) -> Self {
+        let aspect = match format {
+            vk::Format::D16_UNORM | vk::Format::D32_SFLOAT | vk::Format::X8_D24_UNORM_PACK32 => {
+                vk::ImageAspectFlags::DEPTH
+            }
+            vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT => {
+                vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+            }
+            _ => vk::ImageAspectFlags::COLOR,
+        };
+        ImageDesc {
+            format,
+            extent: vk::Extent3D {
+                width,
+                height,
+                depth: 1,
+            },
+            usage,
+            aspect,
+            mip_levels: 1,
+            array_layers: 1,
+        }
+    }
+
+    pub fn subresource_range(&self) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: self.aspect,
+            base_mip_level: 0,
+            level_count: self.mip_levels,
+            base_array_layer: 0,
+            layer_count: self.array_layers,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BufferDesc {
+    pub size: vk::DeviceSize,
+    pub usage: vk::BufferUsageFlags,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ResourceDesc {
+    Image(ImageDesc),
+    Buffer(BufferDesc),
+}
+
+impl ResourceDesc {
+    pub fn is_image(&self) -> bool {
+        matches!(self, ResourceDesc::Image(_))
+    }
+}
+
+/// State of an imported resource at the boundaries of the graph.
+#[derive(Debug, Clone, Copy)]
+pub struct ImportedState {
+    pub initial: ResourceUsage,
+    pub final_usage: Option<ResourceUsage>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ResourceLifetime {
+    Transient,
+    Imported(ImportedState),
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphResource {
+    pub name: String,
+    pub desc: ResourceDesc,
+    pub lifetime: ResourceLifetime,
+}
+
+impl GraphResource {
+    pub fn is_transient(&self) -> bool {
+        matches!(self.lifetime, ResourceLifetime::Transient)
+    }
+}
+
+/// How a pass touches a resource: pipeline stage, access mask and, for images, the layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceUsage {
+    pub stage: vk::PipelineStageFlags,
+    pub access: vk::AccessFlags,
+    pub layout: vk::ImageLayout,
+    pub write: bool,
+}
+
+impl ResourceUsage {
+    pub fn undefined() -> Self {
+        ResourceUsage {
+            stage: vk::PipelineStageFlags::TOP_OF_PIPE,
+            access: vk::AccessFlags::empty(),
+            layout: vk::ImageLayout::UNDEFINED,
+            write: false,
+        }
+    }
+
+    pub fn color_attachment() -> Self {
+        ResourceUsage {
+            stage: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            access: vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            write: true,
+        }
+    }
+
+    pub fn depth_attachment() -> Self {
+        ResourceUsage {
+            stage: vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            access: vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            write: true,
+        }
+    }
+
+    pub fn depth_read() -> Self {
+        ResourceUsage {
+            stage: vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            access: vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ,
+            layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            write: false,
+        }
+    }
+
+    pub fn sampled(stage: vk::PipelineStageFlags) -> Self {
+        ResourceUsage {
+            stage,
+            access: vk::AccessFlags::SHADER_READ,
+            layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            write: false,
+        }
+    }
+
+    pub fn storage_read(stage: vk::PipelineStageFlags) -> Self {
+        ResourceUsage {
+            stage,
+            access: vk::AccessFlags::SHADER_READ,
+            layout: vk::ImageLayout::GENERAL,
+            write: false,
+        }
+    }
+
+    pub fn storage_write(stage: vk::PipelineStageFlags) -> Self {
+        ResourceUsage {
+            stage,
+            access: vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+            layout: vk::ImageLayout::GENERAL,
+            write: true,
+        }
+    }
+
+    pub fn uniform(stage: vk::PipelineStageFlags) -> Self {
+        ResourceUsage {
+            stage,
+            access: vk::AccessFlags::UNIFORM_READ,
+            layout: vk::ImageLayout::UNDEFINED,
+            write: false,
+        }
+    }
+
+    pub fn vertex_buffer() -> Self {
+        ResourceUsage {
+            stage: vk::PipelineStageFlags::VERTEX_INPUT,
+            access: vk::AccessFlags::VERTEX_ATTRIBUTE_READ,
+            layout: vk::ImageLayout::UNDEFINED,
+            write: false,
+        }
+    }
+
+    pub fn index_buffer() -> Self {
+        ResourceUsage {
+            stage: vk::PipelineStageFlags::VERTEX_INPUT,
+            access: vk::AccessFlags::INDEX_READ,
+            layout: vk::ImageLayout::UNDEFINED,
+            write: false,
+        }
+    }
+
+    pub fn indirect_buffer() -> Self {
+        ResourceUsage {
+            stage: vk::PipelineStageFlags::DRAW_INDIRECT,
+            access: vk::AccessFlags::INDIRECT_COMMAND_READ,
+            layout: vk::ImageLayout::UNDEFINED,
+            write: false,
+        }
+    }
+
+    pub fn transfer_src() -> Self {
+        ResourceUsage {
+            stage: vk::PipelineStageFlags::TRANSFER,
+            access: vk::AccessFlags::TRANSFER_READ,
+            layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            write: false,
+        }
+    }
+
+    pub fn transfer_dst() -> Self {
+        ResourceUsage {
+            stage: vk::PipelineStageFlags::TRANSFER,
+            access: vk::AccessFlags::TRANSFER_WRITE,
+            layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            write: true,
+        }
+    }
+
+    pub fn present() -> Self {
+        ResourceUsage {
+            stage: vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            access: vk::AccessFlags::empty(),
+            layout: vk::ImageLayout::PRESENT_SRC_KHR,
+            write: false,
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+use serde_derive::{Deserialize, Serialize};
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::conf::ConfigError;
+use crate::vfs::{Vfs, VfsError, VfsPath};
+
+/// Something a player can press, written in the input map as `{ key: Space }`,
+/// `{ mouse: Left }` or `{ gamepad: South }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    Gamepad(gilrs::Button),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseAxis {
+    X,
+    Y,
+}
+
+/// One source for a named axis; the strongest source bound to an axis wins each frame.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AxisBinding {
+    /// Two buttons mapped to -1 and +1, e.g. A and D.
+    Buttons { negative: Binding, positive: Binding },
+    GamepadAxis(gilrs::Axis),
+    /// Raw mouse movement this frame, in device units.
+    MouseMotion(MouseAxis),
+    MouseWheel(MouseAxis),
+}
+
+/// Named actions and axes loaded from YAML, e.g.
+///
+/// ```yaml
+/// actions:
+///   jump: [{ key: Space }, { gamepad: South }]
+/// axes:
+///   move_x:
+///     - buttons: { negative: { key: A }, positive: { key: D } }
+///     - gamepad_axis: LeftStickX
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct InputMap {
+    #[serde(default)]
+    pub actions: BTreeMap<String, Vec<Binding>>,
+    #[serde(default)]
+    pub axes: BTreeMap<String, Vec<AxisBinding>>,
+    /// Gamepad stick values below this magnitude read as 0. Unset in a user rebind file so
+    /// the engine's value is kept; `dead_zone()` falls back to `DEFAULT_DEAD_ZONE`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dead_zone: Option<f32>,
+}
+
+pub const DEFAULT_DEAD_ZONE: f32 = 0.15;
+
+impl InputMap {
+    /// Reads the map at `path` through `vfs`; `None` when no mounted source holds it.
+    pub fn read(vfs: &Vfs, path: &VfsPath) -> Result<Option<Self>, ConfigError> {
+        let contents = match vfs.read_to_string(path) {
+            Ok(contents) => contents,
+            Err(VfsError::NotFound(_)) | Err(VfsError::NotMounted(_)) => return Ok(None),
+            Err(err) => return Err(ConfigError::Read(err.to_string())),
+        };
+        serde_yaml::from_str(&contents)
+            .map(Some)
+            .map_err(|err| ConfigError::InvalidInputMap(format!("{}: {}", path, err)))
+    }
+
+    pub fn dead_zone(&self) -> f32 {
+        self.dead_zone.unwrap_or(DEFAULT_DEAD_ZONE)
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Replaces every binding of `action`, creating it if needed.
+    pub fn rebind(&mut self, action: &str, bindings: Vec<Binding>) {
+        self.actions.insert(action.to_string(), bindings);
+    }
+
+    /// Adds `binding` to `action`, first removing it from any other action so one press never
+    /// triggers two actions.
+    pub fn bind_exclusive(&mut self, action: &str, binding: Binding) {
+        for bindings in self.actions.values_mut() {
+            bindings.retain(|existing| *existing != binding);
+        }
+        self.actions.entry(action.to_string()).or_default().push(binding);
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|existing| *existing != binding);
+        }
+    }
+
+    pub fn rebind_axis(&mut self, axis: &str, bindings: Vec<AxisBinding>) {
+        self.axes.insert(axis.to_string(), bindings);
+    }
+
+    /// Overlays `other`: its actions and axes replace ours wholesale, the rest are kept, and
+    /// its dead zone applies only when it sets one.
+    pub fn merge(&mut self, other: InputMap) {
+        self.actions.extend(other.actions);
+        self.axes.extend(other.axes);
+        if other.dead_zone.is_some() {
+            self.dead_zone = other.dead_zone;
+        }
+    }
+}
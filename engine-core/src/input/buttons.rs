@@ -0,0 +1,66 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// Per-frame state of a set of buttons: `pressed` and `released` only hold during the frame the
+/// transition happened, `held` for as long as the button is down.
+#[derive(Debug, Clone)]
+pub struct ButtonInput<T: Copy + Eq + Hash> {
+    held: HashSet<T>,
+    pressed: HashSet<T>,
+    released: HashSet<T>,
+}
+
+impl<T: Copy + Eq + Hash> Default for ButtonInput<T> {
+    fn default() -> Self {
+        ButtonInput {
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> ButtonInput<T> {
+    pub fn press(&mut self, button: T) {
+        // Key repeat sends further presses while held; those are not new presses.
+        if self.held.insert(button) {
+            self.pressed.insert(button);
+        }
+    }
+
+    pub fn release(&mut self, button: T) {
+        if self.held.remove(&button) {
+            self.released.insert(button);
+        }
+    }
+
+    /// Releases everything, e.g. when the window loses focus and release events would be missed.
+    pub fn release_all(&mut self) {
+        self.released.extend(self.held.drain());
+    }
+
+    pub fn held(&self, button: T) -> bool {
+        self.held.contains(&button)
+    }
+
+    pub fn pressed(&self, button: T) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn released(&self, button: T) -> bool {
+        self.released.contains(&button)
+    }
+
+    pub fn iter_held(&self) -> impl Iterator<Item = &T> {
+        self.held.iter()
+    }
+
+    pub fn iter_pressed(&self) -> impl Iterator<Item = &T> {
+        self.pressed.iter()
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+
+use super::ButtonInput;
+
+#[derive(Debug, Default)]
+pub struct GamepadState {
+    pub name: String,
+    pub buttons: ButtonInput<Button>,
+    axes: HashMap<Axis, f32>,
+}
+
+impl GamepadState {
+    pub fn axis(&self, axis: Axis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+}
+
+/// Connected gamepads, polled once per frame. Without a gamepad backend this stays empty.
+pub struct Gamepads {
+    gilrs: Option<Gilrs>,
+    pads: HashMap<GamepadId, GamepadState>,
+    last_pressed: Option<Button>,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(err) => {
+                log::warn!("Gamepad support unavailable: {}", err);
+                None
+            }
+        };
+        let mut gamepads = Gamepads {
+            gilrs,
+            pads: HashMap::new(),
+            last_pressed: None,
+        };
+        if let Some(gilrs) = &gamepads.gilrs {
+            for (id, gamepad) in gilrs.gamepads() {
+                gamepads.pads.insert(
+                    id,
+                    GamepadState {
+                        name: gamepad.name().to_string(),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+        gamepads
+    }
+
+    pub(crate) fn poll(&mut self) {
+        self.last_pressed = None;
+        let gilrs = match self.gilrs.as_mut() {
+            Some(gilrs) => gilrs,
+            None => return,
+        };
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::Connected => {
+                    let name = gilrs.gamepad(event.id).name().to_string();
+                    log::info!("Gamepad connected: {}", name);
+                    self.pads.insert(
+                        event.id,
+                        GamepadState {
+                            name,
+                            ..Default::default()
+                        },
+                    );
+                }
+                EventType::Disconnected => {
+                    if let Some(pad) = self.pads.remove(&event.id) {
+                        log::info!("Gamepad disconnected: {}", pad.name);
+                    }
+                }
+                EventType::ButtonPressed(button, _) => {
+                    self.pads.entry(event.id).or_default().buttons.press(button);
+                    self.last_pressed = Some(button);
+                }
+                EventType::ButtonReleased(button, _) => {
+                    self.pads.entry(event.id).or_default().buttons.release(button);
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    self.pads.entry(event.id).or_default().axes.insert(axis, value);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        for pad in self.pads.values_mut() {
+            pad.buttons.end_frame();
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&GamepadId, &GamepadState)> {
+        self.pads.iter()
+    }
+
+    pub fn get(&self, id: GamepadId) -> Option<&GamepadState> {
+        self.pads.get(&id)
+    }
+
+    /// Any connected pad has `button` held; actions do not care which pad.
+    pub fn held(&self, button: Button) -> bool {
+        self.pads.values().any(|pad| pad.buttons.held(button))
+    }
+
+    pub fn pressed(&self, button: Button) -> bool {
+        self.pads.values().any(|pad| pad.buttons.pressed(button))
+    }
+
+    pub fn released(&self, button: Button) -> bool {
+        self.pads.values().any(|pad| pad.buttons.released(button))
+    }
+
+    /// The axis value with the largest magnitude across pads.
+    pub fn axis(&self, axis: Axis) -> f32 {
+        self.pads
+            .values()
+            .map(|pad| pad.axis(axis))
+            .fold(0.0, |best, value| if value.abs() > best.abs() { value } else { best })
+    }
+
+    pub(crate) fn last_pressed(&self) -> Option<Button> {
+        self.last_pressed
+    }
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,9 @@
+mod buttons;
+mod binding;
+mod gamepad;
+mod state;
+
+pub use buttons::*;
+pub use binding::*;
+pub use gamepad::*;
+pub use state::*;
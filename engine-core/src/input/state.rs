@@ -0,0 +1,234 @@
+use winit::event::{DeviceEvent, ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+use winit::window::Window;
+
+use super::{AxisBinding, Binding, ButtonInput, Gamepads, InputMap, MouseAxis};
+
+/// Scroll distance that counts as one wheel line for touchpads reporting pixels.
+const PIXELS_PER_LINE: f64 = 20.0;
+
+pub const INPUT_MAP_FILE: &str = "input.yaml";
+
+/// Keyboard, mouse and gamepad state for the current frame plus the action map read through it.
+pub struct Input {
+    map: InputMap,
+    pub keyboard: ButtonInput<VirtualKeyCode>,
+    pub mouse: ButtonInput<MouseButton>,
+    pub gamepads: Gamepads,
+    cursor_position: Option<(f64, f64)>,
+    mouse_motion: (f64, f64),
+    mouse_wheel: (f64, f64),
+    cursor_grab: bool,
+    cursor_grab_dirty: bool,
+    last_pressed: Option<Binding>,
+}
+
+impl Input {
+    pub fn new(map: InputMap) -> Self {
+        Input {
+            map,
+            keyboard: ButtonInput::default(),
+            mouse: ButtonInput::default(),
+            gamepads: Gamepads::new(),
+            cursor_position: None,
+            mouse_motion: (0.0, 0.0),
+            mouse_wheel: (0.0, 0.0),
+            cursor_grab: false,
+            cursor_grab_dirty: false,
+            last_pressed: None,
+        }
+    }
+
+    pub fn map(&self) -> &InputMap {
+        &self.map
+    }
+
+    /// For runtime rebinding; persist with `Engine::save_input_map`.
+    pub fn map_mut(&mut self) -> &mut InputMap {
+        &mut self.map
+    }
+
+    pub fn binding_held(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.keyboard.held(key),
+            Binding::Mouse(button) => self.mouse.held(button),
+            Binding::Gamepad(button) => self.gamepads.held(button),
+        }
+    }
+
+    pub fn binding_pressed(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.keyboard.pressed(key),
+            Binding::Mouse(button) => self.mouse.pressed(button),
+            Binding::Gamepad(button) => self.gamepads.pressed(button),
+        }
+    }
+
+    pub fn binding_released(&self, binding: Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.keyboard.released(key),
+            Binding::Mouse(button) => self.mouse.released(button),
+            Binding::Gamepad(button) => self.gamepads.released(button),
+        }
+    }
+
+    pub fn action_held(&self, action: &str) -> bool {
+        self.map.bindings(action).iter().any(|binding| self.binding_held(*binding))
+    }
+
+    pub fn action_pressed(&self, action: &str) -> bool {
+        self.map.bindings(action).iter().any(|binding| self.binding_pressed(*binding))
+    }
+
+    pub fn action_released(&self, action: &str) -> bool {
+        self.map.bindings(action).iter().any(|binding| self.binding_released(*binding))
+    }
+
+    /// Value of a named axis, clamped to [-1, 1] for button and stick sources. Mouse sources are
+    /// unclamped; scale them by a sensitivity setting.
+    pub fn axis(&self, axis: &str) -> f32 {
+        let bindings = match self.map.axes.get(axis) {
+            Some(bindings) => bindings,
+            None => return 0.0,
+        };
+        bindings
+            .iter()
+            .map(|binding| self.axis_value(binding))
+            .fold(0.0, |best, value| if value.abs() > best.abs() { value } else { best })
+    }
+
+    fn axis_value(&self, binding: &AxisBinding) -> f32 {
+        match binding {
+            AxisBinding::Buttons { negative, positive } => {
+                let mut value = 0.0;
+                if self.binding_held(*negative) {
+                    value -= 1.0;
+                }
+                if self.binding_held(*positive) {
+                    value += 1.0;
+                }
+                value
+            }
+            AxisBinding::GamepadAxis(axis) => {
+                let value = self.gamepads.axis(*axis);
+                if value.abs() < self.map.dead_zone() {
+                    0.0
+                } else {
+                    value.clamp(-1.0, 1.0)
+                }
+            }
+            AxisBinding::MouseMotion(MouseAxis::X) => self.mouse_motion.0 as f32,
+            AxisBinding::MouseMotion(MouseAxis::Y) => self.mouse_motion.1 as f32,
+            AxisBinding::MouseWheel(MouseAxis::X) => self.mouse_wheel.0 as f32,
+            AxisBinding::MouseWheel(MouseAxis::Y) => self.mouse_wheel.1 as f32,
+        }
+    }
+
+    /// The first binding pressed this frame, for "press a key to rebind" prompts.
+    pub fn last_pressed(&self) -> Option<Binding> {
+        self.last_pressed
+            .or_else(|| self.gamepads.last_pressed().map(Binding::Gamepad))
+    }
+
+    /// Cursor position in physical pixels, `None` while outside the window.
+    pub fn cursor_position(&self) -> Option<(f64, f64)> {
+        self.cursor_position
+    }
+
+    /// Raw mouse movement accumulated this frame, unaffected by cursor grab or window edges.
+    pub fn mouse_motion(&self) -> (f64, f64) {
+        self.mouse_motion
+    }
+
+    /// Wheel movement this frame in lines, positive y scrolls up.
+    pub fn mouse_wheel(&self) -> (f64, f64) {
+        self.mouse_wheel
+    }
+
+    pub fn cursor_grabbed(&self) -> bool {
+        self.cursor_grab
+    }
+
+    /// Locks and hides the cursor for mouse look; applied to the window at the end of the frame.
+    pub fn set_cursor_grab(&mut self, grab: bool) {
+        if self.cursor_grab != grab {
+            self.cursor_grab = grab;
+            self.cursor_grab_dirty = true;
+        }
+    }
+
+    pub(crate) fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => match state {
+                ElementState::Pressed => {
+                    if !self.keyboard.held(*key) {
+                        self.last_pressed.get_or_insert(Binding::Key(*key));
+                    }
+                    self.keyboard.press(*key);
+                }
+                ElementState::Released => self.keyboard.release(*key),
+            },
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    self.last_pressed.get_or_insert(Binding::Mouse(*button));
+                    self.mouse.press(*button);
+                }
+                ElementState::Released => self.mouse.release(*button),
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some((position.x, position.y));
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x as f64, *y as f64),
+                    MouseScrollDelta::PixelDelta(position) => {
+                        (position.x / PIXELS_PER_LINE, position.y / PIXELS_PER_LINE)
+                    }
+                };
+                self.mouse_wheel.0 += x;
+                self.mouse_wheel.1 += y;
+            }
+            // Releases that happen while unfocused never arrive, so nothing may stay held.
+            WindowEvent::Focused(false) => {
+                self.keyboard.release_all();
+                self.mouse.release_all();
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            self.mouse_motion.0 += delta.0;
+            self.mouse_motion.1 += delta.1;
+        }
+    }
+
+    pub(crate) fn begin_frame(&mut self) {
+        self.gamepads.poll();
+    }
+
+    pub(crate) fn end_frame(&mut self, window: &Window) {
+        if self.cursor_grab_dirty {
+            self.cursor_grab_dirty = false;
+            if let Err(err) = window.set_cursor_grab(self.cursor_grab) {
+                log::warn!("Failed to change cursor grab: {}", err);
+            }
+            window.set_cursor_visible(!self.cursor_grab);
+        }
+        self.keyboard.end_frame();
+        self.mouse.end_frame();
+        self.gamepads.end_frame();
+        self.mouse_motion = (0.0, 0.0);
+        self.mouse_wheel = (0.0, 0.0);
+        self.last_pressed = None;
+    }
+}
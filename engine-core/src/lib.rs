@@ -1,40 +1,270 @@
 pub mod util;
 pub mod conf;
 pub mod vulkan;
+pub mod profile;
+pub mod graph;
+pub mod logging;
+pub mod app;
+pub mod input;
+pub mod ecs;
+pub mod scene;
+pub mod asset;
+pub mod material;
+pub mod vfs;
+pub mod render;
 
-use std::path::Path;
+pub use glam;
 
-use crate::{conf::EngineConfig, vulkan::VulkanApp};
-use figment::{Figment, providers::{Format, Yaml}};
+use crate::{
+    app::{Stage, Time},
+    asset::AssetServer,
+    ecs::{Schedule, System, World},
+    conf::{validate, AssetsConfig, ConfigError, ConfigFormat, ConfigLoader, ConfigWatcher, EngineConfig, Migrator},
+    input::{Input, InputMap, INPUT_MAP_FILE},
+    scene::{ComponentRegistry, TransformPropagator},
+    profile::Profiler,
+    vfs::{Vfs, VfsPath, ENGINE_SCHEME, PROJECT_SCHEME, USER_SCHEME},
+    vulkan::{FrameFences, VulkanApp},
+};
 
+// `vulkan` is declared before `config_watcher` so the device goes down first, and after
+// `frame_fences` so the fences are destroyed while the device still exists.
 pub struct Engine {
-    pub config: EngineConfig
+    pub config: EngineConfig,
+    loader: ConfigLoader,
+    frame_fences: Option<FrameFences>,
+    vulkan: Option<VulkanApp>,
+    config_watcher: Option<ConfigWatcher>,
+    exit_requested: bool,
+    time: Time,
+    input: Input,
+    world: World,
+    fixed_schedule: Schedule,
+    update_schedule: Schedule,
+    transform_propagator: TransformPropagator,
+    components: ComponentRegistry,
+    assets: AssetServer
 }
 
 impl Engine {
     
     pub fn new(config_dir: String) -> Self {
-        let config_path = Path::new(config_dir.as_str()).join("default.yaml");
-        let config = Figment::new()
-            .merge(Yaml::file(config_path))
-            .extract::<EngineConfig>();
+        Self::from_loader(
+            &ConfigLoader::new(config_dir.as_str())
+                .with_user("engine")
+                .with_env()
+                .with_migrator(Migrator::engine()),
+        )
+    }
+
+    pub fn from_loader(loader: &ConfigLoader) -> Self {
+        match Self::try_from_loader(loader) {
+            Ok(engine) => engine,
+            Err(err) => {
+                log::error!("Failed to read config: {}", err);
+                panic!("{:?}", err);
+            }
+        }
+    }
 
-        if config.is_err() {
-            log::error!("Failed to read config directory '{:?}': {:?}", config_dir, config);
-            panic!("{:?}", config);
+    pub fn try_from_loader(loader: &ConfigLoader) -> Result<Self, ConfigError> {
+        let config = loader.extract::<EngineConfig>()?;
+        // Fail before any Vulkan object is created from bad values.
+        validate(&config)?;
+        if config.profiler.enabled {
+            Profiler::enable();
         }
-        Self {
-            config: config.unwrap()
+        let vfs = loader.vfs();
+        let input = Input::new(Self::load_input_map(vfs)?);
+        // Assets belong to the project; without one the engine data holds them.
+        let scheme = if vfs.is_mounted(PROJECT_SCHEME) { PROJECT_SCHEME } else { ENGINE_SCHEME };
+        let asset_root = VfsPath::new(scheme, &config.assets.root).expect("assets.root is validated");
+        let watchable = vfs.native_path(&asset_root).is_some_and(|dir| dir.is_dir());
+        let assets = AssetServer::new(vfs.clone(), asset_root, config.assets.threads as usize);
+        if config.assets.hot_reload && watchable {
+            if let Err(err) = assets.watch_for_changes() {
+                log::warn!("Asset hot reload disabled: {}", err);
+            }
         }
+        let mut world = World::new();
+        // Systems reach the server as a resource; it is a cheap shared clone.
+        world.insert_resource(assets.clone());
+        Ok(Self {
+            time: Time::new(&config.time),
+            input,
+            config,
+            loader: loader.clone(),
+            frame_fences: None,
+            vulkan: None,
+            config_watcher: None,
+            exit_requested: false,
+            world,
+            fixed_schedule: Schedule::new(),
+            update_schedule: Schedule::new(),
+            transform_propagator: TransformPropagator::new(),
+            components: ComponentRegistry::default(),
+            assets
+        })
+    }
+
+    /// The engine's input map with the player's saved rebinds from `user://` layered on top.
+    fn load_input_map(vfs: &Vfs) -> Result<InputMap, ConfigError> {
+        vfs.mount_user_dir();
+        let mut map = InputMap::read(vfs, &Self::input_map_path(ENGINE_SCHEME))?.unwrap_or_default();
+        if let Some(rebinds) = InputMap::read(vfs, &Self::input_map_path(USER_SCHEME))? {
+            map.merge(rebinds);
+        }
+        Ok(map)
+    }
+
+    fn input_map_path(scheme: &str) -> VfsPath {
+        VfsPath::new(scheme, INPUT_MAP_FILE).expect("input map file name is a valid path")
+    }
+
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+
+    pub fn input_mut(&mut self) -> &mut Input {
+        &mut self.input
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Schedules `system` to run every frame, or every fixed step, from the next frame on.
+    pub fn add_system<S: System + 'static>(&mut self, stage: Stage, system: S) {
+        match stage {
+            Stage::FixedUpdate => self.fixed_schedule.add_system(system),
+            Stage::Update => self.update_schedule.add_system(system),
+        };
+    }
+
+    pub fn assets(&self) -> &AssetServer {
+        &self.assets
+    }
+
+    /// The mounts config, input maps and assets are read through.
+    pub fn vfs(&self) -> &Vfs {
+        self.loader.vfs()
+    }
+
+    /// Component types that scenes can save and load; register game components here.
+    pub fn component_registry(&self) -> &ComponentRegistry {
+        &self.components
+    }
+
+    pub fn component_registry_mut(&mut self) -> &mut ComponentRegistry {
+        &mut self.components
+    }
+
+    /// Saves the current bindings as the player's input map.
+    pub fn save_input_map(&self) -> Result<(), ConfigError> {
+        let path = Self::input_map_path(USER_SCHEME);
+        let contents = ConfigFormat::Yaml.to_string(self.input.map())?;
+        self.vfs()
+            .write(&path, contents.as_bytes())
+            .map_err(|err| ConfigError::Write(err.to_string()))?;
+        log::info!("Saved input map to {}", path);
+        Ok(())
+    }
+
+    /// Starts watching every file layer of the config this engine was loaded from; live settings
+    /// are applied between frames while `run` is active.
+    pub fn watch_config(&mut self) -> Result<(), ConfigError> {
+        self.config_watcher = Some(ConfigWatcher::new(&self.loader)?);
+        Ok(())
+    }
+
+    /// Creates the window and Vulkan device ahead of `run`, e.g. to upload resources first.
+    pub fn init_vulkan(&mut self) -> &mut VulkanApp {
+        let config = &self.config;
+        self.vulkan.get_or_insert_with(|| VulkanApp::new(config))
+    }
+
+    pub fn vulkan(&self) -> &VulkanApp {
+        self.vulkan.as_ref().expect("Vulkan is not initialized, call Engine::run or init_vulkan first")
+    }
+
+    pub fn vulkan_mut(&mut self) -> &mut VulkanApp {
+        self.vulkan.as_mut().expect("Vulkan is not initialized, call Engine::run or init_vulkan first")
+    }
+
+    /// Index of the frame being rendered, counting from 0; pass it to `ForwardRenderer::render`
+    /// from `Application::render`.
+    pub fn frame(&self) -> u64 {
+        self.frame_fences.as_ref().map_or(0, FrameFences::frame)
+    }
+
+    pub fn time(&self) -> &Time {
+        &self.time
+    }
+
+    /// For adjusting `Time::set_time_scale`, e.g. to pause the simulation.
+    pub fn time_mut(&mut self) -> &mut Time {
+        &mut self.time
+    }
+
+    /// Leaves the loop after the current event; `Application::shutdown` still runs.
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    fn poll_config(&mut self) {
+        let reload = match self.config_watcher.as_mut().and_then(|watcher| watcher.poll()) {
+            Some(reload) => reload,
+            None => return,
+        };
+        profile_scope!("Engine::apply_config");
+        let old = &self.config;
+        let new = &reload.config;
+
+        if old.logging != new.logging {
+            logging::configure(&new.logging, None);
+        }
+        if old.profiler.enabled != new.profiler.enabled {
+            if new.profiler.enabled {
+                Profiler::enable();
+            } else {
+                Profiler::disable();
+            }
+        }
+        if old.time != new.time {
+            self.time.configure(&new.time);
+        }
+        if old.assets.hot_reload != new.assets.hot_reload {
+            if !new.assets.hot_reload {
+                self.assets.stop_watching();
+            } else if let Err(err) = self.assets.watch_for_changes() {
+                log::warn!("Asset hot reload disabled: {}", err);
+            }
+        }
+        if let Some(vulkan) = self.vulkan.as_mut() {
+            vulkan.apply_config(old, new);
+        }
+        for key in reload.restart_required_keys() {
+            log::warn!("Config '{}' changed but only takes effect after a restart", key);
+        }
+
+        // Restart-only sections keep describing the running device.
+        let mut applied = new.clone();
+        applied.version = old.version;
+        applied.vulkan.instance = old.vulkan.instance.clone();
+        applied.vulkan.physical_device = old.vulkan.physical_device.clone();
+        applied.assets = AssetsConfig {
+            hot_reload: new.assets.hot_reload,
+            ..old.assets.clone()
+        };
+        applied.render = old.render.clone();
+        self.config = applied;
     }
 
     pub fn info(&self) {
         log::info!("Using default config of version: {}", self.config.version);
     }
 }
-
-pub fn test() {
-    let engine: Engine = Engine::new("engine-core/config/".to_string());
-    let app = VulkanApp::new(&engine.config);
-    app.run();
-}
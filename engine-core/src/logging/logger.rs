@@ -0,0 +1,166 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use time::{format_description::FormatItem, macros::format_description, OffsetDateTime};
+
+use super::RotatingFile;
+use crate::conf::LoggingConfig;
+
+const TIMESTAMP: &[FormatItem<'static>] =
+    format_description!("[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]");
+
+static LOGGER: Logger = Logger {
+    state: RwLock::new(LoggerState {
+        level: LevelFilter::Info,
+        modules: Vec::new(),
+        color: true,
+        json: false,
+        log_dir: None,
+        file: None,
+    }),
+};
+
+struct LoggerState {
+    level: LevelFilter,
+    // Sorted longest first so the most specific module prefix wins.
+    modules: Vec<(String, LevelFilter)>,
+    color: bool,
+    json: bool,
+    log_dir: Option<PathBuf>,
+    file: Option<Mutex<RotatingFile>>,
+}
+
+impl LoggerState {
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .find(|(module, _)| {
+                target == module || (target.starts_with(module.as_str()) && target[module.len()..].starts_with("::"))
+            })
+            .map(|(_, level)| *level)
+            .unwrap_or(self.level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules.iter().map(|(_, level)| *level).fold(self.level, Ord::max)
+    }
+}
+
+struct Logger {
+    state: RwLock<LoggerState>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.state.read().unwrap().level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        let state = self.state.read().unwrap();
+        if record.level() > state.level_for(record.target()) {
+            return;
+        }
+        let timestamp = OffsetDateTime::now_local()
+            .unwrap_or_else(|_| OffsetDateTime::now_utc())
+            .format(TIMESTAMP)
+            .unwrap_or_default();
+
+        let level = format!("{:<5}", record.level());
+        let level = if state.color { colorize(record.level(), &level) } else { level };
+        println!("{} {} [{}] {}", timestamp, level, record.target(), record.args());
+
+        if let Some(file) = &state.file {
+            let line = if state.json {
+                json_line(&timestamp, record)
+            } else {
+                format!("{} {:<5} [{}] {}", timestamp, record.level(), record.target(), record.args())
+            };
+            if let Err(err) = file.lock().unwrap().write_line(&line) {
+                eprintln!("Failed to write log file: {}", err);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stdout().flush();
+        if let Some(file) = &self.state.read().unwrap().file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+/// Installs the engine logger with console output at `level` until `configure` is called, so
+/// messages emitted while the config itself is loading are not lost.
+pub fn init(level: LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_logger(&LOGGER)?;
+    LOGGER.state.write().unwrap().level = level;
+    log::set_max_level(level);
+    Ok(())
+}
+
+/// Applies `config` to the installed logger; safe to call again on config reload. Relative log
+/// directories resolve against `project_dir`, or the previously configured one when `None`.
+pub fn configure(config: &LoggingConfig, project_dir: Option<&Path>) {
+    let mut state = LOGGER.state.write().unwrap();
+    state.level = parse_level(&config.level, "logging.level");
+    let mut modules: Vec<(String, LevelFilter)> = config
+        .modules
+        .iter()
+        .map(|(module, level)| (module.to_string(), parse_level(level, module)))
+        .collect();
+    modules.sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+    state.modules = modules;
+    state.color = config.color;
+    state.json = config.file.json;
+    if let Some(project_dir) = project_dir {
+        state.log_dir = Some(project_dir.to_path_buf());
+    }
+
+    state.file = None;
+    if config.file.enabled {
+        let directory = match &state.log_dir {
+            Some(base) => base.join(&config.file.directory),
+            None => PathBuf::from(&config.file.directory),
+        };
+        let max_bytes = config.file.max_size_kb.max(1) * 1024;
+        match RotatingFile::open(&directory, &config.file.name, max_bytes, config.file.max_files) {
+            Ok(file) => state.file = Some(Mutex::new(file)),
+            Err(err) => eprintln!("Failed to open log file in {:?}: {}", directory, err),
+        }
+    }
+    log::set_max_level(state.max_level());
+}
+
+fn parse_level(level: &str, key: &str) -> LevelFilter {
+    level.parse().unwrap_or_else(|_| {
+        eprintln!("Unknown log level '{}' for {}, using info", level, key);
+        LevelFilter::Info
+    })
+}
+
+fn colorize(level: Level, text: &str) -> String {
+    let code = match level {
+        Level::Error => "31",
+        Level::Warn => "33",
+        Level::Info => "32",
+        Level::Debug => "36",
+        Level::Trace => "90",
+    };
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+fn json_line(timestamp: &str, record: &Record) -> String {
+    serde_json::json!({
+        "time": timestamp,
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "module": record.module_path(),
+        "file": record.file(),
+        "line": record.line(),
+        "thread": std::thread::current().name(),
+        "message": record.args().to_string(),
+    })
+    .to_string()
+}
@@ -0,0 +1,5 @@
+mod logger;
+mod rotate;
+
+pub use logger::*;
+pub use rotate::*;
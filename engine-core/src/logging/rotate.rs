@@ -0,0 +1,73 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Appends to `<dir>/<name>.log` and shifts it to `<name>.1.log`, `<name>.2.log`, ... once it
+/// grows past `max_bytes`, keeping at most `max_files` rotated files.
+pub struct RotatingFile {
+    directory: PathBuf,
+    name: String,
+    max_bytes: u64,
+    max_files: u32,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFile {
+    pub fn open(directory: &Path, name: &str, max_bytes: u64, max_files: u32) -> io::Result<Self> {
+        std::fs::create_dir_all(directory)?;
+        let path = log_path(directory, name, 0);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(RotatingFile {
+            directory: directory.to_path_buf(),
+            name: name.to_string(),
+            max_bytes,
+            max_files,
+            file,
+            written,
+        })
+    }
+
+    pub fn path(&self) -> PathBuf {
+        log_path(&self.directory, &self.name, 0)
+    }
+
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.written > 0 && self.written + line.len() as u64 + 1 > self.max_bytes {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.written += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let oldest = log_path(&self.directory, &self.name, self.max_files);
+        if oldest.exists() {
+            std::fs::remove_file(&oldest)?;
+        }
+        for index in (0..self.max_files).rev() {
+            let from = log_path(&self.directory, &self.name, index);
+            if from.exists() {
+                std::fs::rename(&from, log_path(&self.directory, &self.name, index + 1))?;
+            }
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(self.path())?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+fn log_path(directory: &Path, name: &str, index: u32) -> PathBuf {
+    if index == 0 {
+        directory.join(format!("{}.log", name))
+    } else {
+        directory.join(format!("{}.{}.log", name, index))
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{
+    BindingKind, MaterialError, MaterialValue, ParameterBlock, ShaderTemplate, ShaderVariant, TextureSlot, UniformData,
+};
+use crate::asset::{AssetError, AssetLoader, CookedTexture, Handle, LoadContext};
+
+pub const MATERIAL_EXTENSION: &str = "material";
+/// Descriptor set holding material parameters. Set 0 is the renderer's per-frame data.
+pub const MATERIAL_SET: u32 = 1;
+
+/// A material file as written, e.g. `materials/brick.material`:
+///
+/// ```yaml
+/// shader: shaders/lit.dshader
+/// keywords: [NORMAL_MAP]
+/// parameters:
+///   base_color: [0.8, 0.3, 0.2, 1.0]
+///   roughness: 0.7
+///   albedo: textures/brick.dtex
+///   normal_map: textures/brick_normal.dtex
+/// ```
+///
+/// Paths are relative to the asset root.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaterialFile {
+    pub shader: String,
+    #[serde(default)]
+    pub keywords: BTreeSet<String>,
+    #[serde(default)]
+    pub parameters: BTreeMap<String, MaterialValue>,
+}
+
+/// A shader template plus the keywords and parameter values to draw it with. Loaded
+/// materials are shared; clone one to get an instance whose parameters can be changed.
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub shader: Handle<ShaderTemplate>,
+    keywords: BTreeSet<String>,
+    parameters: BTreeMap<String, MaterialValue>,
+    textures: BTreeMap<String, Handle<CookedTexture>>,
+}
+
+impl Material {
+    pub fn new(shader: Handle<ShaderTemplate>) -> Self {
+        Material {
+            shader,
+            keywords: BTreeSet::new(),
+            parameters: BTreeMap::new(),
+            textures: BTreeMap::new(),
+        }
+    }
+
+    pub fn keywords(&self) -> &BTreeSet<String> {
+        &self.keywords
+    }
+
+    pub fn set_keyword(&mut self, keyword: &str, enabled: bool) {
+        if enabled {
+            self.keywords.insert(keyword.to_string());
+        } else {
+            self.keywords.remove(keyword);
+        }
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<&MaterialValue> {
+        self.parameters.get(name)
+    }
+
+    /// Sets a uniform parameter. Types are checked against the shader when the parameter
+    /// block is built.
+    pub fn set(&mut self, name: &str, value: MaterialValue) {
+        self.parameters.insert(name.to_string(), value);
+    }
+
+    pub fn texture(&self, name: &str) -> Option<&Handle<CookedTexture>> {
+        self.textures.get(name)
+    }
+
+    pub fn set_texture(&mut self, name: &str, texture: Handle<CookedTexture>) {
+        let path = texture.path().map(|path| path.to_string_lossy().into_owned()).unwrap_or_default();
+        self.parameters.insert(name.to_string(), MaterialValue::Texture(path));
+        self.textures.insert(name.to_string(), texture);
+    }
+
+    /// The variant of `shader` matching the enabled keywords.
+    pub fn variant<'a>(&self, shader: &'a ShaderTemplate) -> Result<&'a ShaderVariant, MaterialError> {
+        shader.variant(&self.keywords)
+    }
+
+    /// Checks every parameter against `shader`: each must be known to at least one variant.
+    /// Parameters only some variants declare, such as a normal map behind a keyword, are
+    /// allowed.
+    pub fn validate(&self, shader: &ShaderTemplate) -> Result<(), MaterialError> {
+        self.variant(shader)?;
+        match self.parameters.keys().find(|name| !shader.has_parameter(name, MATERIAL_SET)) {
+            Some(name) => Err(MaterialError::Parameter(format!(
+                "no variant of the shader declares '{}' in descriptor set {}",
+                name, MATERIAL_SET
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Packs the parameters into the uniform blocks of `variant`'s material set and maps
+    /// textures to their bindings. Block members the material leaves unset are zero.
+    pub fn parameter_block(&self, variant: &ShaderVariant) -> Result<ParameterBlock, MaterialError> {
+        let mut block = ParameterBlock {
+            set: MATERIAL_SET,
+            uniforms: Vec::new(),
+            textures: Vec::new(),
+            samplers: Vec::new(),
+        };
+        for binding in variant.layout.set(MATERIAL_SET) {
+            match &binding.kind {
+                BindingKind::UniformBuffer(layout) => {
+                    let mut bytes = vec![0; layout.size as usize];
+                    for member in layout.members.iter() {
+                        if let Some(value) = self.parameters.get(&member.name) {
+                            let range = member.offset as usize..(member.offset + member.ty.size()) as usize;
+                            value.write(&member.name, member.ty, &mut bytes[range])?;
+                        }
+                    }
+                    block.uniforms.push(UniformData {
+                        binding: binding.binding,
+                        name: binding.name.clone(),
+                        bytes,
+                    });
+                }
+                BindingKind::Texture => {
+                    if let Some(value) = self.parameters.get(&binding.name) {
+                        if !matches!(value, MaterialValue::Texture(_)) {
+                            return Err(MaterialError::Parameter(format!(
+                                "'{}' is a texture in the shader, {:?} does not fit",
+                                binding.name, value
+                            )));
+                        }
+                    }
+                    block.textures.push(TextureSlot {
+                        binding: binding.binding,
+                        name: binding.name.clone(),
+                        texture: self.textures.get(&binding.name).cloned(),
+                    });
+                }
+                BindingKind::Sampler => block.samplers.push(binding.binding),
+                BindingKind::StorageBuffer | BindingKind::StorageTexture => {
+                    return Err(MaterialError::Parameter(format!(
+                        "'{}' is a storage binding, which materials cannot provide; move it out of set {}",
+                        binding.name, MATERIAL_SET
+                    )));
+                }
+            }
+        }
+        Ok(block)
+    }
+}
+
+/// `.material` files. The shader and every texture parameter load as dependencies, so
+/// editing either reloads the material.
+pub struct MaterialLoader;
+
+impl AssetLoader for MaterialLoader {
+    type Asset = Material;
+
+    fn extensions(&self) -> &[&str] {
+        &[MATERIAL_EXTENSION]
+    }
+
+    fn load(&self, bytes: &[u8], context: &mut LoadContext) -> Result<Material, AssetError> {
+        let file: MaterialFile = serde_yaml::from_slice(bytes)
+            .map_err(|err| AssetError::Load(MaterialError::Parse(err.to_string()).to_string()))?;
+        let mut material = Material::new(context.load(&file.shader));
+        material.keywords = file.keywords;
+        for (name, value) in file.parameters {
+            if let MaterialValue::Texture(path) = &value {
+                material.textures.insert(name.clone(), context.load(path));
+            }
+            material.parameters.insert(name, value);
+        }
+        Ok(material)
+    }
+}
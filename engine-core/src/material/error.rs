@@ -0,0 +1,29 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaterialError {
+    /// A material file that does not parse.
+    Parse(String),
+    /// A keyword the shader does not declare, or a combination that was not cooked.
+    Keyword(String),
+    /// A parameter the shader does not declare, or a value of the wrong type.
+    Parameter(String),
+    /// Stages of one program that disagree on a binding.
+    Layout(String),
+    /// A cooked shader template that cannot be read or written.
+    Shader(String),
+}
+
+impl Display for MaterialError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaterialError::Parse(err) => write!(f, "invalid material: {}", err),
+            MaterialError::Keyword(err) => write!(f, "invalid shader keywords: {}", err),
+            MaterialError::Parameter(err) => write!(f, "invalid material parameter: {}", err),
+            MaterialError::Layout(err) => write!(f, "inconsistent shader layout: {}", err),
+            MaterialError::Shader(err) => write!(f, "invalid shader template: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for MaterialError {}
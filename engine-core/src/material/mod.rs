@@ -0,0 +1,11 @@
+mod definition;
+mod error;
+mod params;
+mod reflect;
+mod shader;
+
+pub use definition::*;
+pub use error::*;
+pub use params::*;
+pub use reflect::*;
+pub use shader::*;
@@ -0,0 +1,74 @@
+use serde_derive::{Deserialize, Serialize};
+
+use super::{MaterialError, ParamType};
+use crate::asset::{CookedTexture, Handle};
+
+/// A parameter as written in a material file: a number, a list of numbers for vectors and
+/// matrices, a flag, or the path of a cooked texture.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MaterialValue {
+    Bool(bool),
+    Scalar(f32),
+    Vector(Vec<f32>),
+    Texture(String),
+}
+
+impl MaterialValue {
+    /// Writes the value into `bytes` in the shader's layout for `ty`.
+    pub(crate) fn write(&self, name: &str, ty: ParamType, bytes: &mut [u8]) -> Result<(), MaterialError> {
+        let mismatch = || {
+            MaterialError::Parameter(format!("'{}' is a {:?} in the shader, {:?} does not fit", name, ty, self))
+        };
+        let words: Vec<[u8; 4]> = match (ty, self) {
+            (ParamType::Float, MaterialValue::Scalar(value)) => vec![value.to_le_bytes()],
+            (ParamType::Int, MaterialValue::Scalar(value)) if value.fract() == 0.0 => {
+                vec![(*value as i32).to_le_bytes()]
+            }
+            (ParamType::UInt, MaterialValue::Scalar(value)) if value.fract() == 0.0 && *value >= 0.0 => {
+                vec![(*value as u32).to_le_bytes()]
+            }
+            (ParamType::Bool | ParamType::Int | ParamType::UInt, MaterialValue::Bool(value)) => {
+                vec![(*value as u32).to_le_bytes()]
+            }
+            (ParamType::Vec2 | ParamType::Vec3 | ParamType::Vec4 | ParamType::Mat4, MaterialValue::Vector(values))
+                if values.len() as u32 * 4 == ty.size() =>
+            {
+                // Matrices are listed column by column, as std140 stores them.
+                values.iter().map(|value| value.to_le_bytes()).collect()
+            }
+            _ => return Err(mismatch()),
+        };
+        for (chunk, word) in bytes.chunks_exact_mut(4).zip(words) {
+            chunk.copy_from_slice(&word);
+        }
+        Ok(())
+    }
+}
+
+/// Contents of one uniform block of the material set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniformData {
+    pub binding: u32,
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// A texture binding of the material set; `None` when the material does not set it, in
+/// which case the renderer binds its fallback texture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureSlot {
+    pub binding: u32,
+    pub name: String,
+    pub texture: Option<Handle<CookedTexture>>,
+}
+
+/// What a material puts into its descriptor set for one shader variant: packed uniform
+/// blocks, textures by binding and the sampler bindings the renderer fills.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterBlock {
+    pub set: u32,
+    pub uniforms: Vec<UniformData>,
+    pub textures: Vec<TextureSlot>,
+    pub samplers: Vec<u32>,
+}
@@ -0,0 +1,216 @@
+use ash::vk;
+use serde_derive::{Deserialize, Serialize};
+
+use super::MaterialError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Compute,
+}
+
+impl ShaderStage {
+    pub fn flags(&self) -> vk::ShaderStageFlags {
+        match self {
+            ShaderStage::Vertex => vk::ShaderStageFlags::VERTEX,
+            ShaderStage::Fragment => vk::ShaderStageFlags::FRAGMENT,
+            ShaderStage::Compute => vk::ShaderStageFlags::COMPUTE,
+        }
+    }
+}
+
+/// Type of a uniform block member as laid out by the shader compiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamType {
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+    Int,
+    UInt,
+    Bool,
+    Mat4,
+    /// Arrays, nested structs and other members materials cannot set.
+    Other { size: u32 },
+}
+
+impl ParamType {
+    pub fn size(&self) -> u32 {
+        match self {
+            ParamType::Float | ParamType::Int | ParamType::UInt | ParamType::Bool => 4,
+            ParamType::Vec2 => 8,
+            ParamType::Vec3 => 12,
+            ParamType::Vec4 => 16,
+            ParamType::Mat4 => 64,
+            ParamType::Other { size } => *size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BlockMember {
+    pub name: String,
+    pub offset: u32,
+    pub ty: ParamType,
+}
+
+/// Byte layout of a uniform or push constant block, offsets as the shader reads them.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct BlockLayout {
+    pub size: u32,
+    pub members: Vec<BlockMember>,
+}
+
+impl BlockLayout {
+    pub fn member(&self, name: &str) -> Option<&BlockMember> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BindingKind {
+    UniformBuffer(BlockLayout),
+    StorageBuffer,
+    /// A sampled image, paired with a separate `Sampler` binding in the shader.
+    Texture,
+    StorageTexture,
+    Sampler,
+}
+
+impl BindingKind {
+    pub fn descriptor_type(&self) -> vk::DescriptorType {
+        match self {
+            BindingKind::UniformBuffer(_) => vk::DescriptorType::UNIFORM_BUFFER,
+            BindingKind::StorageBuffer => vk::DescriptorType::STORAGE_BUFFER,
+            BindingKind::Texture => vk::DescriptorType::SAMPLED_IMAGE,
+            BindingKind::StorageTexture => vk::DescriptorType::STORAGE_IMAGE,
+            BindingKind::Sampler => vk::DescriptorType::SAMPLER,
+        }
+    }
+}
+
+/// One descriptor a shader variant reads, with the stages that use it.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ShaderBinding {
+    pub set: u32,
+    pub binding: u32,
+    /// The variable name, or the block name for uniform blocks without one.
+    pub name: String,
+    pub kind: BindingKind,
+    pub stages: Vec<ShaderStage>,
+}
+
+impl ShaderBinding {
+    pub fn stage_flags(&self) -> vk::ShaderStageFlags {
+        self.stages
+            .iter()
+            .fold(vk::ShaderStageFlags::empty(), |flags, stage| flags | stage.flags())
+    }
+}
+
+/// Descriptor bindings and push constants of a shader variant, recovered from its SPIR-V
+/// by the cook tool so pipelines and materials never restate them by hand.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ShaderLayout {
+    pub bindings: Vec<ShaderBinding>,
+    pub push_constants: Option<BlockLayout>,
+    /// Stages reading the push constants.
+    pub push_constant_stages: Vec<ShaderStage>,
+}
+
+impl ShaderLayout {
+    /// Combines the layouts of two stages of one program. A binding both stages declare must
+    /// agree on its kind; it is then visible to both.
+    pub fn merge(&mut self, other: &ShaderLayout) -> Result<(), MaterialError> {
+        for binding in other.bindings.iter() {
+            match self.binding_mut(binding.set, binding.binding) {
+                Some(existing) if existing.kind != binding.kind => {
+                    return Err(MaterialError::Layout(format!(
+                        "set {} binding {} is '{}' in one stage and '{}' in another, with different types",
+                        binding.set, binding.binding, existing.name, binding.name
+                    )));
+                }
+                Some(existing) => {
+                    for stage in binding.stages.iter() {
+                        if !existing.stages.contains(stage) {
+                            existing.stages.push(*stage);
+                        }
+                    }
+                }
+                None => self.bindings.push(binding.clone()),
+            }
+        }
+        self.bindings.sort_by_key(|binding| (binding.set, binding.binding));
+
+        match (&self.push_constants, &other.push_constants) {
+            (Some(ours), Some(theirs)) if ours.size != theirs.size => {
+                return Err(MaterialError::Layout(format!(
+                    "stages disagree on the push constant size, {} and {} bytes",
+                    ours.size, theirs.size
+                )));
+            }
+            (None, Some(theirs)) => self.push_constants = Some(theirs.clone()),
+            _ => {}
+        }
+        for stage in other.push_constant_stages.iter() {
+            if !self.push_constant_stages.contains(stage) {
+                self.push_constant_stages.push(*stage);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn binding(&self, set: u32, binding: u32) -> Option<&ShaderBinding> {
+        self.bindings
+            .iter()
+            .find(|candidate| candidate.set == set && candidate.binding == binding)
+    }
+
+    /// Bindings of descriptor set `set`, in binding order.
+    pub fn set(&self, set: u32) -> impl Iterator<Item = &ShaderBinding> {
+        self.bindings.iter().filter(move |binding| binding.set == set)
+    }
+
+    /// One past the highest descriptor set index used, i.e. how many set layouts the pipeline
+    /// layout needs.
+    pub fn set_count(&self) -> u32 {
+        self.bindings.iter().map(|binding| binding.set + 1).max().unwrap_or(0)
+    }
+
+    pub fn set_layout_bindings(&self, set: u32) -> Vec<vk::DescriptorSetLayoutBinding> {
+        self.set(set)
+            .map(|binding| {
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(binding.binding)
+                    .descriptor_type(binding.kind.descriptor_type())
+                    .descriptor_count(1)
+                    .stage_flags(binding.stage_flags())
+                    .build()
+            })
+            .collect()
+    }
+
+    pub fn push_constant_ranges(&self) -> Vec<vk::PushConstantRange> {
+        match &self.push_constants {
+            Some(block) if block.size > 0 => vec![vk::PushConstantRange {
+                stage_flags: self
+                    .push_constant_stages
+                    .iter()
+                    .fold(vk::ShaderStageFlags::empty(), |flags, stage| flags | stage.flags()),
+                offset: 0,
+                size: block.size,
+            }],
+            _ => Vec::new(),
+        }
+    }
+
+    fn binding_mut(&mut self, set: u32, binding: u32) -> Option<&mut ShaderBinding> {
+        self.bindings
+            .iter_mut()
+            .find(|candidate| candidate.set == set && candidate.binding == binding)
+    }
+}
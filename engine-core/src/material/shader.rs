@@ -0,0 +1,112 @@
+use std::collections::BTreeSet;
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{BindingKind, MaterialError, ShaderLayout, ShaderStage};
+use crate::asset::{AssetError, AssetLoader, LoadContext};
+
+pub const SHADER_TEMPLATE_EXTENSION: &str = "dshader";
+pub const SHADER_TEMPLATE_VERSION: u32 = 1;
+/// Every keyword doubles the variants cooked, so templates are kept to a few of them.
+pub const MAX_SHADER_KEYWORDS: usize = 8;
+
+const SHADER_TEMPLATE_MAGIC: &[u8; 4] = b"DSHD";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ShaderStageCode {
+    pub stage: ShaderStage,
+    pub entry_point: String,
+    pub spirv: Vec<u32>,
+}
+
+/// The program compiled with one combination of keywords defined.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ShaderVariant {
+    /// Enabled keywords, sorted.
+    pub keywords: Vec<String>,
+    pub stages: Vec<ShaderStageCode>,
+    pub layout: ShaderLayout,
+}
+
+impl ShaderVariant {
+    pub fn stage(&self, stage: ShaderStage) -> Option<&ShaderStageCode> {
+        self.stages.iter().find(|code| code.stage == stage)
+    }
+}
+
+/// A cooked shader program: its stages precompiled for every combination of its keywords,
+/// each with the descriptor layout reflected from the result. Materials pick a variant by
+/// the keywords they enable.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ShaderTemplate {
+    pub keywords: Vec<String>,
+    pub variants: Vec<ShaderVariant>,
+}
+
+impl ShaderTemplate {
+    /// The variant compiled with exactly `keywords` enabled.
+    pub fn variant(&self, keywords: &BTreeSet<String>) -> Result<&ShaderVariant, MaterialError> {
+        if let Some(unknown) = keywords.iter().find(|keyword| !self.keywords.contains(keyword)) {
+            return Err(MaterialError::Keyword(format!(
+                "'{}' is not a keyword of the shader, expected one of [{}]",
+                unknown,
+                self.keywords.join(", ")
+            )));
+        }
+        self.variants
+            .iter()
+            .find(|variant| variant.keywords.len() == keywords.len() && keywords.iter().eq(variant.keywords.iter()))
+            .ok_or_else(|| {
+                let keywords: Vec<&str> = keywords.iter().map(String::as_str).collect();
+                MaterialError::Keyword(format!("no variant was cooked for [{}]", keywords.join(", ")))
+            })
+    }
+
+    /// True when any variant declares `name` as a material parameter, a uniform block member
+    /// or a texture of the material set.
+    pub fn has_parameter(&self, name: &str, set: u32) -> bool {
+        self.variants.iter().any(|variant| {
+            variant.layout.set(set).any(|binding| match &binding.kind {
+                BindingKind::UniformBuffer(block) => block.member(name).is_some(),
+                BindingKind::Texture => binding.name == name,
+                _ => false,
+            })
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MaterialError> {
+        let mut bytes = SHADER_TEMPLATE_MAGIC.to_vec();
+        bytes.extend_from_slice(&SHADER_TEMPLATE_VERSION.to_le_bytes());
+        let body = rmp_serde::to_vec_named(self).map_err(|err| MaterialError::Shader(err.to_string()))?;
+        bytes.extend_from_slice(&body);
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MaterialError> {
+        if bytes.len() < 8 || &bytes[..4] != SHADER_TEMPLATE_MAGIC {
+            return Err(MaterialError::Shader("not a cooked shader template".to_string()));
+        }
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if version != SHADER_TEMPLATE_VERSION {
+            return Err(MaterialError::Shader(format!(
+                "shader template version {}, this build reads {}; cook the shader again",
+                version, SHADER_TEMPLATE_VERSION
+            )));
+        }
+        rmp_serde::from_slice(&bytes[8..]).map_err(|err| MaterialError::Shader(err.to_string()))
+    }
+}
+
+pub struct ShaderTemplateLoader;
+
+impl AssetLoader for ShaderTemplateLoader {
+    type Asset = ShaderTemplate;
+
+    fn extensions(&self) -> &[&str] {
+        &[SHADER_TEMPLATE_EXTENSION]
+    }
+
+    fn load(&self, bytes: &[u8], _context: &mut LoadContext) -> Result<ShaderTemplate, AssetError> {
+        ShaderTemplate::from_bytes(bytes).map_err(|err| AssetError::Load(err.to_string()))
+    }
+}
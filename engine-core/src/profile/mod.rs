@@ -0,0 +1,5 @@
+mod profiler;
+mod trace;
+
+pub use profiler::*;
+pub use trace::*;
@@ -0,0 +1,164 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+const MAX_SPANS_PER_THREAD: usize = 1 << 20;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+static EPOCH: Mutex<Option<Instant>> = Mutex::new(None);
+static THREADS: Mutex<Vec<Arc<Mutex<ThreadSpans>>>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static LOCAL: RefCell<Option<LocalProfiler>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone)]
+pub struct Span {
+    pub name: &'static str,
+    pub category: &'static str,
+    pub start_us: u64,
+    pub duration_us: u64,
+    pub depth: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ThreadSpans {
+    pub thread_id: u64,
+    pub thread_name: String,
+    pub spans: Vec<Span>,
+    pub dropped: u64,
+}
+
+struct LocalProfiler {
+    epoch: Instant,
+    depth: u32,
+    buffer: Arc<Mutex<ThreadSpans>>,
+}
+
+impl LocalProfiler {
+    fn new() -> Self {
+        let epoch = *EPOCH.lock().unwrap().get_or_insert_with(Instant::now);
+        let current = std::thread::current();
+        let thread_id = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+        let buffer = Arc::new(Mutex::new(ThreadSpans {
+            thread_id,
+            thread_name: current
+                .name()
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| format!("thread-{}", thread_id)),
+            spans: Vec::new(),
+            dropped: 0,
+        }));
+        THREADS.lock().unwrap().push(buffer.clone());
+        LocalProfiler {
+            epoch,
+            depth: 0,
+            buffer,
+        }
+    }
+}
+
+fn with_local<R>(f: impl FnOnce(&mut LocalProfiler) -> R) -> R {
+    LOCAL.with(|local| {
+        let mut local = local.borrow_mut();
+        f(local.get_or_insert_with(LocalProfiler::new))
+    })
+}
+
+pub struct Profiler;
+
+impl Profiler {
+    pub fn enable() {
+        ENABLED.store(true, Ordering::Relaxed);
+    }
+
+    pub fn disable() {
+        ENABLED.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled() -> bool {
+        ENABLED.load(Ordering::Relaxed)
+    }
+
+    /// Discards every span recorded so far on all threads.
+    pub fn clear() {
+        for thread in THREADS.lock().unwrap().iter() {
+            let mut thread = thread.lock().unwrap();
+            thread.spans.clear();
+            thread.dropped = 0;
+        }
+    }
+
+    /// Copies out the spans recorded so far, one entry per thread.
+    pub fn snapshot() -> Vec<ThreadSpans> {
+        THREADS
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|thread| thread.lock().unwrap().clone())
+            .collect()
+    }
+}
+
+/// Guard created by `profile_scope!`, records a span covering its lifetime.
+pub struct ProfileScope {
+    name: &'static str,
+    category: &'static str,
+    start: Option<Instant>,
+}
+
+impl ProfileScope {
+    pub fn new(name: &'static str, category: &'static str) -> Self {
+        if !Profiler::is_enabled() {
+            return ProfileScope {
+                name,
+                category,
+                start: None,
+            };
+        }
+        with_local(|local| local.depth += 1);
+        ProfileScope {
+            name,
+            category,
+            start: Some(Instant::now()),
+        }
+    }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        let start = match self.start {
+            Some(start) => start,
+            None => return,
+        };
+        let end = Instant::now();
+        with_local(|local| {
+            local.depth = local.depth.saturating_sub(1);
+            let span = Span {
+                name: self.name,
+                category: self.category,
+                start_us: start.saturating_duration_since(local.epoch).as_micros() as u64,
+                duration_us: end.saturating_duration_since(start).as_micros() as u64,
+                depth: local.depth,
+            };
+            let mut buffer = local.buffer.lock().unwrap();
+            if buffer.spans.len() < MAX_SPANS_PER_THREAD {
+                buffer.spans.push(span);
+            } else {
+                buffer.dropped += 1;
+            }
+        });
+    }
+}
+
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::profile::ProfileScope::new($name, "engine");
+    };
+    ($name:expr, $category:expr) => {
+        let _profile_scope = $crate::profile::ProfileScope::new($name, $category);
+    };
+}
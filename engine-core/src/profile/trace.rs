@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use super::{Profiler, ThreadSpans};
+
+const TRACE_PID: u32 = 1;
+
+/// Builds a Chrome Trace Event document, loadable by chrome://tracing and Perfetto.
+pub fn chrome_trace(threads: &[ThreadSpans]) -> Value {
+    let mut events = Vec::new();
+    for thread in threads.iter() {
+        events.push(json!({
+            "name": "thread_name",
+            "ph": "M",
+            "pid": TRACE_PID,
+            "tid": thread.thread_id,
+            "args": { "name": thread.thread_name }
+        }));
+        for span in thread.spans.iter() {
+            events.push(json!({
+                "name": span.name,
+                "cat": span.category,
+                "ph": "X",
+                "ts": span.start_us,
+                "dur": span.duration_us,
+                "pid": TRACE_PID,
+                "tid": thread.thread_id,
+                "args": { "depth": span.depth }
+            }));
+        }
+        if thread.dropped > 0 {
+            log::warn!(
+                "Profiler dropped {} spans on thread '{}'",
+                thread.dropped,
+                thread.thread_name
+            );
+        }
+    }
+    json!({
+        "traceEvents": events,
+        "displayTimeUnit": "ms"
+    })
+}
+
+impl Profiler {
+    pub fn write_chrome_trace<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+        let trace = chrome_trace(&Profiler::snapshot());
+        let mut writer = BufWriter::new(File::create(path.as_ref())?);
+        serde_json::to_writer(&mut writer, &trace)?;
+        writer.flush()?;
+        log::info!("Wrote profiler trace to {:?}", path.as_ref());
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use glam::{Mat4, Vec3};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::scene::GlobalTransform;
+
+/// A perspective camera looking along the entity's forward axis. The first entity with a
+/// `Camera` and a `GlobalTransform` is the one rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Camera {
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+    /// Multiplier applied to the shaded colour before it is written.
+    pub exposure: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            fov_y: 60f32.to_radians(),
+            near: 0.1,
+            far: 1000.0,
+            exposure: 1.0,
+        }
+    }
+}
+
+/// A camera resolved for one frame and target size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderView {
+    pub view: Mat4,
+    /// Right handed with a 0..1 depth range and y pointing down, as Vulkan expects.
+    pub projection: Mat4,
+    pub position: Vec3,
+    pub near: f32,
+    pub far: f32,
+    pub exposure: f32,
+}
+
+impl RenderView {
+    pub fn new(camera: &Camera, transform: &GlobalTransform, aspect_ratio: f32) -> Self {
+        let near = camera.near.max(0.001);
+        let far = camera.far.max(near * 2.0);
+        let mut projection = Mat4::perspective_rh(camera.fov_y, aspect_ratio.max(0.001), near, far);
+        projection.y_axis.y = -projection.y_axis.y;
+        // Scale would distort lighting in view space, so only rotation and translation are kept.
+        let (_, rotation, translation) = transform.affine().to_scale_rotation_translation();
+        RenderView {
+            view: Mat4::from_rotation_translation(rotation, translation).inverse(),
+            projection,
+            position: translation,
+            near,
+            far,
+            exposure: camera.exposure,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use ash::vk;
+
+use super::{GpuLight, RenderView};
+use crate::conf::RenderConfig;
+
+/// Size in bytes of one cluster's view space bounding box, two `vec4`s.
+const CLUSTER_BOUNDS_SIZE: vk::DeviceSize = 32;
+
+/// The view frustum split into tiles on screen and exponentially spaced slices in depth.
+/// Light culling gives every cluster a fixed-size slot in the light index list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClusterGrid {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+    pub max_lights_per_cluster: u32,
+}
+
+impl ClusterGrid {
+    pub fn new(config: &RenderConfig) -> Self {
+        let (x, y, z) = config.clusters;
+        ClusterGrid {
+            x,
+            y,
+            z,
+            max_lights_per_cluster: config.max_lights_per_cluster,
+        }
+    }
+
+    pub fn count(&self) -> u32 {
+        self.x * self.y * self.z
+    }
+
+    pub fn bounds_size(&self) -> vk::DeviceSize {
+        self.count() as vk::DeviceSize * CLUSTER_BOUNDS_SIZE
+    }
+
+    pub fn light_grid_size(&self) -> vk::DeviceSize {
+        self.count() as vk::DeviceSize * 4
+    }
+
+    pub fn light_index_size(&self) -> vk::DeviceSize {
+        self.count() as vk::DeviceSize * self.max_lights_per_cluster as vk::DeviceSize * 4
+    }
+}
+
+/// Mirror of the `Frame` uniform block shared by the cluster, culling and shading shaders,
+/// laid out for std140.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameUniforms {
+    pub view: [[f32; 4]; 4],
+    pub projection: [[f32; 4]; 4],
+    pub inverse_projection: [[f32; 4]; 4],
+    pub camera_position: [f32; 4],
+    pub screen_size: [f32; 2],
+    pub near_plane: f32,
+    pub far_plane: f32,
+    /// Clusters along x, y and z, then the number of lights.
+    pub cluster_grid: [u32; 4],
+    pub exposure: f32,
+    pub environment_intensity: f32,
+    pub prefiltered_mips: f32,
+    pub max_lights_per_cluster: u32,
+}
+
+impl FrameUniforms {
+    pub fn new(
+        view: &RenderView,
+        extent: vk::Extent2D,
+        grid: &ClusterGrid,
+        lights: &[GpuLight],
+        environment_intensity: f32,
+        prefiltered_mips: u32,
+    ) -> Self {
+        FrameUniforms {
+            view: view.view.to_cols_array_2d(),
+            projection: view.projection.to_cols_array_2d(),
+            inverse_projection: view.projection.inverse().to_cols_array_2d(),
+            camera_position: view.position.extend(1.0).to_array(),
+            screen_size: [extent.width as f32, extent.height as f32],
+            near_plane: view.near,
+            far_plane: view.far,
+            cluster_grid: [grid.x, grid.y, grid.z, lights.len() as u32],
+            exposure: view.exposure,
+            environment_intensity,
+            prefiltered_mips: prefiltered_mips as f32,
+            max_lights_per_cluster: grid.max_lights_per_cluster,
+        }
+    }
+}
@@ -0,0 +1,480 @@
+use std::f32::consts::PI;
+
+use ash::vk;
+use glam::Vec3;
+
+use crate::asset::HdrImage;
+use crate::profile_scope;
+use crate::vfs::Vfs;
+use crate::vulkan::{
+    bytes_of, immediate_submit, ComputeBinding, ComputeBindingKind, ComputeCommands, ComputePipeline, ImageLevel,
+    ImageRegion, TextureDesc, VulkanApp, VulkanBuffer, VulkanImage, VulkanSampler,
+};
+
+pub const IRRADIANCE_SIZE: u32 = 32;
+/// Mips of the prefiltered map, from mirror-like at 0 to fully rough at the last.
+pub const PREFILTERED_MIPS: u32 = 6;
+pub const BRDF_LUT_SIZE: u32 = 256;
+
+const CUBE_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+const CUBE_TEXEL_SIZE: vk::DeviceSize = 8;
+/// Per-mip offsets into the prefilter output are bound as storage buffer ranges, so they are
+/// kept at the largest `minStorageBufferOffsetAlignment` the spec allows.
+const RANGE_ALIGNMENT: vk::DeviceSize = 256;
+
+/// Image based lighting: cosine weighted irradiance for diffuse light and a radiance map
+/// prefiltered for increasing roughness along its mips, for specular reflections.
+pub struct Environment {
+    pub irradiance: VulkanImage,
+    pub prefiltered: VulkanImage,
+    /// Scale applied to both maps when shading.
+    pub intensity: f32,
+}
+
+impl Environment {
+    /// Projects an equirectangular HDR image onto a cube of `size` and convolves it on the GPU.
+    /// Blocks until done, so call it while loading rather than mid-frame.
+    pub fn from_hdr(vulkan: &VulkanApp, vfs: &Vfs, image: &HdrImage, size: u32) -> Self {
+        profile_scope!("Environment::from_hdr");
+        let (instance, physical_device, logical_device) =
+            (vulkan.get_instance(), vulkan.get_physical_device(), vulkan.get_logical_device());
+        let source_mips = size.ilog2() + 1;
+        let source = VulkanImage::new(
+            instance,
+            physical_device,
+            logical_device,
+            TextureDesc {
+                format: CUBE_FORMAT,
+                width: size,
+                height: size,
+                mip_levels: source_mips,
+                cube: true,
+                usage: vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+            },
+        );
+        let faces = project_to_cube(image, size, source_mips);
+        let levels: Vec<ImageLevel> = faces
+            .iter()
+            .enumerate()
+            .map(|(index, bytes)| ImageLevel {
+                layer: index as u32 % 6,
+                level: index as u32 / 6,
+                bytes,
+            })
+            .collect();
+        source.upload(instance, physical_device, logical_device, &levels);
+
+        let prefiltered_mips = PREFILTERED_MIPS.min(source_mips);
+        let cube_usage = vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST;
+        let irradiance = VulkanImage::new(
+            instance,
+            physical_device,
+            logical_device,
+            TextureDesc {
+                format: CUBE_FORMAT,
+                width: IRRADIANCE_SIZE,
+                height: IRRADIANCE_SIZE,
+                mip_levels: 1,
+                cube: true,
+                usage: cube_usage,
+            },
+        );
+        let prefiltered = VulkanImage::new(
+            instance,
+            physical_device,
+            logical_device,
+            TextureDesc {
+                format: CUBE_FORMAT,
+                width: size,
+                height: size,
+                mip_levels: prefiltered_mips,
+                cube: true,
+                usage: cube_usage,
+            },
+        );
+
+        let bindings = [
+            ComputeBinding {
+                binding: 0,
+                kind: ComputeBindingKind::Texture,
+            },
+            ComputeBinding {
+                binding: 1,
+                kind: ComputeBindingKind::Sampler,
+            },
+            ComputeBinding {
+                binding: 2,
+                kind: ComputeBindingKind::StorageBuffer,
+            },
+        ];
+        let irradiance_pipeline = ComputePipeline::from_vfs(
+            logical_device,
+            vfs,
+            "engine://shaders/irradiance.comp.spv",
+            "main",
+            &bindings,
+            8,
+            1,
+        );
+        let prefilter_pipeline = ComputePipeline::from_vfs(
+            logical_device,
+            vfs,
+            "engine://shaders/prefilter.comp.spv",
+            "main",
+            &bindings,
+            12,
+            prefiltered_mips,
+        );
+        let sampler = VulkanSampler::new(logical_device, vk::SamplerAddressMode::CLAMP_TO_EDGE, 1.0);
+
+        let irradiance_size = cube_size(IRRADIANCE_SIZE);
+        let prefiltered_regions: Vec<ImageRegion> = (0..prefiltered_mips)
+            .scan(0, |offset, level| {
+                let region = ImageRegion {
+                    level,
+                    base_layer: 0,
+                    layer_count: 6,
+                    offset: *offset,
+                };
+                *offset = (*offset + cube_size(size >> level)).next_multiple_of(RANGE_ALIGNMENT);
+                Some(region)
+            })
+            .collect();
+        let last = prefiltered_regions.last().unwrap();
+        let prefiltered_size = last.offset + cube_size(size >> last.level);
+        let output_usage = vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_SRC;
+        let output = |bytes| {
+            VulkanBuffer::new(
+                instance,
+                physical_device,
+                logical_device,
+                bytes,
+                output_usage,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                &[logical_device.get_queue_family()],
+            )
+        };
+        let irradiance_texels = output(irradiance_size);
+        let prefiltered_texels = output(prefiltered_size);
+
+        let irradiance_set = irradiance_pipeline.allocate_set();
+        bind_source(&irradiance_pipeline, irradiance_set, &source, &sampler);
+        irradiance_pipeline.bind_buffer(irradiance_set, 2, *irradiance_texels.get(), 0, irradiance_size);
+        let prefilter_sets: Vec<vk::DescriptorSet> = prefiltered_regions
+            .iter()
+            .map(|region| {
+                let set = prefilter_pipeline.allocate_set();
+                bind_source(&prefilter_pipeline, set, &source, &sampler);
+                let range = cube_size(size >> region.level);
+                prefilter_pipeline.bind_buffer(set, 2, *prefiltered_texels.get(), region.offset, range);
+                set
+            })
+            .collect();
+
+        let device = logical_device.get_device();
+        immediate_submit(logical_device, |command_buffer| {
+            let commands = ComputeCommands::new(device, command_buffer);
+            // Irradiance is smooth, so it integrates a mip of about its own resolution.
+            let source_lod = (size / IRRADIANCE_SIZE).max(1).ilog2() as f32;
+            commands
+                .bind(&irradiance_pipeline, irradiance_set)
+                .push_constants(
+                    &irradiance_pipeline,
+                    bytes_of(&IrradianceParameters {
+                        size: IRRADIANCE_SIZE,
+                        source_lod,
+                    }),
+                )
+                .dispatch(IRRADIANCE_SIZE.div_ceil(8), IRRADIANCE_SIZE.div_ceil(8), 6);
+            for (region, set) in prefiltered_regions.iter().zip(prefilter_sets.iter()) {
+                let level_size = (size >> region.level).max(1);
+                let roughness = region.level as f32 / (prefiltered_mips - 1).max(1) as f32;
+                let parameters = PrefilterParameters {
+                    size: level_size,
+                    roughness,
+                    source_size: size as f32,
+                };
+                commands
+                    .bind(&prefilter_pipeline, *set)
+                    .push_constants(&prefilter_pipeline, bytes_of(&parameters))
+                    .dispatch(level_size.div_ceil(8), level_size.div_ceil(8), 6);
+            }
+            transfer_barrier(device, command_buffer);
+            irradiance.record_copy(
+                command_buffer,
+                *irradiance_texels.get(),
+                &[ImageRegion {
+                    level: 0,
+                    base_layer: 0,
+                    layer_count: 6,
+                    offset: 0,
+                }],
+            );
+            prefiltered.record_copy(command_buffer, *prefiltered_texels.get(), &prefiltered_regions);
+        });
+        log::info!(
+            "Prefiltered {}x{} environment into {} mips",
+            image.width,
+            image.height,
+            prefiltered_mips
+        );
+
+        Environment {
+            irradiance,
+            prefiltered,
+            intensity: 1.0,
+        }
+    }
+
+    /// No ambient light at all, for scenes lit only by their lights.
+    pub fn black(vulkan: &VulkanApp) -> Self {
+        let black_cube = || {
+            let image = VulkanImage::new(
+                vulkan.get_instance(),
+                vulkan.get_physical_device(),
+                vulkan.get_logical_device(),
+                TextureDesc {
+                    format: CUBE_FORMAT,
+                    width: 1,
+                    height: 1,
+                    mip_levels: 1,
+                    cube: true,
+                    usage: vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+                },
+            );
+            let texel = [0u8; CUBE_TEXEL_SIZE as usize];
+            let levels: Vec<ImageLevel> = (0..6)
+                .map(|layer| ImageLevel {
+                    layer,
+                    level: 0,
+                    bytes: &texel,
+                })
+                .collect();
+            image.upload(
+                vulkan.get_instance(),
+                vulkan.get_physical_device(),
+                vulkan.get_logical_device(),
+                &levels,
+            );
+            image
+        };
+        Environment {
+            irradiance: black_cube(),
+            prefiltered: black_cube(),
+            intensity: 0.0,
+        }
+    }
+
+    pub fn prefiltered_mips(&self) -> u32 {
+        self.prefiltered.desc().mip_levels
+    }
+}
+
+/// The split-sum lookup table of the specular environment BRDF: scale and bias to Fresnel's
+/// F0 by view angle and roughness. Independent of the environment, so computed once.
+pub fn compute_brdf_lut(vulkan: &VulkanApp, vfs: &Vfs) -> VulkanImage {
+    profile_scope!("compute_brdf_lut");
+    let (instance, physical_device, logical_device) =
+        (vulkan.get_instance(), vulkan.get_physical_device(), vulkan.get_logical_device());
+    let pipeline = ComputePipeline::from_vfs(
+        logical_device,
+        vfs,
+        "engine://shaders/brdf_lut.comp.spv",
+        "main",
+        &[ComputeBinding {
+            binding: 0,
+            kind: ComputeBindingKind::StorageBuffer,
+        }],
+        4,
+        1,
+    );
+    let size = (BRDF_LUT_SIZE * BRDF_LUT_SIZE * 4) as vk::DeviceSize;
+    let texels = VulkanBuffer::new(
+        instance,
+        physical_device,
+        logical_device,
+        size,
+        vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::TRANSFER_SRC,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        &[logical_device.get_queue_family()],
+    );
+    let lut = VulkanImage::new(
+        instance,
+        physical_device,
+        logical_device,
+        TextureDesc {
+            format: vk::Format::R16G16_SFLOAT,
+            width: BRDF_LUT_SIZE,
+            height: BRDF_LUT_SIZE,
+            mip_levels: 1,
+            cube: false,
+            usage: vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        },
+    );
+    let set = pipeline.allocate_set();
+    pipeline.bind_buffer(set, 0, *texels.get(), 0, size);
+    let device = logical_device.get_device();
+    immediate_submit(logical_device, |command_buffer| {
+        ComputeCommands::new(device, command_buffer)
+            .bind(&pipeline, set)
+            .push_constants(&pipeline, bytes_of(&BRDF_LUT_SIZE))
+            .dispatch(BRDF_LUT_SIZE.div_ceil(8), BRDF_LUT_SIZE.div_ceil(8), 1);
+        transfer_barrier(device, command_buffer);
+        lut.record_copy(
+            command_buffer,
+            *texels.get(),
+            &[ImageRegion {
+                level: 0,
+                base_layer: 0,
+                layer_count: 1,
+                offset: 0,
+            }],
+        );
+    });
+    lut
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct IrradianceParameters {
+    size: u32,
+    source_lod: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct PrefilterParameters {
+    size: u32,
+    roughness: f32,
+    source_size: f32,
+}
+
+fn cube_size(size: u32) -> vk::DeviceSize {
+    let size = size.max(1) as vk::DeviceSize;
+    size * size * 6 * CUBE_TEXEL_SIZE
+}
+
+fn bind_source(pipeline: &ComputePipeline, set: vk::DescriptorSet, source: &VulkanImage, sampler: &VulkanSampler) {
+    pipeline.bind_image(
+        set,
+        0,
+        source.get_view(),
+        vk::Sampler::null(),
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    );
+    pipeline.bind_image(set, 1, vk::ImageView::null(), sampler.get(), vk::ImageLayout::UNDEFINED);
+}
+
+/// Makes compute shader writes visible to the copies into the cube images.
+fn transfer_barrier(device: &ash::Device, command_buffer: vk::CommandBuffer) {
+    let barrier = [vk::MemoryBarrier::builder()
+        .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+        .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+        .build()];
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &barrier,
+            &[],
+            &[],
+        );
+    }
+}
+
+/// Direction through the centre of texel `uv` of cube `face`, matching `cube_direction` in the
+/// convolution shaders and Vulkan's face order +X, -X, +Y, -Y, +Z, -Z.
+fn cube_direction(face: u32, u: f32, v: f32) -> Vec3 {
+    let (s, t) = (u * 2.0 - 1.0, v * 2.0 - 1.0);
+    let direction = match face {
+        0 => Vec3::new(1.0, -t, -s),
+        1 => Vec3::new(-1.0, -t, s),
+        2 => Vec3::new(s, 1.0, t),
+        3 => Vec3::new(s, -1.0, -t),
+        4 => Vec3::new(s, -t, 1.0),
+        _ => Vec3::new(-s, -t, -1.0),
+    };
+    direction.normalize()
+}
+
+/// Half float RGBA texels of every face of every mip, face-major within a mip. Mips below the
+/// first are box filtered from the one above.
+fn project_to_cube(image: &HdrImage, size: u32, mips: u32) -> Vec<Vec<u8>> {
+    let mut level: Vec<Vec<[f32; 3]>> = (0..6)
+        .map(|face| {
+            let mut texels = Vec::with_capacity((size * size) as usize);
+            for y in 0..size {
+                for x in 0..size {
+                    let u = (x as f32 + 0.5) / size as f32;
+                    let v = (y as f32 + 0.5) / size as f32;
+                    let direction = cube_direction(face, u, v);
+                    let longitude = direction.x.atan2(-direction.z);
+                    let latitude = direction.y.clamp(-1.0, 1.0).acos();
+                    texels.push(image.sample(longitude / (2.0 * PI) + 0.5, latitude / PI));
+                }
+            }
+            texels
+        })
+        .collect();
+
+    let mut faces = Vec::with_capacity((mips * 6) as usize);
+    let mut level_size = size;
+    for mip in 0..mips {
+        if mip > 0 {
+            let half = (level_size / 2).max(1);
+            level = level
+                .iter()
+                .map(|texels| {
+                    let mut smaller = Vec::with_capacity((half * half) as usize);
+                    for y in 0..half {
+                        for x in 0..half {
+                            let mut sum = [0.0; 3];
+                            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                                let sx = (x * 2 + dx).min(level_size - 1);
+                                let sy = (y * 2 + dy).min(level_size - 1);
+                                let texel = texels[(sy * level_size + sx) as usize];
+                                sum.iter_mut().zip(texel).for_each(|(sum, value)| *sum += value / 4.0);
+                            }
+                            smaller.push(sum);
+                        }
+                    }
+                    smaller
+                })
+                .collect();
+            level_size = half;
+        }
+        for texels in level.iter() {
+            let mut bytes = Vec::with_capacity(texels.len() * CUBE_TEXEL_SIZE as usize);
+            for texel in texels.iter() {
+                for value in [texel[0], texel[1], texel[2], 1.0] {
+                    bytes.extend_from_slice(&f16_bits(value).to_le_bytes());
+                }
+            }
+            faces.push(bytes);
+        }
+    }
+    faces
+}
+
+/// Rounds to the nearest half float, clamping to the largest finite one so that a bright sun
+/// does not turn into infinity.
+fn f16_bits(value: f32) -> u16 {
+    let sign = ((value.to_bits() >> 16) & 0x8000) as u16;
+    let value = value.abs();
+    if value.is_nan() {
+        return sign | 0x7e00;
+    }
+    let value = value.min(65504.0);
+    if value < 6.103_515_6e-5 {
+        // Subnormal: a multiple of 2^-24.
+        return sign | (value * 16_777_216.0).round() as u16;
+    }
+    let bits = value.to_bits();
+    let exponent = ((bits >> 23) & 0xff) + 15 - 127;
+    let mantissa = bits & 0x7f_ffff;
+    // A carry out of the mantissa correctly rounds up into the exponent.
+    let half = ((exponent << 10) | (mantissa >> 13)) + ((mantissa >> 12) & 1);
+    sign | half as u16
+}
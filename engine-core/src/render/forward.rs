@@ -0,0 +1,1091 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use ash::vk;
+use ash::vk::Handle as _;
+
+use super::{
+    compute_brdf_lut, solid_texture, upload_texture, vertex_input, Camera, ClusterGrid, Environment, FrameUniforms,
+    GpuLight, GpuMesh, PbrMaterial, RenderScene, RenderView, PBR_SHADER_PATH,
+};
+use crate::asset::{AssetId, AssetServer, CookedMesh, CookedTexture, Handle, Submesh};
+use crate::conf::RenderConfig;
+use crate::graph::{BufferDesc, GraphResources, ImageDesc, ImportedState, PhysicalResource, RenderGraph, ResourceUsage};
+use crate::material::{Material, ParameterBlock, ShaderTemplate, ShaderVariant, MATERIAL_SET};
+use crate::profile_scope;
+use crate::scene::GlobalTransform;
+use crate::vfs::Vfs;
+use crate::vulkan::{
+    bytes_of, ComputeBinding, ComputeBindingKind, ComputeCommands, ComputePipeline, DeferredResource, GraphicsPipeline,
+    GraphicsPipelineDesc, MaterialDescriptors, SharedDeletionQueue, VulkanApp, VulkanBuffer, VulkanImage,
+    VulkanSampler, MAX_FRAMES_IN_FLIGHT,
+};
+
+const DEPTH_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+const MATERIAL_ANISOTROPY: f32 = 16.0;
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+/// Tangent space +Z, bound where a material has no normal map.
+const FLAT_NORMAL: [u8; 4] = [128, 128, 255, 255];
+/// Frames a GPU copy is kept without being drawn, so objects briefly out of view are not
+/// uploaded again.
+const EVICT_AFTER_FRAMES: u64 = 300;
+
+/// Mirror of the `Object` push constant block of `pbr.vert`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct ObjectConstants {
+    model: [[f32; 4]; 4],
+    normal_matrix: [[f32; 4]; 4],
+}
+
+/// The image a frame is rendered into, usually the acquired swapchain image.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderTarget {
+    pub image: vk::Image,
+    pub view: vk::ImageView,
+    pub format: vk::Format,
+    pub width: u32,
+    pub height: u32,
+    /// State of the image when the frame starts, `ResourceUsage::undefined()` for a freshly
+    /// acquired swapchain image.
+    pub initial: ResourceUsage,
+    /// State to leave it in, e.g. `ResourceUsage::present()`.
+    pub final_usage: Option<ResourceUsage>,
+}
+
+/// Host written buffers and descriptor sets of one frame in flight.
+struct FrameSlot {
+    uniforms: VulkanBuffer,
+    lights: VulkanBuffer,
+    set: vk::DescriptorSet,
+    cluster_set: vk::DescriptorSet,
+    cull_set: vk::DescriptorSet,
+}
+
+struct CachedMesh {
+    source: Arc<CookedMesh>,
+    gpu: GpuMesh,
+    used: u64,
+}
+
+struct CachedTexture {
+    source: Arc<CookedTexture>,
+    /// `None` when the device cannot sample the texture's format.
+    image: Option<Arc<VulkanImage>>,
+    used: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    shader: AssetId,
+    keywords: Vec<String>,
+}
+
+/// A pipeline for one shader variant together with the layout of its material set.
+struct ForwardPipeline {
+    deletion_queue: SharedDeletionQueue,
+    pipeline: GraphicsPipeline,
+    material_layout: Option<vk::DescriptorSetLayout>,
+    push_constant_size: u32,
+}
+
+impl Drop for ForwardPipeline {
+    fn drop(&mut self) {
+        if let Some(layout) = self.material_layout {
+            self.deletion_queue
+                .lock()
+                .unwrap()
+                .push(DeferredResource::DescriptorSetLayout(layout));
+        }
+    }
+}
+
+struct CachedPipeline {
+    shader: Arc<ShaderTemplate>,
+    /// `None` when the variant does not fit the renderer's frame set.
+    pipeline: Option<ForwardPipeline>,
+    used: u64,
+}
+
+struct CachedMaterial {
+    material: Arc<Material>,
+    shader: Arc<ShaderTemplate>,
+    /// Keywords of the selected variant and the packed parameters, `None` when the material
+    /// does not fit its shader.
+    block: Option<(Vec<String>, ParameterBlock)>,
+    /// Images bound to the texture slots of `block`, `None` where a fallback is bound.
+    images: Vec<Option<Arc<VulkanImage>>>,
+    descriptors: Option<MaterialDescriptors>,
+    used: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MaterialBinding {
+    pipeline: vk::Pipeline,
+    layout: vk::PipelineLayout,
+    push_constant_stages: vk::ShaderStageFlags,
+    push_constant_size: u32,
+    set: vk::DescriptorSet,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Draw {
+    material: MaterialBinding,
+    vertex_buffer: vk::Buffer,
+    index_buffer: vk::Buffer,
+    first_index: u32,
+    index_count: u32,
+    object: ObjectConstants,
+}
+
+/// Clustered forward shading of metallic-roughness materials. Each frame a compute pass
+/// computes the view space bounds of every cluster, a second one assigns the lights to the
+/// clusters they touch, and the forward pass shades every fragment with the lights of its
+/// cluster plus image based lighting from the current `Environment`.
+///
+/// Meshes, textures, materials and pipelines are uploaded the first frame they are drawn,
+/// rebuilt when their asset is reloaded and released once their asset is unloaded or they go
+/// `EVICT_AFTER_FRAMES` frames without being drawn.
+pub struct ForwardRenderer {
+    deletion_queue: SharedDeletionQueue,
+    grid: ClusterGrid,
+    max_lights: usize,
+    color_format: vk::Format,
+    render_pass: vk::RenderPass,
+    frame_layout: vk::DescriptorSetLayout,
+    frame_pool: vk::DescriptorPool,
+    slots: Vec<FrameSlot>,
+    cluster_pipeline: Arc<ComputePipeline>,
+    cull_pipeline: Arc<ComputePipeline>,
+    clusters: VulkanBuffer,
+    light_grid: VulkanBuffer,
+    light_indices: VulkanBuffer,
+    environment: Environment,
+    brdf_lut: VulkanImage,
+    environment_sampler: VulkanSampler,
+    material_sampler: VulkanSampler,
+    white: VulkanImage,
+    flat_normal: VulkanImage,
+    default_material: Handle<Material>,
+    meshes: HashMap<AssetId, CachedMesh>,
+    textures: HashMap<AssetId, CachedTexture>,
+    materials: HashMap<AssetId, CachedMaterial>,
+    pipelines: HashMap<PipelineKey, CachedPipeline>,
+    pub clear_color: [f32; 4],
+}
+
+impl ForwardRenderer {
+    /// Reads the cooked engine shaders from `vfs`; see `engine-core/shaders` for how they are
+    /// produced. `color_format` is the format of every `RenderTarget` passed to `render`.
+    pub fn new(
+        vulkan: &VulkanApp,
+        vfs: &Vfs,
+        assets: &AssetServer,
+        config: &RenderConfig,
+        color_format: vk::Format,
+    ) -> Self {
+        profile_scope!("ForwardRenderer::new");
+        let (instance, physical_device, logical_device) =
+            (vulkan.get_instance(), vulkan.get_physical_device(), vulkan.get_logical_device());
+        let device = logical_device.get_device();
+        let grid = ClusterGrid::new(config);
+
+        let frame_bindings = frame_bindings();
+        let layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&frame_bindings);
+        let frame_layout = unsafe {
+            device
+                .create_descriptor_set_layout(&layout_info, None)
+                .expect("Failed to create frame descriptor set layout")
+        };
+        let slot_count = MAX_FRAMES_IN_FLIGHT as u32;
+        let pool_sizes = [
+            (vk::DescriptorType::UNIFORM_BUFFER, 1),
+            (vk::DescriptorType::STORAGE_BUFFER, 3),
+            (vk::DescriptorType::SAMPLED_IMAGE, 3),
+            (vk::DescriptorType::SAMPLER, 1),
+        ]
+        .map(|(ty, count)| vk::DescriptorPoolSize {
+            ty,
+            descriptor_count: count * slot_count,
+        });
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .max_sets(slot_count)
+            .pool_sizes(&pool_sizes);
+        let frame_pool = unsafe {
+            device
+                .create_descriptor_pool(&pool_info, None)
+                .expect("Failed to create frame descriptor pool")
+        };
+
+        let storage_buffer = |size: vk::DeviceSize| {
+            VulkanBuffer::new(
+                instance,
+                physical_device,
+                logical_device,
+                size,
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                &[logical_device.get_queue_family()],
+            )
+        };
+        let compute_bindings = |kinds: &[ComputeBindingKind]| -> Vec<ComputeBinding> {
+            kinds
+                .iter()
+                .enumerate()
+                .map(|(binding, kind)| ComputeBinding {
+                    binding: binding as u32,
+                    kind: *kind,
+                })
+                .collect()
+        };
+        let cluster_pipeline = ComputePipeline::from_vfs(
+            logical_device,
+            vfs,
+            "engine://shaders/cluster_build.comp.spv",
+            "main",
+            &compute_bindings(&[ComputeBindingKind::UniformBuffer, ComputeBindingKind::StorageBuffer]),
+            0,
+            slot_count,
+        );
+        let cull_pipeline = ComputePipeline::from_vfs(
+            logical_device,
+            vfs,
+            "engine://shaders/light_cull.comp.spv",
+            "main",
+            &compute_bindings(&[
+                ComputeBindingKind::UniformBuffer,
+                ComputeBindingKind::StorageBuffer,
+                ComputeBindingKind::StorageBuffer,
+                ComputeBindingKind::StorageBuffer,
+                ComputeBindingKind::StorageBuffer,
+            ]),
+            0,
+            slot_count,
+        );
+
+        let mut renderer = ForwardRenderer {
+            deletion_queue: logical_device.get_deletion_queue().clone(),
+            grid,
+            max_lights: config.max_lights as usize,
+            color_format,
+            render_pass: create_render_pass(device, color_format),
+            frame_layout,
+            frame_pool,
+            slots: Vec::new(),
+            cluster_pipeline: Arc::new(cluster_pipeline),
+            cull_pipeline: Arc::new(cull_pipeline),
+            clusters: storage_buffer(grid.bounds_size()),
+            light_grid: storage_buffer(grid.light_grid_size()),
+            light_indices: storage_buffer(grid.light_index_size()),
+            environment: Environment::black(vulkan),
+            brdf_lut: compute_brdf_lut(vulkan, vfs),
+            environment_sampler: VulkanSampler::new(logical_device, vk::SamplerAddressMode::CLAMP_TO_EDGE, 1.0),
+            material_sampler: VulkanSampler::new(logical_device, vk::SamplerAddressMode::REPEAT, MATERIAL_ANISOTROPY),
+            white: solid_texture(vulkan, WHITE),
+            flat_normal: solid_texture(vulkan, FLAT_NORMAL),
+            default_material: default_material(vfs, assets),
+            meshes: HashMap::new(),
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            pipelines: HashMap::new(),
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+        };
+        renderer.slots = (0..MAX_FRAMES_IN_FLIGHT).map(|_| renderer.create_slot(vulkan)).collect();
+        renderer
+    }
+
+    /// Lights beyond this many are dropped; pass it to `SceneExtractor::extract`.
+    pub fn max_lights(&self) -> usize {
+        self.max_lights
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// Replaces the image based lighting. Waits for the device to go idle, so do it while
+    /// loading a scene rather than every frame.
+    pub fn set_environment(&mut self, vulkan: &VulkanApp, environment: Environment) {
+        vulkan.get_logical_device().wait_idle();
+        self.environment = environment;
+        for slot in self.slots.iter() {
+            self.write_frame_set(vulkan, slot);
+        }
+    }
+
+    /// Records the frame into `command_buffer`. `frame` selects the slot of per-frame buffers,
+    /// so call this from `Application::render` with `Engine::frame`. Without a
+    /// camera the scene is viewed from the origin with a default `Camera`.
+    pub fn render(
+        &mut self,
+        vulkan: &VulkanApp,
+        assets: &AssetServer,
+        scene: &RenderScene,
+        target: &RenderTarget,
+        command_buffer: vk::CommandBuffer,
+        frame: u64,
+    ) {
+        profile_scope!("ForwardRenderer::render", "render");
+        if target.format != self.color_format {
+            log::error!(
+                "Render target format {:?} does not match the renderer's {:?}.",
+                target.format,
+                self.color_format
+            );
+            panic!("Render target format mismatch");
+        }
+        if target.width == 0 || target.height == 0 {
+            return;
+        }
+        let extent = vk::Extent2D {
+            width: target.width,
+            height: target.height,
+        };
+        let aspect_ratio = target.width as f32 / target.height as f32;
+        let view = match &scene.camera {
+            Some((camera, transform)) => RenderView::new(camera, transform, aspect_ratio),
+            None => RenderView::new(&Camera::default(), &GlobalTransform::default(), aspect_ratio),
+        };
+        let lights: &[GpuLight] = &scene.lights[..scene.lights.len().min(self.max_lights)];
+        let uniforms = FrameUniforms::new(
+            &view,
+            extent,
+            &self.grid,
+            lights,
+            self.environment.intensity,
+            self.environment.prefiltered_mips(),
+        );
+        let slot = &self.slots[frame as usize % MAX_FRAMES_IN_FLIGHT];
+        slot.uniforms.write(&[uniforms]);
+        if !lights.is_empty() {
+            slot.lights.write(lights);
+        }
+        let (frame_set, cluster_set, cull_set) = (slot.set, slot.cluster_set, slot.cull_set);
+
+        let mut draws = Vec::new();
+        for mesh_draw in scene.meshes.iter() {
+            let mesh = self.prepare_mesh(vulkan, assets, &mesh_draw.mesh, frame);
+            let (vertex_buffer, index_buffer, ranges) = match mesh {
+                Some(mesh) => mesh,
+                None => continue,
+            };
+            let object = ObjectConstants {
+                model: mesh_draw.transform.to_cols_array_2d(),
+                normal_matrix: mesh_draw.transform.inverse().transpose().to_cols_array_2d(),
+            };
+            for range in ranges {
+                let material = range
+                    .material
+                    .and_then(|index| mesh_draw.materials.get(index as usize))
+                    .or_else(|| mesh_draw.materials.first())
+                    .unwrap_or(&self.default_material)
+                    .clone();
+                if let Some(material) = self.prepare_material(vulkan, assets, &material, frame) {
+                    draws.push(Draw {
+                        material,
+                        vertex_buffer,
+                        index_buffer,
+                        first_index: range.first_index,
+                        index_count: range.index_count,
+                        object,
+                    });
+                }
+            }
+        }
+        draws.sort_by_key(|draw| (draw.material.pipeline.as_raw(), draw.material.set.as_raw()));
+        self.evict(frame);
+
+        let mut graph = RenderGraph::new();
+        // Last frame's culling wrote these and its forward pass read them.
+        let shared = ImportedState {
+            initial: ResourceUsage::storage_write(
+                vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER,
+            ),
+            final_usage: None,
+        };
+        let storage_desc = |buffer: &VulkanBuffer| BufferDesc {
+            size: buffer.size(),
+            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+        };
+        let clusters = graph.import_buffer("clusters", storage_desc(&self.clusters), shared);
+        let light_grid = graph.import_buffer("light_grid", storage_desc(&self.light_grid), shared);
+        let light_indices = graph.import_buffer("light_indices", storage_desc(&self.light_indices), shared);
+        let color = graph.import_image(
+            "color",
+            ImageDesc::new_2d(target.format, extent.width, extent.height, vk::ImageUsageFlags::COLOR_ATTACHMENT),
+            ImportedState {
+                initial: target.initial,
+                final_usage: target.final_usage,
+            },
+        );
+        let depth = graph.create_image(
+            "depth",
+            ImageDesc::new_2d(
+                DEPTH_FORMAT,
+                extent.width,
+                extent.height,
+                vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            ),
+        );
+
+        let compute_write = ResourceUsage::storage_write(vk::PipelineStageFlags::COMPUTE_SHADER);
+        let pipeline = self.cluster_pipeline.clone();
+        let grid = self.grid;
+        graph.add_pass(
+            "cluster_build",
+            |builder| {
+                builder.write(clusters, compute_write);
+            },
+            move |ctx| {
+                ComputeCommands::new(ctx.device, ctx.command_buffer)
+                    .bind(&pipeline, cluster_set)
+                    .dispatch(grid.x.div_ceil(8), grid.y.div_ceil(8), grid.z);
+            },
+        );
+        let pipeline = self.cull_pipeline.clone();
+        graph.add_pass(
+            "light_cull",
+            |builder| {
+                builder
+                    .read(clusters, ResourceUsage::storage_read(vk::PipelineStageFlags::COMPUTE_SHADER))
+                    .write(light_grid, compute_write)
+                    .write(light_indices, compute_write);
+            },
+            move |ctx| {
+                ComputeCommands::new(ctx.device, ctx.command_buffer)
+                    .bind(&pipeline, cull_set)
+                    .dispatch(grid.count().div_ceil(64), 1, 1);
+            },
+        );
+        let fragment_read = ResourceUsage::storage_read(vk::PipelineStageFlags::FRAGMENT_SHADER);
+        let render_pass = self.render_pass;
+        let clear_color = self.clear_color;
+        let deletion_queue = self.deletion_queue.clone();
+        graph.add_pass(
+            "forward",
+            |builder| {
+                builder
+                    .read(light_grid, fragment_read)
+                    .read(light_indices, fragment_read)
+                    .write(color, ResourceUsage::color_attachment())
+                    .write(depth, ResourceUsage::depth_attachment());
+            },
+            move |ctx| {
+                let (_, color_view) = ctx.resources.image(color).expect("Render target is not bound");
+                let (_, depth_view) = ctx.resources.image(depth).expect("Depth buffer was not realized");
+                let attachments = [color_view, depth_view];
+                let framebuffer_info = vk::FramebufferCreateInfo::builder()
+                    .render_pass(render_pass)
+                    .attachments(&attachments)
+                    .width(extent.width)
+                    .height(extent.height)
+                    .layers(1);
+                let framebuffer = unsafe {
+                    ctx.device
+                        .create_framebuffer(&framebuffer_info, None)
+                        .expect("Failed to create forward framebuffer")
+                };
+                // The attachments only live for this frame, so the framebuffer goes with them.
+                deletion_queue
+                    .lock()
+                    .unwrap()
+                    .push(DeferredResource::Framebuffer(framebuffer));
+                record_draws(
+                    ctx.device,
+                    ctx.command_buffer,
+                    render_pass,
+                    framebuffer,
+                    extent,
+                    clear_color,
+                    frame_set,
+                    &draws,
+                );
+            },
+        );
+        graph.mark_output(color);
+
+        let compiled = graph.compile();
+        let device = vulkan.get_logical_device().get_device();
+        let mut resources =
+            GraphResources::realize(device, vulkan.get_instance(), vulkan.get_physical_device(), &compiled);
+        resources.bind_imported(
+            color,
+            PhysicalResource::Image {
+                image: target.image,
+                view: target.view,
+            },
+        );
+        resources.bind_imported(clusters, PhysicalResource::Buffer(*self.clusters.get()));
+        resources.bind_imported(light_grid, PhysicalResource::Buffer(*self.light_grid.get()));
+        resources.bind_imported(light_indices, PhysicalResource::Buffer(*self.light_indices.get()));
+        compiled.execute(device, command_buffer, &resources);
+        resources.release(&mut self.deletion_queue.lock().unwrap());
+    }
+
+    fn create_slot(&self, vulkan: &VulkanApp) -> FrameSlot {
+        let (instance, physical_device, logical_device) =
+            (vulkan.get_instance(), vulkan.get_physical_device(), vulkan.get_logical_device());
+        let host_buffer = |size: usize, usage: vk::BufferUsageFlags| {
+            VulkanBuffer::new(
+                instance,
+                physical_device,
+                logical_device,
+                size as vk::DeviceSize,
+                usage,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                &[logical_device.get_queue_family()],
+            )
+        };
+        let uniforms = host_buffer(std::mem::size_of::<FrameUniforms>(), vk::BufferUsageFlags::UNIFORM_BUFFER);
+        let lights = host_buffer(
+            self.max_lights.max(1) * std::mem::size_of::<GpuLight>(),
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+        );
+
+        let set_layouts = [self.frame_layout];
+        let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(self.frame_pool)
+            .set_layouts(&set_layouts);
+        let set = unsafe {
+            logical_device
+                .get_device()
+                .allocate_descriptor_sets(&allocate_info)
+                .expect("Failed to allocate frame descriptor set")[0]
+        };
+
+        let cluster_set = self.cluster_pipeline.allocate_set();
+        self.cluster_pipeline
+            .bind_buffer(cluster_set, 0, *uniforms.get(), 0, uniforms.size());
+        self.cluster_pipeline
+            .bind_buffer(cluster_set, 1, *self.clusters.get(), 0, self.clusters.size());
+        let cull_set = self.cull_pipeline.allocate_set();
+        let cull_buffers = [&uniforms, &lights, &self.clusters, &self.light_grid, &self.light_indices];
+        for (binding, buffer) in cull_buffers.iter().enumerate() {
+            self.cull_pipeline
+                .bind_buffer(cull_set, binding as u32, *buffer.get(), 0, buffer.size());
+        }
+
+        let slot = FrameSlot {
+            uniforms,
+            lights,
+            set,
+            cluster_set,
+            cull_set,
+        };
+        self.write_frame_set(vulkan, &slot);
+        slot
+    }
+
+    fn write_frame_set(&self, vulkan: &VulkanApp, slot: &FrameSlot) {
+        let buffer_info = |buffer: &VulkanBuffer| {
+            [vk::DescriptorBufferInfo {
+                buffer: *buffer.get(),
+                offset: 0,
+                range: buffer.size(),
+            }]
+        };
+        let buffers = [
+            buffer_info(&slot.uniforms),
+            buffer_info(&slot.lights),
+            buffer_info(&self.light_grid),
+            buffer_info(&self.light_indices),
+        ];
+        let image_info = |view: vk::ImageView| {
+            [vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view: view,
+                image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            }]
+        };
+        let images = [
+            image_info(self.environment.irradiance.get_view()),
+            image_info(self.environment.prefiltered.get_view()),
+            image_info(self.brdf_lut.get_view()),
+        ];
+        let sampler = [vk::DescriptorImageInfo {
+            sampler: self.environment_sampler.get(),
+            image_view: vk::ImageView::null(),
+            image_layout: vk::ImageLayout::UNDEFINED,
+        }];
+
+        let write = |binding: u32, descriptor_type: vk::DescriptorType| {
+            vk::WriteDescriptorSet::builder()
+                .dst_set(slot.set)
+                .dst_binding(binding)
+                .descriptor_type(descriptor_type)
+        };
+        let mut writes = Vec::new();
+        writes.push(write(0, vk::DescriptorType::UNIFORM_BUFFER).buffer_info(&buffers[0]).build());
+        for (index, info) in buffers[1..].iter().enumerate() {
+            writes.push(write(index as u32 + 1, vk::DescriptorType::STORAGE_BUFFER).buffer_info(info).build());
+        }
+        for (index, info) in images.iter().enumerate() {
+            writes.push(write(index as u32 + 4, vk::DescriptorType::SAMPLED_IMAGE).image_info(info).build());
+        }
+        writes.push(write(7, vk::DescriptorType::SAMPLER).image_info(&sampler).build());
+        unsafe {
+            vulkan
+                .get_logical_device()
+                .get_device()
+                .update_descriptor_sets(&writes, &[])
+        };
+    }
+
+    /// The buffers and index ranges of a mesh, or `None` while it is loading.
+    fn prepare_mesh(
+        &mut self,
+        vulkan: &VulkanApp,
+        assets: &AssetServer,
+        handle: &Handle<CookedMesh>,
+        frame: u64,
+    ) -> Option<(vk::Buffer, vk::Buffer, Vec<Submesh>)> {
+        let source = assets.get(handle)?;
+        let id = handle.id();
+        let stale = self
+            .meshes
+            .get(&id)
+            .is_none_or(|cached| !Arc::ptr_eq(&cached.source, &source));
+        if stale {
+            let gpu = GpuMesh::new(vulkan, &source);
+            self.meshes.insert(id, CachedMesh { source, gpu, used: frame });
+        }
+        let cached = self.meshes.get_mut(&id)?;
+        cached.used = frame;
+        Some((
+            *cached.gpu.vertex_buffer.get(),
+            *cached.gpu.index_buffer.get(),
+            cached.gpu.draw_ranges(),
+        ))
+    }
+
+    fn prepare_texture(
+        &mut self,
+        vulkan: &VulkanApp,
+        assets: &AssetServer,
+        handle: &Handle<CookedTexture>,
+        frame: u64,
+    ) -> Option<Arc<VulkanImage>> {
+        let source = assets.get(handle)?;
+        let id = handle.id();
+        let stale = self
+            .textures
+            .get(&id)
+            .is_none_or(|cached| !Arc::ptr_eq(&cached.source, &source));
+        if stale {
+            let image = upload_texture(vulkan, &source).map(Arc::new);
+            if image.is_none() {
+                log::warn!(
+                    "Texture {:?} is block compressed but the device cannot sample BC formats; cook it as rgba8",
+                    handle.path()
+                );
+            }
+            self.textures.insert(id, CachedTexture { source, image, used: frame });
+        }
+        let cached = self.textures.get_mut(&id)?;
+        cached.used = frame;
+        cached.image.clone()
+    }
+
+    /// Pipeline and descriptor set to draw with, or `None` while the material or its shader is
+    /// loading or when it cannot be drawn.
+    fn prepare_material(
+        &mut self,
+        vulkan: &VulkanApp,
+        assets: &AssetServer,
+        handle: &Handle<Material>,
+        frame: u64,
+    ) -> Option<MaterialBinding> {
+        let material = assets.get(handle)?;
+        let shader = assets.get(&material.shader)?;
+        let id = handle.id();
+        let mut cached = match self.materials.remove(&id) {
+            Some(cached) if Arc::ptr_eq(&cached.material, &material) && Arc::ptr_eq(&cached.shader, &shader) => cached,
+            _ => {
+                let block = material
+                    .variant(&shader)
+                    .and_then(|variant| Ok((variant.keywords.clone(), material.parameter_block(variant)?)));
+                if let Err(err) = &block {
+                    log::warn!("Material {:?} cannot be drawn: {}", handle.path(), err);
+                }
+                CachedMaterial {
+                    material,
+                    shader,
+                    block: block.ok(),
+                    images: Vec::new(),
+                    descriptors: None,
+                    used: frame,
+                }
+            }
+        };
+        cached.used = frame;
+        let binding = self.bind_material(vulkan, assets, &mut cached, frame);
+        self.materials.insert(id, cached);
+        binding
+    }
+
+    fn bind_material(
+        &mut self,
+        vulkan: &VulkanApp,
+        assets: &AssetServer,
+        cached: &mut CachedMaterial,
+        frame: u64,
+    ) -> Option<MaterialBinding> {
+        let (keywords, block) = cached.block.as_ref()?;
+        let variant = cached.material.variant(&cached.shader).ok()?;
+        let key = PipelineKey {
+            shader: cached.material.shader.id(),
+            keywords: keywords.clone(),
+        };
+        let mut binding = self.prepare_pipeline(vulkan, &key, &cached.shader, variant, frame)?;
+
+        let images: Vec<Option<Arc<VulkanImage>>> = block
+            .textures
+            .iter()
+            .map(|slot| {
+                slot.texture
+                    .as_ref()
+                    .and_then(|texture| self.prepare_texture(vulkan, assets, texture, frame))
+            })
+            .collect();
+        let unchanged = images.len() == cached.images.len()
+            && images.iter().zip(cached.images.iter()).all(|pair| match pair {
+                (Some(image), Some(bound)) => Arc::ptr_eq(image, bound),
+                (None, None) => true,
+                _ => false,
+            });
+        let has_set = variant.layout.set(MATERIAL_SET).next().is_some();
+        if has_set && (cached.descriptors.is_none() || !unchanged) {
+            // A new set rather than rewriting the old one, which a frame in flight may still use.
+            let descriptors = MaterialDescriptors::new(
+                vulkan.get_instance(),
+                vulkan.get_physical_device(),
+                vulkan.get_logical_device(),
+                &variant.layout,
+                block,
+            );
+            for (slot, image) in block.textures.iter().zip(images.iter()) {
+                let view = match image {
+                    Some(image) => image.get_view(),
+                    None if slot.name.contains("normal") => self.flat_normal.get_view(),
+                    None => self.white.get_view(),
+                };
+                descriptors.bind_texture(slot.binding, view, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+            }
+            for sampler in block.samplers.iter() {
+                descriptors.bind_sampler(*sampler, self.material_sampler.get());
+            }
+            cached.descriptors = Some(descriptors);
+            cached.images = images;
+        }
+        if let Some(descriptors) = &cached.descriptors {
+            binding.set = descriptors.get_set();
+        }
+        Some(binding)
+    }
+
+    fn prepare_pipeline(
+        &mut self,
+        vulkan: &VulkanApp,
+        key: &PipelineKey,
+        shader: &Arc<ShaderTemplate>,
+        variant: &ShaderVariant,
+        frame: u64,
+    ) -> Option<MaterialBinding> {
+        let stale = self
+            .pipelines
+            .get(key)
+            .is_none_or(|cached| !Arc::ptr_eq(&cached.shader, shader));
+        if stale {
+            let pipeline = self.create_pipeline(vulkan, variant);
+            self.pipelines.insert(
+                key.clone(),
+                CachedPipeline {
+                    shader: shader.clone(),
+                    pipeline,
+                    used: frame,
+                },
+            );
+        }
+        let cached = self.pipelines.get_mut(key)?;
+        cached.used = frame;
+        cached.pipeline.as_ref().map(|pipeline| MaterialBinding {
+            pipeline: *pipeline.pipeline.get(),
+            layout: *pipeline.pipeline.get_layout(),
+            push_constant_stages: pipeline.pipeline.push_constant_stages(),
+            push_constant_size: pipeline.push_constant_size,
+            set: vk::DescriptorSet::null(),
+        })
+    }
+
+    fn create_pipeline(&self, vulkan: &VulkanApp, variant: &ShaderVariant) -> Option<ForwardPipeline> {
+        if let Err(reason) = check_frame_set(variant) {
+            log::warn!("Shader variant {:?} cannot be drawn by the forward renderer: {}", variant.keywords, reason);
+            return None;
+        }
+        let logical_device = vulkan.get_logical_device();
+        let material_bindings = variant.layout.set_layout_bindings(MATERIAL_SET);
+        let material_layout = if material_bindings.is_empty() {
+            None
+        } else {
+            let layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&material_bindings);
+            Some(unsafe {
+                logical_device
+                    .get_device()
+                    .create_descriptor_set_layout(&layout_info, None)
+                    .expect("Failed to create material descriptor set layout")
+            })
+        };
+        let mut set_layouts = vec![self.frame_layout];
+        set_layouts.extend(material_layout);
+        let (vertex_bindings, vertex_attributes) = vertex_input();
+        let pipeline = GraphicsPipeline::new(
+            logical_device,
+            variant,
+            &set_layouts,
+            self.render_pass,
+            &GraphicsPipelineDesc {
+                vertex_bindings,
+                vertex_attributes,
+                cull_mode: vk::CullModeFlags::BACK,
+                depth_test: true,
+                depth_write: true,
+                alpha_blend: false,
+            },
+        );
+        let push_constant_size = variant
+            .layout
+            .push_constants
+            .as_ref()
+            .map_or(0, |block| block.size)
+            .min(std::mem::size_of::<ObjectConstants>() as u32);
+        Some(ForwardPipeline {
+            deletion_queue: self.deletion_queue.clone(),
+            pipeline,
+            material_layout,
+            push_constant_size,
+        })
+    }
+
+    /// Drops GPU copies that went unused for `EVICT_AFTER_FRAMES` frames, or whose asset the
+    /// server has unloaded so the cache holds its last reference. Frames in flight keep the
+    /// objects alive through the deletion queue.
+    fn evict(&mut self, frame: u64) {
+        let keep = |used: u64, references: usize| references > 1 && frame.saturating_sub(used) < EVICT_AFTER_FRAMES;
+        self.meshes.retain(|_, cached| keep(cached.used, Arc::strong_count(&cached.source)));
+        self.textures.retain(|_, cached| keep(cached.used, Arc::strong_count(&cached.source)));
+        self.materials.retain(|_, cached| keep(cached.used, Arc::strong_count(&cached.material)));
+        self.pipelines.retain(|_, cached| keep(cached.used, Arc::strong_count(&cached.shader)));
+    }
+}
+
+impl Drop for ForwardRenderer {
+    fn drop(&mut self) {
+        let mut deletion_queue = self.deletion_queue.lock().unwrap();
+        deletion_queue.push(DeferredResource::RenderPass(self.render_pass));
+        deletion_queue.push(DeferredResource::DescriptorPool(self.frame_pool));
+        deletion_queue.push(DeferredResource::DescriptorSetLayout(self.frame_layout));
+    }
+}
+
+/// Set 0 of every pipeline: frame uniforms, lights, the culled light lists and the
+/// environment maps.
+fn frame_bindings() -> Vec<vk::DescriptorSetLayoutBinding> {
+    let fragment = vk::ShaderStageFlags::FRAGMENT;
+    [
+        (vk::DescriptorType::UNIFORM_BUFFER, vk::ShaderStageFlags::VERTEX | fragment),
+        (vk::DescriptorType::STORAGE_BUFFER, fragment),
+        (vk::DescriptorType::STORAGE_BUFFER, fragment),
+        (vk::DescriptorType::STORAGE_BUFFER, fragment),
+        (vk::DescriptorType::SAMPLED_IMAGE, fragment),
+        (vk::DescriptorType::SAMPLED_IMAGE, fragment),
+        (vk::DescriptorType::SAMPLED_IMAGE, fragment),
+        (vk::DescriptorType::SAMPLER, fragment),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(binding, (descriptor_type, stage_flags))| {
+        vk::DescriptorSetLayoutBinding::builder()
+            .binding(binding as u32)
+            .descriptor_type(descriptor_type)
+            .descriptor_count(1)
+            .stage_flags(stage_flags)
+            .build()
+    })
+    .collect()
+}
+
+/// A variant may use any subset of the frame set, but nothing beyond it and the material set.
+fn check_frame_set(variant: &ShaderVariant) -> Result<(), String> {
+    let frame_bindings = frame_bindings();
+    for binding in variant.layout.set(0) {
+        let matches = frame_bindings.iter().any(|expected| {
+            expected.binding == binding.binding
+                && expected.descriptor_type == binding.kind.descriptor_type()
+                && expected.stage_flags.contains(binding.stage_flags())
+        });
+        if !matches {
+            return Err(format!(
+                "set 0 binding {} ('{}') is not part of the frame set",
+                binding.binding, binding.name
+            ));
+        }
+    }
+    if variant.layout.set_count() > MATERIAL_SET + 1 {
+        return Err(format!("uses descriptor sets beyond set {}", MATERIAL_SET));
+    }
+    Ok(())
+}
+
+/// Attachments come in already transitioned by the render graph, so the pass keeps their
+/// layouts.
+fn create_render_pass(device: &ash::Device, color_format: vk::Format) -> vk::RenderPass {
+    let attachments = [
+        vk::AttachmentDescription::builder()
+            .format(color_format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .build(),
+        vk::AttachmentDescription::builder()
+            .format(DEPTH_FORMAT)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .build(),
+    ];
+    let color_reference = [vk::AttachmentReference {
+        attachment: 0,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    }];
+    let depth_reference = vk::AttachmentReference {
+        attachment: 1,
+        layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+    };
+    let subpasses = [vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(&color_reference)
+        .depth_stencil_attachment(&depth_reference)
+        .build()];
+    let create_info = vk::RenderPassCreateInfo::builder()
+        .attachments(&attachments)
+        .subpasses(&subpasses);
+    unsafe {
+        let result = device.create_render_pass(&create_info, None);
+        if result.is_err() {
+            log::error!("Failed to create forward render pass for {:?}.", color_format);
+            panic!("{:?}", result.err());
+        }
+        result.unwrap()
+    }
+}
+
+fn default_material(vfs: &Vfs, assets: &AssetServer) -> Handle<Material> {
+    let template = vfs
+        .read(PBR_SHADER_PATH)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| ShaderTemplate::from_bytes(&bytes).map_err(|err| err.to_string()));
+    if template.is_err() {
+        log::error!("Failed to read the PBR shader from {}", PBR_SHADER_PATH);
+        panic!("{:?}", template.err());
+    }
+    let shader = assets.add(template.unwrap());
+    let material = PbrMaterial {
+        metallic_factor: 0.0,
+        ..Default::default()
+    };
+    assets.add(material.to_material(shader))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn record_draws(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    render_pass: vk::RenderPass,
+    framebuffer: vk::Framebuffer,
+    extent: vk::Extent2D,
+    clear_color: [f32; 4],
+    frame_set: vk::DescriptorSet,
+    draws: &[Draw],
+) {
+    let clear_values = [
+        vk::ClearValue {
+            color: vk::ClearColorValue { float32: clear_color },
+        },
+        vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 },
+        },
+    ];
+    let render_area = vk::Rect2D {
+        offset: vk::Offset2D { x: 0, y: 0 },
+        extent,
+    };
+    let begin_info = vk::RenderPassBeginInfo::builder()
+        .render_pass(render_pass)
+        .framebuffer(framebuffer)
+        .render_area(render_area)
+        .clear_values(&clear_values);
+    let viewport = vk::Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: extent.width as f32,
+        height: extent.height as f32,
+        min_depth: 0.0,
+        max_depth: 1.0,
+    };
+    unsafe {
+        device.cmd_begin_render_pass(command_buffer, &begin_info, vk::SubpassContents::INLINE);
+        device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+        device.cmd_set_scissor(command_buffer, 0, &[render_area]);
+
+        let mut bound_pipeline = vk::Pipeline::null();
+        let mut bound_set = vk::DescriptorSet::null();
+        for draw in draws.iter() {
+            let material = &draw.material;
+            if material.pipeline != bound_pipeline {
+                device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, material.pipeline);
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    material.layout,
+                    0,
+                    &[frame_set],
+                    &[],
+                );
+                bound_pipeline = material.pipeline;
+                bound_set = vk::DescriptorSet::null();
+            }
+            if material.set != vk::DescriptorSet::null() && material.set != bound_set {
+                device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    material.layout,
+                    MATERIAL_SET,
+                    &[material.set],
+                    &[],
+                );
+                bound_set = material.set;
+            }
+            if material.push_constant_size > 0 {
+                device.cmd_push_constants(
+                    command_buffer,
+                    material.layout,
+                    material.push_constant_stages,
+                    0,
+                    &bytes_of(&draw.object)[..material.push_constant_size as usize],
+                );
+            }
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[draw.vertex_buffer], &[0]);
+            device.cmd_bind_index_buffer(command_buffer, draw.index_buffer, 0, vk::IndexType::UINT32);
+            device.cmd_draw_indexed(command_buffer, draw.index_count, 1, draw.first_index, 0, 0);
+        }
+        device.cmd_end_render_pass(command_buffer);
+    }
+}
@@ -0,0 +1,97 @@
+use glam::Vec3;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::scene::GlobalTransform;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightKind {
+    Point,
+    /// Shines along the entity's forward axis. Angles are in radians from the axis; the
+    /// intensity falls off smoothly between the two.
+    Spot { inner_angle: f32, outer_angle: f32 },
+}
+
+/// A punctual light as in glTF's `KHR_lights_punctual`: `intensity` is in candela and the
+/// light fades to nothing at `range`, which also bounds the clusters it is assigned to.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Light {
+            kind: LightKind::Point,
+            color: Vec3::ONE,
+            intensity: 10.0,
+            range: 10.0,
+        }
+    }
+}
+
+impl Light {
+    pub fn point(color: Vec3, intensity: f32, range: f32) -> Self {
+        Light {
+            kind: LightKind::Point,
+            color,
+            intensity,
+            range,
+        }
+    }
+
+    pub fn spot(color: Vec3, intensity: f32, range: f32, inner_angle: f32, outer_angle: f32) -> Self {
+        Light {
+            kind: LightKind::Spot {
+                inner_angle,
+                outer_angle,
+            },
+            color,
+            intensity,
+            range,
+        }
+    }
+
+    pub fn to_gpu(&self, transform: &GlobalTransform) -> GpuLight {
+        let position = transform.translation();
+        let direction = transform.affine().transform_vector3(-Vec3::Z).normalize_or_zero();
+        let color = self.color * self.intensity;
+        let (kind, spot) = match self.kind {
+            LightKind::Point => (0.0, [0.0; 4]),
+            LightKind::Spot {
+                inner_angle,
+                outer_angle,
+            } => {
+                let outer = outer_angle.clamp(0.0, std::f32::consts::FRAC_PI_2);
+                let inner = inner_angle.clamp(0.0, outer);
+                // glTF's angular attenuation: clamp(cos * scale + offset, 0, 1)^2.
+                let scale = 1.0 / (inner.cos() - outer.cos()).max(0.001);
+                let offset = -outer.cos() * scale;
+                (1.0, [scale, offset, outer.cos(), outer.sin()])
+            }
+        };
+        GpuLight {
+            position_range: [position.x, position.y, position.z, self.range.max(0.0)],
+            color_type: [color.x, color.y, color.z, kind],
+            direction: [direction.x, direction.y, direction.z, 0.0],
+            spot,
+        }
+    }
+}
+
+/// `Light` as the culling and shading shaders read it from their storage buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GpuLight {
+    /// World position and range.
+    pub position_range: [f32; 4],
+    /// Colour times intensity, then 0 for point and 1 for spot lights.
+    pub color_type: [f32; 4],
+    pub direction: [f32; 4],
+    /// Angular scale and offset, then cosine and sine of the outer angle.
+    pub spot: [f32; 4],
+}
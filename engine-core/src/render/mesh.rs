@@ -0,0 +1,94 @@
+use ash::vk;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::asset::{CookedMesh, Submesh, Vertex};
+use crate::scene::AssetRef;
+use crate::vulkan::{device_local_buffer, VulkanApp, VulkanBuffer};
+
+/// Draws a cooked mesh at the entity's `GlobalTransform`. Submeshes use the material at their
+/// material index, the first one when the index is out of range, and the renderer's default
+/// material when the list is empty.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MeshRenderer {
+    pub mesh: AssetRef,
+    pub materials: Vec<AssetRef>,
+}
+
+impl MeshRenderer {
+    pub fn new(mesh: AssetRef, materials: Vec<AssetRef>) -> Self {
+        MeshRenderer { mesh, materials }
+    }
+}
+
+/// A cooked mesh uploaded to device local vertex and index buffers.
+pub struct GpuMesh {
+    pub vertex_buffer: VulkanBuffer,
+    pub index_buffer: VulkanBuffer,
+    pub submeshes: Vec<Submesh>,
+    pub index_count: u32,
+}
+
+impl GpuMesh {
+    pub fn new(vulkan: &VulkanApp, mesh: &CookedMesh) -> Self {
+        let (instance, physical_device, logical_device) =
+            (vulkan.get_instance(), vulkan.get_physical_device(), vulkan.get_logical_device());
+        GpuMesh {
+            vertex_buffer: device_local_buffer(
+                instance,
+                physical_device,
+                logical_device,
+                &mesh.vertices,
+                vk::BufferUsageFlags::VERTEX_BUFFER,
+            ),
+            index_buffer: device_local_buffer(
+                instance,
+                physical_device,
+                logical_device,
+                &mesh.indices,
+                vk::BufferUsageFlags::INDEX_BUFFER,
+            ),
+            submeshes: mesh.submeshes.clone(),
+            index_count: mesh.indices.len() as u32,
+        }
+    }
+
+    /// Index ranges to draw and the material slot of each; a mesh without submeshes is drawn
+    /// whole with the first material.
+    pub fn draw_ranges(&self) -> Vec<Submesh> {
+        if self.submeshes.is_empty() {
+            vec![Submesh {
+                first_index: 0,
+                index_count: self.index_count,
+                material: None,
+            }]
+        } else {
+            self.submeshes.clone()
+        }
+    }
+}
+
+/// Vertex input state matching `Vertex` and the `pbr.vert` inputs.
+pub fn vertex_input() -> (Vec<vk::VertexInputBindingDescription>, Vec<vk::VertexInputAttributeDescription>) {
+    let binding = vk::VertexInputBindingDescription {
+        binding: 0,
+        stride: std::mem::size_of::<Vertex>() as u32,
+        input_rate: vk::VertexInputRate::VERTEX,
+    };
+    let attributes = [
+        (vk::Format::R32G32B32_SFLOAT, 0),
+        (vk::Format::R32G32B32_SFLOAT, 12),
+        (vk::Format::R32G32_SFLOAT, 24),
+        (vk::Format::R32G32B32A32_SFLOAT, 32),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(location, (format, offset))| vk::VertexInputAttributeDescription {
+        location: location as u32,
+        binding: 0,
+        format,
+        offset,
+    })
+    .collect();
+    (vec![binding], attributes)
+}
@@ -0,0 +1,19 @@
+mod camera;
+mod cluster;
+mod environment;
+mod forward;
+mod light;
+mod mesh;
+mod pbr;
+mod scene;
+mod texture;
+
+pub use camera::*;
+pub use cluster::*;
+pub use environment::*;
+pub use forward::*;
+pub use light::*;
+pub use mesh::*;
+pub use pbr::*;
+pub use scene::*;
+pub use texture::*;
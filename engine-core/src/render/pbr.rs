@@ -0,0 +1,87 @@
+use glam::{Vec3, Vec4};
+
+use crate::asset::{CookedTexture, Handle};
+use crate::material::{Material, MaterialValue, ShaderTemplate};
+
+/// Name of the cooked PBR shader template in the engine data, `engine://shaders/pbr.dshader`.
+pub const PBR_SHADER_PATH: &str = "engine://shaders/pbr.dshader";
+
+/// How the alpha of the base colour is used. glTF's `BLEND` mode is not drawn by the forward
+/// pass; import such materials as `Mask` or `Opaque`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    /// Fragments with alpha below the cutoff are discarded.
+    Mask(f32),
+}
+
+/// The metallic-roughness material model of glTF 2.0, with the same defaults. Textures
+/// multiply their factors; metallic is read from the blue and roughness from the green channel
+/// of `metallic_roughness_texture`, occlusion from the red channel of `occlusion_texture`.
+#[derive(Debug, Clone)]
+pub struct PbrMaterial {
+    pub base_color_factor: Vec4,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub emissive_factor: Vec3,
+    pub occlusion_strength: f32,
+    pub normal_scale: f32,
+    pub alpha_mode: AlphaMode,
+    pub base_color_texture: Option<Handle<CookedTexture>>,
+    pub metallic_roughness_texture: Option<Handle<CookedTexture>>,
+    pub occlusion_texture: Option<Handle<CookedTexture>>,
+    pub emissive_texture: Option<Handle<CookedTexture>>,
+    pub normal_texture: Option<Handle<CookedTexture>>,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        PbrMaterial {
+            base_color_factor: Vec4::ONE,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            emissive_factor: Vec3::ZERO,
+            occlusion_strength: 1.0,
+            normal_scale: 1.0,
+            alpha_mode: AlphaMode::Opaque,
+            base_color_texture: None,
+            metallic_roughness_texture: None,
+            occlusion_texture: None,
+            emissive_texture: None,
+            normal_texture: None,
+        }
+    }
+}
+
+impl PbrMaterial {
+    /// A material for the PBR shader template, with the `NORMAL_MAP` and `ALPHA_TEST`
+    /// keywords enabled as needed.
+    pub fn to_material(&self, shader: Handle<ShaderTemplate>) -> Material {
+        let mut material = Material::new(shader);
+        material.set("base_color_factor", MaterialValue::Vector(self.base_color_factor.to_array().to_vec()));
+        material.set("emissive_factor", MaterialValue::Vector(self.emissive_factor.to_array().to_vec()));
+        material.set("metallic_factor", MaterialValue::Scalar(self.metallic_factor));
+        material.set("roughness_factor", MaterialValue::Scalar(self.roughness_factor));
+        material.set("occlusion_strength", MaterialValue::Scalar(self.occlusion_strength));
+        material.set("normal_scale", MaterialValue::Scalar(self.normal_scale));
+        if let AlphaMode::Mask(cutoff) = self.alpha_mode {
+            material.set("alpha_cutoff", MaterialValue::Scalar(cutoff));
+        }
+        material.set_keyword("ALPHA_TEST", matches!(self.alpha_mode, AlphaMode::Mask(_)));
+        material.set_keyword("NORMAL_MAP", self.normal_texture.is_some());
+
+        let textures = [
+            ("base_color_texture", &self.base_color_texture),
+            ("metallic_roughness_texture", &self.metallic_roughness_texture),
+            ("occlusion_texture", &self.occlusion_texture),
+            ("emissive_texture", &self.emissive_texture),
+            ("normal_texture", &self.normal_texture),
+        ];
+        for (name, texture) in textures {
+            if let Some(texture) = texture {
+                material.set_texture(name, texture.clone());
+            }
+        }
+        material
+    }
+}
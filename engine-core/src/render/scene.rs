@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use glam::Mat4;
+
+use super::{Camera, GpuLight, Light, MeshRenderer};
+use crate::asset::{AssetServer, CookedMesh, Handle};
+use crate::ecs::World;
+use crate::material::Material;
+use crate::scene::GlobalTransform;
+
+/// One `MeshRenderer` with its assets resolved to handles; they may still be loading.
+#[derive(Debug, Clone)]
+pub struct MeshDraw {
+    pub mesh: Handle<CookedMesh>,
+    pub materials: Vec<Handle<Material>>,
+    pub transform: Mat4,
+}
+
+/// What the renderer draws in one frame, copied out of the world.
+#[derive(Debug, Clone, Default)]
+pub struct RenderScene {
+    pub camera: Option<(Camera, GlobalTransform)>,
+    pub meshes: Vec<MeshDraw>,
+    pub lights: Vec<GpuLight>,
+}
+
+/// Collects a `RenderScene` each frame. Handles for the paths the scene references are kept
+/// between frames, so assets are not unloaded and loaded again while they are drawn, and
+/// released once nothing references them.
+#[derive(Default)]
+pub struct SceneExtractor {
+    meshes: HashMap<PathBuf, Handle<CookedMesh>>,
+    materials: HashMap<PathBuf, Handle<Material>>,
+    warned_lights: bool,
+}
+
+impl SceneExtractor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Entities need a `GlobalTransform`, so run transform propagation first. At most
+    /// `max_lights` lights are kept.
+    pub fn extract(&mut self, world: &World, assets: &AssetServer, max_lights: usize) -> RenderScene {
+        let mut scene = RenderScene::default();
+        world.query::<(&Camera, &GlobalTransform)>().for_each(|_, (camera, transform)| {
+            if scene.camera.is_none() {
+                scene.camera = Some((*camera, *transform));
+            }
+        });
+        world.query::<(&Light, &GlobalTransform)>().for_each(|_, (light, transform)| {
+            scene.lights.push(light.to_gpu(transform));
+        });
+        if scene.lights.len() > max_lights {
+            if !self.warned_lights {
+                log::warn!(
+                    "{} lights in the scene but render.max_lights is {}; the rest are not drawn",
+                    scene.lights.len(),
+                    max_lights
+                );
+                self.warned_lights = true;
+            }
+            scene.lights.truncate(max_lights);
+        }
+
+        let mut meshes = HashMap::new();
+        let mut materials = HashMap::new();
+        world.query::<(&MeshRenderer, &GlobalTransform)>().for_each(|_, (renderer, transform)| {
+            if renderer.mesh.path().as_os_str().is_empty() {
+                return;
+            }
+            let mesh = Self::handle(&mut meshes, &self.meshes, assets, renderer.mesh.path());
+            let draw_materials = renderer
+                .materials
+                .iter()
+                .map(|material| Self::handle(&mut materials, &self.materials, assets, material.path()))
+                .collect();
+            scene.meshes.push(MeshDraw {
+                mesh,
+                materials: draw_materials,
+                transform: transform.compute_matrix(),
+            });
+        });
+        // Handles no longer referenced drop here.
+        self.meshes = meshes;
+        self.materials = materials;
+        scene
+    }
+
+    fn handle<T: Send + Sync + 'static>(
+        used: &mut HashMap<PathBuf, Handle<T>>,
+        previous: &HashMap<PathBuf, Handle<T>>,
+        assets: &AssetServer,
+        path: &Path,
+    ) -> Handle<T> {
+        used.entry(path.to_path_buf())
+            .or_insert_with(|| previous.get(path).cloned().unwrap_or_else(|| assets.load(path)))
+            .clone()
+    }
+}
@@ -0,0 +1,74 @@
+use ash::vk;
+
+use crate::asset::{CookedTexture, TextureFormat};
+use crate::vulkan::{ImageLevel, TextureDesc, VulkanApp, VulkanImage};
+
+/// Uploads every mip of `texture`. Returns `None` for block compressed textures on devices
+/// without BC support; cook those as `rgba8` instead.
+pub fn upload_texture(vulkan: &VulkanApp, texture: &CookedTexture) -> Option<VulkanImage> {
+    let logical_device = vulkan.get_logical_device();
+    if texture.format.is_compressed() && logical_device.get_features().texture_compression_bc != vk::TRUE {
+        return None;
+    }
+    let format = match (texture.format, texture.srgb) {
+        (TextureFormat::Rgba8, false) => vk::Format::R8G8B8A8_UNORM,
+        (TextureFormat::Rgba8, true) => vk::Format::R8G8B8A8_SRGB,
+        (TextureFormat::Bc1, false) => vk::Format::BC1_RGB_UNORM_BLOCK,
+        (TextureFormat::Bc1, true) => vk::Format::BC1_RGB_SRGB_BLOCK,
+        (TextureFormat::Bc3, false) => vk::Format::BC3_UNORM_BLOCK,
+        (TextureFormat::Bc3, true) => vk::Format::BC3_SRGB_BLOCK,
+    };
+    let image = VulkanImage::new(
+        vulkan.get_instance(),
+        vulkan.get_physical_device(),
+        logical_device,
+        TextureDesc {
+            format,
+            width: texture.width,
+            height: texture.height,
+            mip_levels: texture.mips.len().max(1) as u32,
+            cube: false,
+            usage: vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        },
+    );
+    let levels: Vec<ImageLevel> = texture
+        .mips
+        .iter()
+        .enumerate()
+        .map(|(level, bytes)| ImageLevel {
+            layer: 0,
+            level: level as u32,
+            bytes,
+        })
+        .collect();
+    image.upload(vulkan.get_instance(), vulkan.get_physical_device(), logical_device, &levels);
+    Some(image)
+}
+
+/// A 1x1 texture of one linear colour, bound where a material leaves a texture unset.
+pub fn solid_texture(vulkan: &VulkanApp, rgba: [u8; 4]) -> VulkanImage {
+    let image = VulkanImage::new(
+        vulkan.get_instance(),
+        vulkan.get_physical_device(),
+        vulkan.get_logical_device(),
+        TextureDesc {
+            format: vk::Format::R8G8B8A8_UNORM,
+            width: 1,
+            height: 1,
+            mip_levels: 1,
+            cube: false,
+            usage: vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+        },
+    );
+    image.upload(
+        vulkan.get_instance(),
+        vulkan.get_physical_device(),
+        vulkan.get_logical_device(),
+        &[ImageLevel {
+            layer: 0,
+            level: 0,
+            bytes: &rgba,
+        }],
+    );
+    image
+}
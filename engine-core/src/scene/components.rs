@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use serde_derive::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Stable identity of a saved entity. Only entities with an `EntityId` are written to scene
+/// files; the id survives save/load cycles so diffs and merges line up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct EntityId(Uuid);
+
+impl Default for EntityId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EntityId {
+    pub fn new() -> Self {
+        EntityId(Uuid::new_v4())
+    }
+
+    pub fn from_uuid(uuid: Uuid) -> Self {
+        EntityId(uuid)
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.0
+    }
+}
+
+impl Display for EntityId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Display name shown in the outliner.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Name(pub String);
+
+impl Name {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Name(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Reference to an asset by its path relative to the project's asset root, written as a plain
+/// string in scene files. Components hold these instead of loaded data so scenes stay small
+/// and portable.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct AssetRef(PathBuf);
+
+impl AssetRef {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        AssetRef(path.into())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+/// Components of a loaded entity whose type is not in the `ComponentRegistry`, kept verbatim so
+/// saving the scene again does not drop data written by newer code or other tools.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UnknownComponents(pub BTreeMap<String, serde_json::Value>);
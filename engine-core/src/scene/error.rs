@@ -0,0 +1,63 @@
+use std::fmt::Display;
+
+use super::{EntityId, HierarchyError};
+use crate::vfs::VfsError;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(String),
+    UnknownFormat(String),
+    Parse(String),
+    Encode(String),
+    UnsupportedVersion { found: u32, supported: u32 },
+    Component { name: String, entity: EntityId, error: String },
+    DuplicateId(EntityId),
+    Hierarchy(HierarchyError),
+    Prefab(String),
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "failed to access scene: {}", err),
+            SceneError::UnknownFormat(path) => write!(
+                f,
+                "cannot tell the scene format of '{}', use a .scene/.prefab/.yaml or .sceneb/.msgpack extension",
+                path
+            ),
+            SceneError::Parse(err) => write!(f, "failed to parse scene: {}", err),
+            SceneError::Encode(err) => write!(f, "failed to encode scene: {}", err),
+            SceneError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "scene format version {} is newer than the newest supported version {}",
+                found, supported
+            ),
+            SceneError::Component { name, entity, error } => {
+                write!(f, "component '{}' of entity {}: {}", name, entity, error)
+            }
+            SceneError::DuplicateId(id) => write!(f, "entity id {} appears more than once", id),
+            SceneError::Hierarchy(err) => write!(f, "{}", err),
+            SceneError::Prefab(err) => write!(f, "prefab error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(err: std::io::Error) -> Self {
+        SceneError::Io(err.to_string())
+    }
+}
+
+impl From<VfsError> for SceneError {
+    fn from(err: VfsError) -> Self {
+        SceneError::Io(err.to_string())
+    }
+}
+
+impl From<HierarchyError> for SceneError {
+    fn from(err: HierarchyError) -> Self {
+        SceneError::Hierarchy(err)
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt::Display;
+
+use glam::Affine3A;
+
+use super::Transform;
+use crate::ecs::{Entity, Without, World};
+
+/// The entity's parent. Only changed through `set_parent`/`reparent`, which keep `Children`
+/// in sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(Entity);
+
+impl Parent {
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+/// Child entities in sibling order, the order the outliner shows them in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(Vec<Entity>);
+
+impl Children {
+    pub fn as_slice(&self) -> &[Entity] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyError {
+    NotAlive(Entity),
+    Cycle { child: Entity, parent: Entity },
+}
+
+impl Display for HierarchyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HierarchyError::NotAlive(entity) => write!(f, "entity {} does not exist", entity),
+            HierarchyError::Cycle { child, parent } => write!(
+                f,
+                "cannot parent {} to {}, it is {} itself or one of its descendants",
+                child, parent, child
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HierarchyError {}
+
+pub fn parent_of(world: &World, entity: Entity) -> Option<Entity> {
+    world.get::<Parent>(entity).map(|parent| parent.get())
+}
+
+pub fn children_of(world: &World, entity: Entity) -> Vec<Entity> {
+    world
+        .get::<Children>(entity)
+        .map_or_else(Vec::new, |children| children.0.clone())
+}
+
+pub fn is_descendant_of(world: &World, entity: Entity, ancestor: Entity) -> bool {
+    let mut current = parent_of(world, entity);
+    while let Some(parent) = current {
+        if parent == ancestor {
+            return true;
+        }
+        current = parent_of(world, parent);
+    }
+    false
+}
+
+/// Attaches `child` as the last child of `parent`, or makes it a root for `None`. The local
+/// `Transform` is kept, so the child moves with its new parent.
+pub fn set_parent(world: &mut World, child: Entity, parent: Option<Entity>) -> Result<(), HierarchyError> {
+    if !world.is_alive(child) {
+        return Err(HierarchyError::NotAlive(child));
+    }
+    if let Some(parent) = parent {
+        if !world.is_alive(parent) {
+            return Err(HierarchyError::NotAlive(parent));
+        }
+        if parent == child || is_descendant_of(world, parent, child) {
+            return Err(HierarchyError::Cycle { child, parent });
+        }
+    }
+
+    let old = parent_of(world, child);
+    if old == parent {
+        return Ok(());
+    }
+    if let Some(old) = old {
+        if let Some(children) = world.get_mut::<Children>(old) {
+            children.0.retain(|entity| *entity != child);
+        }
+    }
+    match parent {
+        Some(parent) => {
+            world.insert(child, Parent(parent));
+            match world.get_mut::<Children>(parent) {
+                Some(children) => children.0.push(child),
+                None => {
+                    world.insert(parent, Children(vec![child]));
+                }
+            }
+        }
+        None => {
+            world.remove::<Parent>(child);
+        }
+    }
+    // Marks every transform of the moved subtree changed so propagation recomputes it, even
+    // when the child itself has no `Transform` to carry the change down.
+    for entity in descendants(world, child) {
+        world.get_mut::<Transform>(entity);
+    }
+    Ok(())
+}
+
+/// Like `set_parent`, but rewrites the local `Transform` so the child stays where it is in the
+/// world, which is what dragging in the outliner should do.
+pub fn reparent(world: &mut World, child: Entity, parent: Option<Entity>) -> Result<(), HierarchyError> {
+    let world_affine = global_affine(world, child);
+    set_parent(world, child, parent)?;
+    let parent_affine = parent.map_or(Affine3A::IDENTITY, |parent| global_affine(world, parent));
+    if let Some(transform) = world.get_mut::<Transform>(child) {
+        *transform = Transform::from_affine(&(parent_affine.inverse() * world_affine));
+    }
+    Ok(())
+}
+
+/// Moves `child` to `index` among its siblings, clamped to the last position. Roots have no
+/// sibling order; returns false for them.
+pub fn set_sibling_index(world: &mut World, child: Entity, index: usize) -> bool {
+    let parent = match parent_of(world, child) {
+        Some(parent) => parent,
+        None => return false,
+    };
+    match world.get_mut::<Children>(parent) {
+        Some(children) => {
+            children.0.retain(|entity| *entity != child);
+            let index = index.min(children.0.len());
+            children.0.insert(index, child);
+            true
+        }
+        None => false,
+    }
+}
+
+/// World-space transform computed from the local transforms up the parent chain. Unlike
+/// `GlobalTransform` this is never stale, at the cost of walking the ancestors.
+pub fn global_affine(world: &World, entity: Entity) -> Affine3A {
+    let local = |entity| {
+        world
+            .get::<Transform>(entity)
+            .map_or(Affine3A::IDENTITY, |transform| transform.compute_affine())
+    };
+    let mut affine = local(entity);
+    let mut current = parent_of(world, entity);
+    while let Some(parent) = current {
+        affine = local(parent) * affine;
+        current = parent_of(world, parent);
+    }
+    affine
+}
+
+/// Entities without a parent, in entity order.
+pub fn roots(world: &World) -> Vec<Entity> {
+    let mut roots = world.query_filtered::<Entity, Without<Parent>>().entities();
+    roots.sort_unstable();
+    roots
+}
+
+/// Every entity depth-first from the roots, parents before children and siblings in order,
+/// with its depth below the root.
+pub fn hierarchy_order(world: &World) -> Vec<(Entity, usize)> {
+    let mut order = Vec::with_capacity(world.entities().len());
+    let mut stack: Vec<(Entity, usize)> = roots(world).into_iter().rev().map(|root| (root, 0)).collect();
+    let mut children = world.query::<&Children>();
+    while let Some((entity, depth)) = stack.pop() {
+        order.push((entity, depth));
+        if let Some(list) = children.get(entity) {
+            stack.extend(list.0.iter().rev().map(|child| (*child, depth + 1)));
+        }
+    }
+    order
+}
+
+/// The entity and all its descendants, depth-first.
+pub fn descendants(world: &World, entity: Entity) -> Vec<Entity> {
+    let mut found = Vec::new();
+    let mut stack = vec![entity];
+    while let Some(entity) = stack.pop() {
+        found.push(entity);
+        if let Some(children) = world.get::<Children>(entity) {
+            stack.extend(children.0.iter().rev().copied());
+        }
+    }
+    found
+}
+
+/// Detaches `entity` from its parent and despawns it together with its descendants.
+pub fn despawn_recursive(world: &mut World, entity: Entity) {
+    if !world.is_alive(entity) {
+        return;
+    }
+    if let Some(parent) = parent_of(world, entity) {
+        if let Some(children) = world.get_mut::<Children>(parent) {
+            children.0.retain(|child| *child != entity);
+        }
+    }
+    for entity in descendants(world, entity) {
+        world.despawn(entity);
+    }
+}
@@ -0,0 +1,19 @@
+mod transform;
+mod hierarchy;
+mod propagate;
+mod components;
+mod error;
+mod registry;
+mod serialize;
+mod overrides;
+mod prefab;
+
+pub use transform::*;
+pub use hierarchy::*;
+pub use propagate::*;
+pub use components::*;
+pub use error::*;
+pub use registry::*;
+pub use serialize::*;
+pub use overrides::*;
+pub use prefab::*;
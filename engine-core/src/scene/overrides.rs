@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::EntityId;
+
+/// One per-instance difference from a prefab: the value at `path` inside `component` of the
+/// prefab entity `entity`. An empty path replaces (or adds) the whole component; `removed`
+/// drops it. Paths are dotted field names like `translation` or `light.color`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PropertyOverride {
+    pub entity: EntityId,
+    pub component: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub value: Value,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub removed: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Appends the overrides that turn `baseline` into `current` for the prefab entity `entity`.
+pub(crate) fn diff_components(
+    entity: EntityId,
+    baseline: &BTreeMap<String, Value>,
+    current: &BTreeMap<String, Value>,
+    overrides: &mut Vec<PropertyOverride>,
+) {
+    for (component, base) in baseline.iter() {
+        match current.get(component) {
+            Some(value) => diff_value(entity, component, String::new(), base, value, overrides),
+            None => overrides.push(PropertyOverride {
+                entity,
+                component: component.clone(),
+                path: String::new(),
+                value: Value::Null,
+                removed: true,
+            }),
+        }
+    }
+    for (component, value) in current.iter().filter(|(component, _)| !baseline.contains_key(*component)) {
+        overrides.push(PropertyOverride {
+            entity,
+            component: component.clone(),
+            path: String::new(),
+            value: value.clone(),
+            removed: false,
+        });
+    }
+}
+
+// Objects with the same keys are compared field by field so an instance that only moved
+// stores its translation, not its whole transform. Anything else is overridden as a unit.
+fn diff_value(entity: EntityId, component: &str, path: String, base: &Value, value: &Value, overrides: &mut Vec<PropertyOverride>) {
+    if base == value {
+        return;
+    }
+    if let (Value::Object(base), Value::Object(value)) = (base, value) {
+        let same_keys = base.len() == value.len() && base.keys().all(|key| value.contains_key(key));
+        if same_keys && base.keys().all(|key| !key.contains('.')) {
+            for (key, base) in base.iter() {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                diff_value(entity, component, path, base, &value[key], overrides);
+            }
+            return;
+        }
+    }
+    overrides.push(PropertyOverride {
+        entity,
+        component: component.to_string(),
+        path,
+        value: value.clone(),
+        removed: false,
+    });
+}
+
+/// Applies `o` to the components of its entity. A property override of a component the
+/// prefab no longer has is dropped with a warning.
+pub(crate) fn apply_override(components: &mut BTreeMap<String, Value>, o: &PropertyOverride) {
+    if o.removed {
+        components.remove(&o.component);
+        return;
+    }
+    if o.path.is_empty() {
+        components.insert(o.component.clone(), o.value.clone());
+        return;
+    }
+    let mut target = match components.get_mut(&o.component) {
+        Some(target) => target,
+        None => {
+            log::warn!(
+                "Dropping override of '{}.{}' on {}, the prefab no longer has that component",
+                o.component,
+                o.path,
+                o.entity
+            );
+            return;
+        }
+    };
+    for key in o.path.split('.') {
+        if !target.is_object() {
+            *target = Value::Object(Map::new());
+        }
+        target = target.as_object_mut().unwrap().entry(key).or_insert(Value::Null);
+    }
+    *target = o.value.clone();
+}
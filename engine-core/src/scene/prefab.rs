@@ -0,0 +1,542 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::{
+    apply_override, capture, descendants, despawn_recursive, diff_components, entity_components, set_parent,
+    write_components, AssetRef, ComponentRegistry, EntityId, PropertyOverride, Scene, SceneError,
+};
+use crate::ecs::{Entity, World};
+use crate::vfs::{Vfs, VfsPath};
+
+/// How a scene (or another prefab) refers to a prefab it instantiates.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PrefabLink {
+    pub source: AssetRef,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<PropertyOverride>,
+}
+
+/// A prefab with its nested prefabs flattened in, root first and parents before children.
+/// Entities of a nested instance get ids derived from the instance's id, so every id is unique
+/// within the expansion and stable across loads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpandedPrefab {
+    source: AssetRef,
+    entities: Vec<ExpandedEntity>,
+    includes: Vec<AssetRef>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpandedEntity {
+    pub id: EntityId,
+    pub parent: Option<EntityId>,
+    pub components: BTreeMap<String, Value>,
+}
+
+impl ExpandedPrefab {
+    pub fn source(&self) -> &AssetRef {
+        &self.source
+    }
+
+    pub fn root(&self) -> EntityId {
+        self.entities[0].id
+    }
+
+    pub fn entities(&self) -> &[ExpandedEntity] {
+        &self.entities
+    }
+
+    /// Every prefab nested in this one, at any depth.
+    pub fn includes(&self) -> &[AssetRef] {
+        &self.includes
+    }
+}
+
+/// On the root entity of a prefab instance.
+#[derive(Debug, Clone)]
+pub struct PrefabInstance {
+    source: AssetRef,
+    prefab: Arc<ExpandedPrefab>,
+    members: BTreeMap<EntityId, Entity>,
+}
+
+impl PrefabInstance {
+    pub fn source(&self) -> &AssetRef {
+        &self.source
+    }
+
+    /// The prefab as it was when the instance was last synced.
+    pub fn prefab(&self) -> &Arc<ExpandedPrefab> {
+        &self.prefab
+    }
+
+    /// Instance entities by their id in the expanded prefab, root included.
+    pub fn members(&self) -> &BTreeMap<EntityId, Entity> {
+        &self.members
+    }
+}
+
+/// On every entity of a prefab instance except its root. Members are not written to scene
+/// files; they are rebuilt from the prefab and the root's overrides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefabMember {
+    pub instance: Entity,
+    pub local: EntityId,
+}
+
+/// A name-based uuid in the instance's namespace, so nesting the same id twice never cancels out.
+fn derive_id(instance: EntityId, local: EntityId) -> EntityId {
+    EntityId::from_uuid(Uuid::new_v5(&instance.uuid(), local.uuid().as_bytes()))
+}
+
+/// The id of the prefab entity `local` inside the instance `instance`; the root takes the
+/// instance's own id.
+fn instance_id(instance: EntityId, root: EntityId, local: EntityId) -> EntityId {
+    if local == root {
+        instance
+    } else {
+        derive_id(instance, local)
+    }
+}
+
+struct LoadedSource {
+    scene: Scene,
+    modified: Option<SystemTime>,
+}
+
+/// Loads prefab files relative to an asset root and caches their expansions. Reloading a
+/// prefab invalidates it and every prefab nesting it; `refresh` then updates the instances.
+pub struct PrefabLibrary {
+    vfs: Vfs,
+    root: VfsPath,
+    sources: HashMap<AssetRef, LoadedSource>,
+    expanded: HashMap<AssetRef, Arc<ExpandedPrefab>>,
+}
+
+impl PrefabLibrary {
+    /// Prefabs are read and written through `vfs`, below `root` such as `project://assets`.
+    pub fn new(vfs: Vfs, root: VfsPath) -> Self {
+        PrefabLibrary {
+            vfs,
+            root,
+            sources: HashMap::new(),
+            expanded: HashMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> &VfsPath {
+        &self.root
+    }
+
+    pub fn path_of(&self, source: &AssetRef) -> Result<VfsPath, SceneError> {
+        Ok(self.root.join(source.path())?)
+    }
+
+    /// The prefab file as written, with nested prefabs still as links.
+    pub fn source(&mut self, source: &AssetRef) -> Result<&Scene, SceneError> {
+        if !self.sources.contains_key(source) {
+            self.reload(source)?;
+        }
+        Ok(&self.sources[source].scene)
+    }
+
+    pub fn get(&mut self, source: &AssetRef) -> Result<Arc<ExpandedPrefab>, SceneError> {
+        self.expand(source, &mut Vec::new())
+    }
+
+    /// Re-reads the prefab file, e.g. after it changed on disk.
+    pub fn reload(&mut self, source: &AssetRef) -> Result<(), SceneError> {
+        let path = self.path_of(source)?;
+        let scene = Scene::read(&self.vfs, &path).map_err(|err| SceneError::Prefab(format!("'{}': {}", path, err)))?;
+        check_single_root(&scene, source)?;
+        let modified = self.modified(&path);
+        self.sources.insert(source.clone(), LoadedSource { scene, modified });
+        self.invalidate(source);
+        Ok(())
+    }
+
+    /// Writes `scene` as the prefab's new content.
+    pub fn save_source(&mut self, source: &AssetRef, scene: Scene) -> Result<(), SceneError> {
+        check_single_root(&scene, source)?;
+        let path = self.path_of(source)?;
+        scene.write(&self.vfs, &path)?;
+        let modified = self.modified(&path);
+        self.sources.insert(source.clone(), LoadedSource { scene, modified });
+        self.invalidate(source);
+        Ok(())
+    }
+
+    /// Reloads prefab files modified since they were read and returns them. Files that fail
+    /// to load are logged and keep their previous content.
+    pub fn poll_changes(&mut self) -> Vec<AssetRef> {
+        let changed: Vec<AssetRef> = self
+            .sources
+            .iter()
+            .filter(|(source, loaded)| {
+                let modified = self.path_of(source).ok().and_then(|path| self.modified(&path));
+                modified.is_some() && modified != loaded.modified
+            })
+            .map(|(source, _)| source.clone())
+            .collect();
+        changed
+            .into_iter()
+            .filter(|source| match self.reload(source) {
+                Ok(()) => true,
+                Err(err) => {
+                    log::error!("Failed to reload prefab: {}", err);
+                    false
+                }
+            })
+            .collect()
+    }
+
+    /// Picks up changed prefab files and propagates them to their instances in `world`.
+    pub fn refresh(&mut self, world: &mut World, registry: &ComponentRegistry) -> usize {
+        if self.poll_changes().is_empty() {
+            return 0;
+        }
+        sync_prefab_instances(world, registry, self)
+    }
+
+    /// When the file on disk behind `path` last changed; `None` for files in archives, which
+    /// cannot change while mounted.
+    fn modified(&self, path: &VfsPath) -> Option<SystemTime> {
+        let native = self.vfs.native_path(path)?;
+        fs::metadata(native).and_then(|metadata| metadata.modified()).ok()
+    }
+
+    fn invalidate(&mut self, source: &AssetRef) {
+        self.expanded
+            .retain(|key, prefab| key != source && !prefab.includes.contains(source));
+    }
+
+    fn expand(&mut self, source: &AssetRef, stack: &mut Vec<AssetRef>) -> Result<Arc<ExpandedPrefab>, SceneError> {
+        if let Some(prefab) = self.expanded.get(source) {
+            return Ok(prefab.clone());
+        }
+        if stack.contains(source) {
+            return Err(SceneError::Prefab(format!(
+                "'{}' contains itself",
+                source.path().display()
+            )));
+        }
+        stack.push(source.clone());
+        let scene = self.source(source)?.clone();
+        let mut entities = Vec::new();
+        let mut includes = Vec::new();
+        for entry in scene.entities.iter() {
+            let link = match &entry.prefab {
+                Some(link) => link,
+                None => {
+                    entities.push(ExpandedEntity {
+                        id: entry.id,
+                        parent: entry.parent,
+                        components: entry.components.clone(),
+                    });
+                    continue;
+                }
+            };
+            let nested = self.expand(&link.source, stack)?;
+            includes.push(link.source.clone());
+            includes.extend(nested.includes.iter().cloned());
+            let root = nested.root();
+            for nested_entity in nested.entities.iter() {
+                let mut components = nested_entity.components.clone();
+                for o in link.overrides.iter().filter(|o| o.entity == nested_entity.id) {
+                    apply_override(&mut components, o);
+                }
+                entities.push(ExpandedEntity {
+                    id: instance_id(entry.id, root, nested_entity.id),
+                    parent: match nested_entity.parent {
+                        Some(parent) => Some(instance_id(entry.id, root, parent)),
+                        None => entry.parent,
+                    },
+                    components,
+                });
+            }
+        }
+        stack.pop();
+        includes.sort();
+        includes.dedup();
+        let prefab = Arc::new(ExpandedPrefab {
+            source: source.clone(),
+            entities,
+            includes,
+        });
+        self.expanded.insert(source.clone(), prefab.clone());
+        Ok(prefab)
+    }
+}
+
+fn check_single_root(scene: &Scene, source: &AssetRef) -> Result<(), SceneError> {
+    let roots = scene.entities.iter().filter(|entity| entity.parent.is_none()).count();
+    match scene.entities.first() {
+        Some(first) if roots == 1 && first.parent.is_none() => Ok(()),
+        _ => Err(SceneError::Prefab(format!(
+            "'{}' must list exactly one root entity first, found {} root(s)",
+            source.path().display(),
+            roots
+        ))),
+    }
+}
+
+/// Spawns an instance of `source`, optionally under `parent`, and returns its root.
+pub fn instantiate(
+    world: &mut World,
+    registry: &ComponentRegistry,
+    prefabs: &mut PrefabLibrary,
+    source: &AssetRef,
+    parent: Option<Entity>,
+) -> Result<Entity, SceneError> {
+    let root = world.spawn((EntityId::new(),));
+    let link = PrefabLink {
+        source: source.clone(),
+        overrides: Vec::new(),
+    };
+    let result = spawn_instance(world, registry, prefabs, root, &link)
+        .and_then(|_| set_parent(world, root, parent).map_err(SceneError::from));
+    if let Err(err) = result {
+        despawn_recursive(world, root);
+        return Err(err);
+    }
+    Ok(root)
+}
+
+/// Turns `root`, which must have an `EntityId`, into an instance of `link`. Returns the
+/// instance's entities by their id in the scene.
+pub(crate) fn spawn_instance(
+    world: &mut World,
+    registry: &ComponentRegistry,
+    prefabs: &mut PrefabLibrary,
+    root: Entity,
+    link: &PrefabLink,
+) -> Result<Vec<(EntityId, Entity)>, SceneError> {
+    let prefab = prefabs.get(&link.source)?;
+    sync_instance(world, registry, root, prefab, &link.overrides)
+}
+
+/// Rebuilds the instance at `root` from `prefab` plus `overrides`, reusing existing member
+/// entities so handles to them stay valid. Members the prefab no longer has are despawned.
+fn sync_instance(
+    world: &mut World,
+    registry: &ComponentRegistry,
+    root: Entity,
+    prefab: Arc<ExpandedPrefab>,
+    overrides: &[PropertyOverride],
+) -> Result<Vec<(EntityId, Entity)>, SceneError> {
+    let instance = *world
+        .get::<EntityId>(root)
+        .ok_or_else(|| SceneError::Prefab(format!("instance root {} has no EntityId", root)))?;
+    let old_members = world
+        .get::<PrefabInstance>(root)
+        .map(|instance| instance.members.clone())
+        .unwrap_or_default();
+
+    for o in overrides.iter().filter(|o| prefab.entities.iter().all(|entity| entity.id != o.entity)) {
+        log::warn!(
+            "Dropping override of '{}' on {}, '{}' no longer has that entity",
+            o.component,
+            o.entity,
+            prefab.source.path().display()
+        );
+    }
+
+    let mut members = BTreeMap::new();
+    let mut ids = Vec::with_capacity(prefab.entities.len());
+    for target in prefab.entities.iter() {
+        let id = instance_id(instance, prefab.root(), target.id);
+        let entity = match old_members.get(&target.id) {
+            _ if target.id == prefab.root() => root,
+            Some(entity) if world.is_alive(*entity) => *entity,
+            _ => world.spawn((
+                id,
+                PrefabMember {
+                    instance: root,
+                    local: target.id,
+                },
+            )),
+        };
+        members.insert(target.id, entity);
+        ids.push((id, entity));
+
+        let mut components = target.components.clone();
+        for o in overrides.iter().filter(|o| o.entity == target.id) {
+            apply_override(&mut components, o);
+        }
+        write_components(world, registry, entity, id, &components)?;
+        if let Some(parent) = target.parent.and_then(|parent| members.get(&parent)) {
+            set_parent(world, entity, Some(*parent))?;
+        }
+    }
+
+    for (_, entity) in old_members.iter().filter(|(local, _)| !members.contains_key(*local)) {
+        despawn_recursive(world, *entity);
+    }
+    world.insert(
+        root,
+        PrefabInstance {
+            source: prefab.source.clone(),
+            prefab,
+            members,
+        },
+    );
+    Ok(ids)
+}
+
+/// How the instance at `root` differs from its prefab, property by property.
+pub fn instance_overrides(world: &World, registry: &ComponentRegistry, root: Entity) -> Result<Vec<PropertyOverride>, SceneError> {
+    let (prefab, members) = match world.get::<PrefabInstance>(root) {
+        Some(instance) => (instance.prefab.clone(), instance.members.clone()),
+        None => return Ok(Vec::new()),
+    };
+    let instance = world.get::<EntityId>(root).map_or_else(EntityId::new, |id| *id);
+    let mut overrides = Vec::new();
+    for target in prefab.entities.iter() {
+        let entity = match members.get(&target.id) {
+            Some(entity) if world.is_alive(*entity) => *entity,
+            _ => continue,
+        };
+        let id = instance_id(instance, prefab.root(), target.id);
+        let current = entity_components(world, registry, entity, id)?;
+        diff_components(target.id, &target.components, &current, &mut overrides);
+    }
+    Ok(overrides)
+}
+
+/// Drops the overrides `filter` selects, restoring those properties to the prefab's values.
+pub fn revert_overrides<F>(world: &mut World, registry: &ComponentRegistry, root: Entity, filter: F) -> Result<usize, SceneError>
+where
+    F: Fn(&PropertyOverride) -> bool,
+{
+    let prefab = match world.get::<PrefabInstance>(root) {
+        Some(instance) => instance.prefab.clone(),
+        None => return Ok(0),
+    };
+    let (reverted, kept): (Vec<_>, Vec<_>) = instance_overrides(world, registry, root)?.into_iter().partition(|o| filter(o));
+    if !reverted.is_empty() {
+        sync_instance(world, registry, root, prefab, &kept)?;
+    }
+    Ok(reverted.len())
+}
+
+/// Writes the overrides `filter` selects into the prefab file, then updates every instance
+/// of it. Overrides of entities that come from a nested prefab are stored on that nested
+/// instance inside the prefab rather than in the nested prefab itself.
+pub fn apply_overrides<F>(
+    world: &mut World,
+    registry: &ComponentRegistry,
+    prefabs: &mut PrefabLibrary,
+    root: Entity,
+    filter: F,
+) -> Result<usize, SceneError>
+where
+    F: Fn(&PropertyOverride) -> bool,
+{
+    let source = match world.get::<PrefabInstance>(root) {
+        Some(instance) => instance.source.clone(),
+        None => return Ok(0),
+    };
+    let applied: Vec<PropertyOverride> = instance_overrides(world, registry, root)?.into_iter().filter(|o| filter(o)).collect();
+    if applied.is_empty() {
+        return Ok(0);
+    }
+
+    let mut scene = prefabs.source(&source)?.clone();
+    for o in applied.iter() {
+        if let Some(entry) = scene.entities.iter_mut().find(|entry| entry.id == o.entity && entry.prefab.is_none()) {
+            apply_override(&mut entry.components, o);
+            continue;
+        }
+        let mut stored = false;
+        for entry in scene.entities.iter_mut() {
+            let link = match entry.prefab.as_mut() {
+                Some(link) => link,
+                None => continue,
+            };
+            let nested = prefabs.get(&link.source)?;
+            let local = nested
+                .entities
+                .iter()
+                .map(|entity| entity.id)
+                .find(|local| instance_id(entry.id, nested.root(), *local) == o.entity);
+            if let Some(local) = local {
+                let o = PropertyOverride { entity: local, ..o.clone() };
+                link.overrides.retain(|existing| !supersedes(&o, existing));
+                link.overrides.push(o);
+                stored = true;
+                break;
+            }
+        }
+        if !stored {
+            log::warn!("Override of '{}' on {} has no entity in '{}'", o.component, o.entity, source.path().display());
+        }
+    }
+    prefabs.save_source(&source, scene)?;
+    sync_prefab_instances(world, registry, prefabs);
+    Ok(applied.len())
+}
+
+// An override replaces earlier ones of the same property or of any property below it.
+fn supersedes(new: &PropertyOverride, old: &PropertyOverride) -> bool {
+    new.entity == old.entity
+        && new.component == old.component
+        && (new.path.is_empty() || old.path == new.path || old.path.starts_with(&format!("{}.", new.path)))
+}
+
+/// Brings every instance whose prefab changed since it was synced up to date, keeping its
+/// overrides. Returns how many instances were updated.
+pub fn sync_prefab_instances(world: &mut World, registry: &ComponentRegistry, prefabs: &mut PrefabLibrary) -> usize {
+    let mut instances = Vec::new();
+    world
+        .query::<&PrefabInstance>()
+        .for_each(|entity, instance| instances.push((entity, instance.source.clone(), instance.prefab.clone())));
+
+    let mut updated = 0;
+    for (root, source, old) in instances {
+        let result = prefabs.get(&source).and_then(|prefab| {
+            if Arc::ptr_eq(&prefab, &old) {
+                return Ok(false);
+            }
+            let overrides = instance_overrides(world, registry, root)?;
+            sync_instance(world, registry, root, prefab, &overrides).map(|_| true)
+        });
+        match result {
+            Ok(true) => updated += 1,
+            Ok(false) => {}
+            Err(err) => log::error!("Failed to update instance {} of '{}': {}", root, source.path().display(), err),
+        }
+    }
+    updated
+}
+
+/// Captures `root` and its descendants as prefab content, with `root` as the prefab root.
+/// Prefab instances inside the subtree become nested prefabs.
+pub fn prefab_from_entity(world: &World, registry: &ComponentRegistry, root: Entity) -> Result<Scene, SceneError> {
+    if !world.has::<EntityId>(root) {
+        return Err(SceneError::Prefab(format!("prefab root {} has no EntityId", root)));
+    }
+    if world.has::<PrefabMember>(root) {
+        return Err(SceneError::Prefab(format!(
+            "prefab root {} is a member of a prefab instance; use the instance root instead",
+            root
+        )));
+    }
+    let root_id = *world.get::<EntityId>(root).unwrap();
+    let mut scene = Scene::default();
+    for entity in descendants(world, root) {
+        if let Some(saved) = capture(world, registry, entity)? {
+            scene.entities.push(saved);
+        }
+    }
+    match scene.entities.first_mut() {
+        Some(first) if first.id == root_id => first.parent = None,
+        _ => return Err(SceneError::Prefab(format!("prefab root {} was not captured", root))),
+    }
+    Ok(scene)
+}
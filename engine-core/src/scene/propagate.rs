@@ -0,0 +1,85 @@
+use std::collections::HashSet;
+
+use glam::Affine3A;
+
+use super::{roots, Children, GlobalTransform, Transform};
+use crate::ecs::{Changed, Entity, World};
+
+/// Keeps `GlobalTransform` up to date. A subtree is recomputed only below entities whose
+/// `Transform` changed since the previous run (which `set_parent` marks for the whole moved
+/// subtree); everything else keeps its cached global transform.
+#[derive(Debug, Default)]
+pub struct TransformPropagator {
+    last_run: u32,
+}
+
+impl TransformPropagator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns how many global transforms were rewritten.
+    pub fn run(&mut self, world: &mut World) -> usize {
+        let this_run = world.change_tick();
+        let updates = Self::collect(world, self.last_run);
+        let updated = updates.len();
+        for (entity, affine) in updates {
+            match world.get_mut::<GlobalTransform>(entity) {
+                Some(global) => *global = GlobalTransform::from_affine(affine),
+                None => {
+                    world.insert(entity, GlobalTransform::from_affine(affine));
+                }
+            }
+        }
+        // Edits made after this point land on a newer tick than `last_run` and are picked up
+        // next time.
+        self.last_run = this_run;
+        world.increment_change_tick();
+        updated
+    }
+
+    fn collect(world: &World, last_run: u32) -> Vec<(Entity, Affine3A)> {
+        let changed: HashSet<Entity> = world
+            .query_since::<Entity, Changed<Transform>>(last_run)
+            .entities()
+            .into_iter()
+            .collect();
+        let mut transforms = world.query::<(&Transform, Option<&GlobalTransform>)>();
+        let mut children = world.query::<&Children>();
+
+        let mut updates = Vec::new();
+        let mut stack: Vec<(Entity, Affine3A, bool)> = roots(world)
+            .into_iter()
+            .map(|root| (root, Affine3A::IDENTITY, false))
+            .collect();
+        while let Some((entity, parent, parent_dirty)) = stack.pop() {
+            // Entities without a transform pass their parent's through unchanged.
+            let (global, dirty) = match transforms.get(entity) {
+                Some((transform, cached)) => {
+                    let dirty = parent_dirty || cached.is_none() || changed.contains(&entity);
+                    let global = match cached {
+                        Some(cached) if !dirty => cached.affine(),
+                        _ => parent * transform.compute_affine(),
+                    };
+                    if dirty {
+                        updates.push((entity, global));
+                    }
+                    (global, dirty)
+                }
+                None => (parent, parent_dirty),
+            };
+            if let Some(list) = children.get(entity) {
+                stack.extend(list.iter().map(|child| (child, global, dirty)));
+            }
+        }
+        updates
+    }
+}
+
+/// Recomputes every `GlobalTransform` regardless of what changed.
+pub fn propagate_transforms(world: &mut World) {
+    let updates = TransformPropagator::collect(world, 0);
+    for (entity, affine) in updates {
+        world.insert(entity, GlobalTransform::from_affine(affine));
+    }
+}
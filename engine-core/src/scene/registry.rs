@@ -0,0 +1,136 @@
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use super::{Name, Transform};
+use crate::ecs::{Component, Entity, World};
+use crate::render::{Camera, Light, MeshRenderer};
+
+type SaveFn = fn(&World, Entity) -> Option<Result<Value, String>>;
+type LoadFn = fn(&mut World, Entity, Value) -> Result<(), String>;
+type RemoveFn = fn(&mut World, Entity) -> bool;
+
+struct Registration {
+    type_id: TypeId,
+    save: SaveFn,
+    load: LoadFn,
+    remove: RemoveFn,
+}
+
+/// Maps the names used in scene files to component types. Names are chosen at registration
+/// rather than taken from `type_name` so moving a type between modules does not break scenes.
+pub struct ComponentRegistry {
+    by_name: BTreeMap<String, Registration>,
+    by_type: HashMap<TypeId, String>,
+}
+
+impl Default for ComponentRegistry {
+    /// The engine's own components.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry
+            .register::<Name>("Name")
+            .register::<Transform>("Transform")
+            .register::<Camera>("Camera")
+            .register::<Light>("Light")
+            .register::<MeshRenderer>("MeshRenderer");
+        registry
+    }
+}
+
+impl ComponentRegistry {
+    pub fn empty() -> Self {
+        ComponentRegistry {
+            by_name: BTreeMap::new(),
+            by_type: HashMap::new(),
+        }
+    }
+
+    /// Registers `T` under `name`, replacing an earlier registration of either.
+    pub fn register<T>(&mut self, name: &str) -> &mut Self
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        if let Some(old) = self.by_type.insert(TypeId::of::<T>(), name.to_string()) {
+            self.by_name.remove(&old);
+        }
+        if let Some(old) = self.by_name.insert(
+            name.to_string(),
+            Registration {
+                type_id: TypeId::of::<T>(),
+                save: save_component::<T>,
+                load: load_component::<T>,
+                remove: remove_component::<T>,
+            },
+        ) {
+            if old.type_id != TypeId::of::<T>() {
+                log::warn!("Component name '{}' was registered for another type and is now reassigned", name);
+                self.by_type.remove(&old.type_id);
+            }
+        }
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.by_name.contains_key(name)
+    }
+
+    pub fn name_of<T: Component>(&self) -> Option<&str> {
+        self.by_type.get(&TypeId::of::<T>()).map(String::as_str)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.by_name.keys().map(String::as_str)
+    }
+
+    /// The registered components of `entity`, by name.
+    pub fn save(&self, world: &World, entity: Entity) -> Result<BTreeMap<String, Value>, (String, String)> {
+        let mut components = BTreeMap::new();
+        for (name, registration) in self.by_name.iter() {
+            match (registration.save)(world, entity) {
+                Some(Ok(value)) => {
+                    components.insert(name.clone(), value);
+                }
+                Some(Err(err)) => return Err((name.clone(), err)),
+                None => {}
+            }
+        }
+        Ok(components)
+    }
+
+    /// Inserts the component `name` parsed from `value`; `Ok(false)` if the name is unknown.
+    pub fn load(&self, world: &mut World, entity: Entity, name: &str, value: Value) -> Result<bool, String> {
+        match self.by_name.get(name) {
+            Some(registration) => (registration.load)(world, entity, value).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Removes the component `name`; false if it is unknown or was not attached.
+    pub fn remove(&self, world: &mut World, entity: Entity, name: &str) -> bool {
+        match self.by_name.get(name) {
+            Some(registration) => (registration.remove)(world, entity),
+            None => false,
+        }
+    }
+}
+
+fn save_component<T: Component + Serialize>(world: &World, entity: Entity) -> Option<Result<Value, String>> {
+    let component = world.get::<T>(entity)?;
+    // Going through a string writes f32s in their shortest form (0.1 rather than
+    // 0.10000000149011612), which keeps text scenes readable and diffs small.
+    let encoded = serde_json::to_string(&*component).and_then(|json| serde_json::from_str(&json));
+    Some(encoded.map_err(|err| err.to_string()))
+}
+
+fn load_component<T: Component + DeserializeOwned>(world: &mut World, entity: Entity, value: Value) -> Result<(), String> {
+    let component: T = serde_json::from_value(value).map_err(|err| err.to_string())?;
+    world.insert(entity, component);
+    Ok(())
+}
+
+fn remove_component<T: Component>(world: &mut World, entity: Entity) -> bool {
+    world.remove::<T>(entity).is_some()
+}
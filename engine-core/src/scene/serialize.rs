@@ -0,0 +1,294 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    hierarchy_order, instance_overrides, parent_of, set_parent, spawn_instance, ComponentRegistry, EntityId,
+    PrefabInstance, PrefabLibrary, PrefabLink, PrefabMember, SceneError, UnknownComponents,
+};
+use crate::ecs::{Entity, World};
+use crate::vfs::{Vfs, VfsPath};
+
+/// Version 2 added prefab instances.
+pub const SCENE_FORMAT_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneFormat {
+    /// Human-diffable text, the format scenes and prefabs are checked in as.
+    Yaml,
+    /// Compact binary for shipped builds.
+    MessagePack,
+}
+
+impl SceneFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "scene" | "prefab" | "yaml" | "yml" => Some(SceneFormat::Yaml),
+            "sceneb" | "msgpack" => Some(SceneFormat::MessagePack),
+            _ => None,
+        }
+    }
+}
+
+/// A saved set of entities. Entities are listed parents first with siblings in order, so
+/// spawning them in sequence rebuilds the same hierarchy.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Scene {
+    pub version: u32,
+    pub entities: Vec<SceneEntity>,
+}
+
+/// One saved entity. For a prefab instance only the root is listed: `prefab` names the source
+/// and the per-instance overrides, and the rest of the instance is rebuilt from the prefab.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SceneEntity {
+    pub id: EntityId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<EntityId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefab: Option<PrefabLink>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub components: BTreeMap<String, Value>,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene {
+            version: SCENE_FORMAT_VERSION,
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl Scene {
+    /// Captures every entity that has an `EntityId`. A parent without one is skipped and its
+    /// saved children become roots.
+    pub fn from_world(world: &World, registry: &ComponentRegistry) -> Result<Scene, SceneError> {
+        let mut scene = Scene::default();
+        for (entity, _) in hierarchy_order(world) {
+            if let Some(saved) = capture(world, registry, entity)? {
+                scene.entities.push(saved);
+            }
+        }
+        Ok(scene)
+    }
+
+    /// Spawns the scene's entities into `world`, returning them in scene order. Nothing is
+    /// spawned if any component or parent link fails to load. Fails on prefab instances; use
+    /// `spawn_with_prefabs` for scenes that have them.
+    pub fn spawn(&self, world: &mut World, registry: &ComponentRegistry) -> Result<Vec<Entity>, SceneError> {
+        self.spawn_inner(world, registry, None)
+    }
+
+    pub fn spawn_with_prefabs(
+        &self,
+        world: &mut World,
+        registry: &ComponentRegistry,
+        prefabs: &mut PrefabLibrary,
+    ) -> Result<Vec<Entity>, SceneError> {
+        self.spawn_inner(world, registry, Some(prefabs))
+    }
+
+    fn spawn_inner(
+        &self,
+        world: &mut World,
+        registry: &ComponentRegistry,
+        mut prefabs: Option<&mut PrefabLibrary>,
+    ) -> Result<Vec<Entity>, SceneError> {
+        let mut seen = HashSet::new();
+        if let Some(saved) = self.entities.iter().find(|saved| !seen.insert(saved.id)) {
+            return Err(SceneError::DuplicateId(saved.id));
+        }
+
+        let mut spawned = Vec::with_capacity(self.entities.len());
+        // Prefab members are not listed in the scene but other entities can be parented to them.
+        let mut ids: HashMap<EntityId, Entity> = HashMap::new();
+        for saved in self.entities.iter() {
+            let entity = world.spawn((saved.id,));
+            spawned.push(entity);
+            ids.insert(saved.id, entity);
+            let result = match (&saved.prefab, prefabs.as_deref_mut()) {
+                (Some(link), Some(prefabs)) => spawn_instance(world, registry, prefabs, entity, link).map(|members| {
+                    ids.extend(members);
+                }),
+                (Some(link), None) => Err(SceneError::Prefab(format!(
+                    "entity {} is an instance of '{}' but no prefab library was given",
+                    saved.id,
+                    link.source.path().display()
+                ))),
+                (None, _) => write_components(world, registry, entity, saved.id, &saved.components),
+            };
+            if let Err(err) = result {
+                despawn_all(world, &spawned);
+                return Err(err);
+            }
+        }
+
+        for (saved, entity) in self.entities.iter().zip(spawned.iter()) {
+            let parent = match saved.parent {
+                Some(parent) => parent,
+                None => continue,
+            };
+            match ids.get(&parent) {
+                Some(parent) => {
+                    if let Err(err) = set_parent(world, *entity, Some(*parent)) {
+                        despawn_all(world, &spawned);
+                        return Err(err.into());
+                    }
+                }
+                None => log::warn!("Entity {} has missing parent {}, spawning it as a root", saved.id, parent),
+            }
+        }
+        Ok(spawned)
+    }
+
+    pub fn to_bytes(&self, format: SceneFormat) -> Result<Vec<u8>, SceneError> {
+        match format {
+            SceneFormat::Yaml => serde_yaml::to_string(self)
+                .map(String::into_bytes)
+                .map_err(|err| SceneError::Encode(err.to_string())),
+            // Named fields keep binary scenes readable after fields are added or reordered.
+            SceneFormat::MessagePack => rmp_serde::to_vec_named(self).map_err(|err| SceneError::Encode(err.to_string())),
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8], format: SceneFormat) -> Result<Scene, SceneError> {
+        let scene: Scene = match format {
+            SceneFormat::Yaml => serde_yaml::from_slice(bytes).map_err(|err| SceneError::Parse(err.to_string()))?,
+            SceneFormat::MessagePack => rmp_serde::from_slice(bytes).map_err(|err| SceneError::Parse(err.to_string()))?,
+        };
+        if scene.version > SCENE_FORMAT_VERSION {
+            return Err(SceneError::UnsupportedVersion {
+                found: scene.version,
+                supported: SCENE_FORMAT_VERSION,
+            });
+        }
+        Ok(scene)
+    }
+
+    pub fn read(vfs: &Vfs, path: &VfsPath) -> Result<Scene, SceneError> {
+        let format = format_of(path)?;
+        Self::from_bytes(&vfs.read(path)?, format)
+    }
+
+    /// `Vfs::write` goes through a temporary file, so a failed save never leaves a truncated scene.
+    pub fn write(&self, vfs: &Vfs, path: &VfsPath) -> Result<(), SceneError> {
+        vfs.write(path, &self.to_bytes(format_of(path)?)?)?;
+        Ok(())
+    }
+}
+
+fn format_of(path: &VfsPath) -> Result<SceneFormat, SceneError> {
+    SceneFormat::from_path(path.path()).ok_or_else(|| SceneError::UnknownFormat(path.to_string()))
+}
+
+/// Registered and unknown components of `entity` by name, as they are written to scene files.
+pub(crate) fn entity_components(
+    world: &World,
+    registry: &ComponentRegistry,
+    entity: Entity,
+    id: EntityId,
+) -> Result<BTreeMap<String, Value>, SceneError> {
+    let mut components = world
+        .get::<UnknownComponents>(entity)
+        .map_or_else(BTreeMap::new, |unknown| unknown.0.clone());
+    let known = registry
+        .save(world, entity)
+        .map_err(|(name, error)| SceneError::Component { name, entity: id, error })?;
+    components.extend(known);
+    Ok(components)
+}
+
+/// Makes the registered and unknown components of `entity` exactly `components`. Components
+/// the registry does not know of, like `Parent` or `GlobalTransform`, are left alone.
+pub(crate) fn write_components(
+    world: &mut World,
+    registry: &ComponentRegistry,
+    entity: Entity,
+    id: EntityId,
+    components: &BTreeMap<String, Value>,
+) -> Result<(), SceneError> {
+    let stale: Vec<String> = registry
+        .names()
+        .filter(|name| !components.contains_key(*name))
+        .map(str::to_string)
+        .collect();
+    for name in stale {
+        registry.remove(world, entity, &name);
+    }
+    let mut unknown = BTreeMap::new();
+    for (name, value) in components.iter() {
+        match registry.load(world, entity, name, value.clone()) {
+            Ok(true) => {}
+            Ok(false) => {
+                unknown.insert(name.clone(), value.clone());
+            }
+            Err(error) => {
+                return Err(SceneError::Component {
+                    name: name.clone(),
+                    entity: id,
+                    error,
+                })
+            }
+        }
+    }
+    if unknown.is_empty() {
+        world.remove::<UnknownComponents>(entity);
+    } else {
+        log::debug!("Entity {} keeps unregistered components {:?}", id, unknown.keys().collect::<Vec<_>>());
+        world.insert(entity, UnknownComponents(unknown));
+    }
+    Ok(())
+}
+
+/// Rolls back a failed spawn; entities already parented to one another are skipped once gone.
+fn despawn_all(world: &mut World, spawned: &[Entity]) {
+    for entity in spawned.iter() {
+        super::despawn_recursive(world, *entity);
+    }
+}
+
+pub(crate) fn capture(world: &World, registry: &ComponentRegistry, entity: Entity) -> Result<Option<SceneEntity>, SceneError> {
+    let id = match world.get::<EntityId>(entity) {
+        Some(id) => *id,
+        None => return Ok(None),
+    };
+    // Members are rebuilt from their instance's prefab and overrides.
+    if world.has::<PrefabMember>(entity) {
+        return Ok(None);
+    }
+    let parent = parent_of(world, entity).and_then(|parent| world.get::<EntityId>(parent).map(|id| *id));
+    if world.has::<PrefabInstance>(entity) {
+        let source = world.get::<PrefabInstance>(entity).unwrap().source().clone();
+        return Ok(Some(SceneEntity {
+            id,
+            parent,
+            prefab: Some(PrefabLink {
+                source,
+                overrides: instance_overrides(world, registry, entity)?,
+            }),
+            components: BTreeMap::new(),
+        }));
+    }
+    Ok(Some(SceneEntity {
+        id,
+        parent,
+        prefab: None,
+        components: entity_components(world, registry, entity, id)?,
+    }))
+}
+
+pub fn save_scene(world: &World, registry: &ComponentRegistry, vfs: &Vfs, path: &VfsPath) -> Result<(), SceneError> {
+    Scene::from_world(world, registry)?.write(vfs, path)
+}
+
+pub fn load_scene(
+    world: &mut World,
+    registry: &ComponentRegistry,
+    vfs: &Vfs,
+    path: &VfsPath,
+) -> Result<Vec<Entity>, SceneError> {
+    Scene::read(vfs, path)?.spawn(world, registry)
+}
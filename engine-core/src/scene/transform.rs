@@ -0,0 +1,143 @@
+use glam::{Affine3A, Mat4, Quat, Vec3};
+use serde_derive::{Deserialize, Serialize};
+
+/// Position, rotation and scale relative to the parent entity, or to the world for roots.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    pub fn from_xyz(x: f32, y: f32, z: f32) -> Self {
+        Self::from_translation(Vec3::new(x, y, z))
+    }
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Transform {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn from_rotation(rotation: Quat) -> Self {
+        Transform {
+            rotation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        Transform {
+            scale,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Decomposes `affine`; shear, which a `Transform` cannot express, is lost.
+    pub fn from_affine(affine: &Affine3A) -> Self {
+        let (scale, rotation, translation) = affine.to_scale_rotation_translation();
+        Transform {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn with_translation(mut self, translation: Vec3) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn compute_affine(&self) -> Affine3A {
+        Affine3A::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    pub fn compute_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    /// Local -Z, the direction cameras and lights face.
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * -Vec3::Z
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    pub fn rotate(&mut self, rotation: Quat) {
+        self.rotation = (rotation * self.rotation).normalize();
+    }
+
+    /// Rotates and translates around `point`, as a gizmo does when pivoting a selection.
+    pub fn rotate_around(&mut self, point: Vec3, rotation: Quat) {
+        self.translation = point + rotation * (self.translation - point);
+        self.rotate(rotation);
+    }
+}
+
+/// World-space transform, written by transform propagation from the entity's `Transform` and
+/// its ancestors. Read it for rendering and picking; edit `Transform` instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform(Affine3A);
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        GlobalTransform(Affine3A::IDENTITY)
+    }
+}
+
+impl GlobalTransform {
+    pub(crate) fn from_affine(affine: Affine3A) -> Self {
+        GlobalTransform(affine)
+    }
+
+    pub fn affine(&self) -> Affine3A {
+        self.0
+    }
+
+    pub fn compute_matrix(&self) -> Mat4 {
+        Mat4::from(self.0)
+    }
+
+    pub fn translation(&self) -> Vec3 {
+        self.0.translation.into()
+    }
+
+    pub fn to_transform(&self) -> Transform {
+        Transform::from_affine(&self.0)
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.0.transform_point3(point)
+    }
+}
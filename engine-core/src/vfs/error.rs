@@ -0,0 +1,29 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VfsError {
+    /// Not of the form `scheme://relative/path`, or escaping the mount with `..`.
+    InvalidPath(String),
+    NotMounted(String),
+    NotFound(String),
+    Io(String),
+    /// Every source that could hold the path is read-only.
+    ReadOnly(String),
+    /// A corrupt or unsupported pack archive.
+    Pack(String),
+}
+
+impl Display for VfsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VfsError::InvalidPath(path) => write!(f, "invalid virtual path '{}', expected scheme://path", path),
+            VfsError::NotMounted(scheme) => write!(f, "nothing is mounted at {}://", scheme),
+            VfsError::NotFound(path) => write!(f, "{} not found in any mounted source", path),
+            VfsError::Io(err) => write!(f, "{}", err),
+            VfsError::ReadOnly(path) => write!(f, "cannot write {}, its mount is read-only", path),
+            VfsError::Pack(err) => write!(f, "invalid pack archive: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for VfsError {}
@@ -0,0 +1,11 @@
+mod error;
+mod mount;
+mod pack;
+mod path;
+mod source;
+
+pub use error::*;
+pub use mount::*;
+pub use pack::*;
+pub use path::*;
+pub use source::*;
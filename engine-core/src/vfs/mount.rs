@@ -0,0 +1,197 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use super::{DirectorySource, PackArchive, VfsError, VfsPath, VfsSource, PACK_EXTENSION};
+
+/// Built-in engine data: the default config, input map and engine shaders.
+pub const ENGINE_SCHEME: &str = "engine";
+/// The open project: its config, assets and scenes.
+pub const PROJECT_SCHEME: &str = "project";
+/// Per-user settings and saves, `<user config dir>/daybreak`.
+pub const USER_SCHEME: &str = "user";
+
+struct Mount {
+    source: Arc<dyn VfsSource>,
+    priority: i32,
+}
+
+/// Maps mount point schemes such as `project://` to one or more sources. When several sources
+/// are mounted at the same scheme they overlay: reads try them from the highest priority down,
+/// so a patch archive mounted above the base archive replaces the files it contains. Clones
+/// share the same mounts.
+#[derive(Clone, Default)]
+pub struct Vfs {
+    mounts: Arc<RwLock<BTreeMap<String, Vec<Mount>>>>,
+}
+
+impl Vfs {
+    pub fn new() -> Self {
+        Vfs::default()
+    }
+
+    /// The usual mounts: `engine://` over the engine defaults, `project://` over the project
+    /// when there is one and a writable `user://`. Pack archives found directly inside those
+    /// directories are mounted above them.
+    pub fn standard(engine_dir: &Path, project_dir: Option<&Path>) -> Result<Self, VfsError> {
+        let vfs = Vfs::new();
+        vfs.mount_dir(ENGINE_SCHEME, engine_dir, 0)?;
+        if let Some(project_dir) = project_dir {
+            vfs.mount_dir(PROJECT_SCHEME, project_dir, 0)?;
+        }
+        vfs.mount_user_dir();
+        Ok(vfs)
+    }
+
+    /// Mounts `source` at `scheme`. Among sources of equal priority the newest wins.
+    pub fn mount<S: VfsSource + 'static>(&self, scheme: &str, source: S, priority: i32) {
+        log::info!("Mounted {} at {}:// (priority {})", source.describe(), scheme, priority);
+        let mut mounts = self.mounts.write().unwrap();
+        let sources = mounts.entry(scheme.to_string()).or_default();
+        let index = sources.iter().position(|mount| mount.priority <= priority).unwrap_or(sources.len());
+        sources.insert(
+            index,
+            Mount {
+                source: Arc::new(source),
+                priority,
+            },
+        );
+    }
+
+    /// Mounts the writable directory `dir` at `scheme`, then every pack archive directly
+    /// inside it one priority higher, later file names above earlier ones.
+    pub fn mount_dir(&self, scheme: &str, dir: &Path, priority: i32) -> Result<(), VfsError> {
+        self.mount(scheme, DirectorySource::writable(dir), priority);
+        let mut packs: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file() && path.extension().is_some_and(|extension| extension == PACK_EXTENSION))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        packs.sort();
+        for pack in packs {
+            self.mount_pack(scheme, &pack, priority + 1)?;
+        }
+        Ok(())
+    }
+
+    pub fn mount_pack(&self, scheme: &str, path: &Path, priority: i32) -> Result<(), VfsError> {
+        let archive = PackArchive::open(path)?;
+        self.mount(scheme, archive, priority);
+        Ok(())
+    }
+
+    /// Mounts `user://` unless something already is; skipped when the platform has no user
+    /// config directory.
+    pub fn mount_user_dir(&self) {
+        if self.is_mounted(USER_SCHEME) {
+            return;
+        }
+        match dirs::config_dir() {
+            Some(dir) => self.mount(USER_SCHEME, DirectorySource::writable(dir.join("daybreak")), 0),
+            None => log::warn!("Could not determine the user config directory, user:// is not mounted"),
+        }
+    }
+
+    /// Removes every source mounted at `scheme` and returns how many there were.
+    pub fn unmount(&self, scheme: &str) -> usize {
+        self.mounts.write().unwrap().remove(scheme).map_or(0, |sources| sources.len())
+    }
+
+    pub fn is_mounted(&self, scheme: &str) -> bool {
+        self.mounts.read().unwrap().contains_key(scheme)
+    }
+
+    /// `(scheme, priority, source)` of every mount, highest priority first per scheme.
+    pub fn mounts(&self) -> Vec<(String, i32, String)> {
+        let mounts = self.mounts.read().unwrap();
+        mounts
+            .iter()
+            .flat_map(|(scheme, sources)| {
+                sources
+                    .iter()
+                    .map(move |mount| (scheme.clone(), mount.priority, mount.source.describe()))
+            })
+            .collect()
+    }
+
+    pub fn read<P: AsRef<str>>(&self, path: P) -> Result<Vec<u8>, VfsError> {
+        let path = VfsPath::parse(path.as_ref())?;
+        for source in self.sources(&path)? {
+            if let Some(bytes) = source.read(path.path())? {
+                return Ok(bytes);
+            }
+        }
+        Err(VfsError::NotFound(path.to_string()))
+    }
+
+    pub fn read_to_string<P: AsRef<str>>(&self, path: P) -> Result<String, VfsError> {
+        let bytes = self.read(path.as_ref())?;
+        String::from_utf8(bytes).map_err(|err| VfsError::Io(format!("{}: {}", path.as_ref(), err)))
+    }
+
+    pub fn exists<P: AsRef<str>>(&self, path: P) -> bool {
+        match VfsPath::parse(path.as_ref()).and_then(|path| Ok((self.sources(&path)?, path))) {
+            Ok((sources, path)) => sources.iter().any(|source| source.exists(path.path())),
+            Err(_) => false,
+        }
+    }
+
+    /// Every file below `dir` across all overlaid sources, each listed once.
+    pub fn list<P: AsRef<str>>(&self, dir: P) -> Result<Vec<VfsPath>, VfsError> {
+        let dir = VfsPath::parse(dir.as_ref())?;
+        let mut files = BTreeSet::new();
+        for source in self.sources(&dir)? {
+            for file in source.list(dir.path()) {
+                files.insert(VfsPath::new(dir.scheme(), file)?);
+            }
+        }
+        Ok(files.into_iter().collect())
+    }
+
+    /// Writes to the highest priority source that accepts writes, normally a directory.
+    pub fn write<P: AsRef<str>>(&self, path: P, bytes: &[u8]) -> Result<(), VfsError> {
+        let path = VfsPath::parse(path.as_ref())?;
+        for source in self.sources(&path)? {
+            match source.write(path.path(), bytes) {
+                Err(VfsError::ReadOnly(_)) => continue,
+                result => return result,
+            }
+        }
+        Err(VfsError::ReadOnly(path.to_string()))
+    }
+
+    /// The file on disk `path` reads from: the highest priority source holding it, or, when
+    /// none does, where the highest priority directory would create it. `None` when the file
+    /// comes from an archive, which also means it cannot be watched or written in place.
+    pub fn native_path<P: AsRef<str>>(&self, path: P) -> Option<PathBuf> {
+        let path = VfsPath::parse(path.as_ref()).ok()?;
+        let sources = self.sources(&path).ok()?;
+        match sources.iter().find(|source| source.exists(path.path())) {
+            Some(source) => source.native_path(path.path()),
+            None => sources.iter().find_map(|source| source.native_path(path.path())),
+        }
+    }
+
+    fn sources(&self, path: &VfsPath) -> Result<Vec<Arc<dyn VfsSource>>, VfsError> {
+        let mounts = self.mounts.read().unwrap();
+        let sources = mounts
+            .get(path.scheme())
+            .ok_or_else(|| VfsError::NotMounted(path.scheme().to_string()))?;
+        Ok(sources.iter().map(|mount| mount.source.clone()).collect())
+    }
+}
+
+impl std::fmt::Debug for Vfs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mounts: Vec<String> = self
+            .mounts()
+            .into_iter()
+            .map(|(scheme, priority, source)| format!("{}://={} ({})", scheme, source, priority))
+            .collect();
+        f.debug_struct("Vfs").field("mounts", &mounts).finish()
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+
+use super::{VfsError, VfsSource};
+
+pub const PACK_EXTENSION: &str = "dpak";
+pub const PACK_FORMAT_VERSION: u32 = 1;
+
+const PACK_MAGIC: &[u8; 4] = b"DPAK";
+/// Magic, version, entry count and index offset.
+const HEADER_SIZE: u64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackCompression {
+    None,
+    Deflate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackEntry {
+    pub offset: u64,
+    /// Bytes in the archive, after compression.
+    pub stored_size: u64,
+    pub size: u64,
+    pub compression: PackCompression,
+    /// CRC-32 of the uncompressed contents.
+    pub crc: u32,
+}
+
+/// A read-only archive of files for shipping builds. The layout is a header (magic, version,
+/// entry count, index offset), the file contents back to back and an index at the end that
+/// maps every path to its offset, sizes, compression and checksum. Mounted like a directory;
+/// only the index is held in memory.
+pub struct PackArchive {
+    path: PathBuf,
+    file: Mutex<File>,
+    entries: BTreeMap<PathBuf, PackEntry>,
+}
+
+impl PackArchive {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, VfsError> {
+        let path = path.as_ref().to_path_buf();
+        let pack_error = |err: String| VfsError::Pack(format!("{}: {}", path.display(), err));
+        let mut file = File::open(&path).map_err(|err| VfsError::Io(format!("{}: {}", path.display(), err)))?;
+        let mut header = [0; HEADER_SIZE as usize];
+        file.read_exact(&mut header).map_err(|err| pack_error(err.to_string()))?;
+        let mut reader = Reader(&header[..]);
+        if reader.take(4) != Some(PACK_MAGIC) {
+            return Err(pack_error("not a pack archive".to_string()));
+        }
+        let version = reader.u32().unwrap();
+        if version != PACK_FORMAT_VERSION {
+            return Err(pack_error(format!(
+                "format version {}, this build reads {}",
+                version, PACK_FORMAT_VERSION
+            )));
+        }
+        let count = reader.u32().unwrap();
+        let index_offset = reader.u64().unwrap();
+
+        let mut index = Vec::new();
+        file.seek(SeekFrom::Start(index_offset))
+            .and_then(|_| file.read_to_end(&mut index))
+            .map_err(|err| pack_error(err.to_string()))?;
+        let mut reader = Reader(&index[..]);
+        let mut entries = BTreeMap::new();
+        for _ in 0..count {
+            let (name, entry) = read_entry(&mut reader).ok_or_else(|| pack_error("truncated index".to_string()))?;
+            let end = entry.offset.checked_add(entry.stored_size);
+            if entry.offset < HEADER_SIZE || end.is_none_or(|end| end > index_offset) {
+                return Err(pack_error(format!("entry '{}' lies outside the data", name)));
+            }
+            entries.insert(PathBuf::from(name), entry);
+        }
+        Ok(PackArchive {
+            path,
+            file: Mutex::new(file),
+            entries,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&Path, &PackEntry)> {
+        self.entries.iter().map(|(path, entry)| (path.as_path(), entry))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl VfsSource for PackArchive {
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>, VfsError> {
+        let entry = match self.entries.get(path) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let pack_error =
+            |err: String| VfsError::Pack(format!("{} in {}: {}", path.display(), self.path.display(), err));
+        let mut stored = vec![0; entry.stored_size as usize];
+        {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(entry.offset))
+                .and_then(|_| file.read_exact(&mut stored))
+                .map_err(|err| pack_error(err.to_string()))?;
+        }
+        let bytes = match entry.compression {
+            PackCompression::None => stored,
+            PackCompression::Deflate => {
+                let mut bytes = Vec::with_capacity(entry.size as usize);
+                DeflateDecoder::new(&stored[..])
+                    .read_to_end(&mut bytes)
+                    .map_err(|err| pack_error(err.to_string()))?;
+                bytes
+            }
+        };
+        if bytes.len() as u64 != entry.size || crc32(&bytes) != entry.crc {
+            return Err(pack_error("contents do not match the index checksum".to_string()));
+        }
+        Ok(Some(bytes))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.entries.contains_key(path)
+    }
+
+    fn list(&self, dir: &Path) -> Vec<PathBuf> {
+        self.entries.keys().filter(|path| path.starts_with(dir)).cloned().collect()
+    }
+}
+
+/// Streams files into a new pack archive; the index is written by `finish`.
+pub struct PackWriter {
+    path: PathBuf,
+    file: File,
+    offset: u64,
+    entries: BTreeMap<String, PackEntry>,
+}
+
+impl PackWriter {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, VfsError> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| VfsError::Io(format!("{}: {}", parent.display(), err)))?;
+        }
+        let mut file = File::create(&path).map_err(|err| VfsError::Io(format!("{}: {}", path.display(), err)))?;
+        // Patched with the real counts by `finish`.
+        file.write_all(&[0; HEADER_SIZE as usize])
+            .map_err(|err| VfsError::Io(format!("{}: {}", path.display(), err)))?;
+        Ok(PackWriter {
+            path,
+            file,
+            offset: HEADER_SIZE,
+            entries: BTreeMap::new(),
+        })
+    }
+
+    /// Adds `bytes` under `path`, a relative path with `/` separators. With `Deflate` the data
+    /// is stored compressed only when that makes it smaller.
+    pub fn add(&mut self, path: &str, bytes: &[u8], compression: PackCompression) -> Result<(), VfsError> {
+        if path.is_empty() || path.starts_with('/') || path.split('/').any(|part| part == ".." || part == ".") {
+            return Err(VfsError::InvalidPath(path.to_string()));
+        }
+        let compressed = match compression {
+            PackCompression::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder
+                    .write_all(bytes)
+                    .and_then(|_| encoder.finish())
+                    .map(Some)
+                    .map_err(|err| VfsError::Io(err.to_string()))?
+            }
+            PackCompression::None => None,
+        };
+        let (stored, compression) = match compressed.as_deref() {
+            Some(compressed) if compressed.len() < bytes.len() => (compressed, PackCompression::Deflate),
+            _ => (bytes, PackCompression::None),
+        };
+        self.file
+            .write_all(stored)
+            .map_err(|err| VfsError::Io(format!("{}: {}", self.path.display(), err)))?;
+        self.entries.insert(
+            path.to_string(),
+            PackEntry {
+                offset: self.offset,
+                stored_size: stored.len() as u64,
+                size: bytes.len() as u64,
+                compression,
+                crc: crc32(bytes),
+            },
+        );
+        self.offset += stored.len() as u64;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<PathBuf, VfsError> {
+        let mut index = Vec::new();
+        for (path, entry) in self.entries.iter() {
+            index.extend_from_slice(&(path.len() as u32).to_le_bytes());
+            index.extend_from_slice(path.as_bytes());
+            index.extend_from_slice(&entry.offset.to_le_bytes());
+            index.extend_from_slice(&entry.stored_size.to_le_bytes());
+            index.extend_from_slice(&entry.size.to_le_bytes());
+            let compression: u32 = match entry.compression {
+                PackCompression::None => 0,
+                PackCompression::Deflate => 1,
+            };
+            index.extend_from_slice(&compression.to_le_bytes());
+            index.extend_from_slice(&entry.crc.to_le_bytes());
+        }
+        let mut header = PACK_MAGIC.to_vec();
+        header.extend_from_slice(&PACK_FORMAT_VERSION.to_le_bytes());
+        header.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        header.extend_from_slice(&self.offset.to_le_bytes());
+
+        let path = self.path.clone();
+        self.file
+            .write_all(&index)
+            .and_then(|_| self.file.seek(SeekFrom::Start(0)))
+            .and_then(|_| self.file.write_all(&header))
+            .and_then(|_| self.file.sync_all())
+            .map_err(|err| VfsError::Io(format!("{}: {}", path.display(), err)))?;
+        Ok(path)
+    }
+}
+
+fn read_entry(reader: &mut Reader) -> Option<(String, PackEntry)> {
+    let len = reader.u32()? as usize;
+    let name = String::from_utf8(reader.take(len)?.to_vec()).ok()?;
+    let offset = reader.u64()?;
+    let stored_size = reader.u64()?;
+    let size = reader.u64()?;
+    let compression = match reader.u32()? {
+        0 => PackCompression::None,
+        1 => PackCompression::Deflate,
+        _ => return None,
+    };
+    let crc = reader.u32()?;
+    Some((
+        name,
+        PackEntry {
+            offset,
+            stored_size,
+            size,
+            compression,
+            crc,
+        },
+    ))
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(bytes);
+    crc.sum()
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take(8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt::Display;
+use std::path::{Component, Path};
+use std::str::FromStr;
+
+use super::VfsError;
+
+const SEPARATOR: &str = "://";
+
+/// A location inside the virtual filesystem such as `project://scenes/intro.scene`: a mount
+/// point scheme and a relative path below it. Paths are normalised on creation, so `./` is
+/// dropped, `a/../b` becomes `b` and leaving the mount with `..` is an error.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VfsPath {
+    full: String,
+    scheme_len: usize,
+}
+
+impl VfsPath {
+    pub fn parse(path: &str) -> Result<Self, VfsError> {
+        let (scheme, relative) = path
+            .split_once(SEPARATOR)
+            .ok_or_else(|| VfsError::InvalidPath(path.to_string()))?;
+        VfsPath::new(scheme, relative).map_err(|_| VfsError::InvalidPath(path.to_string()))
+    }
+
+    pub fn new<P: AsRef<Path>>(scheme: &str, path: P) -> Result<Self, VfsError> {
+        let valid_scheme = !scheme.is_empty()
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+        if !valid_scheme {
+            return Err(VfsError::InvalidPath(format!("{}{}{}", scheme, SEPARATOR, path.as_ref().display())));
+        }
+        let mut parts: Vec<String> = Vec::new();
+        for component in path.as_ref().components() {
+            match component {
+                Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+                Component::CurDir => {}
+                Component::ParentDir if parts.pop().is_some() => {}
+                _ => {
+                    return Err(VfsError::InvalidPath(format!(
+                        "{}{}{}",
+                        scheme,
+                        SEPARATOR,
+                        path.as_ref().display()
+                    )))
+                }
+            }
+        }
+        Ok(VfsPath {
+            full: format!("{}{}{}", scheme, SEPARATOR, parts.join("/")),
+            scheme_len: scheme.len(),
+        })
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.full[..self.scheme_len]
+    }
+
+    /// The path below the mount point, always with `/` separators; empty for the mount root.
+    pub fn path(&self) -> &Path {
+        Path::new(self.relative())
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.relative().is_empty()
+    }
+
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> Result<Self, VfsError> {
+        VfsPath::new(self.scheme(), self.path().join(path))
+    }
+
+    pub fn parent(&self) -> Option<VfsPath> {
+        if self.is_root() {
+            return None;
+        }
+        let parent = self.path().parent().unwrap_or_else(|| Path::new(""));
+        Some(VfsPath::new(self.scheme(), parent).expect("parent of a valid path is valid"))
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.relative().rsplit('/').next().filter(|name| !name.is_empty())
+    }
+
+    pub fn extension(&self) -> Option<&str> {
+        self.path().extension().and_then(|extension| extension.to_str())
+    }
+
+    fn relative(&self) -> &str {
+        &self.full[self.scheme_len + SEPARATOR.len()..]
+    }
+}
+
+impl AsRef<str> for VfsPath {
+    fn as_ref(&self) -> &str {
+        &self.full
+    }
+}
+
+impl Display for VfsPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.full)
+    }
+}
+
+impl FromStr for VfsPath {
+    type Err = VfsError;
+
+    fn from_str(path: &str) -> Result<Self, VfsError> {
+        VfsPath::parse(path)
+    }
+}
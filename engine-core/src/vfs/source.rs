@@ -0,0 +1,115 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use super::VfsError;
+
+/// Backing store of a mount point. Paths are relative to the mount and use `/` separators.
+pub trait VfsSource: Send + Sync {
+    /// Shown in logs and `Vfs::mounts`, e.g. the directory or archive path.
+    fn describe(&self) -> String;
+
+    /// `Ok(None)` when the source does not hold `path`, so lower priority sources are tried.
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>, VfsError>;
+
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Every file below `dir`, recursively.
+    fn list(&self, dir: &Path) -> Vec<PathBuf>;
+
+    /// Where `path` lives on disk, for sources backed by plain files. Used for watching and
+    /// by code that needs a native path, such as config write-back.
+    fn native_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    fn write(&self, path: &Path, _bytes: &[u8]) -> Result<(), VfsError> {
+        Err(VfsError::ReadOnly(path.display().to_string()))
+    }
+}
+
+/// A directory on disk, read-only unless created with `writable`.
+pub struct DirectorySource {
+    root: PathBuf,
+    writable: bool,
+}
+
+impl DirectorySource {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        DirectorySource {
+            root: root.into(),
+            writable: false,
+        }
+    }
+
+    pub fn writable<P: Into<PathBuf>>(root: P) -> Self {
+        DirectorySource {
+            root: root.into(),
+            writable: true,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl VfsSource for DirectorySource {
+    fn describe(&self) -> String {
+        self.root.display().to_string()
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<Vec<u8>>, VfsError> {
+        let full = self.root.join(path);
+        match fs::read(&full) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(VfsError::Io(format!("{}: {}", full.display(), err))),
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.root.join(path).is_file()
+    }
+
+    fn list(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        list_dir(&self.root, dir, &mut files);
+        files
+    }
+
+    fn native_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(self.root.join(path))
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> Result<(), VfsError> {
+        if !self.writable {
+            return Err(VfsError::ReadOnly(path.display().to_string()));
+        }
+        let full = self.root.join(path);
+        let io_error = |err: std::io::Error| VfsError::Io(format!("{}: {}", full.display(), err));
+        if let Some(parent) = full.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        // Through a temporary file so readers never see half a file.
+        let mut temp = full.as_os_str().to_owned();
+        temp.push(".tmp");
+        fs::write(&temp, bytes).map_err(io_error)?;
+        fs::rename(&temp, &full).map_err(io_error)
+    }
+}
+
+fn list_dir(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(root.join(dir)) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let relative = dir.join(entry.file_name());
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => list_dir(root, &relative, files),
+            Ok(_) => files.push(relative),
+            Err(_) => {}
+        }
+    }
+}
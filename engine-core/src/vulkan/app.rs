@@ -1,47 +1,63 @@
 use ash::Entry;
 
-use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
-use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::Fullscreen;
+use winit::event_loop::EventLoop;
+use winit::window::{Fullscreen, Window};
 
 use crate::conf::{EngineConfig, WindowConfig};
+use crate::profile_scope;
 
-use super::{VulkanDebugUtil, VulkanInstance, VulkanPhysicalDevice, REQUIRED_LAYERS, VulkanLogicalDevice, VulkanSurface};
+use super::{set_validation_filter, VulkanDebugUtil, VulkanInstance, VulkanPhysicalDevice, REQUIRED_LAYERS, VulkanLogicalDevice, VulkanSurface};
 
+// Fields drop in declaration order, which is the reverse of creation: everything created
+// from the logical device goes first and the instance goes last.
 pub struct VulkanApp {
-    event_loop: EventLoop<()>,
-    _window: winit::window::Window,
-    _vk_instance: VulkanInstance,
-    _vk_debug: VulkanDebugUtil,
-    _vk_phy_device: VulkanPhysicalDevice,
     _vk_log_device: VulkanLogicalDevice,
-    _vk_surface: VulkanSurface
+    _vk_surface: VulkanSurface,
+    _vk_phy_device: VulkanPhysicalDevice,
+    _vk_debug: VulkanDebugUtil,
+    _vk_instance: VulkanInstance,
+    _window: Window,
+    vsync: bool,
+    event_loop: Option<EventLoop<()>>
 }
 
 impl VulkanApp {
     pub fn new(config: &EngineConfig) -> VulkanApp {
+        profile_scope!("VulkanApp::new");
+        set_validation_filter(&config.vulkan.validation);
+
         let entry = Entry::linked();
         VulkanDebugUtil::validate_layer_support(&entry, REQUIRED_LAYERS);
 
         let main_loop = EventLoop::new();
         let main_window = VulkanApp::init_window(&main_loop, &config.window);
-        let instance = VulkanInstance::new(&entry, &config.vulkan.instance);
+        let instance = {
+            profile_scope!("VulkanInstance::new");
+            VulkanInstance::new(&entry, &config.vulkan.instance)
+        };
         let debug_util = VulkanDebugUtil::new(&entry, &instance);
         let this_surface = VulkanSurface::new(&entry, &instance, &main_window);
-        let this_phys_device = VulkanPhysicalDevice::new(&instance, &this_surface, &config.vulkan.physical_device);
-        let this_log_device = VulkanLogicalDevice::new(&instance, &this_phys_device, &this_surface);
+        let this_phys_device = {
+            profile_scope!("VulkanPhysicalDevice::new");
+            VulkanPhysicalDevice::new(&instance, &this_surface, &config.vulkan.physical_device)
+        };
+        let this_log_device = {
+            profile_scope!("VulkanLogicalDevice::new");
+            VulkanLogicalDevice::new(&instance, &this_phys_device, &this_surface)
+        };
         VulkanApp {
-            event_loop: main_loop,
-            _window: main_window,
-            _vk_instance: instance,
-            _vk_debug: debug_util,
-            _vk_phy_device: this_phys_device,
             _vk_log_device: this_log_device,
-            _vk_surface: this_surface
+            _vk_surface: this_surface,
+            _vk_phy_device: this_phys_device,
+            _vk_debug: debug_util,
+            _vk_instance: instance,
+            _window: main_window,
+            vsync: config.window.vsync,
+            event_loop: Some(main_loop)
         }
     }
 
-    fn init_window(event_loop: &EventLoop<()>, config: &WindowConfig) -> winit::window::Window {
+    fn init_window(event_loop: &EventLoop<()>, config: &WindowConfig) -> Window {
         let fullscreen: Option<Fullscreen> = if config.fullscreen {
             Some(Fullscreen::Borderless(None))
         } else {
@@ -61,28 +77,62 @@ impl VulkanApp {
         result.unwrap()
     }
 
-    pub fn run(self) {
-        log::info!("Beginning game loop");
-        self.event_loop.run(move |event, _, control_flow| {
-            if let Event::WindowEvent { event, .. } = event {
-                match event {
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                    WindowEvent::KeyboardInput { input, .. } => {
-                        let KeyboardInput {
-                            virtual_keycode,
-                            state,
-                            ..
-                        } = input;
-                        if let (Some(VirtualKeyCode::Escape), ElementState::Pressed) =
-                            (virtual_keycode, state)
-                        {
-                            dbg!();
-                            *control_flow = ControlFlow::Exit
-                        };
-                    }
-                    _ => {}
-                }
-            }
-        })
+    pub fn get_logical_device(&self) -> &VulkanLogicalDevice {
+        &self._vk_log_device
+    }
+
+    pub fn get_physical_device(&self) -> &VulkanPhysicalDevice {
+        &self._vk_phy_device
+    }
+
+    pub fn get_instance(&self) -> &VulkanInstance {
+        &self._vk_instance
+    }
+
+    pub fn get_window(&self) -> &Window {
+        &self._window
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.vsync
+    }
+
+    /// Hands the event loop to whoever drives the frame, normally `Engine::run`. Only succeeds once.
+    pub fn take_event_loop(&mut self) -> Option<EventLoop<()>> {
+        self.event_loop.take()
+    }
+
+    /// Applies the window and validation settings that can change on a live device; everything
+    /// else keeps its startup value until the engine is restarted.
+    pub fn apply_config(&mut self, old: &EngineConfig, new: &EngineConfig) {
+        if old.window.title != new.window.title {
+            self._window.set_title(&new.window.title);
+        }
+        if old.window.width != new.window.width || old.window.height != new.window.height {
+            self._window
+                .set_inner_size(winit::dpi::LogicalSize::new(new.window.width, new.window.height));
+        }
+        if old.window.fullscreen != new.window.fullscreen {
+            self._window.set_fullscreen(if new.window.fullscreen {
+                Some(Fullscreen::Borderless(None))
+            } else {
+                None
+            });
+        }
+        if old.window.vsync != new.window.vsync {
+            // Picked up by the next swapchain (re)creation.
+            self.vsync = new.window.vsync;
+            log::info!("vsync {}", if new.window.vsync { "enabled" } else { "disabled" });
+        }
+        if old.vulkan.validation != new.vulkan.validation {
+            set_validation_filter(&new.vulkan.validation);
+        }
+    }
+}
+
+impl Drop for VulkanApp {
+    fn drop(&mut self) {
+        self._vk_log_device.wait_idle();
+        log::info!("Tearing down Vulkan");
     }
 }
@@ -0,0 +1,116 @@
+use ash::vk;
+
+use super::{allocate_memory, DeferredResource, SharedDeletionQueue, VulkanInstance, VulkanLogicalDevice, VulkanPhysicalDevice};
+
+pub struct VulkanBuffer {
+    device: ash::Device,
+    deletion_queue: SharedDeletionQueue,
+    buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    size: vk::DeviceSize,
+}
+
+impl VulkanBuffer {
+    /// Creates a buffer; when `queue_families` lists more than one family the buffer is
+    /// shared concurrently so graphics and async compute can use it without ownership transfers.
+    pub fn new(
+        instance: &VulkanInstance,
+        physical_device: &VulkanPhysicalDevice,
+        logical_device: &VulkanLogicalDevice,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        properties: vk::MemoryPropertyFlags,
+        queue_families: &[u32],
+    ) -> Self {
+        let device = logical_device.get_device();
+        let mut unique_families = queue_families.to_vec();
+        unique_families.sort_unstable();
+        unique_families.dedup();
+        let mut create_info = vk::BufferCreateInfo::builder()
+            .size(size)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        if unique_families.len() > 1 {
+            create_info = create_info
+                .sharing_mode(vk::SharingMode::CONCURRENT)
+                .queue_family_indices(&unique_families);
+        }
+
+        let buffer = unsafe {
+            let result = device.create_buffer(&create_info, None);
+            if result.is_err() {
+                log::error!("Failed to create buffer of {} bytes.", size);
+                panic!("{:?}", result.err());
+            }
+            result.unwrap()
+        };
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+        let memory = allocate_memory(device, instance, physical_device, requirements, properties);
+        unsafe {
+            device
+                .bind_buffer_memory(buffer, memory, 0)
+                .expect("Failed to bind buffer memory");
+        }
+
+        VulkanBuffer {
+            device: device.clone(),
+            deletion_queue: logical_device.get_deletion_queue().clone(),
+            buffer,
+            memory,
+            size,
+        }
+    }
+
+    pub fn get(&self) -> &vk::Buffer {
+        &self.buffer
+    }
+
+    pub fn size(&self) -> vk::DeviceSize {
+        self.size
+    }
+
+    /// Copies `data` into the start of a host visible buffer.
+    pub fn write<T: Copy>(&self, data: &[T]) {
+        let bytes = std::mem::size_of_val(data) as vk::DeviceSize;
+        if bytes > self.size {
+            log::error!("Attempted to write {} bytes into a buffer of {} bytes.", bytes, self.size);
+            panic!("Buffer write out of bounds");
+        }
+        unsafe {
+            let mapped = self
+                .device
+                .map_memory(self.memory, 0, bytes, vk::MemoryMapFlags::empty())
+                .expect("Failed to map buffer memory");
+            std::ptr::copy_nonoverlapping(data.as_ptr() as *const u8, mapped as *mut u8, bytes as usize);
+            self.device.unmap_memory(self.memory);
+        }
+    }
+
+    /// Reads `count` elements back from the start of a host visible buffer.
+    pub fn read<T: Copy + Default>(&self, count: usize) -> Vec<T> {
+        let bytes = (count * std::mem::size_of::<T>()) as vk::DeviceSize;
+        let mut data = vec![T::default(); count];
+        unsafe {
+            let mapped = self
+                .device
+                .map_memory(self.memory, 0, bytes, vk::MemoryMapFlags::empty())
+                .expect("Failed to map buffer memory");
+            std::ptr::copy_nonoverlapping(mapped as *const u8, data.as_mut_ptr() as *mut u8, bytes as usize);
+            self.device.unmap_memory(self.memory);
+        }
+        data
+    }
+}
+
+/// Views a plain `#[repr(C)]` value, e.g. a uniform block mirror, as the bytes to upload.
+pub fn bytes_of<T: Copy>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, std::mem::size_of::<T>()) }
+}
+
+impl Drop for VulkanBuffer {
+    fn drop(&mut self) {
+        let mut deletion_queue = self.deletion_queue.lock().unwrap();
+        deletion_queue.push(DeferredResource::Buffer(self.buffer));
+        deletion_queue.push(DeferredResource::Memory(self.memory));
+    }
+}
@@ -0,0 +1,473 @@
+use std::ffi::CString;
+use std::io::Cursor;
+
+use ash::vk;
+
+use crate::profile_scope;
+use crate::vfs::Vfs;
+
+use super::{DeferredResource, SharedDeletionQueue, VulkanLogicalDevice};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComputeBindingKind {
+    StorageBuffer,
+    UniformBuffer,
+    StorageImage,
+    SampledImage,
+    /// A texture read through a separate `Sampler` binding, as GLSL compiled by naga declares it.
+    Texture,
+    Sampler,
+}
+
+impl ComputeBindingKind {
+    fn descriptor_type(&self) -> vk::DescriptorType {
+        match self {
+            ComputeBindingKind::StorageBuffer => vk::DescriptorType::STORAGE_BUFFER,
+            ComputeBindingKind::UniformBuffer => vk::DescriptorType::UNIFORM_BUFFER,
+            ComputeBindingKind::StorageImage => vk::DescriptorType::STORAGE_IMAGE,
+            ComputeBindingKind::SampledImage => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            ComputeBindingKind::Texture => vk::DescriptorType::SAMPLED_IMAGE,
+            ComputeBindingKind::Sampler => vk::DescriptorType::SAMPLER,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ComputeBinding {
+    pub binding: u32,
+    pub kind: ComputeBindingKind,
+}
+
+pub struct ComputePipeline {
+    device: ash::Device,
+    deletion_queue: SharedDeletionQueue,
+    bindings: Vec<ComputeBinding>,
+    set_layout: vk::DescriptorSetLayout,
+    layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    descriptor_pool: vk::DescriptorPool,
+    push_constant_size: u32,
+}
+
+impl ComputePipeline {
+    pub fn new(
+        logical_device: &VulkanLogicalDevice,
+        spirv: &[u32],
+        entry_point: &str,
+        bindings: &[ComputeBinding],
+        push_constant_size: u32,
+        max_sets: u32,
+    ) -> Self {
+        let device = logical_device.get_device();
+        let layout_bindings: Vec<vk::DescriptorSetLayoutBinding> = bindings
+            .iter()
+            .map(|binding| {
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(binding.binding)
+                    .descriptor_type(binding.kind.descriptor_type())
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::COMPUTE)
+                    .build()
+            })
+            .collect();
+        let set_layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&layout_bindings);
+        let set_layout = unsafe {
+            device
+                .create_descriptor_set_layout(&set_layout_info, None)
+                .expect("Failed to create compute descriptor set layout")
+        };
+
+        let push_constant_ranges = [vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            offset: 0,
+            size: push_constant_size,
+        }];
+        let set_layouts = [set_layout];
+        let mut layout_info = vk::PipelineLayoutCreateInfo::builder().set_layouts(&set_layouts);
+        if push_constant_size > 0 {
+            layout_info = layout_info.push_constant_ranges(&push_constant_ranges);
+        }
+        let layout = unsafe {
+            device
+                .create_pipeline_layout(&layout_info, None)
+                .expect("Failed to create compute pipeline layout")
+        };
+
+        let module_info = vk::ShaderModuleCreateInfo::builder().code(spirv);
+        let module = unsafe {
+            let result = device.create_shader_module(&module_info, None);
+            if result.is_err() {
+                log::error!("Failed to create compute shader module.");
+                panic!("{:?}", result.err());
+            }
+            result.unwrap()
+        };
+        let entry_name = CString::new(entry_point).unwrap();
+        let stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(module)
+            .name(&entry_name);
+        let pipeline_info = [vk::ComputePipelineCreateInfo::builder()
+            .stage(stage.build())
+            .layout(layout)
+            .build()];
+        let pipeline = unsafe {
+            let result = device.create_compute_pipelines(vk::PipelineCache::null(), &pipeline_info, None);
+            device.destroy_shader_module(module, None);
+            match result {
+                Ok(pipelines) => pipelines[0],
+                Err((_, err)) => {
+                    log::error!("Failed to create compute pipeline.");
+                    panic!("{:?}", err);
+                }
+            }
+        };
+
+        let mut pool_sizes: Vec<vk::DescriptorPoolSize> = Vec::new();
+        for binding in bindings.iter() {
+            let descriptor_type = binding.kind.descriptor_type();
+            match pool_sizes.iter_mut().find(|size| size.ty == descriptor_type) {
+                Some(size) => size.descriptor_count += max_sets,
+                None => pool_sizes.push(vk::DescriptorPoolSize {
+                    ty: descriptor_type,
+                    descriptor_count: max_sets,
+                }),
+            }
+        }
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .max_sets(max_sets)
+            .pool_sizes(&pool_sizes);
+        let descriptor_pool = unsafe {
+            device
+                .create_descriptor_pool(&pool_info, None)
+                .expect("Failed to create compute descriptor pool")
+        };
+
+        log::info!("Successfully created compute pipeline '{}'", entry_point);
+        ComputePipeline {
+            device: device.clone(),
+            deletion_queue: logical_device.get_deletion_queue().clone(),
+            bindings: bindings.to_vec(),
+            set_layout,
+            layout,
+            pipeline,
+            descriptor_pool,
+            push_constant_size,
+        }
+    }
+
+    /// Reads SPIR-V from a virtual path such as `engine://shaders/cull.comp.spv`, so shaders
+    /// can ship inside pack archives.
+    pub fn from_vfs(
+        logical_device: &VulkanLogicalDevice,
+        vfs: &Vfs,
+        path: &str,
+        entry_point: &str,
+        bindings: &[ComputeBinding],
+        push_constant_size: u32,
+        max_sets: u32,
+    ) -> Self {
+        let spirv = vfs
+            .read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| ash::util::read_spv(&mut Cursor::new(bytes)).map_err(|err| err.to_string()));
+        if spirv.is_err() {
+            log::error!("Failed to read SPIR-V from {}", path);
+            panic!("{:?}", spirv.err());
+        }
+        Self::new(logical_device, &spirv.unwrap(), entry_point, bindings, push_constant_size, max_sets)
+    }
+
+    pub fn get(&self) -> &vk::Pipeline {
+        &self.pipeline
+    }
+
+    pub fn get_layout(&self) -> &vk::PipelineLayout {
+        &self.layout
+    }
+
+    pub fn allocate_set(&self) -> vk::DescriptorSet {
+        let set_layouts = [self.set_layout];
+        let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(self.descriptor_pool)
+            .set_layouts(&set_layouts);
+        unsafe {
+            let result = self.device.allocate_descriptor_sets(&allocate_info);
+            if result.is_err() {
+                log::error!("Compute descriptor pool exhausted.");
+                panic!("{:?}", result.err());
+            }
+            result.unwrap()[0]
+        }
+    }
+
+    pub fn bind_buffer(&self, set: vk::DescriptorSet, binding: u32, buffer: vk::Buffer, offset: vk::DeviceSize, range: vk::DeviceSize) {
+        let kind = self.binding_kind(binding);
+        let buffer_info = [vk::DescriptorBufferInfo {
+            buffer,
+            offset,
+            range,
+        }];
+        let write = vk::WriteDescriptorSet::builder()
+            .dst_set(set)
+            .dst_binding(binding)
+            .descriptor_type(kind.descriptor_type())
+            .buffer_info(&buffer_info)
+            .build();
+        unsafe { self.device.update_descriptor_sets(&[write], &[]) };
+    }
+
+    pub fn bind_image(&self, set: vk::DescriptorSet, binding: u32, view: vk::ImageView, sampler: vk::Sampler, layout: vk::ImageLayout) {
+        let kind = self.binding_kind(binding);
+        let image_info = [vk::DescriptorImageInfo {
+            sampler,
+            image_view: view,
+            image_layout: layout,
+        }];
+        let write = vk::WriteDescriptorSet::builder()
+            .dst_set(set)
+            .dst_binding(binding)
+            .descriptor_type(kind.descriptor_type())
+            .image_info(&image_info)
+            .build();
+        unsafe { self.device.update_descriptor_sets(&[write], &[]) };
+    }
+
+    fn binding_kind(&self, binding: u32) -> ComputeBindingKind {
+        match self.bindings.iter().find(|candidate| candidate.binding == binding) {
+            Some(found) => found.kind,
+            None => panic!("Compute pipeline has no binding {}", binding),
+        }
+    }
+}
+
+impl Drop for ComputePipeline {
+    fn drop(&mut self) {
+        let mut deletion_queue = self.deletion_queue.lock().unwrap();
+        deletion_queue.push(DeferredResource::DescriptorPool(self.descriptor_pool));
+        deletion_queue.push(DeferredResource::Pipeline(self.pipeline));
+        deletion_queue.push(DeferredResource::PipelineLayout(self.layout));
+        deletion_queue.push(DeferredResource::DescriptorSetLayout(self.set_layout));
+    }
+}
+
+/// Records dispatches on the compute queue and submits them with semaphore synchronization.
+pub struct ComputeContext {
+    device: ash::Device,
+    queue: vk::Queue,
+    queue_family: u32,
+    command_pool: vk::CommandPool,
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+}
+
+impl ComputeContext {
+    pub fn new(logical_device: &VulkanLogicalDevice) -> Self {
+        let device = logical_device.get_device();
+        let queue_family = logical_device.get_compute_queue_family();
+        let pool_info = vk::CommandPoolCreateInfo::builder()
+            .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+            .queue_family_index(queue_family);
+        let (command_pool, command_buffer, fence) = unsafe {
+            let command_pool = device
+                .create_command_pool(&pool_info, None)
+                .expect("Failed to create compute command pool");
+            let allocate_info = vk::CommandBufferAllocateInfo::builder()
+                .command_pool(command_pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1);
+            let command_buffer = device
+                .allocate_command_buffers(&allocate_info)
+                .expect("Failed to allocate compute command buffer")[0];
+            let fence_info = vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED);
+            let fence = device
+                .create_fence(&fence_info, None)
+                .expect("Failed to create compute fence");
+            (command_pool, command_buffer, fence)
+        };
+        ComputeContext {
+            device: device.clone(),
+            queue: *logical_device.get_compute_queue(),
+            queue_family,
+            command_pool,
+            command_buffer,
+            fence,
+        }
+    }
+
+    pub fn get_queue_family(&self) -> u32 {
+        self.queue_family
+    }
+
+    pub fn create_semaphore(&self) -> vk::Semaphore {
+        unsafe {
+            self.device
+                .create_semaphore(&vk::SemaphoreCreateInfo::default(), None)
+                .expect("Failed to create semaphore")
+        }
+    }
+
+    /// Waits for the previous submission and starts recording a new batch of dispatches.
+    pub fn begin(&mut self) -> ComputeCommands<'_> {
+        unsafe {
+            self.device
+                .wait_for_fences(&[self.fence], true, u64::MAX)
+                .expect("Failed to wait for compute fence");
+            self.device
+                .reset_command_buffer(self.command_buffer, vk::CommandBufferResetFlags::empty())
+                .expect("Failed to reset compute command buffer");
+            let begin_info = vk::CommandBufferBeginInfo::builder()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+            self.device
+                .begin_command_buffer(self.command_buffer, &begin_info)
+                .expect("Failed to begin compute command buffer");
+        }
+        ComputeCommands {
+            device: &self.device,
+            command_buffer: self.command_buffer,
+        }
+    }
+
+    /// Submits the recorded batch, waiting on `wait` (e.g. the graphics frame that produced the
+    /// inputs) and signalling `signal` for the graphics queue to consume the results.
+    pub fn submit(&mut self, wait: &[(vk::Semaphore, vk::PipelineStageFlags)], signal: &[vk::Semaphore]) -> vk::Fence {
+        profile_scope!("ComputeContext::submit", "submit");
+        let wait_semaphores: Vec<vk::Semaphore> = wait.iter().map(|(semaphore, _)| *semaphore).collect();
+        let wait_stages: Vec<vk::PipelineStageFlags> = wait.iter().map(|(_, stage)| *stage).collect();
+        let command_buffers = [self.command_buffer];
+        let submit_info = [vk::SubmitInfo::builder()
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
+            .command_buffers(&command_buffers)
+            .signal_semaphores(signal)
+            .build()];
+        unsafe {
+            self.device
+                .end_command_buffer(self.command_buffer)
+                .expect("Failed to end compute command buffer");
+            self.device
+                .reset_fences(&[self.fence])
+                .expect("Failed to reset compute fence");
+            let result = self.device.queue_submit(self.queue, &submit_info, self.fence);
+            if result.is_err() {
+                log::error!("Failed to submit compute work.");
+                panic!("{:?}", result.err());
+            }
+        }
+        self.fence
+    }
+
+    pub fn wait_idle(&self) {
+        unsafe {
+            self.device
+                .wait_for_fences(&[self.fence], true, u64::MAX)
+                .expect("Failed to wait for compute fence");
+        }
+    }
+}
+
+impl Drop for ComputeContext {
+    fn drop(&mut self) {
+        self.wait_idle();
+        unsafe {
+            self.device.destroy_fence(self.fence, None);
+            self.device.destroy_command_pool(self.command_pool, None);
+        }
+        log::debug!("Sucessfully destroyed compute context");
+    }
+}
+
+pub struct ComputeCommands<'a> {
+    device: &'a ash::Device,
+    command_buffer: vk::CommandBuffer,
+}
+
+impl<'a> ComputeCommands<'a> {
+    /// Records into a command buffer owned elsewhere, e.g. the one a render graph pass executes in.
+    pub fn new(device: &'a ash::Device, command_buffer: vk::CommandBuffer) -> Self {
+        ComputeCommands {
+            device,
+            command_buffer,
+        }
+    }
+
+    pub fn get(&self) -> vk::CommandBuffer {
+        self.command_buffer
+    }
+
+    pub fn bind(&self, pipeline: &ComputePipeline, set: vk::DescriptorSet) -> &Self {
+        unsafe {
+            self.device
+                .cmd_bind_pipeline(self.command_buffer, vk::PipelineBindPoint::COMPUTE, pipeline.pipeline);
+            self.device.cmd_bind_descriptor_sets(
+                self.command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                pipeline.layout,
+                0,
+                &[set],
+                &[],
+            );
+        }
+        self
+    }
+
+    pub fn push_constants(&self, pipeline: &ComputePipeline, data: &[u8]) -> &Self {
+        if data.len() as u32 > pipeline.push_constant_size {
+            log::error!(
+                "Push constant data of {} bytes exceeds declared size {}.",
+                data.len(),
+                pipeline.push_constant_size
+            );
+            panic!("Push constant overflow");
+        }
+        unsafe {
+            self.device.cmd_push_constants(
+                self.command_buffer,
+                pipeline.layout,
+                vk::ShaderStageFlags::COMPUTE,
+                0,
+                data,
+            );
+        }
+        self
+    }
+
+    pub fn dispatch(&self, group_count_x: u32, group_count_y: u32, group_count_z: u32) -> &Self {
+        unsafe {
+            self.device
+                .cmd_dispatch(self.command_buffer, group_count_x, group_count_y, group_count_z);
+        }
+        self
+    }
+
+    /// Dispatches with group counts read from a `VkDispatchIndirectCommand` in `buffer`.
+    pub fn dispatch_indirect(&self, buffer: vk::Buffer, offset: vk::DeviceSize) -> &Self {
+        unsafe {
+            self.device.cmd_dispatch_indirect(self.command_buffer, buffer, offset);
+        }
+        self
+    }
+
+    /// Makes storage writes of earlier dispatches visible to later ones in the same batch,
+    /// including as indirect arguments.
+    pub fn storage_barrier(&self) -> &Self {
+        let barrier = [vk::MemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE | vk::AccessFlags::INDIRECT_COMMAND_READ,
+            )
+            .build()];
+        unsafe {
+            self.device.cmd_pipeline_barrier(
+                self.command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::DRAW_INDIRECT,
+                vk::DependencyFlags::empty(),
+                &barrier,
+                &[],
+                &[],
+            );
+        }
+        self
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use ash::vk;
+
+#[derive(Debug, Clone, Copy)]
+pub enum DeferredResource {
+    Buffer(vk::Buffer),
+    Image(vk::Image),
+    ImageView(vk::ImageView),
+    Sampler(vk::Sampler),
+    Memory(vk::DeviceMemory),
+    Pipeline(vk::Pipeline),
+    PipelineLayout(vk::PipelineLayout),
+    DescriptorSetLayout(vk::DescriptorSetLayout),
+    DescriptorPool(vk::DescriptorPool),
+    ShaderModule(vk::ShaderModule),
+    RenderPass(vk::RenderPass),
+    Framebuffer(vk::Framebuffer),
+    CommandPool(vk::CommandPool),
+    Semaphore(vk::Semaphore),
+    Fence(vk::Fence),
+}
+
+impl DeferredResource {
+    unsafe fn destroy(self, device: &ash::Device) {
+        match self {
+            DeferredResource::Buffer(buffer) => device.destroy_buffer(buffer, None),
+            DeferredResource::Image(image) => device.destroy_image(image, None),
+            DeferredResource::ImageView(view) => device.destroy_image_view(view, None),
+            DeferredResource::Sampler(sampler) => device.destroy_sampler(sampler, None),
+            DeferredResource::Memory(memory) => device.free_memory(memory, None),
+            DeferredResource::Pipeline(pipeline) => device.destroy_pipeline(pipeline, None),
+            DeferredResource::PipelineLayout(layout) => device.destroy_pipeline_layout(layout, None),
+            DeferredResource::DescriptorSetLayout(layout) => {
+                device.destroy_descriptor_set_layout(layout, None)
+            }
+            DeferredResource::DescriptorPool(pool) => device.destroy_descriptor_pool(pool, None),
+            DeferredResource::ShaderModule(module) => device.destroy_shader_module(module, None),
+            DeferredResource::RenderPass(render_pass) => device.destroy_render_pass(render_pass, None),
+            DeferredResource::Framebuffer(framebuffer) => device.destroy_framebuffer(framebuffer, None),
+            DeferredResource::CommandPool(pool) => device.destroy_command_pool(pool, None),
+            DeferredResource::Semaphore(semaphore) => device.destroy_semaphore(semaphore, None),
+            DeferredResource::Fence(fence) => device.destroy_fence(fence, None),
+        }
+    }
+}
+
+struct PendingDeletion {
+    frame: u64,
+    resource: DeferredResource,
+}
+
+/// Holds released Vulkan objects until the frame that last used them has finished on the GPU.
+#[derive(Default)]
+pub struct DeletionQueue {
+    pending: VecDeque<PendingDeletion>,
+    frame: u64,
+}
+
+pub type SharedDeletionQueue = Arc<Mutex<DeletionQueue>>;
+
+impl DeletionQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn shared() -> SharedDeletionQueue {
+        Arc::new(Mutex::new(Self::new()))
+    }
+
+    /// Sets the frame subsequently released resources are tagged with.
+    pub fn set_frame(&mut self, frame: u64) {
+        self.frame = frame;
+    }
+
+    pub fn push(&mut self, resource: DeferredResource) {
+        self.pending.push_back(PendingDeletion {
+            frame: self.frame,
+            resource,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Destroys every resource released during or before `completed_frame`.
+    pub fn collect(&mut self, device: &ash::Device, completed_frame: u64) -> usize {
+        let mut destroyed = 0;
+        while let Some(front) = self.pending.front() {
+            if front.frame > completed_frame {
+                break;
+            }
+            let pending = self.pending.pop_front().unwrap();
+            unsafe { pending.resource.destroy(device) };
+            destroyed += 1;
+        }
+        destroyed
+    }
+
+    /// Destroys everything regardless of frame; only valid once the device is idle.
+    pub fn flush(&mut self, device: &ash::Device) {
+        let count = self.pending.len();
+        for pending in self.pending.drain(..) {
+            unsafe { pending.resource.destroy(device) };
+        }
+        log::debug!("Sucessfully flushed {} deferred deletions", count);
+    }
+}
+
+pub const MAX_FRAMES_IN_FLIGHT: usize = 2;
+
+/// One fence per frame in flight; waiting on a slot's fence retires the frame that used it
+/// and lets the deletion queue release that frame's garbage.
+pub struct FrameFences {
+    device: ash::Device,
+    deletion_queue: SharedDeletionQueue,
+    fences: Vec<vk::Fence>,
+    frame: u64,
+}
+
+impl FrameFences {
+    pub fn new(device: &ash::Device, deletion_queue: SharedDeletionQueue) -> Self {
+        let fence_info = vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED);
+        let fences = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| unsafe {
+                device
+                    .create_fence(&fence_info, None)
+                    .expect("Failed to create frame fence")
+            })
+            .collect();
+        FrameFences {
+            device: device.clone(),
+            deletion_queue,
+            fences,
+            frame: 0,
+        }
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Waits until the frame slot is free, destroys the garbage of the frame that used it last
+    /// and returns the index of the frame starting now.
+    pub fn begin_frame(&mut self) -> u64 {
+        let fence = self.fences[self.frame as usize % MAX_FRAMES_IN_FLIGHT];
+        unsafe {
+            self.device
+                .wait_for_fences(&[fence], true, u64::MAX)
+                .expect("Failed to wait for frame fence");
+            self.device
+                .reset_fences(&[fence])
+                .expect("Failed to reset frame fence");
+        }
+        let mut deletion_queue = self.deletion_queue.lock().unwrap();
+        if self.frame >= MAX_FRAMES_IN_FLIGHT as u64 {
+            deletion_queue.collect(&self.device, self.frame - MAX_FRAMES_IN_FLIGHT as u64);
+        }
+        deletion_queue.set_frame(self.frame);
+        self.frame
+    }
+
+    /// Signals the frame's fence once everything submitted to `queue` so far has finished, so
+    /// the frame's own submits need no fence.
+    pub fn end_frame(&mut self, queue: vk::Queue) {
+        let fence = self.fences[self.frame as usize % MAX_FRAMES_IN_FLIGHT];
+        let result = unsafe { self.device.queue_submit(queue, &[], fence) };
+        if result.is_err() {
+            log::error!("Failed to submit frame fence.");
+            panic!("{:?}", result.err());
+        }
+        self.frame += 1;
+    }
+
+    pub fn wait_all(&self) {
+        unsafe {
+            self.device
+                .wait_for_fences(&self.fences, true, u64::MAX)
+                .expect("Failed to wait for frame fences");
+        }
+    }
+}
+
+impl Drop for FrameFences {
+    fn drop(&mut self) {
+        self.wait_all();
+        unsafe {
+            for fence in self.fences.drain(..) {
+                self.device.destroy_fence(fence, None);
+            }
+        }
+        log::debug!("Sucessfully destroyed frame fences");
+    }
+}
@@ -0,0 +1,171 @@
+use std::ffi::CString;
+
+use ash::vk;
+
+use crate::material::{ShaderStage, ShaderVariant};
+
+use super::{DeferredResource, SharedDeletionQueue, VulkanLogicalDevice};
+
+/// Fixed function state of a graphics pipeline. Viewport and scissor are dynamic.
+#[derive(Debug, Clone)]
+pub struct GraphicsPipelineDesc {
+    pub vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+    pub vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+    pub cull_mode: vk::CullModeFlags,
+    pub depth_test: bool,
+    pub depth_write: bool,
+    /// Straight alpha "over" blending on the colour attachment, for translucent materials.
+    pub alpha_blend: bool,
+}
+
+/// A graphics pipeline for one cooked shader variant. The set layouts come from the caller,
+/// since set 0 is usually shared by every pipeline of a renderer; push constant ranges come
+/// from the variant's reflected layout.
+pub struct GraphicsPipeline {
+    deletion_queue: SharedDeletionQueue,
+    layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    push_constant_stages: vk::ShaderStageFlags,
+}
+
+impl GraphicsPipeline {
+    pub fn new(
+        logical_device: &VulkanLogicalDevice,
+        variant: &ShaderVariant,
+        set_layouts: &[vk::DescriptorSetLayout],
+        render_pass: vk::RenderPass,
+        desc: &GraphicsPipelineDesc,
+    ) -> Self {
+        let device = logical_device.get_device();
+        let push_constant_ranges = variant.layout.push_constant_ranges();
+        let layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(set_layouts)
+            .push_constant_ranges(&push_constant_ranges);
+        let layout = unsafe {
+            device
+                .create_pipeline_layout(&layout_info, None)
+                .expect("Failed to create graphics pipeline layout")
+        };
+
+        let mut modules = Vec::new();
+        let mut entry_names = Vec::new();
+        for code in variant.stages.iter() {
+            if code.stage == ShaderStage::Compute {
+                continue;
+            }
+            let module_info = vk::ShaderModuleCreateInfo::builder().code(&code.spirv);
+            let module = unsafe {
+                let result = device.create_shader_module(&module_info, None);
+                if result.is_err() {
+                    log::error!("Failed to create {:?} shader module.", code.stage);
+                    panic!("{:?}", result.err());
+                }
+                result.unwrap()
+            };
+            modules.push((code.stage, module));
+            entry_names.push(CString::new(code.entry_point.as_str()).unwrap());
+        }
+        let stages: Vec<vk::PipelineShaderStageCreateInfo> = modules
+            .iter()
+            .zip(entry_names.iter())
+            .map(|((stage, module), name)| {
+                vk::PipelineShaderStageCreateInfo::builder()
+                    .stage(stage.flags())
+                    .module(*module)
+                    .name(name)
+                    .build()
+            })
+            .collect();
+
+        let vertex_input = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(&desc.vertex_bindings)
+            .vertex_attribute_descriptions(&desc.vertex_attributes);
+        let input_assembly =
+            vk::PipelineInputAssemblyStateCreateInfo::builder().topology(vk::PrimitiveTopology::TRIANGLE_LIST);
+        let viewport = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterization = vk::PipelineRasterizationStateCreateInfo::builder()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .cull_mode(desc.cull_mode)
+            .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+            .line_width(1.0);
+        let multisample =
+            vk::PipelineMultisampleStateCreateInfo::builder().rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(desc.depth_test)
+            .depth_write_enable(desc.depth_write)
+            .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL);
+        let blend_attachment = [vk::PipelineColorBlendAttachmentState::builder()
+            .blend_enable(desc.alpha_blend)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD)
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .build()];
+        let color_blend = vk::PipelineColorBlendStateCreateInfo::builder().attachments(&blend_attachment);
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
+
+        let pipeline_info = [vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport)
+            .rasterization_state(&rasterization)
+            .multisample_state(&multisample)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&color_blend)
+            .dynamic_state(&dynamic)
+            .layout(layout)
+            .render_pass(render_pass)
+            .subpass(0)
+            .build()];
+        let pipeline = unsafe {
+            let result = device.create_graphics_pipelines(vk::PipelineCache::null(), &pipeline_info, None);
+            for (_, module) in modules.iter() {
+                device.destroy_shader_module(*module, None);
+            }
+            match result {
+                Ok(pipelines) => pipelines[0],
+                Err((_, err)) => {
+                    log::error!("Failed to create graphics pipeline for variant {:?}.", variant.keywords);
+                    panic!("{:?}", err);
+                }
+            }
+        };
+
+        GraphicsPipeline {
+            deletion_queue: logical_device.get_deletion_queue().clone(),
+            layout,
+            pipeline,
+            push_constant_stages: push_constant_ranges
+                .iter()
+                .fold(vk::ShaderStageFlags::empty(), |flags, range| flags | range.stage_flags),
+        }
+    }
+
+    pub fn get(&self) -> &vk::Pipeline {
+        &self.pipeline
+    }
+
+    pub fn get_layout(&self) -> &vk::PipelineLayout {
+        &self.layout
+    }
+
+    /// Stages to pass to `cmd_push_constants`; empty when the variant has no push constants.
+    pub fn push_constant_stages(&self) -> vk::ShaderStageFlags {
+        self.push_constant_stages
+    }
+}
+
+impl Drop for GraphicsPipeline {
+    fn drop(&mut self) {
+        let mut deletion_queue = self.deletion_queue.lock().unwrap();
+        deletion_queue.push(DeferredResource::Pipeline(self.pipeline));
+        deletion_queue.push(DeferredResource::PipelineLayout(self.layout));
+    }
+}
@@ -0,0 +1,316 @@
+use ash::vk;
+
+use super::{
+    allocate_memory, immediate_submit, staging_buffer, DeferredResource, SharedDeletionQueue, VulkanInstance,
+    VulkanLogicalDevice, VulkanPhysicalDevice,
+};
+
+/// Shape of a sampled image: a 2D texture or, with `cube`, six square faces as array layers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureDesc {
+    pub format: vk::Format,
+    pub width: u32,
+    pub height: u32,
+    pub mip_levels: u32,
+    pub cube: bool,
+    pub usage: vk::ImageUsageFlags,
+}
+
+impl TextureDesc {
+    pub fn layers(&self) -> u32 {
+        if self.cube {
+            6
+        } else {
+            1
+        }
+    }
+
+    pub fn level_extent(&self, level: u32) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+}
+
+/// Bytes of one mip level of one layer, for `VulkanImage::upload`.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageLevel<'a> {
+    pub layer: u32,
+    pub level: u32,
+    pub bytes: &'a [u8],
+}
+
+/// Where in a buffer the texels of a mip level start; `layer_count` layers follow each other
+/// tightly packed.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageRegion {
+    pub level: u32,
+    pub base_layer: u32,
+    pub layer_count: u32,
+    pub offset: vk::DeviceSize,
+}
+
+/// A device local image with a view over all of its levels and layers, filled by copies
+/// and read by shaders in `SHADER_READ_ONLY_OPTIMAL`.
+pub struct VulkanImage {
+    device: ash::Device,
+    deletion_queue: SharedDeletionQueue,
+    desc: TextureDesc,
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+}
+
+impl VulkanImage {
+    pub fn new(
+        instance: &VulkanInstance,
+        physical_device: &VulkanPhysicalDevice,
+        logical_device: &VulkanLogicalDevice,
+        desc: TextureDesc,
+    ) -> Self {
+        let device = logical_device.get_device();
+        let flags = if desc.cube {
+            vk::ImageCreateFlags::CUBE_COMPATIBLE
+        } else {
+            vk::ImageCreateFlags::empty()
+        };
+        let create_info = vk::ImageCreateInfo::builder()
+            .flags(flags)
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(desc.format)
+            .extent(vk::Extent3D {
+                width: desc.width,
+                height: desc.height,
+                depth: 1,
+            })
+            .mip_levels(desc.mip_levels)
+            .array_layers(desc.layers())
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(desc.usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        let image = unsafe {
+            let result = device.create_image(&create_info, None);
+            if result.is_err() {
+                log::error!("Failed to create {}x{} {:?} image.", desc.width, desc.height, desc.format);
+                panic!("{:?}", result.err());
+            }
+            result.unwrap()
+        };
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+        let memory = allocate_memory(
+            device,
+            instance,
+            physical_device,
+            requirements,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        );
+        let view_type = if desc.cube {
+            vk::ImageViewType::CUBE
+        } else {
+            vk::ImageViewType::TYPE_2D
+        };
+        let view = unsafe {
+            device
+                .bind_image_memory(image, memory, 0)
+                .expect("Failed to bind image memory");
+            let view_info = vk::ImageViewCreateInfo::builder()
+                .image(image)
+                .view_type(view_type)
+                .format(desc.format)
+                .subresource_range(Self::full_range(&desc));
+            device
+                .create_image_view(&view_info, None)
+                .expect("Failed to create image view")
+        };
+
+        VulkanImage {
+            device: device.clone(),
+            deletion_queue: logical_device.get_deletion_queue().clone(),
+            desc,
+            image,
+            memory,
+            view,
+        }
+    }
+
+    pub fn get(&self) -> &vk::Image {
+        &self.image
+    }
+
+    pub fn get_view(&self) -> vk::ImageView {
+        self.view
+    }
+
+    pub fn desc(&self) -> &TextureDesc {
+        &self.desc
+    }
+
+    /// Copies `levels` through a staging buffer and leaves the image ready for sampling.
+    /// Levels not listed keep undefined contents.
+    pub fn upload(
+        &self,
+        instance: &VulkanInstance,
+        physical_device: &VulkanPhysicalDevice,
+        logical_device: &VulkanLogicalDevice,
+        levels: &[ImageLevel],
+    ) {
+        let mut bytes = Vec::new();
+        let mut regions = Vec::with_capacity(levels.len());
+        for level in levels.iter() {
+            // Offsets must be a multiple of the texel block size, which is at most 16 bytes.
+            bytes.resize(bytes.len().next_multiple_of(16), 0);
+            regions.push(ImageRegion {
+                level: level.level,
+                base_layer: level.layer,
+                layer_count: 1,
+                offset: bytes.len() as vk::DeviceSize,
+            });
+            bytes.extend_from_slice(level.bytes);
+        }
+        let staging = staging_buffer(instance, physical_device, logical_device, &bytes);
+        immediate_submit(logical_device, |command_buffer| {
+            self.record_copy(command_buffer, *staging.get(), &regions);
+        });
+    }
+
+    /// Records a copy of `regions` from `buffer`, with the barriers taking the whole image from
+    /// undefined contents to `SHADER_READ_ONLY_OPTIMAL` for fragment and compute shaders.
+    pub fn record_copy(&self, command_buffer: vk::CommandBuffer, buffer: vk::Buffer, regions: &[ImageRegion]) {
+        let copies: Vec<vk::BufferImageCopy> = regions
+            .iter()
+            .map(|region| {
+                let (width, height) = self.desc.level_extent(region.level);
+                vk::BufferImageCopy {
+                    buffer_offset: region.offset,
+                    buffer_row_length: 0,
+                    buffer_image_height: 0,
+                    image_subresource: vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: region.level,
+                        base_array_layer: region.base_layer,
+                        layer_count: region.layer_count,
+                    },
+                    image_offset: vk::Offset3D::default(),
+                    image_extent: vk::Extent3D {
+                        width,
+                        height,
+                        depth: 1,
+                    },
+                }
+            })
+            .collect();
+        let range = Self::full_range(&self.desc);
+        let to_transfer = [vk::ImageMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::empty())
+            .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.image)
+            .subresource_range(range)
+            .build()];
+        let to_shader = [vk::ImageMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ)
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.image)
+            .subresource_range(range)
+            .build()];
+        unsafe {
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &to_transfer,
+            );
+            self.device.cmd_copy_buffer_to_image(
+                command_buffer,
+                buffer,
+                self.image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &copies,
+            );
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &to_shader,
+            );
+        }
+    }
+
+    fn full_range(desc: &TextureDesc) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: desc.mip_levels,
+            base_array_layer: 0,
+            layer_count: desc.layers(),
+        }
+    }
+}
+
+impl Drop for VulkanImage {
+    fn drop(&mut self) {
+        let mut deletion_queue = self.deletion_queue.lock().unwrap();
+        deletion_queue.push(DeferredResource::ImageView(self.view));
+        deletion_queue.push(DeferredResource::Image(self.image));
+        deletion_queue.push(DeferredResource::Memory(self.memory));
+    }
+}
+
+/// A trilinear sampler; anisotropic filtering is used only if the device enabled it.
+pub struct VulkanSampler {
+    deletion_queue: SharedDeletionQueue,
+    sampler: vk::Sampler,
+}
+
+impl VulkanSampler {
+    pub fn new(logical_device: &VulkanLogicalDevice, address_mode: vk::SamplerAddressMode, anisotropy: f32) -> Self {
+        let anisotropy_enabled = anisotropy > 1.0 && logical_device.get_features().sampler_anisotropy == vk::TRUE;
+        let create_info = vk::SamplerCreateInfo::builder()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+            .address_mode_u(address_mode)
+            .address_mode_v(address_mode)
+            .address_mode_w(address_mode)
+            .anisotropy_enable(anisotropy_enabled)
+            .max_anisotropy(if anisotropy_enabled { anisotropy } else { 1.0 })
+            .min_lod(0.0)
+            .max_lod(vk::LOD_CLAMP_NONE);
+        let sampler = unsafe {
+            logical_device
+                .get_device()
+                .create_sampler(&create_info, None)
+                .expect("Failed to create sampler")
+        };
+        VulkanSampler {
+            deletion_queue: logical_device.get_deletion_queue().clone(),
+            sampler,
+        }
+    }
+
+    pub fn get(&self) -> vk::Sampler {
+        self.sampler
+    }
+}
+
+impl Drop for VulkanSampler {
+    fn drop(&mut self) {
+        self.deletion_queue
+            .lock()
+            .unwrap()
+            .push(DeferredResource::Sampler(self.sampler));
+    }
+}
@@ -5,19 +5,46 @@ use std::os::raw::c_char;
 
 use crate::vulkan::QueueFamilyIndices;
 
-use super::{VulkanInstance, VulkanPhysicalDevice, ENABLE_VALIDATION_LAYERS, REQUIRED_LAYERS, VulkanSurface};
+use super::{DeletionQueue, SharedDeletionQueue, VulkanInstance, VulkanPhysicalDevice, ENABLE_VALIDATION_LAYERS, REQUIRED_LAYERS, VulkanSurface};
 
 pub struct VulkanLogicalDevice {
     logical_device: ash::Device,
     queue: vk::Queue,
+    queue_family: u32,
+    compute_queue: vk::Queue,
+    compute_queue_family: u32,
+    features: vk::PhysicalDeviceFeatures,
+    deletion_queue: SharedDeletionQueue,
 }
 
 impl VulkanLogicalDevice {
     pub fn new(instance: &VulkanInstance, physical_device: &VulkanPhysicalDevice, surface: &VulkanSurface) -> Self {
-        let (this_device, this_queue) = Self::create_logical_device(instance, physical_device, surface);
+        let indices = QueueFamilyIndices::new(instance, surface, *physical_device.get());
+        let graphics_family = indices.graphics_family.unwrap();
+        let compute_family = indices.async_compute_family().unwrap_or(graphics_family);
+
+        let features = Self::enabled_features(instance, physical_device);
+        let this_device =
+            Self::create_logical_device(instance, physical_device, graphics_family, compute_family, &features);
+        let (this_queue, this_compute_queue) = unsafe {
+            (
+                this_device.get_device_queue(graphics_family, 0),
+                this_device.get_device_queue(compute_family, 0),
+            )
+        };
+        log::info!(
+            "Successfully initialized logical device with graphics queue family {} and compute queue family {}",
+            graphics_family,
+            compute_family
+        );
         VulkanLogicalDevice {
             logical_device: this_device,
             queue: this_queue,
+            queue_family: graphics_family,
+            compute_queue: this_compute_queue,
+            compute_queue_family: compute_family,
+            features,
+            deletion_queue: DeletionQueue::shared(),
         }
     }
 
@@ -29,23 +56,75 @@ impl VulkanLogicalDevice {
         &self.queue
     }
 
-    fn create_logical_device(
+    pub fn get_queue_family(&self) -> u32 {
+        self.queue_family
+    }
+
+    pub fn get_compute_queue(&self) -> &vk::Queue {
+        &self.compute_queue
+    }
+
+    pub fn get_compute_queue_family(&self) -> u32 {
+        self.compute_queue_family
+    }
+
+    /// Optional features turned on at creation, e.g. to check for anisotropic filtering.
+    pub fn get_features(&self) -> &vk::PhysicalDeviceFeatures {
+        &self.features
+    }
+
+    pub fn get_deletion_queue(&self) -> &SharedDeletionQueue {
+        &self.deletion_queue
+    }
+
+    pub fn wait_idle(&self) {
+        unsafe {
+            if let Err(err) = self.logical_device.device_wait_idle() {
+                log::error!("Failed to wait for device idle: {:?}", err);
+            }
+        }
+    }
+
+    pub fn has_async_compute(&self) -> bool {
+        self.compute_queue_family != self.queue_family
+    }
+
+    /// Sampler anisotropy and BC texture compression when the device has them; the renderer
+    /// falls back to plain filtering and uncompressed textures otherwise.
+    fn enabled_features(
         instance: &VulkanInstance,
         physical_device: &VulkanPhysicalDevice,
-        surface: &VulkanSurface
-    ) -> (ash::Device, vk::Queue) {
-        let indices = QueueFamilyIndices::new(instance, surface, *physical_device.get());
+    ) -> vk::PhysicalDeviceFeatures {
+        let supported = unsafe { instance.get().get_physical_device_features(*physical_device.get()) };
+        vk::PhysicalDeviceFeatures {
+            sampler_anisotropy: supported.sampler_anisotropy,
+            texture_compression_bc: supported.texture_compression_bc,
+            ..Default::default()
+        }
+    }
 
+    fn create_logical_device(
+        instance: &VulkanInstance,
+        physical_device: &VulkanPhysicalDevice,
+        graphics_family: u32,
+        compute_family: u32,
+        physical_device_features: &vk::PhysicalDeviceFeatures,
+    ) -> ash::Device {
         let queue_priorities = [1.0_f32];
-        let queue_create_info = [vk::DeviceQueueCreateInfo::builder()
+        let mut queue_create_info = vec![vk::DeviceQueueCreateInfo::builder()
             .flags(vk::DeviceQueueCreateFlags::empty())
-            .queue_family_index(indices.graphics_family.unwrap())
+            .queue_family_index(graphics_family)
             .queue_priorities(&queue_priorities)
             .build()];
-
-        let physical_device_features = vk::PhysicalDeviceFeatures {
-            ..Default::default()
-        };
+        if compute_family != graphics_family {
+            queue_create_info.push(
+                vk::DeviceQueueCreateInfo::builder()
+                    .flags(vk::DeviceQueueCreateFlags::empty())
+                    .queue_family_index(compute_family)
+                    .queue_priorities(&queue_priorities)
+                    .build(),
+            );
+        }
 
         let requred_validation_layer_raw_names: Vec<CString> = REQUIRED_LAYERS
             .iter()
@@ -59,7 +138,7 @@ impl VulkanLogicalDevice {
         let mut device_create_info_builder = vk::DeviceCreateInfo::builder()
             .flags(vk::DeviceCreateFlags::empty())
             .queue_create_infos(&queue_create_info)
-            .enabled_features(&physical_device_features);
+            .enabled_features(physical_device_features);
 
         if ENABLE_VALIDATION_LAYERS {
             device_create_info_builder =
@@ -79,16 +158,17 @@ impl VulkanLogicalDevice {
             result.unwrap()
         };
 
-        let graphics_queue =
-            unsafe { device.get_device_queue(indices.graphics_family.unwrap(), 0) };
-
-        log::info!("Successfully initialized logical device and graphics queue");
-        (device, graphics_queue)
+        device
     }
 }
 
 impl Drop for VulkanLogicalDevice {
     fn drop(&mut self) {
+        self.wait_idle();
+        self.deletion_queue
+            .lock()
+            .unwrap()
+            .flush(&self.logical_device);
         unsafe {
             self.logical_device.destroy_device(None);
         }
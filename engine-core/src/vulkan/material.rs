@@ -0,0 +1,166 @@
+use ash::vk;
+
+use crate::material::{BindingKind, ParameterBlock, ShaderBinding, ShaderLayout};
+
+use super::{
+    DeferredResource, SharedDeletionQueue, VulkanBuffer, VulkanInstance, VulkanLogicalDevice, VulkanPhysicalDevice,
+};
+
+/// The descriptor set of one material for one shader variant: its set layout, taken from the
+/// variant's reflected layout, a host visible uniform buffer per parameter block and the set
+/// itself. Textures and samplers are bound by the renderer once their images exist.
+pub struct MaterialDescriptors {
+    device: ash::Device,
+    deletion_queue: SharedDeletionQueue,
+    bindings: Vec<ShaderBinding>,
+    set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    set: vk::DescriptorSet,
+    uniform_buffers: Vec<(u32, VulkanBuffer)>,
+}
+
+impl MaterialDescriptors {
+    pub fn new(
+        instance: &VulkanInstance,
+        physical_device: &VulkanPhysicalDevice,
+        logical_device: &VulkanLogicalDevice,
+        layout: &ShaderLayout,
+        block: &ParameterBlock,
+    ) -> Self {
+        let device = logical_device.get_device();
+        let bindings: Vec<ShaderBinding> = layout.set(block.set).cloned().collect();
+        let layout_bindings = layout.set_layout_bindings(block.set);
+        let set_layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&layout_bindings);
+        let set_layout = unsafe {
+            device
+                .create_descriptor_set_layout(&set_layout_info, None)
+                .expect("Failed to create material descriptor set layout")
+        };
+
+        let mut pool_sizes: Vec<vk::DescriptorPoolSize> = Vec::new();
+        for binding in layout_bindings.iter() {
+            match pool_sizes.iter_mut().find(|size| size.ty == binding.descriptor_type) {
+                Some(size) => size.descriptor_count += 1,
+                None => pool_sizes.push(vk::DescriptorPoolSize {
+                    ty: binding.descriptor_type,
+                    descriptor_count: 1,
+                }),
+            }
+        }
+        let pool_info = vk::DescriptorPoolCreateInfo::builder().max_sets(1).pool_sizes(&pool_sizes);
+        let descriptor_pool = unsafe {
+            device
+                .create_descriptor_pool(&pool_info, None)
+                .expect("Failed to create material descriptor pool")
+        };
+        let set_layouts = [set_layout];
+        let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&set_layouts);
+        let set = unsafe {
+            device
+                .allocate_descriptor_sets(&allocate_info)
+                .expect("Failed to allocate material descriptor set")[0]
+        };
+
+        let mut uniform_buffers = Vec::new();
+        for binding in bindings.iter() {
+            let size = match &binding.kind {
+                BindingKind::UniformBuffer(layout) => layout.size.max(1) as vk::DeviceSize,
+                _ => continue,
+            };
+            let buffer = VulkanBuffer::new(
+                instance,
+                physical_device,
+                logical_device,
+                size,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                &[logical_device.get_queue_family()],
+            );
+            let buffer_info = [vk::DescriptorBufferInfo {
+                buffer: *buffer.get(),
+                offset: 0,
+                range: size,
+            }];
+            let write = vk::WriteDescriptorSet::builder()
+                .dst_set(set)
+                .dst_binding(binding.binding)
+                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                .buffer_info(&buffer_info)
+                .build();
+            unsafe { device.update_descriptor_sets(&[write], &[]) };
+            uniform_buffers.push((binding.binding, buffer));
+        }
+
+        let descriptors = MaterialDescriptors {
+            device: device.clone(),
+            deletion_queue: logical_device.get_deletion_queue().clone(),
+            bindings,
+            set_layout,
+            descriptor_pool,
+            set,
+            uniform_buffers,
+        };
+        descriptors.update(block);
+        descriptors
+    }
+
+    /// Uploads the uniform blocks of `block`, e.g. after a parameter changed. The GPU must be
+    /// done with the previous values.
+    pub fn update(&self, block: &ParameterBlock) {
+        for uniform in block.uniforms.iter() {
+            match self.uniform_buffers.iter().find(|(binding, _)| *binding == uniform.binding) {
+                Some((_, buffer)) => buffer.write(&uniform.bytes),
+                None => log::warn!("Material set has no uniform block at binding {}", uniform.binding),
+            }
+        }
+    }
+
+    pub fn bind_texture(&self, binding: u32, view: vk::ImageView, layout: vk::ImageLayout) {
+        self.write_image(binding, vk::DescriptorImageInfo {
+            sampler: vk::Sampler::null(),
+            image_view: view,
+            image_layout: layout,
+        });
+    }
+
+    pub fn bind_sampler(&self, binding: u32, sampler: vk::Sampler) {
+        self.write_image(binding, vk::DescriptorImageInfo {
+            sampler,
+            image_view: vk::ImageView::null(),
+            image_layout: vk::ImageLayout::UNDEFINED,
+        });
+    }
+
+    pub fn get_set(&self) -> vk::DescriptorSet {
+        self.set
+    }
+
+    pub fn get_set_layout(&self) -> vk::DescriptorSetLayout {
+        self.set_layout
+    }
+
+    fn write_image(&self, binding: u32, image_info: vk::DescriptorImageInfo) {
+        let descriptor_type = match self.bindings.iter().find(|candidate| candidate.binding == binding) {
+            Some(found) => found.kind.descriptor_type(),
+            None => panic!("Material set has no binding {}", binding),
+        };
+        let image_info = [image_info];
+        let write = vk::WriteDescriptorSet::builder()
+            .dst_set(self.set)
+            .dst_binding(binding)
+            .descriptor_type(descriptor_type)
+            .image_info(&image_info)
+            .build();
+        unsafe { self.device.update_descriptor_sets(&[write], &[]) };
+    }
+}
+
+impl Drop for MaterialDescriptors {
+    fn drop(&mut self) {
+        let mut deletion_queue = self.deletion_queue.lock().unwrap();
+        deletion_queue.push(DeferredResource::DescriptorPool(self.descriptor_pool));
+        deletion_queue.push(DeferredResource::DescriptorSetLayout(self.set_layout));
+    }
+}
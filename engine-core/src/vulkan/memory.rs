@@ -0,0 +1,48 @@
+use ash::vk;
+
+use super::{VulkanInstance, VulkanPhysicalDevice};
+
+pub fn find_memory_type(
+    instance: &VulkanInstance,
+    physical_device: &VulkanPhysicalDevice,
+    memory_type_bits: u32,
+    properties: vk::MemoryPropertyFlags,
+) -> Option<u32> {
+    let memory = unsafe {
+        instance
+            .get()
+            .get_physical_device_memory_properties(*physical_device.get())
+    };
+    memory.memory_types[..memory.memory_type_count as usize]
+        .iter()
+        .enumerate()
+        .find(|(index, memory_type)| {
+            memory_type_bits & (1 << index) != 0 && memory_type.property_flags.contains(properties)
+        })
+        .map(|(index, _)| index as u32)
+}
+
+pub fn allocate_memory(
+    device: &ash::Device,
+    instance: &VulkanInstance,
+    physical_device: &VulkanPhysicalDevice,
+    requirements: vk::MemoryRequirements,
+    properties: vk::MemoryPropertyFlags,
+) -> vk::DeviceMemory {
+    let memory_type = find_memory_type(instance, physical_device, requirements.memory_type_bits, properties);
+    if memory_type.is_none() {
+        log::error!("No memory type matches {:?} with properties {:?}", requirements, properties);
+        panic!("Failed to find suitable memory type");
+    }
+    let allocate_info = vk::MemoryAllocateInfo::builder()
+        .allocation_size(requirements.size)
+        .memory_type_index(memory_type.unwrap());
+    unsafe {
+        let result = device.allocate_memory(&allocate_info, None);
+        if result.is_err() {
+            log::error!("Failed to allocate {} bytes of device memory.", requirements.size);
+            panic!("{:?}", result.err());
+        }
+        result.unwrap()
+    }
+}
@@ -4,10 +4,28 @@ mod physical_device;
 mod logical_device;
 mod surface;
 mod app;
+mod report;
+mod memory;
+mod buffer;
+mod compute;
+mod material;
+mod deletion;
+mod upload;
+mod image;
+mod graphics;
 
 pub use validation::*;
 pub use instance::*;
 pub use physical_device::*;
 pub use logical_device::*;
 pub use surface::*;
-pub use app::*;
\ No newline at end of file
+pub use app::*;
+pub use report::*;
+pub use memory::*;
+pub use buffer::*;
+pub use compute::*;
+pub use material::*;
+pub use deletion::*;
+pub use upload::*;
+pub use image::*;
+pub use graphics::*;
\ No newline at end of file
@@ -1,13 +1,13 @@
 use std::fmt::Display;
 
 use ash::vk::{self, QueueFamilyProperties};
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{conf::VulkanPhysicalDeviceConfig, util};
 
 use super::{VulkanInstance, VulkanSurface};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct QueueFlagSupportMatrix {
     pub graphics: bool,
     pub compute: bool,
@@ -38,7 +38,7 @@ impl Display for QueueFlagSupportMatrix {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct DeviceFeatureSupportMatrix {
     pub geometry_shader: bool,
 }
@@ -49,6 +49,14 @@ impl DeviceFeatureSupportMatrix {
             geometry_shader: features.geometry_shader == 1,
         }
     }
+
+    pub fn missing(&self, required: &DeviceFeatureSupportMatrix) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if required.geometry_shader && !self.geometry_shader {
+            missing.push("geometry_shader");
+        }
+        missing
+    }
 }
 
 impl Display for DeviceFeatureSupportMatrix {
@@ -61,6 +69,7 @@ impl Display for DeviceFeatureSupportMatrix {
 pub struct QueueFamilyIndices {
     pub graphics_family: Option<u32>,
     pub compute_family: Option<u32>,
+    pub dedicated_compute_family: Option<u32>,
     pub transfer_family: Option<u32>,
     pub sparse_family: Option<u32>,
     pub present_family: Option<u32>,
@@ -83,6 +92,21 @@ impl QueueFamilyIndices {
             && self.is_part_complete(true, self.present_family)
     }
 
+    pub fn missing(&self, required_matrix: &QueueFlagSupportMatrix) -> Vec<&'static str> {
+        let parts = [
+            ("graphics", required_matrix.graphics, self.graphics_family),
+            ("compute", required_matrix.compute, self.compute_family),
+            ("transfer", required_matrix.transfer, self.transfer_family),
+            ("sparse", required_matrix.sparse, self.sparse_family),
+            ("present", true, self.present_family),
+        ];
+        parts
+            .iter()
+            .filter(|(_, required, optional)| !self.is_part_complete(*required, *optional))
+            .map(|(name, _, _)| *name)
+            .collect()
+    }
+
     fn is_part_complete(&self, required: bool, optional: Option<u32>) -> bool {
         if required {
             optional.is_some()
@@ -109,6 +133,11 @@ impl QueueFamilyIndices {
                 indices.mark_one(support_matrix.graphics, indices.graphics_family, idx);
             indices.compute_family =
                 indices.mark_one(support_matrix.compute, indices.compute_family, idx);
+            indices.dedicated_compute_family = indices.mark_one(
+                support_matrix.compute && !support_matrix.graphics,
+                indices.dedicated_compute_family,
+                idx,
+            );
             indices.transfer_family =
                 indices.mark_one(support_matrix.transfer, indices.transfer_family, idx);
             indices.sparse_family =
@@ -128,6 +157,11 @@ impl QueueFamilyIndices {
         indices
     }
 
+    /// Prefers a compute-only family so dispatches can overlap graphics work.
+    pub fn async_compute_family(&self) -> Option<u32> {
+        self.dedicated_compute_family.or(self.compute_family)
+    }
+
     fn mark_one(&self, is_available: bool, optional: Option<u32>, index: u32) -> Option<u32> {
         if is_available {
             Some(index)
@@ -174,9 +208,26 @@ impl VulkanPhysicalDevice {
             physical_devices.len()
         );
 
-        let result = physical_devices.iter().find(|device| {
-            Self::is_device_suitable(instance, **device, surface, &config.desired_queue_flags)
+        let suitable: Vec<vk::PhysicalDevice> = physical_devices
+            .iter()
+            .copied()
+            .filter(|device| Self::is_device_suitable(instance, *device, surface, config))
+            .collect();
+        let preferred = config.preferred_device.as_ref().and_then(|preferred| {
+            let found = suitable.iter().find(|device| {
+                Self::device_name(instance, **device)
+                    .to_lowercase()
+                    .contains(&preferred.to_lowercase())
+            });
+            if found.is_none() {
+                log::warn!(
+                    "No suitable GPU matches preferred device '{}', falling back to the first suitable one",
+                    preferred
+                );
+            }
+            found
         });
+        let result = preferred.or_else(|| suitable.first());
 
         match result {
             None => panic!("Failed to find a suitable GPU!"),
@@ -190,43 +241,38 @@ impl VulkanPhysicalDevice {
         }
     }
 
+    pub fn device_name(instance: &VulkanInstance, vk_physical_device: vk::PhysicalDevice) -> String {
+        let properties = unsafe {
+            instance
+                .get()
+                .get_physical_device_properties(vk_physical_device)
+        };
+        util::string::c_char_arr_to_string(&properties.device_name)
+    }
+
     fn is_device_suitable(
         instance: &VulkanInstance,
         vk_physical_device: vk::PhysicalDevice,
         surface: &VulkanSurface,
-        required_flags: &QueueFlagSupportMatrix,
+        config: &VulkanPhysicalDeviceConfig,
     ) -> bool {
         let properties = unsafe {
             instance
                 .get()
                 .get_physical_device_properties(vk_physical_device)
         };
-        let features = unsafe {
-            instance
-                .get()
-                .get_physical_device_features(vk_physical_device)
-        };
         let queue_families = unsafe {
             instance
                 .get()
                 .get_physical_device_queue_family_properties(vk_physical_device)
         };
 
-        let device_type = match properties.device_type {
-            vk::PhysicalDeviceType::CPU => "Cpu",
-            vk::PhysicalDeviceType::INTEGRATED_GPU => "Integrated GPU",
-            vk::PhysicalDeviceType::DISCRETE_GPU => "Discrete GPU",
-            vk::PhysicalDeviceType::VIRTUAL_GPU => "Virtual GPU",
-            vk::PhysicalDeviceType::OTHER => "Unknown",
-            _ => panic!("Unable to determine GPU type"),
-        };
-
         let device_name = util::string::c_char_arr_to_string(&properties.device_name);
         log::debug!(
             "Device Name: {}, id: {}, type: {}",
             device_name,
             properties.device_id,
-            device_type
+            device_type_name(properties.device_type)
         );
 
         let major_version = vk::api_version_major(properties.api_version);
@@ -249,10 +295,49 @@ impl VulkanPhysicalDevice {
             );
         }
 
+        log::debug!("Required - {:?}", config);
+        let reasons = Self::unsuitable_reasons(instance, vk_physical_device, surface, config);
+        for reason in reasons.iter() {
+            log::debug!("Device '{}' rejected: {}", device_name, reason);
+        }
+        reasons.is_empty()
+    }
+
+    pub fn unsuitable_reasons(
+        instance: &VulkanInstance,
+        vk_physical_device: vk::PhysicalDevice,
+        surface: &VulkanSurface,
+        config: &VulkanPhysicalDeviceConfig,
+    ) -> Vec<String> {
+        let features = unsafe {
+            instance
+                .get()
+                .get_physical_device_features(vk_physical_device)
+        };
         let device_matrix = DeviceFeatureSupportMatrix::new(features);
         log::debug!("Device Feature Support - {}", device_matrix);
 
-        log::debug!("Required - {:?}", required_flags);
-        QueueFamilyIndices::new(instance, surface, vk_physical_device).is_complete(required_flags)
+        let mut reasons: Vec<String> = QueueFamilyIndices::new(instance, surface, vk_physical_device)
+            .missing(&config.desired_queue_flags)
+            .iter()
+            .map(|queue| format!("no queue family supports {}", queue))
+            .collect();
+        reasons.extend(
+            device_matrix
+                .missing(&config.desired_device_features)
+                .iter()
+                .map(|feature| format!("missing device feature {}", feature)),
+        );
+        reasons
+    }
+}
+
+pub fn device_type_name(device_type: vk::PhysicalDeviceType) -> &'static str {
+    match device_type {
+        vk::PhysicalDeviceType::CPU => "Cpu",
+        vk::PhysicalDeviceType::INTEGRATED_GPU => "Integrated GPU",
+        vk::PhysicalDeviceType::DISCRETE_GPU => "Discrete GPU",
+        vk::PhysicalDeviceType::VIRTUAL_GPU => "Virtual GPU",
+        _ => "Unknown",
     }
 }
@@ -0,0 +1,287 @@
+use std::fmt::Display;
+
+use ash::{vk, Entry};
+use serde_derive::Serialize;
+use winit::event_loop::EventLoop;
+
+use crate::{conf::EngineConfig, util};
+
+use super::{
+    device_type_name, DeviceFeatureSupportMatrix, QueueFlagSupportMatrix, VulkanInstance,
+    VulkanPhysicalDevice, VulkanSurface,
+};
+
+#[derive(Debug, Serialize)]
+pub struct DeviceLimitsReport {
+    pub max_image_dimension_2d: u32,
+    pub max_image_array_layers: u32,
+    pub max_bound_descriptor_sets: u32,
+    pub max_push_constants_size: u32,
+    pub max_uniform_buffer_range: u32,
+    pub max_storage_buffer_range: u32,
+    pub max_memory_allocation_count: u32,
+    pub max_sampler_anisotropy: f32,
+    pub max_compute_work_group_count: [u32; 3],
+    pub max_compute_work_group_size: [u32; 3],
+    pub max_compute_work_group_invocations: u32,
+    pub min_uniform_buffer_offset_alignment: u64,
+    pub min_storage_buffer_offset_alignment: u64,
+    pub timestamp_period: f32,
+}
+
+impl DeviceLimitsReport {
+    pub fn new(limits: &vk::PhysicalDeviceLimits) -> Self {
+        DeviceLimitsReport {
+            max_image_dimension_2d: limits.max_image_dimension2_d,
+            max_image_array_layers: limits.max_image_array_layers,
+            max_bound_descriptor_sets: limits.max_bound_descriptor_sets,
+            max_push_constants_size: limits.max_push_constants_size,
+            max_uniform_buffer_range: limits.max_uniform_buffer_range,
+            max_storage_buffer_range: limits.max_storage_buffer_range,
+            max_memory_allocation_count: limits.max_memory_allocation_count,
+            max_sampler_anisotropy: limits.max_sampler_anisotropy,
+            max_compute_work_group_count: limits.max_compute_work_group_count,
+            max_compute_work_group_size: limits.max_compute_work_group_size,
+            max_compute_work_group_invocations: limits.max_compute_work_group_invocations,
+            min_uniform_buffer_offset_alignment: limits.min_uniform_buffer_offset_alignment,
+            min_storage_buffer_offset_alignment: limits.min_storage_buffer_offset_alignment,
+            timestamp_period: limits.timestamp_period,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemoryHeapReport {
+    pub index: u32,
+    pub size: u64,
+    pub device_local: bool,
+    pub memory_types: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueueFamilyReport {
+    pub index: u32,
+    pub queue_count: u32,
+    pub flags: QueueFlagSupportMatrix,
+    pub present: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SurfaceFormatReport {
+    pub format: String,
+    pub color_space: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeviceReport {
+    pub name: String,
+    pub device_id: u32,
+    pub vendor_id: u32,
+    pub device_type: String,
+    pub api_version: String,
+    pub driver_version: u32,
+    pub limits: DeviceLimitsReport,
+    pub memory_heaps: Vec<MemoryHeapReport>,
+    pub queue_families: Vec<QueueFamilyReport>,
+    pub features: DeviceFeatureSupportMatrix,
+    pub extensions: Vec<String>,
+    pub surface_formats: Vec<SurfaceFormatReport>,
+    pub present_modes: Vec<String>,
+    pub suitable: bool,
+    pub unsuitable_reasons: Vec<String>,
+}
+
+impl DeviceReport {
+    pub fn new(
+        instance: &VulkanInstance,
+        surface: &VulkanSurface,
+        vk_physical_device: vk::PhysicalDevice,
+        config: &EngineConfig,
+    ) -> Self {
+        let vk_instance = instance.get();
+        let (properties, features, memory, queue_families, extensions) = unsafe {
+            (
+                vk_instance.get_physical_device_properties(vk_physical_device),
+                vk_instance.get_physical_device_features(vk_physical_device),
+                vk_instance.get_physical_device_memory_properties(vk_physical_device),
+                vk_instance.get_physical_device_queue_family_properties(vk_physical_device),
+                vk_instance
+                    .enumerate_device_extension_properties(vk_physical_device)
+                    .unwrap_or_default(),
+            )
+        };
+        let loader = surface.get_loader();
+        let (surface_formats, present_modes) = unsafe {
+            (
+                loader
+                    .get_physical_device_surface_formats(vk_physical_device, *surface.get_surface())
+                    .unwrap_or_default(),
+                loader
+                    .get_physical_device_surface_present_modes(
+                        vk_physical_device,
+                        *surface.get_surface(),
+                    )
+                    .unwrap_or_default(),
+            )
+        };
+
+        let memory_heaps = memory.memory_heaps[..memory.memory_heap_count as usize]
+            .iter()
+            .enumerate()
+            .map(|(index, heap)| MemoryHeapReport {
+                index: index as u32,
+                size: heap.size,
+                device_local: heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+                memory_types: memory.memory_types[..memory.memory_type_count as usize]
+                    .iter()
+                    .filter(|memory_type| memory_type.heap_index == index as u32)
+                    .map(|memory_type| format!("{:?}", memory_type.property_flags))
+                    .collect(),
+            })
+            .collect();
+
+        let queue_families = queue_families
+            .iter()
+            .enumerate()
+            .map(|(index, queue_family)| QueueFamilyReport {
+                index: index as u32,
+                queue_count: queue_family.queue_count,
+                flags: QueueFlagSupportMatrix::new(queue_family),
+                present: unsafe {
+                    loader
+                        .get_physical_device_surface_support(
+                            vk_physical_device,
+                            index as u32,
+                            *surface.get_surface(),
+                        )
+                        .unwrap_or(false)
+                },
+            })
+            .collect();
+
+        let unsuitable_reasons = VulkanPhysicalDevice::unsuitable_reasons(
+            instance,
+            vk_physical_device,
+            surface,
+            &config.vulkan.physical_device,
+        );
+
+        DeviceReport {
+            name: util::string::c_char_arr_to_string(&properties.device_name),
+            device_id: properties.device_id,
+            vendor_id: properties.vendor_id,
+            device_type: device_type_name(properties.device_type).to_string(),
+            api_version: format!(
+                "{}.{}.{}",
+                vk::api_version_major(properties.api_version),
+                vk::api_version_minor(properties.api_version),
+                vk::api_version_patch(properties.api_version)
+            ),
+            driver_version: properties.driver_version,
+            limits: DeviceLimitsReport::new(&properties.limits),
+            memory_heaps,
+            queue_families,
+            features: DeviceFeatureSupportMatrix::new(features),
+            extensions: extensions
+                .iter()
+                .map(|extension| util::string::c_char_arr_to_string(&extension.extension_name))
+                .collect(),
+            surface_formats: surface_formats
+                .iter()
+                .map(|format| SurfaceFormatReport {
+                    format: format!("{:?}", format.format),
+                    color_space: format!("{:?}", format.color_space),
+                })
+                .collect(),
+            present_modes: present_modes
+                .iter()
+                .map(|mode| format!("{:?}", mode))
+                .collect(),
+            suitable: unsuitable_reasons.is_empty(),
+            unsuitable_reasons,
+        }
+    }
+
+    /// Enumerates every physical device, using a hidden window to query surface support.
+    pub fn collect_all(config: &EngineConfig) -> Vec<DeviceReport> {
+        let entry = Entry::linked();
+        let event_loop = EventLoop::new();
+        let result = winit::window::WindowBuilder::new()
+            .with_title(config.window.title.to_string())
+            .with_visible(false)
+            .build(&event_loop);
+        if result.is_err() {
+            log::error!("Failed to initialize hidden window for device report.");
+            panic!("{:?}", result);
+        }
+        let window = result.unwrap();
+
+        let instance = VulkanInstance::new(&entry, &config.vulkan.instance);
+        let surface = VulkanSurface::new(&entry, &instance, &window);
+        let physical_devices = unsafe {
+            let result = instance.get().enumerate_physical_devices();
+            if result.is_err() {
+                log::error!("Failed to enumerate physical devices.");
+                panic!("{:?}", result.err());
+            }
+            result.unwrap()
+        };
+
+        let reports = physical_devices
+            .iter()
+            .map(|device| DeviceReport::new(&instance, &surface, *device, config))
+            .collect();
+        drop(surface);
+        drop(instance);
+        reports
+    }
+}
+
+impl Display for DeviceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} ({})", self.name, self.device_type)?;
+        writeln!(
+            f,
+            "  vendor: {:#06x}, device: {:#06x}, api: {}, driver: {}",
+            self.vendor_id, self.device_id, self.api_version, self.driver_version
+        )?;
+        if self.suitable {
+            writeln!(f, "  suitable: yes")?;
+        } else {
+            writeln!(f, "  suitable: no")?;
+            for reason in self.unsuitable_reasons.iter() {
+                writeln!(f, "    - {}", reason)?;
+            }
+        }
+        writeln!(f, "  limits: {:?}", self.limits)?;
+        writeln!(f, "  memory heaps:")?;
+        for heap in self.memory_heaps.iter() {
+            writeln!(
+                f,
+                "    [{}] {} MiB{} - types: {}",
+                heap.index,
+                heap.size / (1024 * 1024),
+                if heap.device_local { " (device local)" } else { "" },
+                heap.memory_types.join(", ")
+            )?;
+        }
+        writeln!(f, "  queue families:")?;
+        for family in self.queue_families.iter() {
+            writeln!(
+                f,
+                "    [{}] count: {}, {}, present: {}",
+                family.index, family.queue_count, family.flags, family.present
+            )?;
+        }
+        writeln!(f, "  features: {}", self.features)?;
+        writeln!(f, "  extensions ({}):", self.extensions.len())?;
+        for extension in self.extensions.iter() {
+            writeln!(f, "    {}", extension)?;
+        }
+        writeln!(f, "  surface formats:")?;
+        for format in self.surface_formats.iter() {
+            writeln!(f, "    {} / {}", format.format, format.color_space)?;
+        }
+        writeln!(f, "  present modes: {}", self.present_modes.join(", "))
+    }
+}
@@ -0,0 +1,106 @@
+use ash::vk;
+
+use crate::profile_scope;
+
+use super::{VulkanBuffer, VulkanInstance, VulkanLogicalDevice, VulkanPhysicalDevice};
+
+/// Records commands with `record` and runs them on the graphics queue, blocking until they
+/// finish. Meant for loading-time uploads, not for per-frame work.
+pub fn immediate_submit<F: FnOnce(vk::CommandBuffer)>(logical_device: &VulkanLogicalDevice, record: F) {
+    profile_scope!("immediate_submit", "submit");
+    let device = logical_device.get_device();
+    let pool_info = vk::CommandPoolCreateInfo::builder()
+        .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+        .queue_family_index(logical_device.get_queue_family());
+    unsafe {
+        let command_pool = device
+            .create_command_pool(&pool_info, None)
+            .expect("Failed to create upload command pool");
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        let command_buffer = device
+            .allocate_command_buffers(&allocate_info)
+            .expect("Failed to allocate upload command buffer")[0];
+        let begin_info = vk::CommandBufferBeginInfo::builder().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        device
+            .begin_command_buffer(command_buffer, &begin_info)
+            .expect("Failed to begin upload command buffer");
+        record(command_buffer);
+        device
+            .end_command_buffer(command_buffer)
+            .expect("Failed to end upload command buffer");
+
+        let fence = device
+            .create_fence(&vk::FenceCreateInfo::default(), None)
+            .expect("Failed to create upload fence");
+        let command_buffers = [command_buffer];
+        let submit_info = [vk::SubmitInfo::builder().command_buffers(&command_buffers).build()];
+        let result = device.queue_submit(*logical_device.get_queue(), &submit_info, fence);
+        if result.is_err() {
+            log::error!("Failed to submit upload commands.");
+            panic!("{:?}", result.err());
+        }
+        device
+            .wait_for_fences(&[fence], true, u64::MAX)
+            .expect("Failed to wait for upload fence");
+        device.destroy_fence(fence, None);
+        device.destroy_command_pool(command_pool, None);
+    }
+}
+
+/// A host visible buffer filled with `data`, to copy from with `immediate_submit`.
+pub fn staging_buffer(
+    instance: &VulkanInstance,
+    physical_device: &VulkanPhysicalDevice,
+    logical_device: &VulkanLogicalDevice,
+    data: &[u8],
+) -> VulkanBuffer {
+    let buffer = VulkanBuffer::new(
+        instance,
+        physical_device,
+        logical_device,
+        data.len().max(1) as vk::DeviceSize,
+        vk::BufferUsageFlags::TRANSFER_SRC,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        &[logical_device.get_queue_family()],
+    );
+    buffer.write(data);
+    buffer
+}
+
+/// A device local buffer holding `data`, uploaded through a staging buffer.
+pub fn device_local_buffer<T: Copy>(
+    instance: &VulkanInstance,
+    physical_device: &VulkanPhysicalDevice,
+    logical_device: &VulkanLogicalDevice,
+    data: &[T],
+    usage: vk::BufferUsageFlags,
+) -> VulkanBuffer {
+    let size = std::mem::size_of_val(data);
+    let bytes = unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, size) };
+    let staging = staging_buffer(instance, physical_device, logical_device, bytes);
+    let buffer = VulkanBuffer::new(
+        instance,
+        physical_device,
+        logical_device,
+        size.max(1) as vk::DeviceSize,
+        usage | vk::BufferUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        &[logical_device.get_queue_family()],
+    );
+    if size > 0 {
+        let region = [vk::BufferCopy {
+            src_offset: 0,
+            dst_offset: 0,
+            size: size as vk::DeviceSize,
+        }];
+        immediate_submit(logical_device, |command_buffer| unsafe {
+            logical_device
+                .get_device()
+                .cmd_copy_buffer(command_buffer, *staging.get(), *buffer.get(), &region);
+        });
+    }
+    buffer
+}
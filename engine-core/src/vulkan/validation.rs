@@ -1,6 +1,7 @@
 use std::{
     ffi::{c_void, CStr},
     ptr, 
+    sync::RwLock,
 };
 
 use ash::{
@@ -9,6 +10,8 @@ use ash::{
     Entry,
 };
 
+use crate::conf::VulkanValidationConfig;
+
 use super::VulkanInstance;
 
 #[cfg(debug_assertions)]
@@ -18,6 +21,36 @@ pub const ENABLE_VALIDATION_LAYERS: bool = false;
 
 pub const REQUIRED_LAYERS: [&str; 1] = ["VK_LAYER_KHRONOS_validation"];
 
+struct ValidationFilter {
+    min_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    ignored_messages: Vec<String>,
+}
+
+// Read by the debug callback on every message, so it can be swapped while running.
+static VALIDATION_FILTER: RwLock<ValidationFilter> = RwLock::new(ValidationFilter {
+    min_severity: vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE,
+    ignored_messages: Vec::new(),
+});
+
+pub fn severity_from_name(name: &str) -> Option<vk::DebugUtilsMessageSeverityFlagsEXT> {
+    match name.to_lowercase().as_str() {
+        "verbose" => Some(vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE),
+        "info" => Some(vk::DebugUtilsMessageSeverityFlagsEXT::INFO),
+        "warning" => Some(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING),
+        "error" => Some(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR),
+        _ => None,
+    }
+}
+
+/// Replaces the filter applied to validation messages, messages matching an entry of
+/// `ignored_messages` by message id name or substring are dropped.
+pub fn set_validation_filter(config: &VulkanValidationConfig) {
+    let mut filter = VALIDATION_FILTER.write().unwrap();
+    filter.min_severity = severity_from_name(&config.min_severity)
+        .unwrap_or(vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE);
+    filter.ignored_messages = config.ignored_messages.clone();
+}
+
 unsafe extern "system" fn vulkan_debug_callback(
     flag: vk::DebugUtilsMessageSeverityFlagsEXT,
     typ: vk::DebugUtilsMessageTypeFlagsEXT,
@@ -27,6 +60,16 @@ unsafe extern "system" fn vulkan_debug_callback(
     use vk::DebugUtilsMessageSeverityFlagsEXT as Flag;
 
     let message = CStr::from_ptr((*p_callback_data).p_message);
+    {
+        let filter = VALIDATION_FILTER.read().unwrap();
+        if flag.as_raw() < filter.min_severity.as_raw() {
+            return vk::FALSE;
+        }
+        let text = message.to_string_lossy();
+        if filter.ignored_messages.iter().any(|ignored| text.contains(ignored.as_str())) {
+            return vk::FALSE;
+        }
+    }
     match flag {
         Flag::VERBOSE => log::debug!("{:?} - {:?}", typ, message),
         Flag::INFO => log::info!("{:?} - {:?}", typ, message),
@@ -93,6 +136,7 @@ pub fn create_debug_messenger(
         p_next: ptr::null(),
         flags: vk::DebugUtilsMessengerCreateFlagsEXT::empty(),
         message_severity: vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE
+            | vk::DebugUtilsMessageSeverityFlagsEXT::INFO
             | vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
             | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
         message_type: vk::DebugUtilsMessageTypeFlagsEXT::GENERAL